@@ -0,0 +1,178 @@
+//! Program Binary Format
+//!
+//! `AssembledProgram::to_binary` stores a program compactly so it can be
+//! saved to a file and loaded again without the source. All integers are
+//! big-endian:
+//!
+//! ```text
+//! magic        4 bytes  "S130"
+//! origin       u16      load address of the first word
+//! entry point  u16      0xFFFF = none
+//! word count   u32
+//! words        u16 * word count
+//! symbols      u32 count, then per symbol: u16 name length, UTF-8 name, u16 value
+//! ```
+//!
+//! The words are the flattened image (`flat_words`), so gaps between
+//! segments are stored as zeros and a loaded program has one segment.
+//! Linkage metadata (`entries`, `external_refs`) is not stored: a binary is
+//! a loadable image, not a module for linking.
+
+use super::{AssembledProgram, Result, Segment};
+use crate::error::AssemblerError;
+use std::collections::{BTreeMap, HashMap};
+
+/// Leading bytes of every program binary
+pub const MAGIC: [u8; 4] = *b"S130";
+
+/// Entry point field value meaning "no entry point"
+const NO_ENTRY: u16 = 0xFFFF;
+
+impl AssembledProgram {
+    /// Serialize the program image and symbol table
+    pub fn to_binary(&self) -> Vec<u8> {
+        let (origin, words) = self.flat_words(0);
+        let mut data = Vec::with_capacity(12 + words.len() * 2);
+        data.extend_from_slice(&MAGIC);
+        data.extend_from_slice(&origin.to_be_bytes());
+        data.extend_from_slice(&self.entry_point.unwrap_or(NO_ENTRY).to_be_bytes());
+        data.extend_from_slice(&(words.len() as u32).to_be_bytes());
+        for word in &words {
+            data.extend_from_slice(&word.to_be_bytes());
+        }
+
+        // Sorted so the same program always produces the same bytes
+        let symbols: BTreeMap<&String, &u16> = self.symbols.iter().collect();
+        data.extend_from_slice(&(symbols.len() as u32).to_be_bytes());
+        for (name, value) in symbols {
+            data.extend_from_slice(&(name.len() as u16).to_be_bytes());
+            data.extend_from_slice(name.as_bytes());
+            data.extend_from_slice(&value.to_be_bytes());
+        }
+        data
+    }
+
+    /// Parse a program written by `to_binary`
+    ///
+    /// The binary does not record which words are code, data or BSS, so
+    /// the loaded program's footprint counts every word as data.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AssemblerError::InvalidBinary` if the magic number is wrong
+    /// or the data is truncated or malformed.
+    pub fn from_binary(data: &[u8]) -> Result<Self> {
+        let mut reader = Reader { data, pos: 0 };
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err(invalid("not a program binary (bad magic number)"));
+        }
+        let origin = reader.u16()?;
+        let entry_point = Some(reader.u16()?).filter(|&entry| entry != NO_ENTRY);
+
+        let word_count = reader.u32()? as usize;
+        if origin as usize + word_count > 0x10000 {
+            return Err(invalid("program extends past the end of memory"));
+        }
+        let words = (0..word_count)
+            .map(|_| reader.u16())
+            .collect::<Result<Vec<u16>>>()?;
+
+        let symbol_count = reader.u32()?;
+        let mut symbols = HashMap::new();
+        for _ in 0..symbol_count {
+            let length = reader.u16()? as usize;
+            let name = std::str::from_utf8(reader.take(length)?)
+                .map_err(|_| invalid("symbol name is not UTF-8"))?;
+            symbols.insert(name.to_string(), reader.u16()?);
+        }
+        if reader.pos != data.len() {
+            return Err(invalid("trailing bytes after symbol table"));
+        }
+
+        let segments = if words.is_empty() {
+            Vec::new()
+        } else {
+            vec![Segment { origin, words }]
+        };
+        let mut program = AssembledProgram::from_segments(segments, entry_point);
+        program.origin = origin;
+        program.symbols = symbols;
+        Ok(program)
+    }
+}
+
+fn invalid(message: &str) -> AssemblerError {
+    AssemblerError::InvalidBinary(message.to_string())
+}
+
+/// Cursor over the binary, failing on truncation
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, count: usize) -> Result<&'a [u8]> {
+        let bytes = self
+            .data
+            .get(self.pos..self.pos + count)
+            .ok_or_else(|| invalid("unexpected end of data"))?;
+        self.pos += count;
+        Ok(bytes)
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assembler::Assembler;
+
+    fn sample() -> AssembledProgram {
+        Assembler::new()
+            .assemble("        ORG  /0100\nSTART   LD   VAL\n        WAIT\nVAL     DC   5\n        END  START\n")
+            .unwrap()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let program = sample();
+        let loaded = AssembledProgram::from_binary(&program.to_binary()).unwrap();
+
+        assert_eq!(loaded.flat_words(0), program.flat_words(0));
+        assert_eq!(loaded.origin, 0x0100);
+        assert_eq!(loaded.entry_point, Some(0x0100));
+        assert_eq!(loaded.symbols, program.symbols);
+        assert_eq!(loaded.footprint().total_words, 4);
+    }
+
+    #[test]
+    fn test_layout() {
+        let data = sample().to_binary();
+        assert_eq!(&data[..4], b"S130");
+        assert_eq!(&data[4..12], [0x01, 0x00, 0x01, 0x00, 0, 0, 0, 4]);
+    }
+
+    #[test]
+    fn test_rejects_bad_data() {
+        let data = sample().to_binary();
+        assert!(matches!(
+            AssembledProgram::from_binary(b"XXXX"),
+            Err(AssemblerError::InvalidBinary(_))
+        ));
+        assert!(AssembledProgram::from_binary(&data[..data.len() - 1]).is_err());
+
+        let mut extra = data.clone();
+        extra.push(0);
+        assert!(AssembledProgram::from_binary(&extra).is_err());
+    }
+}
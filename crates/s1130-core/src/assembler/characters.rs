@@ -0,0 +1,168 @@
+//! Character Constants
+//!
+//! A quoted `DC` operand packs its text two characters per word:
+//!
+//! ```text
+//!         DC   'AB'        4142 (high byte first)
+//!         DC   'ABC'       4142 4320
+//! ```
+//!
+//! Characters are stored as 8-bit ASCII codes; anything else is an error.
+//! An odd-length string is padded with a blank, and an empty one (`''`) is
+//! a single word of padding. The IBM convention puts the first character
+//! in the high byte; `CharPacking::LowFirst` swaps the bytes for decks that
+//! were punched the other way round. A doubled quote is a literal one:
+//!
+//! ```text
+//!         DC   'IT''S'     4954 2753
+//! ```
+//!
+//! `DMES` messages always use the IBM order and pad with a zero byte.
+
+/// Order of the two characters in a packed word
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CharPacking {
+    /// First character in bits 0-7 (IBM standard)
+    #[default]
+    HighFirst,
+    /// First character in bits 8-15
+    LowFirst,
+}
+
+/// Blank used to fill the last word of an odd-length string
+const PAD: u8 = b' ';
+
+/// Text of a quoted operand (`'TEXT'`) of `DC` or `DMES`, with `''` read
+/// as one quote
+///
+/// Returns `None` if the operand does not start with a quote, and an
+/// error message if the string is not terminated or the closing quote is
+/// followed by anything but whitespace and remarks.
+pub fn quoted_text(operand: &str) -> Option<Result<String, String>> {
+    let mut chars = operand.trim_start().strip_prefix('\'')?.chars();
+    let mut text = String::new();
+    while let Some(c) = chars.next() {
+        if c == '\'' {
+            if chars.as_str().starts_with('\'') {
+                chars.next();
+                text.push(c);
+                continue;
+            }
+            let rest = chars.as_str();
+            if rest.starts_with(|c: char| !c.is_whitespace()) {
+                return Some(Err(format!(
+                    "Unexpected text after quoted string: {}",
+                    rest
+                )));
+            }
+            return Some(Ok(text));
+        }
+        text.push(c);
+    }
+    Some(Err(format!(
+        "Unterminated quoted string: {}",
+        operand.trim()
+    )))
+}
+
+/// Check that `text` is ASCII, the only characters that pack into a byte
+pub fn check_ascii(text: &str) -> Result<(), String> {
+    match text.chars().find(|c| !c.is_ascii()) {
+        Some(c) => Err(format!("Character '{}' is not ASCII", c)),
+        None => Ok(()),
+    }
+}
+
+/// Number of words `text` packs into (at least one)
+pub fn packed_len(text: &str) -> u16 {
+    text.len().div_ceil(2).max(1) as u16
+}
+
+/// Pack `text` two characters per word
+pub fn pack(text: &str, packing: CharPacking) -> Vec<u16> {
+    pack_padded(text, packing, PAD)
+}
+
+/// Pack a `DMES` message: IBM order, zero-padded
+pub fn pack_message(text: &str) -> Vec<u16> {
+    pack_padded(text, CharPacking::HighFirst, 0)
+}
+
+fn pack_padded(text: &str, packing: CharPacking, pad: u8) -> Vec<u16> {
+    if text.is_empty() {
+        return vec![u16::from_be_bytes([pad, pad])];
+    }
+    text.as_bytes()
+        .chunks(2)
+        .map(|pair| {
+            let first = u16::from(pair[0]);
+            let second = u16::from(pair.get(1).copied().unwrap_or(pad));
+            match packing {
+                CharPacking::HighFirst => (first << 8) | second,
+                CharPacking::LowFirst => (second << 8) | first,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pack_orders() {
+        assert_eq!(pack("AB", CharPacking::HighFirst), [0x4142]);
+        assert_eq!(pack("AB", CharPacking::LowFirst), [0x4241]);
+        assert_eq!(CharPacking::default(), CharPacking::HighFirst);
+    }
+
+    #[test]
+    fn test_odd_length_is_padded() {
+        assert_eq!(pack("ABC", CharPacking::HighFirst), [0x4142, 0x4320]);
+        assert_eq!(packed_len("ABC"), 2);
+    }
+
+    #[test]
+    fn test_empty_string_is_one_word() {
+        assert_eq!(pack("", CharPacking::LowFirst), [0x2020]);
+        assert_eq!(pack_message(""), [0x0000]);
+        assert_eq!(packed_len(""), 1);
+    }
+
+    #[test]
+    fn test_quoted_text() {
+        assert_eq!(
+            quoted_text("'HI THERE' greeting"),
+            Some(Ok("HI THERE".to_string()))
+        );
+        assert_eq!(quoted_text("''"), Some(Ok(String::new())));
+        assert!(matches!(quoted_text("'OPEN"), Some(Err(_))));
+        assert_eq!(quoted_text("/0041"), None);
+    }
+
+    #[test]
+    fn test_quoted_text_escapes() {
+        assert_eq!(quoted_text("'IT''S'"), Some(Ok("IT'S".to_string())));
+        assert_eq!(quoted_text("''''"), Some(Ok("'".to_string())));
+        assert!(matches!(quoted_text("'OPEN''"), Some(Err(_))));
+        assert_eq!(pack_message("ABC"), [0x4142, 0x4300]);
+    }
+
+    #[test]
+    fn test_text_after_closing_quote_is_an_error() {
+        assert_eq!(
+            quoted_text("'AB',1"),
+            Some(Err("Unexpected text after quoted string: ,1".to_string()))
+        );
+        assert!(matches!(quoted_text("'A'*X"), Some(Err(_))));
+    }
+
+    #[test]
+    fn test_check_ascii() {
+        assert_eq!(check_ascii("AB 12'"), Ok(()));
+        assert_eq!(
+            check_ascii("caf\u{e9}"),
+            Err("Character '\u{e9}' is not ASCII".to_string())
+        );
+    }
+}
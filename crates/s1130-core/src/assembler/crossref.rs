@@ -0,0 +1,134 @@
+//! Cross-Reference Table
+//!
+//! Built by `Assembler::assemble_with_crossref`. For every symbol it keeps
+//! the defined value and the address of each line whose operand uses the
+//! symbol. `format` lays the table out as in an IBM 1130 listing:
+//!
+//! ```text
+//! SYMBOL  VALUE  REFERENCES
+//! LOOP    0102   0104 0108 010C
+//! VALUE   0110   0100
+//! ```
+//!
+//! Symbols without a value in this module (`EXTRN`) show `----`.
+
+use std::collections::{BTreeSet, HashMap};
+
+/// References listed per row before continuing on the next
+const REFERENCES_PER_ROW: usize = 8;
+
+/// Symbol definitions and the addresses referring to them
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CrossRefTable {
+    /// Value each symbol was defined with
+    pub definitions: HashMap<String, u16>,
+
+    /// Addresses of the lines using each symbol, ascending
+    pub references: HashMap<String, Vec<u16>>,
+}
+
+impl CrossRefTable {
+    /// Collect `(symbol, address)` uses against the symbol table
+    pub(super) fn build(symbols: &HashMap<String, u16>, uses: &[(String, u16)]) -> Self {
+        let mut references: HashMap<String, Vec<u16>> = HashMap::new();
+        for (name, address) in uses {
+            references.entry(name.clone()).or_default().push(*address);
+        }
+        for addresses in references.values_mut() {
+            // A symbol used twice in one operand is one reference
+            addresses.sort_unstable();
+            addresses.dedup();
+        }
+        Self {
+            definitions: symbols.clone(),
+            references,
+        }
+    }
+
+    /// Addresses referring to `name` (empty if it is never used)
+    pub fn references_to(&self, name: &str) -> &[u16] {
+        self.references.get(name).map_or(&[], Vec::as_slice)
+    }
+
+    /// Printable table sorted by symbol name
+    pub fn format(&self) -> String {
+        let names: BTreeSet<&str> = self
+            .definitions
+            .keys()
+            .chain(self.references.keys())
+            .map(String::as_str)
+            .collect();
+        let width = names
+            .iter()
+            .map(|name| name.len())
+            .max()
+            .unwrap_or(0)
+            .max(6);
+
+        let mut table = format!("{:<width$}  VALUE  REFERENCES\n", "SYMBOL", width = width);
+        for name in names {
+            let value = self
+                .definitions
+                .get(name)
+                .map_or_else(|| "----".to_string(), |value| format!("{:04X}", value));
+            let rows: Vec<String> = self
+                .references_to(name)
+                .chunks(REFERENCES_PER_ROW)
+                .map(|chunk| {
+                    chunk
+                        .iter()
+                        .map(|address| format!("{:04X}", address))
+                        .collect::<Vec<_>>()
+                        .join(" ")
+                })
+                .collect();
+
+            let first = rows.first().map_or("", String::as_str);
+            let row = format!("{:<width$}  {}   {}", name, value, first, width = width);
+            table.push_str(row.trim_end());
+            table.push('\n');
+            for continuation in rows.iter().skip(1) {
+                table.push_str(&format!(
+                    "{:width$}         {}\n",
+                    "",
+                    continuation,
+                    width = width
+                ));
+            }
+        }
+        table
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_wraps_long_reference_lists() {
+        let symbols = HashMap::from([("LOOP".to_string(), 0x0102)]);
+        let uses: Vec<(String, u16)> = (0..10).map(|i| ("LOOP".to_string(), 0x0200 + i)).collect();
+        let table = CrossRefTable::build(&symbols, &uses);
+
+        assert_eq!(
+            table.format(),
+            "SYMBOL  VALUE  REFERENCES\n\
+             LOOP    0102   0200 0201 0202 0203 0204 0205 0206 0207\n\
+             \x20              0208 0209\n"
+        );
+    }
+
+    #[test]
+    fn test_undefined_and_unused_symbols() {
+        let symbols = HashMap::from([("UNUSED".to_string(), 0x0010)]);
+        let uses = [("SUB".to_string(), 0x0101), ("SUB".to_string(), 0x0101)];
+        let table = CrossRefTable::build(&symbols, &uses);
+
+        assert_eq!(table.references_to("SUB"), [0x0101]);
+        assert!(table.references_to("UNUSED").is_empty());
+        assert_eq!(
+            table.format(),
+            "SYMBOL  VALUE  REFERENCES\nSUB     ----   0101\nUNUSED  0010\n"
+        );
+    }
+}
@@ -0,0 +1,374 @@
+//! Operand Expression Evaluation
+//!
+//! Evaluates assembler operand expressions such as `BASE+4`, `(END-START)/2`
+//! or `*+4`. Grammar, lowest precedence first:
+//!
+//! ```text
+//! expr    := term (('+' | '-') term)*
+//! term    := unary (('*' | '/') unary)*
+//! unary   := ('-' | '+') unary | primary
+//! primary := number | symbol | '*' | '(' expr ')'
+//! ```
+//!
+//! `*` and `/` are context sensitive: where a value is expected, `*` is the
+//! current location counter and `/` starts a hexadecimal literal (`/0100`);
+//! between two values they multiply and divide. Arithmetic wraps to 16 bits,
+//! so `-1` evaluates to `0xFFFF`. A result outside -32768..=65535 fits
+//! neither a signed nor an unsigned word; it still wraps, and the assembler
+//! warns about it (see `fits_word`). Intermediate results saturate at the
+//! `i32` limits, so an overflow such as `65536*65536` stays out of range
+//! instead of coming back round to a word.
+
+use super::symbols::SymbolTable;
+use super::Result;
+use crate::error::{AssemblerError, SourceSpan};
+use std::collections::HashSet;
+
+/// Evaluate an operand expression
+///
+/// `location_counter` is the value of `*`. Errors are reported with line 0
+/// and, where a token is to blame, its columns counted from the start of
+/// the trimmed expression; the assembler maps them onto the source line.
+pub fn eval_expression(expr: &str, symbols: &SymbolTable, location_counter: u16) -> Result<u16> {
+    evaluate(expr, symbols, location_counter).map(|evaluation| evaluation.value as u16)
+}
+
+/// Value of an expression and the symbols it used
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Evaluation {
+    /// Result before wrapping to 16 bits
+    pub value: i32,
+    /// Symbols referenced, in order of appearance
+    pub symbols: Vec<String>,
+}
+
+/// Evaluate an operand expression without wrapping the result to 16 bits
+pub fn evaluate(expr: &str, symbols: &SymbolTable, location_counter: u16) -> Result<Evaluation> {
+    evaluate_moved(expr, symbols, location_counter, None)
+}
+
+/// How far an expression's value moves when its module moves one word
+///
+/// `*` and the symbols in `relative` (the module's own addresses) move
+/// with the module; literals and other symbols do not. An address such as
+/// `BUF+2` gives 1 and must be relocated, a length such as `END-START`
+/// gives 0. An expression that does not evaluate gives 0.
+pub fn relocation_count(
+    expr: &str,
+    symbols: &SymbolTable,
+    relative: &HashSet<String>,
+    location_counter: u16,
+) -> i32 {
+    let value = |moved| {
+        evaluate_moved(expr, symbols, location_counter, moved).map(|evaluation| evaluation.value)
+    };
+    match (value(None), value(Some(relative))) {
+        (Ok(at), Ok(moved)) => moved.saturating_sub(at),
+        _ => 0,
+    }
+}
+
+/// Evaluate with `*` and the `moved` symbols one word higher
+fn evaluate_moved(
+    expr: &str,
+    symbols: &SymbolTable,
+    location_counter: u16,
+    moved: Option<&HashSet<String>>,
+) -> Result<Evaluation> {
+    let mut parser = ExprParser {
+        chars: expr.trim().chars().collect(),
+        pos: 0,
+        symbols,
+        location_counter,
+        moved,
+        referenced: Vec::new(),
+    };
+
+    let value = parser.expr()?;
+    if parser.pos < parser.chars.len() {
+        return Err(parser.error(format!("Unexpected character in expression: {}", expr)));
+    }
+
+    Ok(Evaluation {
+        value,
+        symbols: parser.referenced,
+    })
+}
+
+/// Check whether `value` is representable as a signed or unsigned word
+pub fn fits_word(value: i32) -> bool {
+    (i32::from(i16::MIN)..=i32::from(u16::MAX)).contains(&value)
+}
+
+struct ExprParser<'a> {
+    chars: Vec<char>,
+    pos: usize,
+    symbols: &'a SymbolTable,
+    location_counter: u16,
+    moved: Option<&'a HashSet<String>>,
+    referenced: Vec<String>,
+}
+
+impl ExprParser<'_> {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    fn error(&self, message: String) -> AssemblerError {
+        AssemblerError::syntax(0, message)
+    }
+
+    /// Error about the token from `start` up to the current position
+    fn token_error(&self, start: usize, message: String) -> AssemblerError {
+        AssemblerError::syntax_at(self.token_span(start), message)
+    }
+
+    fn token_span(&self, start: usize) -> SourceSpan {
+        SourceSpan::new(0, start + 1, self.pos.max(start + 1) + 1)
+    }
+
+    fn expr(&mut self) -> Result<i32> {
+        let mut value = self.term()?;
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                Some('+') => {
+                    self.pos += 1;
+                    value = value.saturating_add(self.term()?);
+                }
+                Some('-') => {
+                    self.pos += 1;
+                    value = value.saturating_sub(self.term()?);
+                }
+                _ => return Ok(value),
+            }
+        }
+    }
+
+    fn term(&mut self) -> Result<i32> {
+        let mut value = self.unary()?;
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                Some('*') => {
+                    self.pos += 1;
+                    value = value.saturating_mul(self.unary()?);
+                }
+                Some('/') => {
+                    self.pos += 1;
+                    let divisor = self.unary()?;
+                    if divisor == 0 {
+                        return Err(self.error("Division by zero in expression".to_string()));
+                    }
+                    value = value.saturating_div(divisor);
+                }
+                _ => return Ok(value),
+            }
+        }
+    }
+
+    fn unary(&mut self) -> Result<i32> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('-') => {
+                self.pos += 1;
+                Ok(self.unary()?.saturating_neg())
+            }
+            Some('+') => {
+                self.pos += 1;
+                self.unary()
+            }
+            _ => self.primary(),
+        }
+    }
+
+    fn primary(&mut self) -> Result<i32> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('*') => {
+                self.pos += 1;
+                let shift = i32::from(self.moved.is_some());
+                Ok(self.location_counter as i32 + shift)
+            }
+            Some('(') => {
+                self.pos += 1;
+                let value = self.expr()?;
+                self.skip_whitespace();
+                if self.peek() != Some(')') {
+                    return Err(self.error("Missing ')' in expression".to_string()));
+                }
+                self.pos += 1;
+                Ok(value)
+            }
+            Some('/') => {
+                let start = self.pos;
+                self.pos += 1;
+                let digits = self.word();
+                if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+                    return Err(self.token_error(
+                        start,
+                        format!("Invalid hex literal (non-hex digits): /{}", digits),
+                    ));
+                }
+                i32::from_str_radix(&digits, 16).map_err(|_| {
+                    self.token_error(start, format!("Invalid hex literal: /{}", digits))
+                })
+            }
+            Some(c) if c.is_ascii_digit() => {
+                let start = self.pos;
+                let literal = self.word();
+                parse_number(&literal)
+                    .ok_or_else(|| self.token_error(start, format!("Invalid number: {}", literal)))
+            }
+            Some(c) if is_symbol_char(c) => {
+                let start = self.pos;
+                let name = self.word();
+                let Some(value) = self.symbols.lookup(&name) else {
+                    return Err(AssemblerError::UndefinedSymbol {
+                        name,
+                        span: self.token_span(start),
+                    });
+                };
+                let shift = i32::from(self.moved.is_some_and(|moved| moved.contains(&name)));
+                self.referenced.push(name);
+                Ok(i32::from(value) + shift)
+            }
+            Some(c) => Err(self.token_error(self.pos, format!("Unexpected '{}' in expression", c))),
+            None => Err(self.error("Missing value in expression".to_string())),
+        }
+    }
+
+    /// Consume a run of symbol characters (names and number literals)
+    fn word(&mut self) -> String {
+        let start = self.pos;
+        while self.peek().is_some_and(is_symbol_char) {
+            self.pos += 1;
+        }
+        self.chars[start..self.pos].iter().collect()
+    }
+}
+
+pub(super) fn is_symbol_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '$' | '#' | '@' | '_')
+}
+
+/// Parse `0xNNNN` hex, `0NNN` octal or decimal
+///
+/// Literals wider than a word are accepted here so they can wrap with a
+/// warning like any other out-of-range value.
+fn parse_number(literal: &str) -> Option<i32> {
+    if let Some(hex) = literal
+        .strip_prefix("0x")
+        .or_else(|| literal.strip_prefix("0X"))
+    {
+        i32::from_str_radix(hex, 16).ok()
+    } else if literal.starts_with('0') && literal.len() > 1 {
+        i32::from_str_radix(&literal[1..], 8).ok()
+    } else {
+        literal.parse().ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn symbols() -> SymbolTable {
+        let mut table = SymbolTable::new();
+        table.define("A", 10).unwrap();
+        table.define("B", 3).unwrap();
+        table
+    }
+
+    fn eval(expr: &str) -> Result<u16> {
+        eval_expression(expr, &symbols(), 0x0100)
+    }
+
+    #[test]
+    fn test_literals() {
+        assert_eq!(eval("42"), Ok(42));
+        assert_eq!(eval("/01F"), Ok(0x1F));
+        assert_eq!(eval("0x1F"), Ok(0x1F));
+        assert_eq!(eval("017"), Ok(15));
+    }
+
+    #[test]
+    fn test_precedence_and_parentheses() {
+        assert_eq!(eval("A+B*2"), Ok(16));
+        assert_eq!(eval("(A+B)*2"), Ok(26));
+        assert_eq!(eval("A/B"), Ok(3));
+        assert_eq!(eval("A - B - 1"), Ok(6));
+    }
+
+    #[test]
+    fn test_unary_minus_wraps() {
+        assert_eq!(eval("-1"), Ok(0xFFFF));
+        assert_eq!(eval("B-A"), Ok(0xFFF9));
+        assert_eq!(eval("-(A)+B"), Ok(0xFFF9));
+    }
+
+    #[test]
+    fn test_out_of_range_values_wrap() {
+        let unwrapped = |expr| evaluate(expr, &symbols(), 0x0100).unwrap().value;
+        assert_eq!(unwrapped("65535+1"), 0x10000);
+        assert!(!fits_word(unwrapped("65535+1")));
+        assert!(!fits_word(unwrapped("-32769")));
+        assert!(fits_word(unwrapped("-32768")) && fits_word(unwrapped("/FFFF")));
+        assert_eq!(eval("70000"), Ok(0x1170));
+        assert!(!fits_word(unwrapped("65536*65536")));
+        assert!(!fits_word(unwrapped("-(65536*65536)-65536*65536")));
+    }
+
+    #[test]
+    fn test_referenced_symbols() {
+        let evaluation = evaluate("(A+B)*2-A", &symbols(), 0).unwrap();
+        assert_eq!(evaluation.symbols, ["A", "B", "A"]);
+        assert!(evaluate("/0A+1", &symbols(), 0).unwrap().symbols.is_empty());
+    }
+
+    #[test]
+    fn test_relocation_count() {
+        let relative: HashSet<String> = ["A".to_string()].into();
+        let count = |expr| relocation_count(expr, &symbols(), &relative, 0x0100);
+        assert_eq!(count("A+B"), 1);
+        assert_eq!(count("*+4"), 1);
+        assert_eq!(count("*-A"), 0);
+        assert_eq!(count("B*2"), 0);
+        assert_eq!(count("2*A"), 2);
+        assert_eq!(count("UNDEFINED"), 0);
+    }
+
+    #[test]
+    fn test_current_address() {
+        assert_eq!(eval("*"), Ok(0x0100));
+        assert_eq!(eval("*+4"), Ok(0x0104));
+        assert_eq!(eval("*-*"), Ok(0));
+        assert_eq!(eval("2**"), Ok(0x0200));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            eval("A+CC"),
+            Err(AssemblerError::UndefinedSymbol {
+                name: "CC".into(),
+                span: SourceSpan::new(0, 3, 5),
+            })
+        );
+        assert!(matches!(
+            eval("A+)"),
+            Err(AssemblerError::SyntaxError { span, .. }) if span == SourceSpan::new(0, 3, 4)
+        ));
+        assert!(eval("A/0").is_err());
+        assert!(eval("(A+B").is_err());
+        assert!(eval("A+").is_err());
+        assert!(eval("/GHIJ").is_err());
+        assert!(eval("A B").is_err());
+    }
+}
@@ -0,0 +1,325 @@
+//! Intel Hex Format
+//!
+//! `AssembledProgram::to_intel_hex` writes a program as Intel Hex text for
+//! tools such as `objcopy` and hex viewers. Intel Hex addresses bytes, so
+//! each word is two bytes, high byte first, at byte address `2 * word
+//! address`:
+//!
+//! ```text
+//! :0802000060000103B000002AB8     data: 4 words at word 0x0100
+//! :0400000500000200F5              start address: entry point * 2
+//! :00000001FF                      end of file
+//! ```
+//!
+//! Data records hold up to 16 words. Memory above word 0x7FFF lies past
+//! byte 0xFFFF, so extended linear address (type 04) records set the upper
+//! 16 bits of the byte address when needed. Reading also accepts extended
+//! segment address (type 02) records. Bytes missing from a word (an odd
+//! byte count) read as zero.
+
+use super::{AssembledProgram, Result, Segment};
+use crate::error::AssemblerError;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+/// Words per data record
+const WORDS_PER_RECORD: usize = 16;
+
+/// Words addressable below each extended linear address
+const WORDS_PER_BANK: usize = 0x8000;
+
+const DATA: u8 = 0x00;
+const END_OF_FILE: u8 = 0x01;
+const EXTENDED_SEGMENT_ADDRESS: u8 = 0x02;
+const EXTENDED_LINEAR_ADDRESS: u8 = 0x04;
+const START_LINEAR_ADDRESS: u8 = 0x05;
+
+impl AssembledProgram {
+    /// Write the program as Intel Hex
+    ///
+    /// Segments are written in order; the entry point, if any, becomes a
+    /// start linear address record. Symbols and linkage are not stored.
+    pub fn to_intel_hex(&self) -> String {
+        let mut hex = String::new();
+        let mut bank = 0u16;
+
+        for segment in &self.segments {
+            let mut offset = 0;
+            while offset < segment.words.len() {
+                let address = segment.origin as usize + offset;
+                // A record must not cross into the next bank
+                let room = WORDS_PER_BANK - address % WORDS_PER_BANK;
+                let count = WORDS_PER_RECORD.min(room).min(segment.words.len() - offset);
+
+                let byte_address = (address * 2) as u32;
+                let upper = (byte_address >> 16) as u16;
+                if upper != bank {
+                    record(&mut hex, EXTENDED_LINEAR_ADDRESS, 0, &upper.to_be_bytes());
+                    bank = upper;
+                }
+
+                let bytes: Vec<u8> = segment.words[offset..offset + count]
+                    .iter()
+                    .flat_map(|word| word.to_be_bytes())
+                    .collect();
+                record(&mut hex, DATA, byte_address as u16, &bytes);
+                offset += count;
+            }
+        }
+
+        if let Some(entry) = self.entry_point {
+            let start = u32::from(entry) * 2;
+            record(&mut hex, START_LINEAR_ADDRESS, 0, &start.to_be_bytes());
+        }
+        record(&mut hex, END_OF_FILE, 0, &[]);
+        hex
+    }
+
+    /// Read a program written in Intel Hex
+    ///
+    /// Runs of consecutive words become segments, lowest address first. The
+    /// footprint counts every word as data, and there are no symbols.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AssemblerError::InvalidBinary` naming the line for a
+    /// malformed record, a checksum mismatch, an unsupported record type,
+    /// an address past word 0xFFFF or a missing end-of-file record.
+    pub fn from_intel_hex(data: &str) -> Result<Self> {
+        let mut bytes: BTreeMap<u32, u8> = BTreeMap::new();
+        let mut base = 0u32;
+        let mut entry_point = None;
+        let mut ended = false;
+
+        for (index, line) in data.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let error = |message: &str| {
+                AssemblerError::InvalidBinary(format!("Intel Hex line {}: {}", index + 1, message))
+            };
+            let (kind, address, payload) = parse_record(line).map_err(error)?;
+
+            match kind {
+                DATA => {
+                    for (offset, &byte) in payload.iter().enumerate() {
+                        let byte_address = base
+                            .checked_add(u32::from(address) + offset as u32)
+                            .ok_or_else(|| error("data runs past the 32-bit address space"))?;
+                        bytes.insert(byte_address, byte);
+                    }
+                }
+                END_OF_FILE => {
+                    ended = true;
+                    break;
+                }
+                EXTENDED_SEGMENT_ADDRESS | EXTENDED_LINEAR_ADDRESS => {
+                    let value: [u8; 2] = payload
+                        .try_into()
+                        .map_err(|_| error("address record needs 2 data bytes"))?;
+                    let value = u32::from(u16::from_be_bytes(value));
+                    base = if kind == EXTENDED_LINEAR_ADDRESS {
+                        value << 16
+                    } else {
+                        value << 4
+                    };
+                }
+                START_LINEAR_ADDRESS => {
+                    let value: [u8; 4] = payload
+                        .try_into()
+                        .map_err(|_| error("start address record needs 4 data bytes"))?;
+                    let start = u32::from_be_bytes(value);
+                    if !start.is_multiple_of(2) || start / 2 > 0xFFFF {
+                        return Err(error("start address is not a word address"));
+                    }
+                    entry_point = Some((start / 2) as u16);
+                }
+                other => {
+                    return Err(error(&format!("unsupported record type {:02X}", other)));
+                }
+            }
+        }
+        if !ended {
+            return Err(AssemblerError::InvalidBinary(
+                "Intel Hex: missing end-of-file record".to_string(),
+            ));
+        }
+
+        let mut words: BTreeMap<u16, u16> = BTreeMap::new();
+        for (byte_address, byte) in bytes {
+            let address = u16::try_from(byte_address / 2).map_err(|_| {
+                AssemblerError::InvalidBinary(format!(
+                    "Intel Hex: byte address {:#X} is past the end of memory",
+                    byte_address
+                ))
+            })?;
+            let word = words.entry(address).or_insert(0);
+            *word |= if byte_address.is_multiple_of(2) {
+                u16::from(byte) << 8
+            } else {
+                u16::from(byte)
+            };
+        }
+
+        let mut segments: Vec<Segment> = Vec::new();
+        for (address, word) in words {
+            match segments.last_mut() {
+                Some(segment)
+                    if segment.origin as usize + segment.words.len() == address as usize =>
+                {
+                    segment.words.push(word);
+                }
+                _ => segments.push(Segment {
+                    origin: address,
+                    words: vec![word],
+                }),
+            }
+        }
+        Ok(AssembledProgram::from_segments(segments, entry_point))
+    }
+}
+
+/// Append one record: `:LLAAAATT<data>CC`
+fn record(hex: &mut String, kind: u8, address: u16, data: &[u8]) {
+    let mut bytes = vec![data.len() as u8];
+    bytes.extend_from_slice(&address.to_be_bytes());
+    bytes.push(kind);
+    bytes.extend_from_slice(data);
+    bytes.push(checksum(&bytes));
+
+    hex.push(':');
+    for byte in bytes {
+        let _ = write!(hex, "{:02X}", byte);
+    }
+    hex.push('\n');
+}
+
+/// Two's complement of the byte sum, so a record's bytes sum to zero
+fn checksum(bytes: &[u8]) -> u8 {
+    bytes
+        .iter()
+        .fold(0u8, |sum, &byte| sum.wrapping_add(byte))
+        .wrapping_neg()
+}
+
+/// Split a record line into type, address and data, checking its length
+/// and checksum
+fn parse_record(line: &str) -> std::result::Result<(u8, u16, Vec<u8>), &'static str> {
+    let digits = line
+        .strip_prefix(':')
+        .ok_or("record does not start with ':'")?;
+    if !digits.len().is_multiple_of(2) || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err("record is not whole hex bytes");
+    }
+    let bytes: Vec<u8> = (0..digits.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&digits[i..i + 2], 16).expect("checked hex digits"))
+        .collect();
+
+    let [length, address_high, address_low, kind, ..] = bytes[..] else {
+        return Err("record is too short");
+    };
+    if bytes.len() != length as usize + 5 {
+        return Err("record length does not match its byte count");
+    }
+    if checksum(&bytes) != 0 {
+        return Err("checksum mismatch");
+    }
+    let address = u16::from_be_bytes([address_high, address_low]);
+    Ok((kind, address, bytes[4..bytes.len() - 1].to_vec()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assembler::Assembler;
+
+    #[test]
+    fn test_round_trip_non_contiguous_segments() {
+        let source = "
+        ORG  /0100
+START   LD   VALUE
+        WAIT
+VALUE   DC   42
+        ORG  /0200
+TABLE   BSS  20
+        END  START
+";
+        let program = Assembler::new().assemble(source).unwrap();
+        let hex = program.to_intel_hex();
+
+        // 4 words, then 20 words over two records, start address, EOF
+        assert_eq!(hex.lines().count(), 5);
+        assert!(hex.starts_with(":0802000060000103B000002AB8\n"), "{}", hex);
+        assert!(
+            hex.ends_with(":0400000500000200F5\n:00000001FF\n"),
+            "{}",
+            hex
+        );
+
+        let loaded = AssembledProgram::from_intel_hex(&hex).unwrap();
+        assert_eq!(loaded.segments, program.segments);
+        assert_eq!(loaded.entry_point, Some(0x0100));
+        assert_eq!(loaded.origin, 0x0100);
+    }
+
+    #[test]
+    fn test_round_trip_at_address_zero_and_high_memory() {
+        let program = AssembledProgram::from_segments(
+            vec![
+                Segment {
+                    origin: 0x0000,
+                    words: vec![0xB000, 0x0001],
+                },
+                Segment {
+                    origin: 0x7FFE,
+                    words: (0..20).collect(),
+                },
+            ],
+            None,
+        );
+        let hex = program.to_intel_hex();
+
+        // The second segment crosses byte 0x10000: 2 words, then bank 1
+        assert!(hex.contains("\n:020000040001F9\n"), "{}", hex);
+        let loaded = AssembledProgram::from_intel_hex(&hex).unwrap();
+        assert_eq!(loaded.segments, program.segments);
+        assert_eq!(loaded.entry_point, None);
+    }
+
+    #[test]
+    fn test_odd_byte_count_pads_last_word() {
+        // Three bytes at byte 0x0200: word 0x0100 = 0x1234, 0x0101 = 0x5600
+        let loaded = AssembledProgram::from_intel_hex(":030200001234565F\n:00000001FF\n").unwrap();
+        assert_eq!(
+            loaded.segments,
+            [Segment {
+                origin: 0x0100,
+                words: vec![0x1234, 0x5600],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_invalid_records() {
+        let cases = [
+            (":0100000000FE\n:00000001FF\n", "checksum"),
+            (":00000001FF", "ok"),
+            ("0000000100\n", "start with"),
+            (":020000000000\n:00000001FF\n", "length"),
+            (":00000003FD\n:00000001FF\n", "record type 03"),
+            (":0000000000\n", "missing end-of-file"),
+            (
+                ":02000004FFFFFC\n:02FFFF001234BA\n:00000001FF\n",
+                "32-bit address space",
+            ),
+        ];
+        for (hex, expected) in cases {
+            match AssembledProgram::from_intel_hex(hex) {
+                Ok(_) => assert_eq!(expected, "ok", "{:?}", hex),
+                Err(err) => assert!(err.to_string().contains(expected), "{:?}: {}", hex, err),
+            }
+        }
+    }
+}
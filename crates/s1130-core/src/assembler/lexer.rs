@@ -1,8 +1,13 @@
 //! Lexer for IBM 1130 Assembly Language
 //!
 //! Tokenizes assembly source code into a stream of tokens.
+//!
+//! A `/` immediately followed by a word made only of hex digits is an
+//! IBM-style hex literal (`/0100`, `/FFFF`) and lexes as a single
+//! `Token::Number`. Any other `/`, as in `/PTR` or `/ LABEL`, lexes as
+//! `Token::Slash` (indirect addressing).
 
-use crate::error::AssemblerError;
+use crate::error::{AssemblerError, SourceSpan};
 
 /// Result type for assembler operations
 pub type Result<T> = std::result::Result<T, AssemblerError>;
@@ -19,7 +24,7 @@ pub enum Token {
     /// Pseudo-op (ORG, DC, BSS, END, EQU)
     PseudoOp(String),
 
-    /// Numeric literal (decimal, hex, or octal)
+    /// Numeric literal (decimal, `/` or `0x` hex, or octal)
     Number(u16),
 
     /// Symbol/identifier
@@ -52,7 +57,7 @@ pub struct Lexer {
     /// Current line number
     line: usize,
 
-    /// Column in line
+    /// Characters consumed so far on the current line
     column: usize,
 }
 
@@ -77,6 +82,16 @@ impl Lexer {
         self.source.get(self.position + 1).copied()
     }
 
+    /// Check whether the word right after a `/` at the current position is all hex digits
+    fn slash_starts_hex_literal(&self) -> bool {
+        let word: Vec<char> = self.source[self.position + 1..]
+            .iter()
+            .copied()
+            .take_while(|ch| ch.is_alphanumeric() || *ch == '_')
+            .collect();
+        !word.is_empty() && word.iter().all(char::is_ascii_hexdigit)
+    }
+
     /// Consume and return current character
     fn advance(&mut self) -> Option<char> {
         let ch = self.peek()?;
@@ -126,29 +141,42 @@ impl Lexer {
         result
     }
 
+    /// Span from the 1-indexed `column_start` to the current position
+    fn span_from(&self, column_start: usize) -> SourceSpan {
+        SourceSpan::new(self.line, column_start, self.column + 1)
+    }
+
+    /// Read hex digits following an already-consumed `prefix`
+    fn read_hex(&mut self, prefix: &str) -> Result<u16> {
+        let start_column = self.column + 1 - prefix.chars().count();
+
+        let mut hex_str = String::new();
+        while let Some(ch) = self.peek() {
+            if ch.is_ascii_hexdigit() {
+                hex_str.push(ch);
+                self.advance();
+            } else {
+                break;
+            }
+        }
+
+        u16::from_str_radix(&hex_str, 16).map_err(|_| {
+            AssemblerError::syntax_at(
+                self.span_from(start_column),
+                format!("Invalid hexadecimal number: {}{}", prefix, hex_str),
+            )
+        })
+    }
+
     /// Read a number (decimal, hex, or octal)
     fn read_number(&mut self) -> Result<u16> {
-        let start_line = self.line;
+        let start_column = self.column + 1;
 
         // Check for hex (0x prefix)
         if self.peek() == Some('0') && matches!(self.peek_next(), Some('x') | Some('X')) {
             self.advance(); // consume '0'
             self.advance(); // consume 'x'
-
-            let mut hex_str = String::new();
-            while let Some(ch) = self.peek() {
-                if ch.is_ascii_hexdigit() {
-                    hex_str.push(ch);
-                    self.advance();
-                } else {
-                    break;
-                }
-            }
-
-            return u16::from_str_radix(&hex_str, 16).map_err(|_| AssemblerError::SyntaxError {
-                line: start_line,
-                message: format!("Invalid hexadecimal number: 0x{}", hex_str),
-            });
+            return self.read_hex("0x");
         }
 
         // Read decimal or octal
@@ -170,18 +198,20 @@ impl Lexer {
 
         if is_octal && num_str.len() > 1 {
             // Octal number (leading zero)
-            u16::from_str_radix(&num_str[1..], 8).map_err(|_| AssemblerError::SyntaxError {
-                line: start_line,
-                message: format!("Invalid octal number: {}", num_str),
+            u16::from_str_radix(&num_str[1..], 8).map_err(|_| {
+                AssemblerError::syntax_at(
+                    self.span_from(start_column),
+                    format!("Invalid octal number: {}", num_str),
+                )
             })
         } else {
             // Decimal number
-            num_str
-                .parse::<u16>()
-                .map_err(|_| AssemblerError::SyntaxError {
-                    line: start_line,
-                    message: format!("Invalid decimal number: {}", num_str),
-                })
+            num_str.parse::<u16>().map_err(|_| {
+                AssemblerError::syntax_at(
+                    self.span_from(start_column),
+                    format!("Invalid decimal number: {}", num_str),
+                )
+            })
         }
     }
 
@@ -212,6 +242,11 @@ impl Lexer {
                 Ok(Token::Asterisk)
             }
 
+            Some('/') if self.slash_starts_hex_literal() => {
+                self.advance();
+                Ok(Token::Number(self.read_hex("/")?))
+            }
+
             Some('/') => {
                 self.advance();
                 Ok(Token::Slash)
@@ -233,9 +268,15 @@ impl Lexer {
                 // Check if it's a pseudo-op
                 if ident.eq_ignore_ascii_case("ORG")
                     || ident.eq_ignore_ascii_case("DC")
+                    || ident.eq_ignore_ascii_case("DMES")
                     || ident.eq_ignore_ascii_case("BSS")
                     || ident.eq_ignore_ascii_case("END")
                     || ident.eq_ignore_ascii_case("EQU")
+                    || ident.eq_ignore_ascii_case("MACRO")
+                    || ident.eq_ignore_ascii_case("MEND")
+                    || ident.eq_ignore_ascii_case("MEXIT")
+                    || ident.eq_ignore_ascii_case("TITLE")
+                    || ident.eq_ignore_ascii_case("HDNG")
                 {
                     Ok(Token::PseudoOp(ident.to_uppercase()))
                 }
@@ -249,10 +290,10 @@ impl Lexer {
                 }
             }
 
-            Some(ch) => Err(AssemblerError::SyntaxError {
-                line: self.line,
-                message: format!("Unexpected character: '{}'", ch),
-            }),
+            Some(ch) => Err(AssemblerError::syntax_at(
+                SourceSpan::new(self.line, self.column + 1, self.column + 2),
+                format!("Unexpected character: '{}'", ch),
+            )),
         }
     }
 
@@ -366,13 +407,50 @@ mod tests {
         assert_eq!(lexer.next_token().unwrap(), Token::Number(1));
     }
 
+    #[test]
+    fn test_tokenize_slash_hex_literal() {
+        let mut lexer = Lexer::new("/100 /FFFF");
+
+        assert_eq!(lexer.next_token().unwrap(), Token::Number(0x100));
+        assert_eq!(lexer.next_token().unwrap(), Token::Number(0xFFFF));
+        assert_eq!(lexer.next_token().unwrap(), Token::Eof);
+    }
+
     #[test]
     fn test_tokenize_indirect() {
-        let source = "/100";
-        let mut lexer = Lexer::new(source);
+        let mut lexer = Lexer::new("/ LABEL");
 
         assert_eq!(lexer.next_token().unwrap(), Token::Slash);
-        assert_eq!(lexer.next_token().unwrap(), Token::Number(100));
+        assert_eq!(
+            lexer.next_token().unwrap(),
+            Token::Identifier("LABEL".to_string())
+        );
+    }
+
+    #[test]
+    fn test_tokenize_slash_before_symbol() {
+        let mut lexer = Lexer::new("/BUF");
+
+        assert_eq!(lexer.next_token().unwrap(), Token::Slash);
+        assert_eq!(
+            lexer.next_token().unwrap(),
+            Token::Identifier("BUF".to_string())
+        );
+    }
+
+    #[test]
+    fn test_tokenize_slash_hex_overflow() {
+        let mut lexer = Lexer::new("/10000");
+        assert!(lexer.next_token().is_err());
+    }
+
+    #[test]
+    fn test_error_spans() {
+        let err = Lexer::new("LD 100\nDC /10000").tokenize().unwrap_err();
+        assert_eq!(err.span(), Some(SourceSpan::new(2, 4, 10)));
+
+        let err = Lexer::new("  LD ~").tokenize().unwrap_err();
+        assert_eq!(err.span(), Some(SourceSpan::new(1, 6, 7)));
     }
 
     #[test]
@@ -0,0 +1,479 @@
+//! Module Linkage Metadata
+//!
+//! `ENT` and `EXTRN` describe how a module connects to others:
+//!
+//! ```text
+//!         ENT   START      START may be called from other modules
+//!         EXTRN SUB        SUB is defined in another module
+//! START   BSI   SUB        displacement word assembles as 0, fixup recorded
+//! ```
+//!
+//! An external symbol assembles as 0 and every word referring to it is
+//! recorded as an `ExternalReference` for a linker to patch. References
+//! must name the symbol alone (optionally indirect or indexed) in a
+//! long-format instruction or a `DC`.
+//!
+//! `LIBF` and `CALL` call a subprogram by name, assembling as a `BSI`:
+//!
+//! ```text
+//!         CALL  SUB        BSI  SUB      4800 <SUB>
+//!         LIBF  SQRT       BSI  *SQRT    4820 <SQRT>
+//! ```
+//!
+//! `CALL` branches to the subprogram itself. `LIBF` branches indirectly
+//! through a transfer vector word, so `SQRT` labels a word holding the
+//! routine's address, as the loader's LIBF table did. A name not defined
+//! in the file is external, as if declared with `EXTRN`, and its word is
+//! recorded like any other external reference.
+//!
+//! `DSA` is not supported. In the Disk Monitor System it gives the disk
+//! sector address of a stored program, and there is no disk-resident
+//! program store here to resolve one against, so it is rejected as an
+//! unknown operation.
+//!
+//! `link` combines modules into one program, patching each module's
+//! external references with the addresses of other modules' entries.
+//! A module with an `ORG` stays at the addresses it gave; a module without
+//! one is moved to follow the modules before it. Moving a module adjusts
+//! every word the assembler recorded as holding one of its own addresses:
+//! long-format address words, `DC` and `IOCC` addresses and `CALL`/`LIBF`
+//! targets. Short-format displacements are relative to the instruction
+//! and move with it. Modules must not overlap.
+
+use super::characters::quoted_text;
+use super::expression::{is_symbol_char, relocation_count};
+use super::parser::{Operation, ParsedLine};
+use super::{split_indirect, AssembledProgram, Assembler, MemoryFootprint, Result, Segment};
+use crate::error::AssemblerError;
+use std::collections::HashMap;
+
+/// A word that must be set to the address of an external symbol
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExternalReference {
+    /// The external symbol
+    pub symbol: String,
+    /// Address of the word to patch
+    pub address: u16,
+}
+
+/// Split an `ENT`/`EXTRN` operand into symbol names
+pub(super) fn symbol_list(operand: Option<&str>) -> Vec<&str> {
+    operand
+        .map(|list| {
+            list.split(',')
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Check whether `op` is a subprogram call pseudo-op (`LIBF` or `CALL`)
+pub(super) fn is_call(op: &str) -> bool {
+    op == "LIBF" || op == "CALL"
+}
+
+/// The subprogram name of a `LIBF`/`CALL` operand
+///
+/// Fails unless the operand is a single symbol.
+pub(super) fn called_name<'a>(
+    op: &str,
+    operand: Option<&'a str>,
+    line_num: usize,
+) -> Result<&'a str> {
+    let name = operand.map(str::trim).unwrap_or_default();
+    let is_symbol =
+        name.starts_with(|c: char| c.is_ascii_alphabetic()) && name.chars().all(is_symbol_char);
+    if !is_symbol {
+        return Err(AssemblerError::syntax(
+            line_num + 1,
+            format!("{} requires a subprogram name", op),
+        ));
+    }
+    Ok(name)
+}
+
+/// Link assembled modules into one program
+///
+/// Relocatable modules (no `ORG`) are moved to follow the module before
+/// them, then every `EXTRN` reference is set to the address of the
+/// matching `ENT` symbol in another module. The result keeps all segments
+/// in module order, the first module's origin, title and entry point, and
+/// only the entry symbols: labels that were not exported stay private to
+/// their module. Errors on an entry defined twice (`DuplicateLabel`), a
+/// reference with no entry (`UndefinedSymbol`) and overlapping modules
+/// (`InvalidAddress` of the first shared word).
+pub fn link(modules: &[AssembledProgram]) -> Result<AssembledProgram> {
+    let modules = place(modules)?;
+
+    let mut entries: Vec<String> = Vec::new();
+    let mut symbols: HashMap<String, u16> = HashMap::new();
+    for module in &modules {
+        for name in &module.entries {
+            let address = *module
+                .symbols
+                .get(name)
+                .ok_or_else(|| AssemblerError::undefined_symbol(name.clone()))?;
+            if symbols.insert(name.clone(), address).is_some() {
+                return Err(AssemblerError::duplicate_label(name.clone()));
+            }
+            entries.push(name.clone());
+        }
+    }
+
+    let mut segments: Vec<Segment> = modules
+        .iter()
+        .flat_map(|module| module.segments.iter().cloned())
+        .collect();
+    check_overlap(&segments)?;
+
+    for reference in modules.iter().flat_map(|module| &module.external_refs) {
+        let address = *symbols
+            .get(&reference.symbol)
+            .ok_or_else(|| AssemblerError::undefined_symbol(reference.symbol.clone()))?;
+        patch(&mut segments, reference.address, |_| address)?;
+    }
+
+    let first = modules.first();
+    Ok(AssembledProgram {
+        segments,
+        origin: first.map_or(0, |module| module.origin),
+        symbols,
+        entry_point: modules.iter().find_map(|module| module.entry_point),
+        listing_title: first.and_then(|module| module.listing_title.clone()),
+        entries,
+        external_refs: Vec::new(),
+        relocatable: false,
+        relocations: Vec::new(),
+        footprint: combined_footprint(&modules),
+    })
+}
+
+/// The modules at their final addresses
+///
+/// Each relocatable module after the first starts at the word following
+/// the highest one used by the modules before it.
+fn place(modules: &[AssembledProgram]) -> Result<Vec<AssembledProgram>> {
+    let mut placed: Vec<AssembledProgram> = Vec::with_capacity(modules.len());
+    for module in modules {
+        let end = combined_footprint(&placed);
+        let module = if module.relocatable && end.total_words > 0 {
+            let start = end
+                .last_address
+                .checked_add(1)
+                .ok_or(AssemblerError::InvalidAddress(end.last_address))?;
+            relocate(module, start.wrapping_sub(module.footprint().origin))?
+        } else {
+            module.clone()
+        };
+        placed.push(module);
+    }
+    Ok(placed)
+}
+
+/// Move `module` up by `offset` words
+fn relocate(module: &AssembledProgram, offset: u16) -> Result<AssembledProgram> {
+    let moved = |address: u16| address.wrapping_add(offset);
+    let mut module = module.clone();
+
+    // Words are adjusted at their assembled addresses, before moving
+    for &address in &module.relocations {
+        patch(&mut module.segments, address, moved)?;
+    }
+    for segment in &mut module.segments {
+        segment.origin = moved(segment.origin);
+    }
+    for address in module.symbols.values_mut() {
+        *address = moved(*address);
+    }
+    for reference in &mut module.external_refs {
+        reference.address = moved(reference.address);
+    }
+    for address in &mut module.relocations {
+        *address = moved(*address);
+    }
+    module.origin = moved(module.origin);
+    module.entry_point = module.entry_point.map(moved);
+    module.footprint.origin = moved(module.footprint.origin);
+    module.footprint.last_address = moved(module.footprint.last_address);
+    Ok(module)
+}
+
+/// Reject segments that share an address
+fn check_overlap(segments: &[Segment]) -> Result<()> {
+    let mut ranges: Vec<(usize, usize)> = segments
+        .iter()
+        .filter(|segment| !segment.words.is_empty())
+        .map(|segment| {
+            let start = segment.origin as usize;
+            (start, start + segment.words.len())
+        })
+        .collect();
+    ranges.sort_unstable();
+    for pair in ranges.windows(2) {
+        let ((_, end), (next_start, _)) = (pair[0], pair[1]);
+        if next_start < end {
+            return Err(AssemblerError::InvalidAddress(next_start as u16));
+        }
+    }
+    Ok(())
+}
+
+/// Replace the word at `address`, in whichever segment holds it, with
+/// `value` of the old word
+fn patch(segments: &mut [Segment], address: u16, value: impl FnOnce(u16) -> u16) -> Result<()> {
+    let word = segments
+        .iter_mut()
+        .find_map(|segment| {
+            let offset = address.checked_sub(segment.origin)? as usize;
+            segment.words.get_mut(offset)
+        })
+        .ok_or(AssemblerError::InvalidAddress(address))?;
+    *word = value(*word);
+    Ok(())
+}
+
+/// Sum of the modules' footprints
+fn combined_footprint(modules: &[AssembledProgram]) -> MemoryFootprint {
+    let mut footprints = modules
+        .iter()
+        .map(AssembledProgram::footprint)
+        .filter(|footprint| footprint.total_words > 0);
+    let Some(first) = footprints.next() else {
+        let origin = modules.first().map_or(0, |module| module.origin);
+        return MemoryFootprint {
+            origin,
+            last_address: origin,
+            ..MemoryFootprint::default()
+        };
+    };
+    footprints.fold(first, |total, footprint| MemoryFootprint {
+        origin: total.origin.min(footprint.origin),
+        last_address: total.last_address.max(footprint.last_address),
+        total_words: total.total_words + footprint.total_words,
+        code_words: total.code_words + footprint.code_words,
+        data_words: total.data_words + footprint.data_words,
+        bss_words: total.bss_words + footprint.bss_words,
+    })
+}
+
+impl Assembler {
+    /// Record `EXTRN` symbols, defining each as a zero placeholder
+    pub(super) fn declare_externals(
+        &mut self,
+        operand: Option<&str>,
+        line_num: usize,
+    ) -> Result<()> {
+        let names = symbol_list(operand);
+        if names.is_empty() {
+            return Err(AssemblerError::syntax(
+                line_num + 1,
+                "EXTRN requires at least one symbol".to_string(),
+            ));
+        }
+        for name in names {
+            let span = self.expression_span(line_num + 1, name, None);
+            self.define_symbol_at(name, 0, span)?;
+            self.externals.push(name.to_string());
+        }
+        Ok(())
+    }
+
+    /// Make every `LIBF`/`CALL` name not defined by the end of pass 1
+    /// external
+    pub(super) fn declare_called_externals(&mut self, lines: &[ParsedLine]) -> Result<()> {
+        for line in lines {
+            let Operation::PseudoOp(op) = &line.operation else {
+                continue;
+            };
+            if !is_call(op) {
+                continue;
+            }
+            let line_num = line.line_number - 1;
+            let name = called_name(op, line.operand.as_deref(), line_num)?;
+            if self.symbols.lookup(name).is_none() {
+                self.define_symbol(name, 0, line_num)?;
+                self.externals.push(name.to_string());
+            }
+        }
+        Ok(())
+    }
+
+    /// Check that every `ENT` symbol is defined in this module
+    pub(super) fn check_entries(&self) -> Result<()> {
+        for (name, span) in &self.entries {
+            if self.symbols.lookup(name).is_none() || self.is_external(name) {
+                return Err(AssemblerError::UndefinedSymbol {
+                    name: name.clone(),
+                    span: *span,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    pub(super) fn is_external(&self, name: &str) -> bool {
+        self.externals.iter().any(|external| external == name)
+    }
+
+    /// Record a fixup if the line at `address` refers to an external symbol
+    ///
+    /// `words` is what the line assembled to.
+    pub(super) fn note_external_reference(
+        &mut self,
+        operation: &Operation,
+        operand: Option<&str>,
+        address: u16,
+        words: &[u16],
+        line_num: usize,
+    ) -> Result<()> {
+        let Some(operand) = operand else {
+            return Ok(());
+        };
+        if quoted_text(operand).is_some() {
+            return Ok(());
+        }
+        let Some(symbol) = self.external_in(operand) else {
+            return Ok(());
+        };
+        let error = |message: String| Err(AssemblerError::syntax(line_num + 1, message));
+
+        let (address_part, word) = match operation {
+            Operation::PseudoOp(op) if op == "ENT" || op == "EXTRN" => return Ok(()),
+            Operation::PseudoOp(op) if op == "IOCC" => {
+                return error(format!("External symbol {} cannot be used here", symbol))
+            }
+            _ => match self.address_word(operation, operand, address, words) {
+                Some(found) => found,
+                None if matches!(operation, Operation::Instruction(_)) => {
+                    return error(format!(
+                        "External symbol {} needs a long-format instruction",
+                        symbol
+                    ))
+                }
+                None => return error(format!("External symbol {} cannot be used here", symbol)),
+            },
+        };
+
+        if address_part != symbol {
+            return error(format!(
+                "External symbol {} must be referenced on its own",
+                symbol
+            ));
+        }
+        self.external_refs.push(ExternalReference {
+            symbol,
+            address: word,
+        });
+        Ok(())
+    }
+
+    /// Record the word the line at `address` assembled, if it holds an
+    /// address in this program
+    pub(super) fn note_relocation(
+        &mut self,
+        operation: &Operation,
+        operand: Option<&str>,
+        address: u16,
+        words: &[u16],
+    ) {
+        let Some(operand) = operand.filter(|operand| quoted_text(operand).is_none()) else {
+            return;
+        };
+        let Some((address_part, word)) = self.address_word(operation, operand, address, words)
+        else {
+            return;
+        };
+        let count = relocation_count(address_part, &self.symbols, &self.relative_symbols, address);
+        if count == 1 {
+            self.relocations.push(word);
+        }
+    }
+
+    /// The address expression of a line and the word it assembled to, for
+    /// lines with one: long-format instructions, `DC`, `IOCC` (its WCA)
+    /// and `CALL`/`LIBF`
+    ///
+    /// An indirect marker is removed from the expression.
+    fn address_word<'a>(
+        &self,
+        operation: &Operation,
+        operand: &'a str,
+        address: u16,
+        words: &[u16],
+    ) -> Option<(&'a str, u16)> {
+        let (address_part, word) = match operation {
+            Operation::PseudoOp(op) if op == "DC" => (operand, address),
+            Operation::PseudoOp(op) if op == "IOCC" => {
+                (operand.split(',').next().unwrap_or(operand), address)
+            }
+            Operation::PseudoOp(op) if is_call(op) => (operand, address.wrapping_add(1)),
+            Operation::Instruction(mnemonic) if words.len() == 2 => {
+                let address_part = match mnemonic.as_str() {
+                    // Index instructions take "tag,address"
+                    "LDX" | "STX" | "MDX" => operand.split_once(',').map_or(operand, |(_, a)| a),
+                    _ => operand.rsplit_once(',').map_or(operand, |(a, _)| a),
+                };
+                (address_part, address.wrapping_add(1))
+            }
+            _ => return None,
+        };
+        let (_, address_part) = split_indirect(address_part.trim());
+        Some((address_part.trim(), word))
+    }
+
+    /// First external symbol named in `operand`, if any
+    fn external_in(&self, operand: &str) -> Option<String> {
+        operand
+            .split(|c: char| !is_symbol_char(c))
+            .find(|word| self.is_external(word))
+            .map(str::to_string)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_symbol_list() {
+        assert_eq!(symbol_list(Some("SUB, ADD1")), ["SUB", "ADD1"]);
+        assert!(symbol_list(None).is_empty());
+    }
+
+    #[test]
+    fn test_overlapping_modules_are_rejected() {
+        let a = Assembler::new()
+            .assemble("        ORG  /0100\n        LD   /0100\n")
+            .unwrap();
+        let b = Assembler::new()
+            .assemble("        ORG  /0101\n        WAIT\n")
+            .unwrap();
+
+        assert!(matches!(
+            link(&[a, b]),
+            Err(AssemblerError::InvalidAddress(0x0101))
+        ));
+    }
+
+    #[test]
+    fn test_duplicate_and_unresolved_entries() {
+        let entry = "        ENT  SUB\nSUB     WAIT\n";
+        let a = Assembler::new().assemble(entry).unwrap();
+        let b = Assembler::new()
+            .assemble(&format!("        ORG  /0010\n{}", entry))
+            .unwrap();
+        assert!(matches!(
+            link(&[a, b]),
+            Err(AssemblerError::DuplicateLabel { name, .. }) if name == "SUB"
+        ));
+
+        let caller = Assembler::new()
+            .assemble("        EXTRN SUB\n        BSI  SUB\n")
+            .unwrap();
+        assert!(matches!(
+            link(&[caller]),
+            Err(AssemblerError::UndefinedSymbol { name, .. }) if name == "SUB"
+        ));
+    }
+}
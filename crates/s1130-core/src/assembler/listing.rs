@@ -0,0 +1,95 @@
+//! Assembly Listing
+//!
+//! Formats the listing returned by `Assembler::assemble_with_listing`. Each
+//! source line is preceded by its address and up to two generated words:
+//!
+//! ```text
+//! 0100  6000 0108   LD   VALUE
+//! ```
+//!
+//! Lines that generate no code keep the columns blank. `BSS` blocks show only
+//! their address, since the reserved words are not meaningful. A symbol table
+//! sorted by name follows the source, and a `TITLE`/`HDNG` heading, if any,
+//! precedes it.
+
+use super::parser::{Operation, ParsedLine};
+use super::{AssembledProgram, GeneratedLine};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Width of the address and words columns, including the gap before the source
+const CODE_COLUMNS: usize = 18;
+
+/// Build the listing text
+pub(super) fn format_listing(
+    source: &str,
+    lines: &[ParsedLine],
+    generated: &[GeneratedLine],
+    program: &AssembledProgram,
+) -> String {
+    // A macro invocation generates several lines under one source line number
+    let mut by_line: HashMap<usize, (u16, Vec<u16>)> = HashMap::new();
+    for line in generated {
+        by_line
+            .entry(line.line_number)
+            .or_insert_with(|| (line.address, Vec::new()))
+            .1
+            .extend_from_slice(&line.words);
+    }
+    let reserves: HashSet<usize> = lines
+        .iter()
+        .filter(|line| matches!(&line.operation, Operation::PseudoOp(op) if op == "BSS"))
+        .map(|line| line.line_number)
+        .collect();
+
+    let mut listing = String::new();
+    if let Some(title) = &program.listing_title {
+        listing.push_str(title);
+        listing.push_str("\n\n");
+    }
+    for (index, text) in source.lines().enumerate() {
+        let line_number = index + 1;
+        let code = match by_line.get(&line_number) {
+            Some((address, _)) if reserves.contains(&line_number) => format!("{:04X}", address),
+            Some((address, words)) if !words.is_empty() => {
+                let words: Vec<String> = words
+                    .iter()
+                    .take(2)
+                    .map(|word| format!("{:04X}", word))
+                    .collect();
+                format!("{:04X}  {}", address, words.join(" "))
+            }
+            _ => String::new(),
+        };
+
+        let row = format!("{:<width$}{}", code, text, width = CODE_COLUMNS);
+        listing.push_str(row.trim_end());
+        listing.push('\n');
+    }
+
+    listing.push_str(&format_symbol_table(lines, &program.symbols));
+    listing
+}
+
+/// Symbol table section: name, value and how the symbol was defined
+fn format_symbol_table(lines: &[ParsedLine], symbols: &HashMap<String, u16>) -> String {
+    let equates: HashSet<&str> = lines
+        .iter()
+        .filter(|line| matches!(&line.operation, Operation::PseudoOp(op) if op == "EQU"))
+        .filter_map(|line| line.label.as_deref())
+        .collect();
+    let sorted: BTreeMap<&str, u16> = symbols
+        .iter()
+        .map(|(name, &value)| (name.as_str(), value))
+        .collect();
+
+    let mut table = String::from("\nSYMBOL TABLE\n\nNAME      VALUE  TYPE\n");
+    for (name, value) in sorted {
+        let kind = if equates.contains(name) {
+            "EQU"
+        } else {
+            "LABEL"
+        };
+        table.push_str(&format!("{:<8}  {:04X}   {}\n", name, value, kind));
+    }
+    table
+}
@@ -0,0 +1,283 @@
+//! Macro Definitions
+//!
+//! Macros are expanded textually before pass 1. A definition runs from
+//! `NAME MACRO P1,P2` to `MEND`:
+//!
+//! ```text
+//! ADDTO   MACRO SRC,DST
+//!         LD    DST
+//!         A     SRC
+//!         STO   DST
+//!         MEND
+//!
+//!         ADDTO ONE,TOTAL
+//! ```
+//!
+//! On expansion each parameter symbol in the body is replaced by the
+//! corresponding argument (missing arguments are empty). Body symbols
+//! starting with `L$` are local: every expansion renames `L$X` to `L$001X`,
+//! `L$002X`, ... so a macro can be used more than once. `MEXIT` ends the
+//! current expansion early. A label on the invocation line labels the
+//! first expanded word. Expanded lines keep the invocation's line number.
+
+use super::parser;
+use super::{Assembler, Result};
+use crate::error::AssemblerError;
+
+/// Deepest allowed chain of macros expanding other macros
+const MAX_EXPANSION_DEPTH: usize = 32;
+
+/// Prefix marking a body symbol as local to one expansion
+const LOCAL_PREFIX: &str = "L$";
+
+/// A macro definition
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MacroDef {
+    /// Macro name (the label of the `MACRO` line)
+    pub name: String,
+
+    /// Parameter names, in positional order
+    pub params: Vec<String>,
+
+    /// Body source lines between `MACRO` and `MEND`
+    pub body: Vec<String>,
+}
+
+impl MacroDef {
+    /// Expand the body for one invocation
+    ///
+    /// `invocation` numbers the local labels. Expansion stops at `MEXIT`.
+    pub fn expand(&self, args: &[String], invocation: u32) -> Vec<String> {
+        let mut lines = Vec::new();
+        for line in &self.body {
+            let fields = parser::split_fields(line);
+            if fields
+                .operation
+                .is_some_and(|op| op.eq_ignore_ascii_case("MEXIT"))
+            {
+                break;
+            }
+            if fields.operation.is_none() && fields.label.is_none() {
+                // Comments and blank lines pass through untouched
+                lines.push(line.clone());
+                continue;
+            }
+            lines.push(self.substitute_fields(line, args, invocation));
+        }
+        lines
+    }
+
+    /// Substitute in the label and operand fields, leaving the operation alone
+    fn substitute_fields(&self, line: &str, args: &[String], invocation: u32) -> String {
+        let operation_index = usize::from(!line.starts_with(char::is_whitespace));
+        let Some(op) = line.split_whitespace().nth(operation_index) else {
+            return self.substitute(line, args, invocation);
+        };
+        let op_start = op.as_ptr() as usize - line.as_ptr() as usize;
+        let op_end = op_start + op.len();
+
+        format!(
+            "{}{}{}",
+            self.substitute(&line[..op_start], args, invocation),
+            op,
+            self.substitute(&line[op_end..], args, invocation)
+        )
+    }
+
+    /// Replace parameter and local symbols in one body line
+    fn substitute(&self, line: &str, args: &[String], invocation: u32) -> String {
+        let mut result = String::with_capacity(line.len());
+        let mut chars = line.char_indices().peekable();
+
+        while let Some((start, c)) = chars.next() {
+            if !is_symbol_char(c) {
+                result.push(c);
+                continue;
+            }
+
+            let mut end = start + c.len_utf8();
+            while let Some(&(pos, next)) = chars.peek() {
+                if !is_symbol_char(next) {
+                    break;
+                }
+                end = pos + next.len_utf8();
+                chars.next();
+            }
+
+            let symbol = &line[start..end];
+            if let Some(index) = self.params.iter().position(|p| p == symbol) {
+                result.push_str(args.get(index).map_or("", String::as_str));
+            } else if let Some(local) = symbol.strip_prefix(LOCAL_PREFIX) {
+                result.push_str(&format!("{}{:03}{}", LOCAL_PREFIX, invocation, local));
+            } else {
+                result.push_str(symbol);
+            }
+        }
+        result
+    }
+}
+
+fn is_symbol_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '$' | '#' | '@' | '_')
+}
+
+/// Split a comma-separated parameter or argument list
+fn split_list(operand: Option<&str>) -> Vec<String> {
+    operand
+        .map(|list| {
+            list.split(',')
+                .map(|item| item.trim().to_string())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn syntax_error(line: usize, message: String) -> AssemblerError {
+    AssemblerError::syntax(line, message)
+}
+
+impl Assembler {
+    /// Collect macro definitions and expand invocations
+    ///
+    /// Returns the program as `(line_number, text)` pairs with definitions
+    /// removed and invocations replaced by their expansions.
+    pub(super) fn expand_macros(&mut self, source: &str) -> Result<Vec<(usize, String)>> {
+        let mut output = Vec::new();
+        let mut definition: Option<(usize, MacroDef)> = None;
+
+        for (index, line) in source.lines().enumerate() {
+            let line_num = index + 1;
+            let fields = parser::split_fields(line);
+            let operation = fields.operation.map(str::to_uppercase);
+
+            if let Some((_, def)) = definition.as_mut() {
+                match operation.as_deref() {
+                    Some("MEND") => {
+                        let (_, def) = definition.take().expect("definition in progress");
+                        self.macros.insert(def.name.clone(), def);
+                    }
+                    Some("MACRO") => {
+                        return Err(syntax_error(
+                            line_num,
+                            "Macro definitions cannot be nested".to_string(),
+                        ))
+                    }
+                    _ => def.body.push(line.to_string()),
+                }
+                continue;
+            }
+
+            match operation.as_deref() {
+                Some("MACRO") => {
+                    let Some(name) = fields.label else {
+                        return Err(syntax_error(line_num, "MACRO requires a name".to_string()));
+                    };
+                    definition = Some((
+                        line_num,
+                        MacroDef {
+                            name: name.to_uppercase(),
+                            params: split_list(fields.operand.as_deref()),
+                            body: Vec::new(),
+                        },
+                    ));
+                }
+                Some(op @ ("MEND" | "MEXIT")) => {
+                    return Err(syntax_error(
+                        line_num,
+                        format!("{} outside a macro definition", op),
+                    ));
+                }
+                _ => self.expand_line(line, line_num, 0, &mut output)?,
+            }
+        }
+
+        if let Some((line_num, def)) = definition {
+            return Err(syntax_error(
+                line_num,
+                format!("Macro {} has no MEND", def.name),
+            ));
+        }
+        Ok(output)
+    }
+
+    /// Emit one line, expanding it (recursively) if it invokes a macro
+    fn expand_line(
+        &mut self,
+        line: &str,
+        line_num: usize,
+        depth: usize,
+        output: &mut Vec<(usize, String)>,
+    ) -> Result<()> {
+        let fields = parser::split_fields(line);
+        let def = match fields.operation {
+            Some(op) => match self.macros.get(&op.to_uppercase()) {
+                Some(def) => def.clone(),
+                None => {
+                    output.push((line_num, line.to_string()));
+                    return Ok(());
+                }
+            },
+            None => {
+                output.push((line_num, line.to_string()));
+                return Ok(());
+            }
+        };
+
+        if depth >= MAX_EXPANSION_DEPTH {
+            return Err(syntax_error(
+                line_num,
+                format!("Macro {} expands too deeply (recursive?)", def.name),
+            ));
+        }
+
+        self.macro_invocation_counter += 1;
+        let invocation = self.macro_invocation_counter;
+
+        if let Some(label) = fields.label {
+            output.push((line_num, label.to_string()));
+        }
+        let args = split_list(fields.operand.as_deref());
+        for expanded in def.expand(&args, invocation) {
+            self.expand_line(&expanded, line_num, depth + 1, output)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn def(params: &[&str], body: &[&str]) -> MacroDef {
+        MacroDef {
+            name: "TEST".to_string(),
+            params: params.iter().map(|p| p.to_string()).collect(),
+            body: body.iter().map(|l| l.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_substitutes_whole_symbols_only() {
+        let mac = def(&["X"], &["        LD   X", "        DC   XX+X"]);
+        let lines = mac.expand(&["VAL".to_string()], 1);
+        assert_eq!(lines, ["        LD   VAL", "        DC   XX+VAL"]);
+    }
+
+    #[test]
+    fn test_operation_field_is_not_substituted() {
+        let mac = def(&["A"], &["A       A    A"]);
+        assert_eq!(mac.expand(&["ONE".to_string()], 1), ["ONE       A    ONE"]);
+    }
+
+    #[test]
+    fn test_local_labels_are_numbered() {
+        let mac = def(&[], &["L$TOP   MDX  L$TOP"]);
+        assert_eq!(mac.expand(&[], 7), ["L$007TOP   MDX  L$007TOP"]);
+    }
+
+    #[test]
+    fn test_mexit_stops_expansion() {
+        let mac = def(&[], &["        WAIT", "        MEXIT", "        WAIT"]);
+        assert_eq!(mac.expand(&[], 1), ["        WAIT"]);
+    }
+}
@@ -1,25 +1,113 @@
 //! IBM 1130 Assembler
 //!
 //! This module implements a two-pass assembler for IBM 1130 assembly language.
-//! It supports the full instruction set, pseudo-ops, labels, and expressions.
+//! It supports the full instruction set, pseudo-ops, labels, expressions and
+//! macros (see `macro_definition`).
+//!
+//! Operands always resolve against the symbol table, never against the
+//! mnemonic list, so a label named like an instruction (e.g. `S`) can be
+//! referenced normally. Such labels are legal but produce a warning since
+//! `A    S` reads ambiguously.
 
+pub mod binary;
+pub mod characters;
+pub mod crossref;
+pub mod expression;
+pub mod intel_hex;
 pub mod lexer;
+pub mod linkage;
+mod listing;
+pub mod macro_definition;
 pub mod parser;
 pub mod symbols;
 
-use crate::error::AssemblerError;
-use std::collections::HashMap;
+pub use characters::CharPacking;
+pub use crossref::CrossRefTable;
+pub use linkage::{link, ExternalReference};
+
+use crate::error::{AssemblerError, SourceSpan};
+use std::collections::{BTreeMap, HashMap, HashSet};
 
 /// Result type for assembler operations
 pub type Result<T> = std::result::Result<T, AssemblerError>;
 
-/// Assembled program output
+/// Words generated by each source line: `(line_number, words)`
+pub type LineMapping = Vec<(usize, Vec<u16>)>;
+
+/// Source line of every assembled word
+///
+/// Each entry is `(memory_address, source_line_number)` with 1-indexed
+/// line numbers, in source order. A long instruction has an entry for each
+/// of its words; lines emitting nothing (`ORG`, `EQU`) have none.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize)]
+pub struct SourceMap {
+    pub entries: Vec<(u16, usize)>,
+}
+
+impl SourceMap {
+    fn build(generated: &[GeneratedLine]) -> Self {
+        let entries = generated
+            .iter()
+            .flat_map(|line| {
+                (0..line.words.len())
+                    .map(move |offset| (line.address.wrapping_add(offset as u16), line.line_number))
+            })
+            .collect();
+        Self { entries }
+    }
+
+    /// Source line that generated the word at `addr`
+    ///
+    /// If several lines assembled to the same address, the last one wins,
+    /// as it is the one left in memory after loading.
+    pub fn address_to_line(&self, addr: u16) -> Option<usize> {
+        self.entries
+            .iter()
+            .rev()
+            .find(|&&(address, _)| address == addr)
+            .map(|&(_, line)| line)
+    }
+
+    /// Addresses of the words generated by `line`, ascending
+    pub fn line_to_addresses(&self, line: usize) -> Vec<u16> {
+        self.entries
+            .iter()
+            .filter(|&&(_, source_line)| source_line == line)
+            .map(|&(address, _)| address)
+            .collect()
+    }
+}
+
+/// Everything one assembly produces, from `assemble_with_outputs`
 #[derive(Debug, Clone)]
-pub struct AssembledProgram {
+pub struct AssemblyOutputs {
+    /// The assembled program
+    pub program: AssembledProgram,
+    /// Printable listing, as from `assemble_with_listing`
+    pub listing: String,
+    /// Cross-reference table, as from `assemble_with_crossref`
+    pub crossref: CrossRefTable,
+    /// Source line of each word, as from `assemble_with_source_map`
+    pub source_map: SourceMap,
+}
+
+/// A run of words assembled to consecutive addresses
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Segment {
+    /// Address of the first word
+    pub origin: u16,
+
     /// Assembled binary words
     pub words: Vec<u16>,
+}
+
+/// Assembled program output
+#[derive(Debug, Clone)]
+pub struct AssembledProgram {
+    /// Code and data, one segment per contiguous address range, in source order
+    pub segments: Vec<Segment>,
 
-    /// Starting address (from ORG or default 0)
+    /// Starting address (from the first ORG or default 0)
     pub origin: u16,
 
     /// Symbol table (for debugging)
@@ -27,6 +115,345 @@ pub struct AssembledProgram {
 
     /// Entry point (from END directive or None)
     pub entry_point: Option<u16>,
+
+    /// Listing heading (from the first TITLE or HDNG directive)
+    pub listing_title: Option<String>,
+
+    /// Symbols other modules may reference (from ENT), in source order
+    pub entries: Vec<String>,
+
+    /// Words to patch with the address of an EXTRN symbol
+    pub external_refs: Vec<ExternalReference>,
+
+    /// Assembled without an `ORG`, so `link` may move it
+    pub relocatable: bool,
+
+    /// Addresses of words holding an address within this program, which
+    /// `link` adjusts when it moves the program
+    pub relocations: Vec<u16>,
+
+    /// Memory occupied, by kind of word (see `footprint`)
+    footprint: MemoryFootprint,
+}
+
+/// Memory occupied by an assembled program
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MemoryFootprint {
+    /// Lowest occupied address
+    pub origin: u16,
+    /// Highest occupied address
+    pub last_address: u16,
+    /// All occupied words: `code_words + data_words + bss_words`
+    pub total_words: usize,
+    /// Words generated by machine instructions
+    pub code_words: usize,
+    /// Words generated by data pseudo-ops such as DC and IOCC
+    pub data_words: usize,
+    /// Words reserved by BSS
+    pub bss_words: usize,
+}
+
+impl MemoryFootprint {
+    /// Tally the words each line generated
+    fn measure(lines: &[parser::ParsedLine], generated: &[GeneratedLine], origin: u16) -> Self {
+        let mut footprint = MemoryFootprint {
+            origin,
+            last_address: origin,
+            ..MemoryFootprint::default()
+        };
+        let mut bounds: Option<(u16, u16)> = None;
+
+        for (line, output) in lines.iter().zip(generated) {
+            let count = output.words.len();
+            if count == 0 {
+                continue;
+            }
+            match &line.operation {
+                parser::Operation::Instruction(_) => footprint.code_words += count,
+                parser::Operation::PseudoOp(op) if linkage::is_call(op) => {
+                    footprint.code_words += count
+                }
+                parser::Operation::PseudoOp(op) if op == "BSS" => footprint.bss_words += count,
+                _ => footprint.data_words += count,
+            }
+
+            let last = output.address.wrapping_add(count as u16 - 1);
+            bounds = Some(match bounds {
+                Some((low, high)) => (low.min(output.address), high.max(last)),
+                None => (output.address, last),
+            });
+        }
+
+        if let Some((low, high)) = bounds {
+            footprint.origin = low;
+            footprint.last_address = high;
+        }
+        footprint.total_words = footprint.code_words + footprint.data_words + footprint.bss_words;
+        footprint
+    }
+}
+
+impl AssembledProgram {
+    /// A program made of bare words, e.g. read back from a binary image
+    ///
+    /// The origin is the first segment's. Every word counts as data in the
+    /// footprint, and there are no symbols or linkage.
+    pub fn from_segments(segments: Vec<Segment>, entry_point: Option<u16>) -> Self {
+        let origin = segments.first().map_or(0, |segment| segment.origin);
+        let mut footprint = MemoryFootprint {
+            origin,
+            last_address: origin,
+            ..MemoryFootprint::default()
+        };
+        let occupied = segments.iter().filter(|segment| !segment.words.is_empty());
+        for (index, segment) in occupied.enumerate() {
+            let last = segment.origin.wrapping_add(segment.words.len() as u16 - 1);
+            if index == 0 {
+                footprint.origin = segment.origin;
+                footprint.last_address = last;
+            } else {
+                footprint.origin = footprint.origin.min(segment.origin);
+                footprint.last_address = footprint.last_address.max(last);
+            }
+            footprint.data_words += segment.words.len();
+        }
+        footprint.total_words = footprint.data_words;
+
+        AssembledProgram {
+            segments,
+            origin,
+            symbols: HashMap::new(),
+            entry_point,
+            listing_title: None,
+            entries: Vec::new(),
+            external_refs: Vec::new(),
+            relocatable: false,
+            relocations: Vec::new(),
+            footprint,
+        }
+    }
+
+    /// Memory occupied, including BSS blocks
+    pub fn footprint(&self) -> MemoryFootprint {
+        self.footprint
+    }
+
+    /// Total number of assembled words across all segments
+    pub fn word_count(&self) -> usize {
+        self.segments
+            .iter()
+            .map(|segment| segment.words.len())
+            .sum()
+    }
+
+    /// Flatten the segments into one image
+    ///
+    /// Returns the lowest segment address and the words from there to the
+    /// end of the highest segment, with gaps between segments set to `fill`.
+    /// Where segments overlap, the later one wins.
+    pub fn flat_words(&self, fill: u16) -> (u16, Vec<u16>) {
+        let Some(start) = self.segments.iter().map(|s| s.origin as usize).min() else {
+            return (self.origin, Vec::new());
+        };
+        let end = self
+            .segments
+            .iter()
+            .map(|s| s.origin as usize + s.words.len())
+            .max()
+            .unwrap_or(start);
+
+        let mut words = vec![fill; end - start];
+        for segment in &self.segments {
+            let offset = segment.origin as usize - start;
+            words[offset..offset + segment.words.len()].copy_from_slice(&segment.words);
+        }
+        (start as u16, words)
+    }
+
+    /// Invert the symbol table into an address -> label map
+    ///
+    /// When several labels share an address the alphabetically first one
+    /// is kept, so the result does not depend on hash order.
+    pub fn address_labels(&self) -> BTreeMap<u16, String> {
+        let mut labels: BTreeMap<u16, String> = BTreeMap::new();
+        for (name, &address) in &self.symbols {
+            labels
+                .entry(address)
+                .and_modify(|existing| {
+                    if name < existing {
+                        existing.clone_from(name);
+                    }
+                })
+                .or_insert_with(|| name.clone());
+        }
+        labels
+    }
+}
+
+/// Non-fatal diagnostic produced during assembly
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssemblerWarning {
+    /// Line number (1-indexed)
+    pub line: usize,
+    /// Warning message
+    pub message: String,
+}
+
+impl std::fmt::Display for AssemblerWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Warning on line {}: {}", self.line, self.message)
+    }
+}
+
+/// An `EQU` whose expression referenced a not-yet-defined symbol in pass 1
+struct DeferredEqu {
+    label: String,
+    operand: String,
+    line_num: usize,
+    location_counter: u16,
+    columns: LineColumns,
+}
+
+/// Where the label and operand of the line being assembled are, for
+/// error spans; columns are 0 where not known
+#[derive(Debug, Clone, Default)]
+struct LineColumns {
+    label: usize,
+    operand: usize,
+    operand_text: String,
+    /// Address of the operand string that expressions are sliced from
+    operand_address: usize,
+}
+
+impl LineColumns {
+    fn of(line: &parser::ParsedLine) -> Self {
+        let operand_text = line.operand.clone().unwrap_or_default();
+        Self {
+            label: line.label_column,
+            operand: line.operand_column,
+            operand_address: line
+                .operand
+                .as_deref()
+                .map_or(0, |text| text.as_ptr() as usize),
+            operand_text,
+        }
+    }
+
+    /// The same columns for expressions sliced from `operand`, a copy of
+    /// the line's operand
+    fn for_copy(&self, operand: &str) -> Self {
+        Self {
+            operand_address: operand.as_ptr() as usize,
+            ..self.clone()
+        }
+    }
+
+    /// Byte offset of `text` in the operand, if it is a slice of it
+    fn offset_of(&self, text: &str) -> Option<usize> {
+        let offset = (text.as_ptr() as usize).checked_sub(self.operand_address)?;
+        let fits = offset + text.len() <= self.operand_text.len()
+            && self.operand_text.is_char_boundary(offset);
+        (self.operand > 0 && fits).then_some(offset)
+    }
+}
+
+/// Heading text of a TITLE/HDNG operand, without surrounding quotes
+fn title_text(operand: &str) -> String {
+    let operand = operand.trim();
+    operand
+        .strip_prefix('\'')
+        .and_then(|text| text.strip_suffix('\''))
+        .unwrap_or(operand)
+        .to_string()
+}
+
+/// Group per-line output into segments of consecutive addresses
+fn build_segments(generated: &[GeneratedLine]) -> Vec<Segment> {
+    let mut segments: Vec<Segment> = Vec::new();
+    for line in generated.iter().filter(|line| !line.words.is_empty()) {
+        match segments.last_mut() {
+            Some(segment)
+                if segment.origin as usize + segment.words.len() == line.address as usize =>
+            {
+                segment.words.extend_from_slice(&line.words);
+            }
+            _ => segments.push(Segment {
+                origin: line.address,
+                words: line.words.clone(),
+            }),
+        }
+    }
+    segments
+}
+
+/// Output of one parsed line in pass 2
+struct GeneratedLine {
+    /// Source line number (1-indexed)
+    line_number: usize,
+    /// Location counter when the line was reached
+    address: u16,
+    /// Words emitted by the line
+    words: Vec<u16>,
+}
+
+/// State a file passed to `assemble_incremental` may change, saved so a
+/// failed file can be undone
+///
+/// The finished files' lines and output are not included: a file only
+/// adds to them once it has assembled.
+struct FileCheckpoint {
+    symbols: symbols::SymbolTable,
+    location_counter: u16,
+    origin: Option<u16>,
+    entry_point: Option<u16>,
+    listing_title: Option<String>,
+    warnings: Vec<AssemblerWarning>,
+    macros: HashMap<String, macro_definition::MacroDef>,
+    macro_invocation_counter: u32,
+    entries: Vec<(String, SourceSpan)>,
+    externals: Vec<String>,
+    external_refs: Vec<ExternalReference>,
+    relative_symbols: HashSet<String>,
+    relocations: Vec<u16>,
+    symbol_refs: Vec<(String, u16)>,
+}
+
+impl FileCheckpoint {
+    fn save(assembler: &Assembler) -> Self {
+        Self {
+            symbols: assembler.symbols.clone(),
+            location_counter: assembler.location_counter,
+            origin: assembler.origin,
+            entry_point: assembler.entry_point,
+            listing_title: assembler.listing_title.clone(),
+            warnings: assembler.warnings.clone(),
+            macros: assembler.macros.clone(),
+            macro_invocation_counter: assembler.macro_invocation_counter,
+            entries: assembler.entries.clone(),
+            externals: assembler.externals.clone(),
+            external_refs: assembler.external_refs.clone(),
+            relative_symbols: assembler.relative_symbols.clone(),
+            relocations: assembler.relocations.clone(),
+            symbol_refs: assembler.symbol_refs.clone(),
+        }
+    }
+
+    fn restore(self, assembler: &mut Assembler) {
+        assembler.symbols = self.symbols;
+        assembler.location_counter = self.location_counter;
+        assembler.origin = self.origin;
+        assembler.entry_point = self.entry_point;
+        assembler.listing_title = self.listing_title;
+        assembler.warnings = self.warnings;
+        assembler.macros = self.macros;
+        assembler.macro_invocation_counter = self.macro_invocation_counter;
+        assembler.entries = self.entries;
+        assembler.externals = self.externals;
+        assembler.external_refs = self.external_refs;
+        assembler.relative_symbols = self.relative_symbols;
+        assembler.relocations = self.relocations;
+        assembler.symbol_refs = self.symbol_refs;
+    }
 }
 
 /// Two-pass assembler
@@ -37,11 +464,54 @@ pub struct Assembler {
     /// Current location counter
     location_counter: u16,
 
-    /// Origin address
-    origin: u16,
+    /// Address of the first ORG
+    origin: Option<u16>,
 
     /// Entry point
     entry_point: Option<u16>,
+
+    /// Listing heading
+    listing_title: Option<String>,
+
+    /// Warnings from the last assembly
+    warnings: Vec<AssemblerWarning>,
+
+    /// Macros defined so far, by upper-case name
+    macros: HashMap<String, macro_definition::MacroDef>,
+
+    /// Expansions so far, numbering macro-local labels
+    macro_invocation_counter: u32,
+
+    /// Byte order of packed character constants
+    char_packing: CharPacking,
+
+    /// ENT symbols, with where each is named
+    entries: Vec<(String, SourceSpan)>,
+
+    /// EXTRN symbols
+    externals: Vec<String>,
+
+    /// References to EXTRN symbols found in pass 2
+    external_refs: Vec<ExternalReference>,
+
+    /// Symbols whose value is an address in this program (labels, and
+    /// equates relative to one)
+    relative_symbols: HashSet<String>,
+
+    /// Words found in pass 2 that hold an address in this program
+    relocations: Vec<u16>,
+
+    /// `(symbol, address)` for each symbol use in pass 2
+    symbol_refs: Vec<(String, u16)>,
+
+    /// Lines of the files assembled by `assemble_incremental` so far
+    incremental_lines: Vec<parser::ParsedLine>,
+
+    /// Output of those lines, for `finalize`
+    incremental_output: Vec<GeneratedLine>,
+
+    /// Columns of the line being assembled
+    line_columns: LineColumns,
 }
 
 impl Assembler {
@@ -50,49 +520,308 @@ impl Assembler {
         Self {
             symbols: symbols::SymbolTable::new(),
             location_counter: 0,
-            origin: 0,
+            origin: None,
             entry_point: None,
+            listing_title: None,
+            warnings: Vec::new(),
+            macros: HashMap::new(),
+            macro_invocation_counter: 0,
+            char_packing: CharPacking::default(),
+            entries: Vec::new(),
+            externals: Vec::new(),
+            external_refs: Vec::new(),
+            relative_symbols: HashSet::new(),
+            relocations: Vec::new(),
+            symbol_refs: Vec::new(),
+            incremental_lines: Vec::new(),
+            incremental_output: Vec::new(),
+            line_columns: LineColumns::default(),
         }
     }
 
+    /// Choose how character constants pack two characters into a word
+    ///
+    /// Defaults to `CharPacking::HighFirst`, the IBM convention.
+    pub fn set_char_packing(&mut self, packing: CharPacking) {
+        self.char_packing = packing;
+    }
+
+    /// Current character packing order
+    pub fn char_packing(&self) -> CharPacking {
+        self.char_packing
+    }
+
+    /// Warnings produced by the last call to `assemble`
+    pub fn warnings(&self) -> &[AssemblerWarning] {
+        &self.warnings
+    }
+
     /// Assemble source code into binary
     pub fn assemble(&mut self, source: &str) -> Result<AssembledProgram> {
-        // Reset state
+        let (program, _) = self.assemble_with_mapping(source)?;
+        Ok(program)
+    }
+
+    /// Assemble source code, also returning the words produced by each source line
+    ///
+    /// Each mapping entry is `(line_number, words)` where `line_number` is the
+    /// 1-indexed source line. Only lines containing a label or operation appear;
+    /// lines that emit no code (e.g. `ORG`) map to an empty vector.
+    pub fn assemble_with_mapping(
+        &mut self,
+        source: &str,
+    ) -> Result<(AssembledProgram, LineMapping)> {
+        let (program, _, generated) = self.assemble_lines(source)?;
+        let mapping = generated
+            .into_iter()
+            .map(|line| (line.line_number, line.words))
+            .collect();
+        Ok((program, mapping))
+    }
+
+    /// Assemble source code, also returning the source line of each word
+    /// (see `SourceMap`)
+    pub fn assemble_with_source_map(
+        &mut self,
+        source: &str,
+    ) -> Result<(AssembledProgram, SourceMap)> {
+        let (program, _, generated) = self.assemble_lines(source)?;
+        let source_map = SourceMap::build(&generated);
+        Ok((program, source_map))
+    }
+
+    /// Assemble source code, also returning a printable listing
+    ///
+    /// Each source line is listed after its address and generated words,
+    /// e.g. `0100  6000 0108   LD   VALUE`; comments and blank lines are
+    /// listed without them. A symbol table closes the listing.
+    pub fn assemble_with_listing(&mut self, source: &str) -> Result<(AssembledProgram, String)> {
+        let (program, lines, generated) = self.assemble_lines(source)?;
+        let listing = listing::format_listing(source, &lines, &generated, &program);
+        Ok((program, listing))
+    }
+
+    /// Assemble source code, also returning a cross-reference table
+    ///
+    /// The table maps each symbol to its value and to the address of every
+    /// line whose operand uses it (see `CrossRefTable::format`).
+    pub fn assemble_with_crossref(
+        &mut self,
+        source: &str,
+    ) -> Result<(AssembledProgram, CrossRefTable)> {
+        let (program, _, _) = self.assemble_lines(source)?;
+        let table = CrossRefTable::build(&program.symbols, &self.symbol_refs);
+        Ok((program, table))
+    }
+
+    /// Assemble source code once, returning the listing, cross-reference
+    /// table and source map along with the program
+    ///
+    /// For a caller that wants several of them, this saves assembling the
+    /// same source once per output.
+    pub fn assemble_with_outputs(&mut self, source: &str) -> Result<AssemblyOutputs> {
+        let (program, lines, generated) = self.assemble_lines(source)?;
+        let listing = listing::format_listing(source, &lines, &generated, &program);
+        Ok(AssemblyOutputs {
+            listing,
+            crossref: CrossRefTable::build(&program.symbols, &self.symbol_refs),
+            source_map: SourceMap::build(&generated),
+            program,
+        })
+    }
+
+    /// Run both passes, keeping the parsed lines and per-line output
+    ///
+    /// Syntax errors come back with the text of the offending source line.
+    fn assemble_lines(
+        &mut self,
+        source: &str,
+    ) -> Result<(
+        AssembledProgram,
+        Vec<parser::ParsedLine>,
+        Vec<GeneratedLine>,
+    )> {
+        self.run_passes(source)
+            .map_err(|e| e.with_source_text(source))
+    }
+
+    fn run_passes(
+        &mut self,
+        source: &str,
+    ) -> Result<(
+        AssembledProgram,
+        Vec<parser::ParsedLine>,
+        Vec<GeneratedLine>,
+    )> {
+        self.reset_state();
+
+        let lines = self.parse_source(source)?;
+
+        // Pass 1: Build symbol table
+        self.pass1(&lines, 0)?;
+
+        // Pass 2: Generate code
+        let generated = self.pass2(&lines, 0)?;
+        self.check_entries()?;
+
+        let program = self.build_program(&lines, &generated);
+        Ok((program, lines, generated))
+    }
+
+    /// Assemble one file of a multi-file program
+    ///
+    /// The file sees every symbol and macro defined by the files before it
+    /// and starts where the previous file ended, unless it has its own
+    /// `ORG`. Symbols from files not assembled yet are undefined, so a
+    /// subroutine library goes before the files calling it. Returns the
+    /// words this file generated; `finalize` combines all the files.
+    ///
+    /// # Errors
+    ///
+    /// Errors report lines within `source`; that includes redefining a
+    /// label from an earlier file. A file that fails leaves nothing
+    /// behind: no symbols, words, `ENT`/`EXTRN` names, macros, warnings,
+    /// origin, entry point or title. A corrected file can be assembled in
+    /// its place.
+    pub fn assemble_incremental(&mut self, source: &str) -> Result<Vec<u16>> {
+        let checkpoint = FileCheckpoint::save(self);
+
+        let result = self.assemble_file(source);
+        if result.is_err() {
+            checkpoint.restore(self);
+        }
+        result.map_err(|e| e.with_source_text(source))
+    }
+
+    fn assemble_file(&mut self, source: &str) -> Result<Vec<u16>> {
+        let start = self.location_counter;
+        let lines = self.parse_source(source)?;
+        self.pass1(&lines, start)?;
+        let generated = self.pass2(&lines, start)?;
+
+        let words = generated
+            .iter()
+            .flat_map(|line| line.words.iter().copied())
+            .collect();
+        self.incremental_lines.extend(lines);
+        self.incremental_output.extend(generated);
+        Ok(words)
+    }
+
+    /// Combine the files passed to `assemble_incremental` into one program
+    ///
+    /// The origin is the first `ORG` of any file and the entry point the
+    /// last `END` operand. The assembler is reset for a new program.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AssemblerError::UndefinedSymbol` for an `ENT` symbol no file
+    /// defined.
+    pub fn finalize(&mut self) -> Result<AssembledProgram> {
+        let checked = self.check_entries();
+        let lines = std::mem::take(&mut self.incremental_lines);
+        let generated = std::mem::take(&mut self.incremental_output);
+        let program = self.build_program(&lines, &generated);
+        self.reset_state();
+        checked.map(|()| program)
+    }
+
+    /// Forget everything from earlier assemblies
+    fn reset_state(&mut self) {
         self.symbols.clear();
         self.location_counter = 0;
-        self.origin = 0;
+        self.origin = None;
         self.entry_point = None;
+        self.listing_title = None;
+        self.warnings.clear();
+        self.macros.clear();
+        self.macro_invocation_counter = 0;
+        self.entries.clear();
+        self.externals.clear();
+        self.external_refs.clear();
+        self.relative_symbols.clear();
+        self.relocations.clear();
+        self.incremental_lines.clear();
+        self.incremental_output.clear();
+    }
 
-        // Parse source into lines
-        let lines = parser::parse_source(source)?;
+    /// Expand macros, then parse the result into lines
+    fn parse_source(&mut self, source: &str) -> Result<Vec<parser::ParsedLine>> {
+        let expanded = self.expand_macros(source)?;
+        let mut lines = parser::parse_numbered(
+            expanded
+                .iter()
+                .map(|(line_num, text)| (*line_num, text.as_str())),
+        )?;
 
-        // Pass 1: Build symbol table
-        self.pass1(&lines)?;
+        // Columns of a macro expansion do not match its invocation line
+        let source_lines: Vec<&str> = source.lines().collect();
+        let expanded_lines: HashSet<usize> = expanded
+            .iter()
+            .filter(|(line_num, text)| source_lines.get(line_num - 1) != Some(&text.as_str()))
+            .map(|(line_num, _)| *line_num)
+            .collect();
+        for line in &mut lines {
+            if expanded_lines.contains(&line.line_number) {
+                line.label_column = 0;
+                line.operand_column = 0;
+            }
+        }
+        Ok(lines)
+    }
 
-        // Pass 2: Generate code
-        let words = self.pass2(&lines)?;
+    /// Collect the assembled lines and symbols into a program
+    fn build_program(
+        &self,
+        lines: &[parser::ParsedLine],
+        generated: &[GeneratedLine],
+    ) -> AssembledProgram {
+        // External placeholders are not addresses in this module
+        let mut symbols = self.symbols.get_all();
+        symbols.retain(|name, _| !self.is_external(name));
 
-        Ok(AssembledProgram {
-            words,
-            origin: self.origin,
-            symbols: self.symbols.get_all(),
+        AssembledProgram {
+            segments: build_segments(generated),
+            origin: self.origin.unwrap_or(0),
+            symbols,
             entry_point: self.entry_point,
-        })
+            listing_title: self.listing_title.clone(),
+            entries: self.entries.iter().map(|(name, _)| name.clone()).collect(),
+            external_refs: self.external_refs.clone(),
+            relocatable: self.origin.is_none(),
+            relocations: self.relocations.clone(),
+            footprint: MemoryFootprint::measure(lines, generated, self.origin.unwrap_or(0)),
+        }
     }
 
-    /// Pass 1: Build symbol table and calculate addresses
-    fn pass1(&mut self, lines: &[parser::ParsedLine]) -> Result<()> {
-        self.location_counter = self.origin;
+    /// Pass 1: Build symbol table and calculate addresses, from `start`
+    fn pass1(&mut self, lines: &[parser::ParsedLine], start: u16) -> Result<()> {
+        self.location_counter = start;
+
+        let mut deferred_equs = Vec::new();
+
+        for line in lines {
+            // Zero-based source line, so errors report `line_num + 1`
+            let line_num = line.line_number - 1;
+            self.line_columns = LineColumns::of(line);
+            if matches!(&line.operation, parser::Operation::PseudoOp(op) if op == "EQU") {
+                if let Some(deferred) = self.define_equ(line, line_num)? {
+                    deferred_equs.push(deferred);
+                }
+                continue;
+            }
 
-        for (line_num, line) in lines.iter().enumerate() {
             // Process label if present
             if let Some(ref label) = line.label {
-                self.symbols
-                    .define(label, self.location_counter)
-                    .map_err(|e| AssemblerError::SyntaxError {
-                        line: line_num + 1,
-                        message: e.to_string(),
-                    })?;
+                self.define_symbol(label, self.location_counter, line_num)?;
+                self.relative_symbols.insert(label.clone());
+
+                if parser::is_instruction(label) {
+                    self.warnings.push(AssemblerWarning {
+                        line: line.line_number,
+                        message: format!("Label {} shadows instruction mnemonic", label),
+                    });
+                }
             }
 
             // Update location counter based on instruction/pseudo-op
@@ -109,31 +838,213 @@ impl Assembler {
             }
         }
 
+        self.resolve_deferred_equs(deferred_equs)?;
+        self.declare_called_externals(lines)
+    }
+
+    /// Define an `EQU` symbol in pass 1
+    ///
+    /// Returns the equate for later resolution when its expression refers to
+    /// a symbol that is not defined yet.
+    fn define_equ(
+        &mut self,
+        line: &parser::ParsedLine,
+        line_num: usize,
+    ) -> Result<Option<DeferredEqu>> {
+        let (label, operand) = match (&line.label, &line.operand) {
+            (Some(label), Some(operand)) => (label.clone(), operand.clone()),
+            (None, _) => {
+                return Err(AssemblerError::syntax(
+                    line_num + 1,
+                    "EQU requires a label".to_string(),
+                ))
+            }
+            (_, None) => {
+                return Err(AssemblerError::syntax(
+                    line_num + 1,
+                    "EQU requires an operand".to_string(),
+                ))
+            }
+        };
+
+        match expression::eval_expression(&operand, &self.symbols, self.location_counter) {
+            Ok(value) => {
+                self.define_symbol(&label, value, line_num)?;
+                self.note_relative_equ(&label, &operand, self.location_counter);
+                Ok(None)
+            }
+            Err(AssemblerError::UndefinedSymbol { .. }) => Ok(Some(DeferredEqu {
+                columns: self.line_columns.for_copy(&operand),
+                label,
+                operand,
+                line_num,
+                location_counter: self.location_counter,
+            })),
+            Err(_) => {
+                self.line_columns = self.line_columns.for_copy(&operand);
+                self.parse_expression(&operand, line_num).map(|_| None)
+            }
+        }
+    }
+
+    /// Resolve forward-referencing equates once all labels are known
+    ///
+    /// Equates may depend on each other, so resolution repeats until no
+    /// further progress is made.
+    fn resolve_deferred_equs(&mut self, mut pending: Vec<DeferredEqu>) -> Result<()> {
+        while !pending.is_empty() {
+            let before = pending.len();
+            let mut unresolved = Vec::new();
+
+            for equ in pending {
+                match expression::eval_expression(&equ.operand, &self.symbols, equ.location_counter)
+                {
+                    Ok(value) => {
+                        self.line_columns = equ.columns.clone();
+                        self.define_symbol(&equ.label, value, equ.line_num)?;
+                        self.note_relative_equ(&equ.label, &equ.operand, equ.location_counter);
+                    }
+                    Err(_) => unresolved.push(equ),
+                }
+            }
+
+            if unresolved.len() == before {
+                // No progress: report the first one with its real error
+                let equ = &unresolved[0];
+                self.location_counter = equ.location_counter;
+                self.line_columns = equ.columns.clone();
+                self.parse_expression(&equ.operand, equ.line_num)?;
+            }
+            pending = unresolved;
+        }
         Ok(())
     }
 
+    /// Mark an equate relative if its value is an address in this program
+    fn note_relative_equ(&mut self, label: &str, operand: &str, location_counter: u16) {
+        let count = expression::relocation_count(
+            operand,
+            &self.symbols,
+            &self.relative_symbols,
+            location_counter,
+        );
+        if count == 1 {
+            self.relative_symbols.insert(label.to_string());
+        }
+    }
+
+    /// Check an `EQU` in pass 2 against its pass 1 value
+    fn check_equ(&mut self, line: &parser::ParsedLine, line_num: usize) -> Result<()> {
+        let (Some(label), Some(operand)) = (&line.label, &line.operand) else {
+            return Ok(());
+        };
+
+        let value = self.parse_expression(operand, line_num)?;
+        match self.symbols.lookup(label) {
+            Some(defined) if defined == value => Ok(()),
+            defined => Err(AssemblerError::syntax(
+                line_num + 1,
+                format!(
+                    "Phase error: {} was {:#06x} in pass 1, {:#06x} in pass 2",
+                    label,
+                    defined.unwrap_or(0),
+                    value
+                ),
+            )),
+        }
+    }
+
+    /// Define the label of the current line
+    ///
+    /// A second definition is reported at the label.
+    fn define_symbol(&mut self, name: &str, value: u16, line_num: usize) -> Result<()> {
+        let start = self.line_columns.label;
+        let span = if start > 0 {
+            SourceSpan::new(line_num + 1, start, start + name.chars().count())
+        } else {
+            SourceSpan::whole_line(line_num + 1)
+        };
+        self.define_symbol_at(name, value, span)
+    }
+
+    /// Define `name`, reporting a second definition at `span`
+    fn define_symbol_at(&mut self, name: &str, value: u16, span: SourceSpan) -> Result<()> {
+        self.symbols
+            .define(name, value)
+            .map_err(|_| AssemblerError::DuplicateLabel {
+                name: name.to_string(),
+                span,
+            })
+    }
+
+    /// Span of `expr` within the current line's operand
+    ///
+    /// `expr` must be a slice of the operand being assembled. `within` is
+    /// a span relative to the trimmed expression, as reported by
+    /// `expression::evaluate`; without columns the whole expression is
+    /// meant. Falls back to the whole line if the columns are not known.
+    fn expression_span(&self, line: usize, expr: &str, within: Option<SourceSpan>) -> SourceSpan {
+        let expr = expr.trim();
+        let columns = &self.line_columns;
+        let Some(offset) = columns.offset_of(expr) else {
+            return SourceSpan::whole_line(line);
+        };
+        let start = columns.operand + columns.operand_text[..offset].chars().count();
+        match within.filter(SourceSpan::has_columns) {
+            Some(token) => SourceSpan::new(
+                line,
+                start + token.column_start - 1,
+                start + token.column_end - 1,
+            ),
+            None => SourceSpan::new(line, start, start + expr.chars().count()),
+        }
+    }
+
     /// Pass 2: Generate machine code
-    fn pass2(&mut self, lines: &[parser::ParsedLine]) -> Result<Vec<u16>> {
-        let mut words = Vec::new();
-        self.location_counter = self.origin;
+    ///
+    /// Returns the address and words generated by each parsed line.
+    fn pass2(&mut self, lines: &[parser::ParsedLine], start: u16) -> Result<Vec<GeneratedLine>> {
+        let mut generated = Vec::with_capacity(lines.len());
+        self.location_counter = start;
+        // Only pass 2 references count; pass 1 may have evaluated some
+        self.symbol_refs.clear();
 
-        for (line_num, line) in lines.iter().enumerate() {
-            match &line.operation {
+        for line in lines {
+            let line_num = line.line_number - 1;
+            self.line_columns = LineColumns::of(line);
+            let address = self.location_counter;
+            let words = match &line.operation {
                 parser::Operation::Instruction(instr) => {
                     let encoded = self.encode_instruction(instr, &line.operand, line_num)?;
-                    words.extend_from_slice(&encoded);
                     self.location_counter =
                         self.location_counter.wrapping_add(encoded.len() as u16);
+                    encoded
+                }
+                parser::Operation::PseudoOp(pseudo) if pseudo == "EQU" => {
+                    self.check_equ(line, line_num)?;
+                    vec![]
                 }
                 parser::Operation::PseudoOp(pseudo) => {
-                    let data = self.process_pseudo_pass2(pseudo, &line.operand, line_num)?;
-                    words.extend_from_slice(&data);
+                    self.process_pseudo_pass2(pseudo, &line.operand, line_num)?
                 }
-                parser::Operation::None => {}
-            }
+                parser::Operation::None => vec![],
+            };
+            self.note_external_reference(
+                &line.operation,
+                line.operand.as_deref(),
+                address,
+                &words,
+                line_num,
+            )?;
+            self.note_relocation(&line.operation, line.operand.as_deref(), address, &words);
+            generated.push(GeneratedLine {
+                line_number: line.line_number,
+                address,
+                words,
+            });
         }
 
-        Ok(words)
+        Ok(generated)
     }
 
     /// Get instruction size in words
@@ -172,10 +1083,10 @@ impl Assembler {
                     "XIO" => OpCode::XIO,
                     "SDS" => OpCode::SDS,
                     _ => {
-                        return Err(AssemblerError::SyntaxError {
-                            line: 0,
-                            message: format!("Unknown instruction: {}", instr),
-                        })
+                        return Err(AssemblerError::syntax(
+                            0,
+                            format!("Unknown instruction: {}", instr),
+                        ))
                     }
                 };
 
@@ -198,12 +1109,26 @@ impl Assembler {
                 if let Some(ref addr_str) = operand {
                     let addr = self.parse_expression(addr_str, line_num)?;
                     self.location_counter = addr;
-                    self.origin = addr;
+                    self.origin.get_or_insert(addr);
                 }
             }
             "DC" => {
-                // Define constant - advances location by 1
-                self.location_counter = self.location_counter.wrapping_add(1);
+                // Define constant - one word, or one per two characters
+                let size = operand
+                    .as_deref()
+                    .and_then(|operand| characters::quoted_text(operand)?.ok())
+                    .map_or(1, |text| characters::packed_len(&text));
+                self.location_counter = self.location_counter.wrapping_add(size);
+            }
+            "DMES" => {
+                // Message - one word per two characters
+                let text = self.dmes_text(operand, line_num)?;
+                let size = characters::packed_len(&text);
+                self.location_counter = self.location_counter.wrapping_add(size);
+            }
+            "IOCC" => {
+                // I/O channel command - WCA word plus device/function word
+                self.location_counter = self.location_counter.wrapping_add(2);
             }
             "BSS" => {
                 // Block started by symbol - reserve space
@@ -216,13 +1141,42 @@ impl Assembler {
                 // End of assembly
             }
             "EQU" => {
-                // Equate - handled during label processing
+                // Equate - handled by define_equ
+            }
+            "TITLE" | "HDNG" => {
+                // Listing heading - emits no words
+                if self.listing_title.is_none() {
+                    self.listing_title = Some(title_text(operand.as_deref().unwrap_or("")));
+                }
+            }
+            "ENT" => {
+                // Entry point for other modules - checked after pass 2
+                let names = linkage::symbol_list(operand.as_deref());
+                if names.is_empty() {
+                    return Err(AssemblerError::syntax(
+                        line_num + 1,
+                        "ENT requires at least one symbol".to_string(),
+                    ));
+                }
+                for name in names {
+                    let span = self.expression_span(line_num + 1, name, None);
+                    self.entries.push((name.to_string(), span));
+                }
+            }
+            "EXTRN" => {
+                // Symbols defined in another module
+                self.declare_externals(operand.as_deref(), line_num)?;
+            }
+            "LIBF" | "CALL" => {
+                // Subprogram call - a BSI, external if the name is not defined
+                linkage::called_name(pseudo, operand.as_deref(), line_num)?;
+                self.location_counter = self.location_counter.wrapping_add(2);
             }
             _ => {
-                return Err(AssemblerError::SyntaxError {
-                    line: line_num + 1,
-                    message: format!("Unknown pseudo-op: {}", pseudo),
-                });
+                return Err(AssemblerError::syntax(
+                    line_num + 1,
+                    format!("Unknown pseudo-op: {}", pseudo),
+                ));
             }
         }
         Ok(())
@@ -237,23 +1191,47 @@ impl Assembler {
     ) -> Result<Vec<u16>> {
         match pseudo {
             "ORG" => {
+                // Origin was recorded in pass 1
                 if let Some(ref addr_str) = operand {
-                    let addr = self.parse_expression(addr_str, line_num)?;
-                    self.location_counter = addr;
-                    self.origin = addr;
+                    self.location_counter = self.parse_expression(addr_str, line_num)?;
                 }
                 Ok(vec![])
             }
             "DC" => {
-                if let Some(ref value_str) = operand {
+                if let Some(text) = operand.as_deref().and_then(characters::quoted_text) {
+                    let text = text
+                        .and_then(|text| characters::check_ascii(&text).map(|()| text))
+                        .map_err(|message| AssemblerError::syntax(line_num + 1, message))?;
+                    let words = characters::pack(&text, self.char_packing);
+                    self.location_counter = self.location_counter.wrapping_add(words.len() as u16);
+                    Ok(words)
+                } else if let Some(ref value_str) = operand {
                     let value = self.parse_expression(value_str, line_num)?;
                     self.location_counter = self.location_counter.wrapping_add(1);
                     Ok(vec![value])
                 } else {
-                    Err(AssemblerError::SyntaxError {
-                        line: line_num + 1,
-                        message: "DC requires an operand".to_string(),
-                    })
+                    Err(AssemblerError::syntax(
+                        line_num + 1,
+                        "DC requires an operand".to_string(),
+                    ))
+                }
+            }
+            "DMES" => {
+                let text = self.dmes_text(operand, line_num)?;
+                let words = characters::pack_message(&text);
+                self.location_counter = self.location_counter.wrapping_add(words.len() as u16);
+                Ok(words)
+            }
+            "IOCC" => {
+                if let Some(ref iocc_str) = operand {
+                    let words = self.encode_iocc(iocc_str, line_num)?;
+                    self.location_counter = self.location_counter.wrapping_add(2);
+                    Ok(words)
+                } else {
+                    Err(AssemblerError::syntax(
+                        line_num + 1,
+                        "IOCC requires WCA,DEVICE,FUNCTION,MODIFIERS".to_string(),
+                    ))
                 }
             }
             "BSS" => {
@@ -262,10 +1240,10 @@ impl Assembler {
                     self.location_counter = self.location_counter.wrapping_add(size);
                     Ok(vec![0; size as usize])
                 } else {
-                    Err(AssemblerError::SyntaxError {
-                        line: line_num + 1,
-                        message: "BSS requires a size operand".to_string(),
-                    })
+                    Err(AssemblerError::syntax(
+                        line_num + 1,
+                        "BSS requires a size operand".to_string(),
+                    ))
                 }
             }
             "END" => {
@@ -276,16 +1254,48 @@ impl Assembler {
                 Ok(vec![])
             }
             "EQU" => {
-                // EQU is handled during symbol definition
+                // Equate - checked by check_equ
                 Ok(vec![])
             }
+            "TITLE" | "HDNG" => {
+                // Listing heading - recorded in pass 1
+                Ok(vec![])
+            }
+            "ENT" | "EXTRN" => {
+                // Linkage - recorded in pass 1
+                Ok(vec![])
+            }
+            "LIBF" | "CALL" => {
+                let name = linkage::called_name(pseudo, operand.as_deref(), line_num)?;
+                // LIBF goes through the transfer vector word NAME labels
+                let target = if pseudo == "LIBF" {
+                    format!("*{}", name)
+                } else {
+                    name.to_string()
+                };
+                let words = self.encode_instruction("BSI", &Some(target), line_num)?;
+                self.location_counter = self.location_counter.wrapping_add(words.len() as u16);
+                Ok(words)
+            }
             _ => Ok(vec![]),
         }
     }
 
+    /// Quoted text of a `DMES` operand
+    fn dmes_text(&self, operand: &Option<String>, line_num: usize) -> Result<String> {
+        operand
+            .as_deref()
+            .and_then(characters::quoted_text)
+            .ok_or_else(|| {
+                AssemblerError::syntax(line_num + 1, "DMES requires a quoted string".to_string())
+            })?
+            .and_then(|text| characters::check_ascii(&text).map(|()| text))
+            .map_err(|message| AssemblerError::syntax(line_num + 1, message))
+    }
+
     /// Encode an instruction to machine code
     fn encode_instruction(
-        &self,
+        &mut self,
         mnemonic: &str,
         operand: &Option<String>,
         line_num: usize,
@@ -323,26 +1333,14 @@ impl Assembler {
             "XIO" => OpCode::XIO as u16,
             "SDS" => OpCode::SDS as u16,
             _ => {
-                return Err(AssemblerError::SyntaxError {
-                    line: line_num + 1,
-                    message: format!("Unknown instruction: {}", mnemonic),
-                });
+                return Err(AssemblerError::syntax(
+                    line_num + 1,
+                    format!("Unknown instruction: {}", mnemonic),
+                ));
             }
         };
 
-        // Parse operand if present
-        // Note: LDX/STX/MDX have reversed operand format: "tag,address" not "address,tag"
-        let (displacement, tag, indirect) = if let Some(ref op_str) = operand {
-            if matches!(mnemonic, "LDX" | "STX" | "MDX") {
-                self.parse_index_operand(op_str, line_num)?
-            } else {
-                self.parse_operand(op_str, line_num)?
-            }
-        } else {
-            (0, 0, false)
-        };
-
-        // Encode based on format
+        // Long-format instructions reference memory
         let is_long = matches!(
             mnemonic,
             "LD" | "LDD"
@@ -366,46 +1364,153 @@ impl Assembler {
                 | "XIO"
         );
 
+        // Parse operand if present. Memory-reference instructions need one;
+        // the rest (WAIT, shifts, BSC) default to zero.
+        // Note: LDX/STX/MDX have reversed operand format: "tag,address" not "address,tag"
+        // MDX 0,ADDR,INC modifies memory: INC goes in word1's modifier bits
+        // BC/BSC take "address,condition" with a 0-15 condition mask
+        let mut modifiers = 0;
+        let mut condition = 0;
+        let (displacement, tag, indirect) = if let Some(ref op_str) = operand {
+            if mnemonic == "MDX" && op_str.matches(',').count() == 2 {
+                let (index_operand, increment) = op_str.rsplit_once(',').unwrap_or_default();
+                let parsed = self.parse_index_operand(index_operand, line_num)?;
+                modifiers = self.parse_mdx_increment(increment, parsed.1, line_num)?;
+                parsed
+            } else if matches!(mnemonic, "LDX" | "STX" | "MDX") {
+                self.parse_index_operand(op_str, line_num)?
+            } else if matches!(mnemonic, "BC" | "BSC") {
+                let (parsed, mask) = self.parse_branch_operand(op_str, line_num)?;
+                condition = mask;
+                parsed
+            } else {
+                self.parse_operand(op_str, line_num)?
+            }
+        } else if is_long {
+            return Err(AssemblerError::syntax(
+                line_num + 1,
+                format!("{} requires an operand", mnemonic),
+            ));
+        } else {
+            (0, 0, false)
+        };
+
+        // Direct short-format branches are IAR-relative: encode the signed
+        // distance from the next instruction rather than the absolute address.
+        // (Direct BSC is a skip, so its operand only carries the condition.)
+        // BC has no long format here, so every direct branch is already the
+        // one-word form; a far target has to go through an indirect pointer.
+        let displacement = if mnemonic == "BC" && operand.is_some() && !indirect {
+            let next = self.location_counter.wrapping_add(1);
+            let offset = displacement.wrapping_sub(next) as i16;
+            if !(-16..=15).contains(&offset) {
+                return Err(AssemblerError::syntax(
+                    line_num + 1,
+                    format!(
+                        "Branch target {:#06x} out of short-format range (offset {}); \
+                         branch indirectly through a DC word instead",
+                        displacement, offset
+                    ),
+                ));
+            }
+            offset as u16
+        } else {
+            displacement
+        };
+
         if is_long {
             // Long format: opcode + tag + indirect + displacement word
-            let word1 = (opcode << 8) | ((tag as u16) << 6) | (if indirect { 0x20 } else { 0 });
+            let word1 =
+                (opcode << 8) | ((tag as u16) << 6) | (if indirect { 0x20 } else { 0 }) | modifiers;
             Ok(vec![word1, displacement])
         } else {
-            // Short format: opcode + tag + indirect + 5-bit address
+            // Short format: opcode + tag + indirect + 5-bit address; a
+            // branch condition overlays the tag and the opcode's low bits
             let word1 = (opcode << 8)
                 | ((tag as u16) << 6)
+                | (u16::from(condition) << crate::instructions::CONDITION_SHIFT)
                 | (if indirect { 0x20 } else { 0 })
                 | (displacement & 0x1F);
             Ok(vec![word1])
         }
     }
 
+    /// Encode an `IOCC WCA,DEVICE,FUNCTION,MODIFIERS` operand into two words
+    ///
+    /// DEVICE may be a standard device name (e.g. `KEYBOARD`) or a device
+    /// code; FUNCTION may be a function name (e.g. `READ`) or a 0-7 code.
+    fn encode_iocc(&mut self, operand: &str, line_num: usize) -> Result<Vec<u16>> {
+        use crate::devices::{DeviceFunction, Iocc, StandardDevice};
+
+        let fields: Vec<&str> = operand.split(',').map(str::trim).collect();
+        if fields.len() != 4 {
+            return Err(AssemblerError::syntax(
+                line_num + 1,
+                format!(
+                    "IOCC expects WCA,DEVICE,FUNCTION,MODIFIERS, got: {}",
+                    operand
+                ),
+            ));
+        }
+
+        let wca = self.parse_expression(fields[0], line_num)?;
+
+        let device_code = match StandardDevice::from_name(fields[1]) {
+            Some(device) => device.code(),
+            None => {
+                let code = self.parse_expression(fields[1], line_num)?;
+                if code > 31 {
+                    return Err(AssemblerError::ValueOutOfRange(code as i32));
+                }
+                code as u8
+            }
+        };
+
+        let function = match DeviceFunction::from_name(fields[2]) {
+            Some(function) => function,
+            None => {
+                let code = self.parse_expression(fields[2], line_num)?;
+                if code > 7 {
+                    return Err(AssemblerError::ValueOutOfRange(code as i32));
+                }
+                DeviceFunction::from_bits(code as u8)
+                    .ok_or(AssemblerError::ValueOutOfRange(code as i32))?
+            }
+        };
+
+        let modifiers = self.parse_expression(fields[3], line_num)?;
+        if modifiers > 0xFF {
+            return Err(AssemblerError::ValueOutOfRange(modifiers as i32));
+        }
+
+        let (word1, word2) = Iocc {
+            wca,
+            device_code,
+            function,
+            modifiers: modifiers as u8,
+        }
+        .encode();
+        Ok(vec![word1, word2])
+    }
+
     /// Parse operand string into (displacement, tag, indirect)
-    fn parse_operand(&self, operand: &str, line_num: usize) -> Result<(u16, u8, bool)> {
+    fn parse_operand(&mut self, operand: &str, line_num: usize) -> Result<(u16, u8, bool)> {
         let operand = operand.trim();
 
-        // Check for indirect addressing: /address or *address
-        let (indirect, operand) = if operand.starts_with('/') || operand.starts_with('*') {
-            (true, &operand[1..])
-        } else {
-            (false, operand)
-        };
+        let (indirect, operand) = split_indirect(operand);
 
         // Check for index register: address,1 or address,2 or address,3
         let (address_str, tag) = if let Some(comma_pos) = operand.rfind(',') {
             let addr = &operand[..comma_pos];
             let tag_str = operand[comma_pos + 1..].trim();
-            let tag = tag_str
-                .parse::<u8>()
-                .map_err(|_| AssemblerError::SyntaxError {
-                    line: line_num + 1,
-                    message: format!("Invalid index register: {}", tag_str),
-                })?;
+            let tag = tag_str.parse::<u8>().map_err(|_| {
+                AssemblerError::syntax(line_num + 1, format!("Invalid index register: {}", tag_str))
+            })?;
             if tag > 3 {
-                return Err(AssemblerError::SyntaxError {
-                    line: line_num + 1,
-                    message: format!("Index register must be 0-3, got {}", tag),
-                });
+                return Err(AssemblerError::syntax(
+                    line_num + 1,
+                    format!("Index register must be 0-3, got {}", tag),
+                ));
             }
             (addr, tag)
         } else {
@@ -418,34 +1523,55 @@ impl Assembler {
         Ok((displacement, tag, indirect))
     }
 
+    /// Parse a BC/BSC operand: `address` or `address,condition`
+    ///
+    /// The condition is a 0-15 mask (see `instructions::CONDITION_*`), not
+    /// an index register; the address part is parsed as usual.
+    fn parse_branch_operand(
+        &mut self,
+        operand: &str,
+        line_num: usize,
+    ) -> Result<((u16, u8, bool), u8)> {
+        let (address, condition) = match operand.rsplit_once(',') {
+            Some((address, condition_str)) => {
+                let condition_str = condition_str.trim();
+                let condition = condition_str
+                    .parse::<u8>()
+                    .ok()
+                    .filter(|&condition| condition <= 0x0F)
+                    .ok_or_else(|| {
+                        AssemblerError::syntax(
+                            line_num + 1,
+                            format!("Branch condition must be 0-15, got {}", condition_str),
+                        )
+                    })?;
+                (address, condition)
+            }
+            None => (operand, 0),
+        };
+        Ok((self.parse_operand(address, line_num)?, condition))
+    }
+
     /// Parse index register operand (format: "tag,address" for LDX/STX/MDX)
-    fn parse_index_operand(&self, operand: &str, line_num: usize) -> Result<(u16, u8, bool)> {
+    fn parse_index_operand(&mut self, operand: &str, line_num: usize) -> Result<(u16, u8, bool)> {
         let operand = operand.trim();
 
-        // Check for indirect addressing: /address or *address
-        let (indirect, operand) = if operand.starts_with('/') || operand.starts_with('*') {
-            (true, &operand[1..])
-        } else {
-            (false, operand)
-        };
+        let (indirect, operand) = split_indirect(operand);
 
         // For index instructions, format is "tag,address" (reversed from normal)
         if let Some(comma_pos) = operand.find(',') {
             let tag_str = operand[..comma_pos].trim();
             let address_str = &operand[comma_pos + 1..].trim();
 
-            let tag = tag_str
-                .parse::<u8>()
-                .map_err(|_| AssemblerError::SyntaxError {
-                    line: line_num + 1,
-                    message: format!("Invalid index register: {}", tag_str),
-                })?;
+            let tag = tag_str.parse::<u8>().map_err(|_| {
+                AssemblerError::syntax(line_num + 1, format!("Invalid index register: {}", tag_str))
+            })?;
 
             if tag > 3 {
-                return Err(AssemblerError::SyntaxError {
-                    line: line_num + 1,
-                    message: format!("Index register must be 0-3, got {}", tag),
-                });
+                return Err(AssemblerError::syntax(
+                    line_num + 1,
+                    format!("Index register must be 0-3, got {}", tag),
+                ));
             }
 
             let displacement = self.parse_expression(address_str, line_num)?;
@@ -457,54 +1583,99 @@ impl Assembler {
         }
     }
 
-    /// Parse numeric expression (supports decimal, hex, octal, and symbols)
-    fn parse_expression(&self, expr: &str, line_num: usize) -> Result<u16> {
-        let expr = expr.trim();
+    /// Parse the increment of `MDX 0,ADDR,INC` into five modifier bits
+    ///
+    /// Only tag 0 takes an increment, and it must fit -16..=15.
+    fn parse_mdx_increment(&mut self, increment: &str, tag: u8, line_num: usize) -> Result<u16> {
+        if tag != 0 {
+            return Err(AssemblerError::syntax(
+                line_num + 1,
+                "MDX increment is only allowed with tag 0".to_string(),
+            ));
+        }
+        let value = self.parse_expression(increment, line_num)? as i16;
+        if !(-16..=15).contains(&value) {
+            return Err(AssemblerError::syntax(
+                line_num + 1,
+                format!("MDX increment {} outside -16..15", value),
+            ));
+        }
+        Ok(value as u16 & 0x1F)
+    }
+
+    /// Evaluate an operand expression (see `expression::eval_expression`)
+    ///
+    /// Symbols are looked up first; mnemonics have no meaning in an operand.
+    /// Each symbol used is recorded against the location counter for the
+    /// cross-reference.
+    fn parse_expression(&mut self, expr: &str, line_num: usize) -> Result<u16> {
+        let evaluation =
+            expression::evaluate(expr, &self.symbols, self.location_counter).map_err(|e| {
+                let (within, message) = match e {
+                    AssemblerError::SyntaxError { span, message, .. } => (Some(span), message),
+                    AssemblerError::UndefinedSymbol { span, .. } => (Some(span), e.to_string()),
+                    other => (None, other.to_string()),
+                };
+                AssemblerError::syntax_at(self.expression_span(line_num + 1, expr, within), message)
+            })?;
 
-        // Check if it's a symbol
-        if let Some(value) = self.symbols.lookup(expr) {
-            return Ok(value);
+        let address = self.location_counter;
+        self.symbol_refs
+            .extend(evaluation.symbols.into_iter().map(|name| (name, address)));
+
+        let value = evaluation.value;
+        let word = value as u16;
+        if !expression::fits_word(value) {
+            self.warn(
+                line_num + 1,
+                format!(
+                    "{}, wrapped to {:#06x}",
+                    AssemblerError::ValueOutOfRange(value),
+                    word
+                ),
+            );
         }
+        Ok(word)
+    }
 
-        // Parse numeric literal
-        if expr.starts_with('/') && expr.len() > 1 {
-            // IBM 1130 hexadecimal notation: /NNNN
-            let hex_str = &expr[1..];
-            // Validate all characters are hex digits
-            if hex_str.chars().all(|c| c.is_ascii_hexdigit()) {
-                u16::from_str_radix(hex_str, 16).map_err(|_| AssemblerError::SyntaxError {
-                    line: line_num + 1,
-                    message: format!("Invalid hex literal: {}", expr),
-                })
-            } else {
-                Err(AssemblerError::SyntaxError {
-                    line: line_num + 1,
-                    message: format!("Invalid hex literal (non-hex digits): {}", expr),
-                })
-            }
-        } else if expr.starts_with("0X") || expr.starts_with("0x") {
-            // Alternative hexadecimal format: 0xNNNN
-            u16::from_str_radix(&expr[2..], 16).map_err(|_| AssemblerError::SyntaxError {
-                line: line_num + 1,
-                message: format!("Invalid hex literal: {}", expr),
-            })
-        } else if expr.starts_with('0') && expr.len() > 1 {
-            // Octal: 0NNN (leading zero)
-            u16::from_str_radix(&expr[1..], 8).map_err(|_| AssemblerError::SyntaxError {
-                line: line_num + 1,
-                message: format!("Invalid octal literal: {}", expr),
-            })
-        } else {
-            // Decimal
-            expr.parse::<u16>()
-                .map_err(|_| AssemblerError::SyntaxError {
-                    line: line_num + 1,
-                    message: format!("Undefined symbol or invalid number: {}", expr),
-                })
+    /// Record a warning once, even if both passes evaluate the line
+    fn warn(&mut self, line: usize, message: String) {
+        let warning = AssemblerWarning { line, message };
+        if !self.warnings.contains(&warning) {
+            self.warnings.push(warning);
+        }
+    }
+}
+
+/// Strip an indirect prefix: `/address` or `*address`
+///
+/// A `*` followed by an operator or nothing is the current-address value
+/// (as in `*+4`), not an indirect marker. Likewise a `/` followed by hex
+/// digits only is a hex literal (`/0100`), matching the lexer, even when
+/// a label of the same name exists; write `/ A` or `*A` for indirect
+/// through label `A`.
+fn split_indirect(operand: &str) -> (bool, &str) {
+    let mut chars = operand.chars();
+    match (chars.next(), chars.next()) {
+        (Some('/'), _) if starts_with_hex_word(&operand[1..]) => (false, operand),
+        (Some('/'), _) => (true, &operand[1..]),
+        (Some('*'), Some(c))
+            if !(matches!(c, '+' | '-' | '*' | '/' | ',') || c.is_whitespace()) =>
+        {
+            (true, &operand[1..])
         }
+        _ => (false, operand),
     }
 }
 
+/// Check whether `text` starts with a word made only of hex digits
+fn starts_with_hex_word(text: &str) -> bool {
+    let word_end = text
+        .find(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '$' | '#' | '@' | '_')))
+        .unwrap_or(text.len());
+    word_end > 0 && text[..word_end].chars().all(|c| c.is_ascii_hexdigit())
+}
+
 impl Default for Assembler {
     fn default() -> Self {
         Self::new()
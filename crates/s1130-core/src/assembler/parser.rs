@@ -2,7 +2,7 @@
 //!
 //! Parses tokens into structured assembly lines.
 
-use crate::error::AssemblerError;
+use crate::error::{AssemblerError, SourceSpan};
 
 /// Result type for assembler operations
 pub type Result<T> = std::result::Result<T, AssemblerError>;
@@ -10,6 +10,9 @@ pub type Result<T> = std::result::Result<T, AssemblerError>;
 /// Parsed assembly line
 #[derive(Debug, Clone)]
 pub struct ParsedLine {
+    /// Source line number (1-indexed)
+    pub line_number: usize,
+
     /// Optional label
     pub label: Option<String>,
 
@@ -18,6 +21,15 @@ pub struct ParsedLine {
 
     /// Optional operand
     pub operand: Option<String>,
+
+    /// Column (1-indexed) where the label starts, 0 if not known
+    pub label_column: usize,
+
+    /// Column (1-indexed) where the operand starts, 0 if not known
+    ///
+    /// The operand keeps single spaces between words, so columns after a
+    /// run of spaces inside it are approximate.
+    pub operand_column: usize,
 }
 
 /// Operation type
@@ -35,132 +47,150 @@ pub enum Operation {
 
 /// Parse source code into lines
 pub fn parse_source(source: &str) -> Result<Vec<ParsedLine>> {
-    let mut lines = Vec::new();
+    parse_numbered(
+        source
+            .lines()
+            .enumerate()
+            .map(|(index, line)| (index + 1, line)),
+    )
+}
+
+/// Parse numbered source lines, e.g. after macro expansion
+///
+/// Like `parse_source`, but each line carries its own (1-indexed) number.
+pub fn parse_numbered<'a>(
+    lines: impl IntoIterator<Item = (usize, &'a str)>,
+) -> Result<Vec<ParsedLine>> {
+    let mut parsed_lines = Vec::new();
 
-    for (line_num, line_text) in source.lines().enumerate() {
-        let parsed = parse_line(line_text, line_num + 1)?;
+    for (line_num, line_text) in lines {
+        let parsed = parse_line(line_text, line_num)?;
         if !matches!(parsed.operation, Operation::None) || parsed.label.is_some() {
-            lines.push(parsed);
+            parsed_lines.push(parsed);
         }
     }
 
-    Ok(lines)
+    Ok(parsed_lines)
 }
 
-/// Parse a single line
-fn parse_line(line: &str, line_num: usize) -> Result<ParsedLine> {
-    // Don't trim initially - we need to check for leading whitespace
-    let original_line = line;
-
-    // Skip empty lines
-    if original_line.trim().is_empty() {
-        return Ok(ParsedLine {
-            label: None,
-            operation: Operation::None,
-            operand: None,
-        });
-    }
+/// Label, operation and operand fields of a source line, before classification
+#[derive(Debug, Default, PartialEq, Eq)]
+pub(crate) struct Fields<'a> {
+    pub label: Option<&'a str>,
+    pub operation: Option<&'a str>,
+    pub operand: Option<String>,
+    /// Columns (1-indexed) of the operation and operand, 0 if absent
+    pub operation_column: usize,
+    pub operand_column: usize,
+}
 
-    // Check for full-line comment (starts with *)
-    if original_line.trim_start().starts_with('*') {
-        return Ok(ParsedLine {
-            label: None,
-            operation: Operation::None,
-            operand: None,
-        });
+/// Split a line into its fields, dropping comments
+///
+/// A line starting with whitespace has no label. Comment and blank lines
+/// have no fields at all.
+pub(crate) fn split_fields(line: &str) -> Fields<'_> {
+    if line.trim_start().starts_with('*') {
+        return Fields::default();
     }
 
-    // Strip inline comments (everything after first '*' that's not at position 0)
-    let line_without_comment = if let Some(comment_pos) = original_line.find('*') {
-        if comment_pos > 0 {
-            &original_line[..comment_pos]
-        } else {
-            original_line
-        }
-    } else {
-        original_line
+    // Strip inline comments (see find_inline_comment for where '*' starts one)
+    let line_without_comment = match find_inline_comment(line) {
+        Some(comment_pos) => &line[..comment_pos],
+        None => line,
     };
 
-    // Check if line starts with whitespace to determine if there's a label
-    let has_leading_whitespace = line_without_comment
-        .chars()
-        .next()
-        .map(|c| c.is_whitespace())
-        .unwrap_or(false);
-
-    // Now split into tokens (this will trim each token)
     let parts: Vec<&str> = line_without_comment.split_whitespace().collect();
-
     if parts.is_empty() {
-        return Ok(ParsedLine {
-            label: None,
-            operation: Operation::None,
-            operand: None,
-        });
+        return Fields::default();
     }
 
-    let mut label = None;
-    let mut operation = Operation::None;
-    let mut operand = None;
-
-    if has_leading_whitespace {
-        // No label - line starts with whitespace
-        // First token is operation
-        let first = parts[0];
-
-        if is_instruction(first) {
-            operation = Operation::Instruction(first.to_uppercase());
-        } else if is_pseudo_op(first) {
-            operation = Operation::PseudoOp(first.to_uppercase());
-        } else {
-            return Err(AssemblerError::SyntaxError {
-                line: line_num,
-                message: format!("Expected instruction or pseudo-op, got: {}", first),
-            });
-        }
-
-        // Rest is operand
-        if parts.len() > 1 {
-            operand = Some(parts[1..].join(" "));
-        }
+    // Check if line starts with whitespace to determine if there's a label
+    let (label, rest) = if line.starts_with(char::is_whitespace) {
+        (None, &parts[..])
     } else {
-        // Line starts with non-whitespace - first token is label
-        label = Some(parts[0].to_string());
-
-        if parts.len() > 1 {
-            let second = parts[1];
-
-            if is_instruction(second) {
-                operation = Operation::Instruction(second.to_uppercase());
-
-                if parts.len() > 2 {
-                    operand = Some(parts[2..].join(" "));
-                }
-            } else if is_pseudo_op(second) {
-                operation = Operation::PseudoOp(second.to_uppercase());
-
-                if parts.len() > 2 {
-                    operand = Some(parts[2..].join(" "));
-                }
-            } else {
-                return Err(AssemblerError::SyntaxError {
-                    line: line_num,
-                    message: format!("Expected instruction or pseudo-op, got: {}", second),
-                });
-            }
-        }
-        // If only one token (label only), operation stays None
+        (Some(parts[0]), &parts[1..])
+    };
+
+    let column = |word: Option<&&str>| word.map_or(0, |word| column_of(line, word));
+    Fields {
+        label,
+        operation: rest.first().copied(),
+        operand: (rest.len() > 1).then(|| rest[1..].join(" ")),
+        operation_column: column(rest.first()),
+        operand_column: column(rest.get(1)),
     }
+}
+
+/// Column (1-indexed) at which `word`, a slice of `line`, starts
+fn column_of(line: &str, word: &str) -> usize {
+    let offset = word.as_ptr() as usize - line.as_ptr() as usize;
+    line[..offset].chars().count() + 1
+}
+
+/// Parse a single line
+fn parse_line(line: &str, line_num: usize) -> Result<ParsedLine> {
+    let fields = split_fields(line);
+
+    let operation = match fields.operation {
+        None => Operation::None,
+        Some(op) if is_instruction(op) => Operation::Instruction(op.to_uppercase()),
+        Some(op) if is_pseudo_op(op) => Operation::PseudoOp(op.to_uppercase()),
+        Some(op) => {
+            let start = fields.operation_column;
+            return Err(AssemblerError::syntax_at(
+                SourceSpan::new(line_num, start, start + op.chars().count()),
+                format!("Expected instruction or pseudo-op, got: {}", op),
+            ));
+        }
+    };
 
     Ok(ParsedLine {
-        label,
+        line_number: line_num,
+        label: fields.label.map(str::to_string),
         operation,
-        operand,
+        label_column: usize::from(fields.label.is_some()),
+        operand: fields.operand,
+        operand_column: fields.operand_column,
     })
 }
 
+/// Find the byte offset of an inline `*` comment, if any
+///
+/// Fields are label, operation, operand, remarks. A word starting with `*`
+/// in the operation or remarks field begins a comment. In the operand field
+/// `*` is the current address (`*`, `*+4`) or an indirect marker (`*VAL`),
+/// except after WAIT, which takes no operand.
+fn find_inline_comment(line: &str) -> Option<usize> {
+    let first_field = if line.starts_with(char::is_whitespace) {
+        1
+    } else {
+        0
+    };
+    let mut operation = "";
+
+    for (index, (pos, word)) in words_with_offsets(line).enumerate() {
+        let field = first_field + index;
+        if field == 1 {
+            operation = word;
+        }
+        if !word.starts_with('*') || field == 0 {
+            continue;
+        }
+        if field != 2 || operation.eq_ignore_ascii_case("WAIT") {
+            return Some(pos);
+        }
+    }
+    None
+}
+
+/// Whitespace-separated words with their byte offsets
+fn words_with_offsets(line: &str) -> impl Iterator<Item = (usize, &str)> {
+    line.split_whitespace()
+        .map(move |word| (word.as_ptr() as usize - line.as_ptr() as usize, word))
+}
+
 /// Check if string is a valid instruction
-fn is_instruction(s: &str) -> bool {
+pub(crate) fn is_instruction(s: &str) -> bool {
     matches!(
         s.to_uppercase().as_str(),
         "LD" | "LDD"
@@ -197,7 +227,19 @@ fn is_instruction(s: &str) -> bool {
 fn is_pseudo_op(s: &str) -> bool {
     matches!(
         s.to_uppercase().as_str(),
-        "ORG" | "DC" | "BSS" | "END" | "EQU"
+        "ORG"
+            | "DC"
+            | "DMES"
+            | "BSS"
+            | "END"
+            | "EQU"
+            | "IOCC"
+            | "TITLE"
+            | "HDNG"
+            | "ENT"
+            | "EXTRN"
+            | "LIBF"
+            | "CALL"
     )
 }
 
@@ -236,6 +278,14 @@ mod tests {
         assert!(matches!(line.operation, Operation::None));
     }
 
+    #[test]
+    fn test_parse_source_keeps_line_numbers() {
+        let lines = parse_source("* comment\n\n    LD 100\n    WAIT").unwrap();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].line_number, 3);
+        assert_eq!(lines[1].line_number, 4);
+    }
+
     #[test]
     fn test_parse_comment() {
         let line = parse_line("* This is a comment", 1).unwrap();
@@ -259,4 +309,31 @@ mod tests {
         let line = parse_line("    LD 100,1", 1).unwrap();
         assert_eq!(line.operand, Some("100,1".to_string()));
     }
+
+    #[test]
+    fn test_operand_and_error_columns() {
+        let line = parse_line("LOOP\tLD   VAL", 1).unwrap();
+        assert_eq!(line.operand_column, 11);
+
+        let err = parse_line("      FOO  1", 7).unwrap_err();
+        assert_eq!(err.span(), Some(SourceSpan::new(7, 7, 10)));
+    }
+
+    #[test]
+    fn test_parse_inline_comment_vs_star_operand() {
+        let line = parse_line("    LD A   * load A", 1).unwrap();
+        assert_eq!(line.operand, Some("A".to_string()));
+
+        let line = parse_line("    LD *+4", 1).unwrap();
+        assert_eq!(line.operand, Some("*+4".to_string()));
+
+        let line = parse_line("    DC A*2", 1).unwrap();
+        assert_eq!(line.operand, Some("A*2".to_string()));
+
+        let line = parse_line("HERE EQU *  * current address", 1).unwrap();
+        assert_eq!(line.operand, Some("*".to_string()));
+
+        let line = parse_line("    WAIT *halt", 1).unwrap();
+        assert_eq!(line.operand, None);
+    }
 }
@@ -26,7 +26,7 @@ impl SymbolTable {
     /// Define a new symbol
     pub fn define(&mut self, name: &str, address: u16) -> Result<()> {
         if self.symbols.contains_key(name) {
-            return Err(AssemblerError::DuplicateLabel(name.to_string()));
+            return Err(AssemblerError::duplicate_label(name));
         }
 
         self.symbols.insert(name.to_string(), address);
@@ -0,0 +1,167 @@
+//! Hollerith Card Encoding
+//!
+//! IBM 1130 card readers deliver one word per card column with the twelve
+//! punch rows in the high bits: row 12 in bit 0 (0x8000), row 11 in bit 1,
+//! row 0 in bit 2, then rows 1-9 down to bit 11 (0x0010). The low four bits
+//! are always zero.
+//!
+//! The character set is the 64-character IBM 029 keypunch set.
+
+/// Row 12 punch
+pub const ROW_12: u16 = 0x8000;
+/// Row 11 punch
+pub const ROW_11: u16 = 0x4000;
+/// Row 0 punch
+pub const ROW_0: u16 = 0x2000;
+/// Row 1 punch
+pub const ROW_1: u16 = 0x1000;
+/// Row 2 punch
+pub const ROW_2: u16 = 0x0800;
+/// Row 3 punch
+pub const ROW_3: u16 = 0x0400;
+/// Row 4 punch
+pub const ROW_4: u16 = 0x0200;
+/// Row 5 punch
+pub const ROW_5: u16 = 0x0100;
+/// Row 6 punch
+pub const ROW_6: u16 = 0x0080;
+/// Row 7 punch
+pub const ROW_7: u16 = 0x0040;
+/// Row 8 punch
+pub const ROW_8: u16 = 0x0020;
+/// Row 9 punch
+pub const ROW_9: u16 = 0x0010;
+
+/// Bits that can hold punches
+const PUNCH_MASK: u16 = 0xFFF0;
+
+/// Character to punch pattern for the 64-character set
+pub const HOLLERITH_TABLE: [(char, u16); 64] = [
+    (' ', 0),
+    ('0', ROW_0),
+    ('1', ROW_1),
+    ('2', ROW_2),
+    ('3', ROW_3),
+    ('4', ROW_4),
+    ('5', ROW_5),
+    ('6', ROW_6),
+    ('7', ROW_7),
+    ('8', ROW_8),
+    ('9', ROW_9),
+    ('A', ROW_12 | ROW_1),
+    ('B', ROW_12 | ROW_2),
+    ('C', ROW_12 | ROW_3),
+    ('D', ROW_12 | ROW_4),
+    ('E', ROW_12 | ROW_5),
+    ('F', ROW_12 | ROW_6),
+    ('G', ROW_12 | ROW_7),
+    ('H', ROW_12 | ROW_8),
+    ('I', ROW_12 | ROW_9),
+    ('J', ROW_11 | ROW_1),
+    ('K', ROW_11 | ROW_2),
+    ('L', ROW_11 | ROW_3),
+    ('M', ROW_11 | ROW_4),
+    ('N', ROW_11 | ROW_5),
+    ('O', ROW_11 | ROW_6),
+    ('P', ROW_11 | ROW_7),
+    ('Q', ROW_11 | ROW_8),
+    ('R', ROW_11 | ROW_9),
+    ('S', ROW_0 | ROW_2),
+    ('T', ROW_0 | ROW_3),
+    ('U', ROW_0 | ROW_4),
+    ('V', ROW_0 | ROW_5),
+    ('W', ROW_0 | ROW_6),
+    ('X', ROW_0 | ROW_7),
+    ('Y', ROW_0 | ROW_8),
+    ('Z', ROW_0 | ROW_9),
+    ('&', ROW_12),
+    ('-', ROW_11),
+    ('/', ROW_0 | ROW_1),
+    ('¢', ROW_12 | ROW_2 | ROW_8),
+    ('.', ROW_12 | ROW_3 | ROW_8),
+    ('<', ROW_12 | ROW_4 | ROW_8),
+    ('(', ROW_12 | ROW_5 | ROW_8),
+    ('+', ROW_12 | ROW_6 | ROW_8),
+    ('|', ROW_12 | ROW_7 | ROW_8),
+    ('!', ROW_11 | ROW_2 | ROW_8),
+    ('$', ROW_11 | ROW_3 | ROW_8),
+    ('*', ROW_11 | ROW_4 | ROW_8),
+    (')', ROW_11 | ROW_5 | ROW_8),
+    (';', ROW_11 | ROW_6 | ROW_8),
+    ('¬', ROW_11 | ROW_7 | ROW_8),
+    ('\\', ROW_0 | ROW_2 | ROW_8),
+    (',', ROW_0 | ROW_3 | ROW_8),
+    ('%', ROW_0 | ROW_4 | ROW_8),
+    ('_', ROW_0 | ROW_5 | ROW_8),
+    ('>', ROW_0 | ROW_6 | ROW_8),
+    ('?', ROW_0 | ROW_7 | ROW_8),
+    (':', ROW_2 | ROW_8),
+    ('#', ROW_3 | ROW_8),
+    ('@', ROW_4 | ROW_8),
+    ('\'', ROW_5 | ROW_8),
+    ('=', ROW_6 | ROW_8),
+    ('"', ROW_7 | ROW_8),
+];
+
+/// Decode a card column to its character
+///
+/// Returns `None` for punch patterns outside the character set.
+pub fn hollerith_to_ascii(col: u16) -> Option<char> {
+    let punches = col & PUNCH_MASK;
+    HOLLERITH_TABLE
+        .iter()
+        .find(|&&(_, code)| code == punches)
+        .map(|&(ch, _)| ch)
+}
+
+/// Encode a character as a card column
+///
+/// Lowercase letters punch as uppercase; characters outside the set
+/// punch as a blank column.
+pub fn ascii_to_hollerith(ch: char) -> u16 {
+    let ch = ch.to_ascii_uppercase();
+    HOLLERITH_TABLE
+        .iter()
+        .find(|&&(c, _)| c == ch)
+        .map_or(0, |&(_, code)| code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_known_codes() {
+        assert_eq!(ascii_to_hollerith('A'), 0x9000);
+        assert_eq!(ascii_to_hollerith('Z'), 0x2010);
+        assert_eq!(ascii_to_hollerith('0'), 0x2000);
+        assert_eq!(ascii_to_hollerith(' '), 0);
+        assert_eq!(ascii_to_hollerith('a'), ascii_to_hollerith('A'));
+        assert_eq!(ascii_to_hollerith('~'), 0);
+    }
+
+    #[test]
+    fn test_round_trip_full_character_set() {
+        for &(ch, code) in HOLLERITH_TABLE.iter() {
+            assert_eq!(ascii_to_hollerith(ch), code, "encoding {:?}", ch);
+            assert_eq!(hollerith_to_ascii(code), Some(ch), "decoding {:#06x}", code);
+        }
+    }
+
+    #[test]
+    fn test_codes_are_unique() {
+        for (i, &(_, a)) in HOLLERITH_TABLE.iter().enumerate() {
+            for &(_, b) in &HOLLERITH_TABLE[i + 1..] {
+                assert_ne!(a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn test_unknown_pattern() {
+        // Rows 1 and 2 together are not a character
+        assert_eq!(hollerith_to_ascii(ROW_1 | ROW_2), None);
+        // Low bits are ignored
+        assert_eq!(hollerith_to_ascii(0x9000 | 0x000F), Some('A'));
+    }
+}
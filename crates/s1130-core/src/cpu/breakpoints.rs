@@ -0,0 +1,143 @@
+//! Debugger Breakpoints
+//!
+//! A `BreakpointSet` holds the stop conditions checked by `Cpu::step`:
+//! - Instruction breakpoints: stop before executing the word at an address
+//! - Watchpoints: stop after an instruction writes a watched address
+//! - Register triggers: stop after a register becomes a given value
+
+use std::collections::HashSet;
+
+/// Register selector for register-value triggers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RegisterId {
+    /// Accumulator
+    Acc,
+    /// Extension register
+    Ext,
+    /// Instruction Address Register
+    Iar,
+    /// Index register 1
+    Xr1,
+    /// Index register 2
+    Xr2,
+    /// Index register 3
+    Xr3,
+}
+
+/// Outcome of `Cpu::run_until_breakpoint`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunResult {
+    /// The program executed WAIT
+    Halted,
+    /// Stopped at an instruction breakpoint or register trigger (IAR)
+    Breakpoint(u16),
+    /// Stopped after a write to a watched address
+    Watchpoint(u16),
+    /// `max_steps` instructions executed without stopping
+    StepLimitReached,
+}
+
+/// Instruction breakpoints, memory-write watchpoints and register triggers
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BreakpointSet {
+    breakpoints: HashSet<u16>,
+    watchpoints: HashSet<u16>,
+    register_triggers: Vec<(RegisterId, u16)>,
+}
+
+impl BreakpointSet {
+    /// Create an empty set
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Break before executing the instruction at `address`
+    pub fn set_breakpoint(&mut self, address: u16) {
+        self.breakpoints.insert(address);
+    }
+
+    /// Remove an instruction breakpoint
+    pub fn clear_breakpoint(&mut self, address: u16) -> bool {
+        self.breakpoints.remove(&address)
+    }
+
+    /// Check for an instruction breakpoint at `address`
+    pub fn has_breakpoint(&self, address: u16) -> bool {
+        self.breakpoints.contains(&address)
+    }
+
+    /// Break after any write to `address`
+    pub fn set_watchpoint(&mut self, address: u16) {
+        self.watchpoints.insert(address);
+    }
+
+    /// Remove a watchpoint
+    pub fn clear_watchpoint(&mut self, address: u16) -> bool {
+        self.watchpoints.remove(&address)
+    }
+
+    /// Check for a watchpoint at `address`
+    pub fn has_watchpoint(&self, address: u16) -> bool {
+        self.watchpoints.contains(&address)
+    }
+
+    /// Break when `register` becomes `value`
+    pub fn set_register_trigger(&mut self, register: RegisterId, value: u16) {
+        if !self.register_triggers.contains(&(register, value)) {
+            self.register_triggers.push((register, value));
+        }
+    }
+
+    /// Remove a register trigger
+    pub fn clear_register_trigger(&mut self, register: RegisterId, value: u16) -> bool {
+        let before = self.register_triggers.len();
+        self.register_triggers
+            .retain(|&trigger| trigger != (register, value));
+        self.register_triggers.len() != before
+    }
+
+    /// Registered register triggers
+    pub fn register_triggers(&self) -> &[(RegisterId, u16)] {
+        &self.register_triggers
+    }
+
+    /// Check whether nothing is set
+    pub fn is_empty(&self) -> bool {
+        self.breakpoints.is_empty()
+            && self.watchpoints.is_empty()
+            && self.register_triggers.is_empty()
+    }
+
+    /// Remove all breakpoints, watchpoints and triggers
+    pub fn clear(&mut self) {
+        self.breakpoints.clear();
+        self.watchpoints.clear();
+        self.register_triggers.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_breakpoints_set_and_clear() {
+        let mut set = BreakpointSet::new();
+        assert!(set.is_empty());
+
+        set.set_breakpoint(0x0100);
+        set.set_watchpoint(0x0200);
+        set.set_register_trigger(RegisterId::Acc, 5);
+        set.set_register_trigger(RegisterId::Acc, 5);
+
+        assert!(set.has_breakpoint(0x0100));
+        assert!(set.has_watchpoint(0x0200));
+        assert_eq!(set.register_triggers(), &[(RegisterId::Acc, 5)]);
+
+        assert!(set.clear_breakpoint(0x0100));
+        assert!(!set.clear_breakpoint(0x0100));
+        assert!(set.clear_watchpoint(0x0200));
+        assert!(set.clear_register_trigger(RegisterId::Acc, 5));
+        assert!(set.is_empty());
+    }
+}
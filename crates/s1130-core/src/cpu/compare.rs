@@ -0,0 +1,149 @@
+//! Execution Comparison
+//!
+//! `compare_execution` runs one program on two CPUs in lockstep and
+//! reports the first step after which their states differ. It is meant for
+//! regression tests: run a program on CPUs configured the old and new way
+//! and check that nothing diverges.
+
+use super::state::FieldDiff;
+use super::Cpu;
+use crate::assembler::AssembledProgram;
+
+/// First point where two runs of a program differ
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    /// 1-based number of the step after which the states differed (0 if
+    /// the program could not be loaded on one of the CPUs)
+    pub step: u64,
+    /// IAR of the first CPU when the step started
+    pub iar: u16,
+    /// Fields that differ; `outcome` if only one step failed, or they
+    /// failed differently
+    pub differences: Vec<FieldDiff>,
+}
+
+/// Load `program` on both CPUs and step them together
+///
+/// Returns the first divergence within `max_steps`, or `None` if the
+/// states stayed equal until both CPUs stopped the same way (e.g. both
+/// reached `WAIT`) or the step limit was hit.
+pub fn compare_execution(
+    cpu_a: &mut Cpu,
+    cpu_b: &mut Cpu,
+    program: &AssembledProgram,
+    max_steps: u64,
+) -> Option<Divergence> {
+    let loaded = (cpu_a.load_program(program), cpu_b.load_program(program));
+    if loaded.0 != loaded.1 {
+        return Some(Divergence {
+            step: 0,
+            iar: cpu_a.get_iar(),
+            differences: vec![outcome_diff(&loaded.0, &loaded.1)],
+        });
+    }
+    if loaded.0.is_err() {
+        return None;
+    }
+
+    for step in 1..=max_steps {
+        let iar = cpu_a.get_iar();
+        let outcome_a = cpu_a.step();
+        let outcome_b = cpu_b.step();
+
+        let mut differences = cpu_a.get_state().diff(&cpu_b.get_state());
+        if outcome_a != outcome_b {
+            differences.push(outcome_diff(&outcome_a, &outcome_b));
+        }
+        if !differences.is_empty() {
+            return Some(Divergence {
+                step,
+                iar,
+                differences,
+            });
+        }
+        if outcome_a.is_err() {
+            return None;
+        }
+    }
+    None
+}
+
+fn outcome_diff<T>(a: &crate::Result<T>, b: &crate::Result<T>) -> FieldDiff {
+    let describe = |outcome: &crate::Result<T>| match outcome {
+        Ok(_) => "ok".to_string(),
+        Err(e) => e.to_string(),
+    };
+    FieldDiff {
+        field: "outcome",
+        left: describe(a),
+        right: describe(b),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assembler::Assembler;
+
+    const SOURCE: &str = "
+        ORG  /0100
+        LD   VAL
+        A    VAL
+        STO  OUT
+        WAIT
+VAL     DC   21
+OUT     BSS  1
+";
+
+    #[test]
+    fn test_identical_cpus_do_not_diverge() {
+        let program = Assembler::new().assemble(SOURCE).unwrap();
+        let (mut a, mut b) = (Cpu::new(), Cpu::new());
+
+        assert_eq!(compare_execution(&mut a, &mut b, &program, 100), None);
+        assert_eq!(a.get_acc(), 42);
+        assert!(b.get_state().wait);
+    }
+
+    #[test]
+    fn test_altered_instruction_diverges_at_its_step() {
+        // Falls through to 0x0104, outside the program, where each CPU
+        // has a different resident shift
+        let source = "
+        ORG  /0200
+VAL     DC   21
+        ORG  /0100
+START   LD   VAL
+        A    VAL
+        END  START
+";
+        let program = Assembler::new().assemble(source).unwrap();
+        let (mut a, mut b) = (Cpu::new(), Cpu::new());
+        a.write_memory_range(0x0104, &[0x2001, 0xB000]).unwrap(); // SLA 1; WAIT
+        b.write_memory_range(0x0104, &[0x2002, 0xB000]).unwrap(); // SLA 2; WAIT
+
+        let divergence = compare_execution(&mut a, &mut b, &program, 100).unwrap();
+        assert_eq!(divergence.step, 3);
+        assert_eq!(divergence.iar, 0x0104);
+        assert_eq!(
+            divergence.differences,
+            [FieldDiff {
+                field: "acc",
+                left: "0x0054".to_string(),
+                right: "0x00a8".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_one_sided_fault_is_a_divergence() {
+        let program = Assembler::new().assemble(SOURCE).unwrap();
+        let (mut a, mut b) = (Cpu::new(), Cpu::new());
+        b.protect_memory_range(0x0108, 0x0109).unwrap();
+
+        let divergence = compare_execution(&mut a, &mut b, &program, 100).unwrap();
+        assert_eq!(divergence.step, 3);
+        let outcome = divergence.differences.last().unwrap();
+        assert_eq!((outcome.field, outcome.left.as_str()), ("outcome", "ok"));
+    }
+}
@@ -0,0 +1,156 @@
+//! Event Hooks
+//!
+//! A `CpuEventHook` set with `Cpu::set_event_hook` is told about each
+//! executed instruction, the memory it reads and writes, and each XIO it
+//! issues. Memory events cover operand accesses while an instruction
+//! executes; instruction fetch, address calculation and host calls such
+//! as `Cpu::read_memory` from outside a step are not reported.
+//!
+//! Every method has an empty default, so a hook implements only what it
+//! needs. With no hook set the CPU does no work beyond an `Option` check.
+
+use crate::devices::DeviceFunction;
+use crate::instructions::OpCode;
+use std::sync::{Arc, Mutex};
+
+/// Observer of CPU activity
+///
+/// Hooks are shared by clones of the CPU, so they take `&self`; use
+/// interior mutability to record events.
+pub trait CpuEventHook: Send + Sync {
+    /// An instruction at `iar` is about to execute
+    fn on_instruction_execute(&self, _iar: u16, _opcode: OpCode) {}
+
+    /// The executing instruction read `value` from `addr`
+    fn on_memory_read(&self, _addr: u16, _value: u16) {}
+
+    /// The executing instruction wrote `value` to `addr`
+    fn on_memory_write(&self, _addr: u16, _value: u16) {}
+
+    /// An XIO issued `function` to the device at `device_code`
+    fn on_device_io(&self, _device_code: u8, _function: DeviceFunction) {}
+}
+
+/// Hook that ignores every event
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoOpHook;
+
+impl CpuEventHook for NoOpHook {}
+
+/// Hook that records each event as a line of text
+///
+/// Clones share the log, so keep a clone to read events back after
+/// handing the hook to the CPU:
+///
+/// ```text
+/// exec 0100 LD
+/// read 0108 002A
+/// write 0109 002A
+/// io 06 InitWrite
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct TraceLogHook {
+    events: Arc<Mutex<Vec<String>>>,
+}
+
+impl TraceLogHook {
+    /// Create a hook with an empty log
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Events recorded so far, oldest first
+    pub fn events(&self) -> Vec<String> {
+        self.log().clone()
+    }
+
+    /// Discard the recorded events
+    pub fn clear(&self) {
+        self.log().clear();
+    }
+
+    fn log(&self) -> std::sync::MutexGuard<'_, Vec<String>> {
+        // A panic while holding the lock cannot leave the log inconsistent
+        self.events
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+impl CpuEventHook for TraceLogHook {
+    fn on_instruction_execute(&self, iar: u16, opcode: OpCode) {
+        self.log()
+            .push(format!("exec {:04X} {}", iar, opcode.mnemonic()));
+    }
+
+    fn on_memory_read(&self, addr: u16, value: u16) {
+        self.log().push(format!("read {:04X} {:04X}", addr, value));
+    }
+
+    fn on_memory_write(&self, addr: u16, value: u16) {
+        self.log().push(format!("write {:04X} {:04X}", addr, value));
+    }
+
+    fn on_device_io(&self, device_code: u8, function: DeviceFunction) {
+        self.log()
+            .push(format!("io {:02} {:?}", device_code, function));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::devices::DeviceConsolePrinter;
+    use crate::Cpu;
+
+    #[test]
+    fn test_trace_log_hook_records_execution() {
+        let mut cpu = Cpu::new();
+        cpu.attach_device(Box::new(DeviceConsolePrinter::new()))
+            .unwrap();
+        // LD VALUE; STO OUT; XIO IOCC; WAIT
+        cpu.write_memory_range(
+            0x0100,
+            &[0x6000, 0x0108, 0x7000, 0x0109, 0x4400, 0x010A, 0xB000],
+        )
+        .unwrap();
+        cpu.write_memory_range(0x0108, &[0x002A, 0x0000, 0x0000, 0x1000])
+            .unwrap();
+        cpu.set_iar(0x0100);
+
+        let hook = TraceLogHook::new();
+        cpu.set_event_hook(Box::new(hook.clone()));
+        cpu.run(10);
+
+        assert_eq!(
+            hook.events(),
+            [
+                "exec 0100 LD",
+                "read 0108 002A",
+                "exec 0102 STO",
+                "write 0109 002A",
+                "exec 0104 XIO",
+                "io 02 Sense",
+                "exec 0106 WAIT",
+            ]
+        );
+
+        hook.clear();
+        cpu.clear_event_hook();
+        cpu.set_wait(false);
+        cpu.set_iar(0x0100);
+        cpu.run(10);
+        assert!(hook.events().is_empty());
+    }
+
+    #[test]
+    fn test_host_memory_access_is_not_reported() {
+        let mut cpu = Cpu::new();
+        let hook = TraceLogHook::new();
+        cpu.set_event_hook(Box::new(hook.clone()));
+
+        cpu.write_memory(0x0100, 1).unwrap();
+        cpu.read_memory(0x0100).unwrap();
+        assert!(hook.events().is_empty());
+    }
+}
@@ -5,7 +5,10 @@
 
 use super::Cpu;
 use crate::error::{CpuError, Result};
-use crate::instructions::{InstructionInfo, OpCode};
+use crate::instructions::{
+    InstructionInfo, OpCode, CONDITION_CARRY, CONDITION_OVERFLOW, CONDITION_POSITIVE,
+    CONDITION_ZERO,
+};
 
 impl Cpu {
     /// Execute a decoded instruction
@@ -45,13 +48,13 @@ impl Cpu {
 
             // Branch Instructions
             OpCode::BSI => self.execute_bsi(effective_address),
-            OpCode::BC => self.execute_bc(effective_address, instr.tag),
-            OpCode::BSC => self.execute_bsc(effective_address, instr.tag),
+            OpCode::BC => self.execute_bc(effective_address, instr.condition),
+            OpCode::BSC => self.execute_bsc(effective_address, instr),
 
             // Index Register Instructions
             OpCode::LDX => self.execute_ldx(effective_address, instr.tag),
             OpCode::STX => self.execute_stx(effective_address, instr.tag),
-            OpCode::MDX => self.execute_mdx(effective_address, instr.tag),
+            OpCode::MDX => self.execute_mdx(effective_address, instr.tag, instr.signed_modifiers()),
 
             // Status Instructions
             OpCode::LDS => self.execute_lds(effective_address),
@@ -84,8 +87,9 @@ impl Cpu {
     /// First word goes to ACC, second word goes to EXT.
     /// Flags affected: None
     fn execute_ldd(&mut self, address: u16) -> Result<()> {
+        let second = self.second_word_address(address)?;
         let acc_value = self.read_memory(address as usize)?;
-        let ext_value = self.read_memory((address + 1) as usize)?;
+        let ext_value = self.read_memory(second)?;
         self.set_acc(acc_value);
         self.set_ext(ext_value);
         Ok(())
@@ -107,13 +111,26 @@ impl Cpu {
     /// ACC goes to first location, EXT goes to second location.
     /// Flags affected: None
     fn execute_std(&mut self, address: u16) -> Result<()> {
+        let second = self.second_word_address(address)?;
         let acc_value = self.get_acc();
         let ext_value = self.get_ext();
         self.write_memory(address as usize, acc_value)?;
-        self.write_memory((address + 1) as usize, ext_value)?;
+        self.write_memory(second, ext_value)?;
         Ok(())
     }
 
+    /// Address of the second word of a double-word operand at `address`
+    ///
+    /// Checked up front so a pair straddling the top of memory fails with
+    /// `MemoryViolation(address)` before either word is touched.
+    fn second_word_address(&self, address: u16) -> Result<usize> {
+        let second = address as usize + 1;
+        if second >= self.memory.size() {
+            return Err(CpuError::MemoryViolation(address));
+        }
+        Ok(second)
+    }
+
     // === Arithmetic Instructions ===
 
     /// A - Add
@@ -121,11 +138,12 @@ impl Cpu {
     /// Adds a memory word to the accumulator.
     /// Flags affected: Carry, Overflow
     fn execute_a(&mut self, address: u16) -> Result<()> {
-        let operand = self.read_memory(address as usize)? as i16;
-        let acc = self.get_acc() as i16;
+        let operand = self.read_memory(address as usize)?;
+        let acc = self.get_acc();
 
-        let (result, overflow) = acc.overflowing_add(operand);
-        let carry = (self.get_acc() as u32 + operand as u32) > 0xFFFF;
+        // Carry is the unsigned carry-out; overflow is judged on signed values
+        let carry = acc.checked_add(operand).is_none();
+        let (result, overflow) = (acc as i16).overflowing_add(operand as i16);
 
         self.set_acc(result as u16);
         self.set_carry(carry);
@@ -138,8 +156,9 @@ impl Cpu {
     /// Adds a 32-bit value from memory to ACC:EXT.
     /// Flags affected: Carry, Overflow
     fn execute_ad(&mut self, address: u16) -> Result<()> {
+        let second = self.second_word_address(address)?;
         let high = self.read_memory(address as usize)? as u32;
-        let low = self.read_memory((address + 1) as usize)? as u32;
+        let low = self.read_memory(second)? as u32;
         let operand = (high << 16) | low;
 
         let acc_ext = self.get_acc_ext();
@@ -149,13 +168,7 @@ impl Cpu {
         let carry = acc_ext > u32::MAX - operand;
 
         // Check for signed overflow
-        let overflow = {
-            let acc_ext_signed = acc_ext as i32;
-            let operand_signed = operand as i32;
-            let result_signed = result as i32;
-            (acc_ext_signed > 0 && operand_signed > 0 && result_signed < 0)
-                || (acc_ext_signed < 0 && operand_signed < 0 && result_signed > 0)
-        };
+        let overflow = (acc_ext as i32).overflowing_add(operand as i32).1;
 
         self.set_acc_ext(result);
         self.set_carry(carry);
@@ -168,11 +181,12 @@ impl Cpu {
     /// Subtracts a memory word from the accumulator.
     /// Flags affected: Carry, Overflow
     fn execute_s(&mut self, address: u16) -> Result<()> {
-        let operand = self.read_memory(address as usize)? as i16;
-        let acc = self.get_acc() as i16;
+        let operand = self.read_memory(address as usize)?;
+        let acc = self.get_acc();
 
-        let (result, overflow) = acc.overflowing_sub(operand);
-        let carry = (self.get_acc() as u32) < (operand as u32);
+        // Carry is the unsigned borrow; overflow is judged on signed values
+        let carry = acc < operand;
+        let (result, overflow) = (acc as i16).overflowing_sub(operand as i16);
 
         self.set_acc(result as u16);
         self.set_carry(carry);
@@ -185,22 +199,16 @@ impl Cpu {
     /// Subtracts a 32-bit value from ACC:EXT.
     /// Flags affected: Carry, Overflow
     fn execute_sd(&mut self, address: u16) -> Result<()> {
+        let second = self.second_word_address(address)?;
         let high = self.read_memory(address as usize)? as u32;
-        let low = self.read_memory((address + 1) as usize)? as u32;
+        let low = self.read_memory(second)? as u32;
         let operand = (high << 16) | low;
 
         let acc_ext = self.get_acc_ext();
         let result = acc_ext.wrapping_sub(operand);
 
         let carry = acc_ext < operand;
-
-        let overflow = {
-            let acc_ext_signed = acc_ext as i32;
-            let operand_signed = operand as i32;
-            let result_signed = result as i32;
-            (acc_ext_signed > 0 && operand_signed < 0 && result_signed < 0)
-                || (acc_ext_signed < 0 && operand_signed > 0 && result_signed > 0)
-        };
+        let overflow = (acc_ext as i32).overflowing_sub(operand as i32).1;
 
         self.set_acc_ext(result);
         self.set_carry(carry);
@@ -234,12 +242,13 @@ impl Cpu {
             return Ok(());
         }
 
-        let dividend = self.get_acc_ext() as i32;
-        let quotient = dividend / (divisor as i32);
-        let remainder = dividend % (divisor as i32);
+        // Widen so that -2^31 / -1 cannot overflow the division itself
+        let dividend = i64::from(self.get_acc_ext() as i32);
+        let quotient = dividend / i64::from(divisor);
+        let remainder = dividend % i64::from(divisor);
 
         // Check if quotient fits in 16 bits
-        if quotient > i16::MAX as i32 || quotient < i16::MIN as i32 {
+        if quotient > i16::MAX as i64 || quotient < i16::MIN as i64 {
             self.set_overflow(true);
             return Ok(());
         }
@@ -289,7 +298,7 @@ impl Cpu {
 
     /// SLA - Shift Left Accumulator
     ///
-    /// Shifts ACC left by specified count.
+    /// Shifts ACC left by specified count; 16 or more places clear it.
     /// Flags affected: Carry (last bit shifted out)
     fn execute_sla(&mut self, count: u16) -> Result<()> {
         let shift_count = (count & 0x1F) as u32; // Use lower 5 bits
@@ -298,8 +307,12 @@ impl Cpu {
         }
 
         let acc = self.get_acc();
-        let carry = (acc >> (16 - shift_count)) & 1 != 0;
-        let result = acc << shift_count;
+        let (result, carry) = match shift_count {
+            1..=15 => (acc << shift_count, (acc >> (16 - shift_count)) & 1 != 0),
+            // Bit 15 (the low bit) leaves last; beyond that only zeros do
+            16 => (0, acc & 1 != 0),
+            _ => (0, false),
+        };
 
         self.set_acc(result);
         self.set_carry(carry);
@@ -308,17 +321,19 @@ impl Cpu {
 
     /// SLCA - Shift Left Combined Accumulator
     ///
-    /// Shifts ACC:EXT left by specified count.
-    /// Flags affected: Carry (last bit shifted out)
+    /// Shifts the 32-bit ACC:EXT left by `count & 0x1F` places.
+    /// Flags affected: Carry (last bit shifted out; unchanged for a zero count)
     fn execute_slca(&mut self, count: u16) -> Result<()> {
         let shift_count = (count & 0x1F) as u32;
         if shift_count == 0 {
             return Ok(());
         }
 
-        let acc_ext = self.get_acc_ext() as u64;
-        let carry = (acc_ext >> (32 - shift_count)) & 1 != 0;
-        let result = (acc_ext << shift_count) as u32;
+        // With 1-31 places every shift below stays within the 32-bit width
+        let acc_ext = self.get_acc_ext();
+        let last_out = 32 - shift_count;
+        let carry = (acc_ext >> last_out) & 1 != 0;
+        let result = acc_ext << shift_count;
 
         self.set_acc_ext(result);
         self.set_carry(carry);
@@ -327,7 +342,8 @@ impl Cpu {
 
     /// SRA - Shift Right Accumulator
     ///
-    /// Arithmetic right shift of ACC (sign extends).
+    /// Arithmetic right shift of ACC (sign extends); 16 or more places
+    /// leave only copies of the sign bit.
     /// Flags affected: Carry (last bit shifted out)
     fn execute_sra(&mut self, count: u16) -> Result<()> {
         let shift_count = (count & 0x1F) as u32;
@@ -336,8 +352,9 @@ impl Cpu {
         }
 
         let acc = self.get_acc() as i16;
-        let carry = (acc >> (shift_count - 1)) & 1 != 0;
-        let result = acc >> shift_count;
+        // An i16 shifted by 15 is already all sign bits
+        let carry = (acc >> (shift_count - 1).min(15)) & 1 != 0;
+        let result = acc >> shift_count.min(15);
 
         self.set_acc(result as u16);
         self.set_carry(carry);
@@ -346,8 +363,10 @@ impl Cpu {
 
     /// SRT - Shift Right Combined (logical)
     ///
-    /// Logical right shift of ACC:EXT (zero fill).
-    /// Flags affected: Carry (last bit shifted out)
+    /// Logical right shift of the 32-bit ACC:EXT by `count & 0x1F` places:
+    /// the vacated high bits of ACC are zero, unlike SRA, which copies the
+    /// sign bit. Bits leave EXT's low end.
+    /// Flags affected: Carry (last bit shifted out; unchanged for a zero count)
     fn execute_srt(&mut self, count: u16) -> Result<()> {
         let shift_count = (count & 0x1F) as u32;
         if shift_count == 0 {
@@ -377,35 +396,72 @@ impl Cpu {
 
     /// BC - Branch on Condition
     ///
-    /// Conditional branch based on tag bits.
-    fn execute_bc(&mut self, address: u16, tag: u8) -> Result<()> {
-        if self.check_branch_condition(tag) {
+    /// Conditional branch based on the condition mask.
+    fn execute_bc(&mut self, address: u16, condition: u8) -> Result<()> {
+        if self.check_branch_condition(condition) {
             self.set_iar(address);
         }
         Ok(())
     }
 
-    /// BSC - Branch and Store on Condition
+    /// BSC - Branch and Store / Skip on Condition
     ///
-    /// Conditional BSI.
-    fn execute_bsc(&mut self, address: u16, tag: u8) -> Result<()> {
-        if self.check_branch_condition(tag) {
+    /// Direct (short) form: skips the next instruction when the condition
+    /// holds, as on the hardware. Indirect form: conditional BSI through
+    /// the pointer.
+    fn execute_bsc(&mut self, address: u16, instr: &InstructionInfo) -> Result<()> {
+        if !self.check_branch_condition(instr.condition) {
+            return Ok(());
+        }
+
+        if instr.indirect {
             let return_address = self.get_iar();
             self.write_memory(address as usize, return_address)?;
             self.set_iar(address.wrapping_add(1));
+        } else {
+            let skip = self.skip_length();
+            self.increment_iar(skip);
         }
         Ok(())
     }
 
-    /// Check branch condition based on tag bits
-    fn check_branch_condition(&self, tag: u8) -> bool {
-        match tag {
-            0 => true,                // Unconditional
-            1 => self.get_carry(),    // Carry set
-            2 => self.get_overflow(), // Overflow set
-            3 => !self.get_carry(),   // Carry clear
-            _ => false,
+    /// Words in the instruction at IAR, for a BSC skip
+    ///
+    /// The skipped word may be data, so anything that does not decode as
+    /// a long-format instruction counts as one word.
+    fn skip_length(&self) -> u16 {
+        let long = self
+            .read_memory(self.get_iar() as usize)
+            .ok()
+            .and_then(|word| OpCode::from_word(word).ok())
+            .is_some_and(OpCode::is_long_format);
+        if long {
+            2
+        } else {
+            1
+        }
+    }
+
+    /// Check a BC/BSC condition mask
+    ///
+    /// Each of the four mask bits selects a condition (see `CONDITION_*`);
+    /// the branch is taken if any selected condition holds. Mask 0 selects
+    /// none and is unconditional.
+    fn check_branch_condition(&self, condition: u8) -> bool {
+        let condition = condition & 0x0F;
+        if condition == 0 {
+            return true;
         }
+
+        let acc = self.get_acc() as i16;
+        [
+            (CONDITION_CARRY, self.get_carry()),
+            (CONDITION_OVERFLOW, self.get_overflow()),
+            (CONDITION_POSITIVE, acc > 0),
+            (CONDITION_ZERO, acc == 0),
+        ]
+        .iter()
+        .any(|&(bit, holds)| condition & bit != 0 && holds)
     }
 
     // === Index Register Instructions ===
@@ -431,16 +487,24 @@ impl Cpu {
     }
 
     /// MDX - Modify Index and Skip
-    fn execute_mdx(&mut self, address: u16, tag: u8) -> Result<()> {
-        if tag == 0 {
-            return Ok(());
-        }
-
-        let operand = self.read_memory(address as usize)? as i16;
-        let index_value = self.get_index_register(tag) as i16;
-        let result = index_value.wrapping_add(operand);
-
-        self.set_index_register(tag, result as u16);
+    ///
+    /// With a tag, adds the word at `address` to that index register. With
+    /// tag 0, adds the signed `increment` from the instruction's modifier
+    /// bits to the word at `address` instead. Either way the next
+    /// instruction is skipped if the result is zero.
+    fn execute_mdx(&mut self, address: u16, tag: u8, increment: i16) -> Result<()> {
+        let result = if tag == 0 {
+            let value = self.read_memory(address as usize)? as i16;
+            let result = value.wrapping_add(increment);
+            self.write_memory(address as usize, result as u16)?;
+            result
+        } else {
+            let operand = self.read_memory(address as usize)? as i16;
+            let index_value = self.get_index_register(tag) as i16;
+            let result = index_value.wrapping_add(operand);
+            self.set_index_register(tag, result as u16);
+            result
+        };
 
         // Skip next instruction if result is zero
         if result == 0 {
@@ -456,20 +520,13 @@ impl Cpu {
     /// LDS - Load Status
     fn execute_lds(&mut self, address: u16) -> Result<()> {
         let value = self.read_memory(address as usize)?;
-        let flags = crate::cpu::registers::StatusFlags::from_word(value);
-        self.set_carry(flags.carry);
-        self.set_overflow(flags.overflow);
+        self.set_flags_word(value);
         Ok(())
     }
 
     /// STS - Store Status
     fn execute_sts(&mut self, address: u16) -> Result<()> {
-        let flags = crate::cpu::registers::StatusFlags {
-            carry: self.get_carry(),
-            overflow: self.get_overflow(),
-            wait: self.get_wait(),
-        };
-        let word = flags.to_word();
+        let word = self.flags_word();
         self.write_memory(address as usize, word)?;
         Ok(())
     }
@@ -493,12 +550,105 @@ impl Cpu {
     /// - Word 0: WCA (Word Count Address)
     /// - Word 1: Device code + Function + Modifiers
     fn execute_xio(&mut self, address: u16) -> Result<()> {
-        // Decode IOCC from memory
-        self.decode_iocc(address)?;
+        self.execute_xio_at(address)
+    }
+}
 
-        // Execute the I/O operation
-        self.execute_iocc()?;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instructions::CONDITION_SHIFT;
+
+    /// Run `BC +2` with `condition` and report whether it branched
+    fn bc_taken(condition: u8, carry: bool, overflow: bool, acc: u16) -> bool {
+        let mut cpu = Cpu::new();
+        cpu.set_carry(carry);
+        cpu.set_overflow(overflow);
+        cpu.set_acc(acc);
+        let word = (OpCode::BC as u16) << 8 | u16::from(condition) << CONDITION_SHIFT | 0x0002;
+        cpu.write_memory(0x0100, word).unwrap();
+        cpu.set_iar(0x0100);
+        cpu.step().unwrap();
+        match cpu.get_iar() {
+            0x0103 => true,
+            0x0101 => false,
+            iar => panic!("unexpected IAR {:#06x}", iar),
+        }
+    }
 
-        Ok(())
+    #[test]
+    fn test_branch_condition_matrix() {
+        for carry in [false, true] {
+            for overflow in [false, true] {
+                for acc in [0x0000, 0x0005, 0x8000] {
+                    let positive = (acc as i16) > 0;
+                    let zero = acc == 0;
+
+                    for condition in 0..16u8 {
+                        let expected = condition == 0
+                            || (condition & CONDITION_CARRY != 0 && carry)
+                            || (condition & CONDITION_OVERFLOW != 0 && overflow)
+                            || (condition & CONDITION_POSITIVE != 0 && positive)
+                            || (condition & CONDITION_ZERO != 0 && zero);
+                        assert_eq!(
+                            bc_taken(condition, carry, overflow, acc),
+                            expected,
+                            "condition {} carry {} overflow {} acc {:#06x}",
+                            condition,
+                            carry,
+                            overflow,
+                            acc
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_branch_condition_multi_bit_masks() {
+        // 3: carry OR overflow
+        assert!(bc_taken(3, true, false, 0));
+        assert!(bc_taken(3, false, true, 0));
+        assert!(!bc_taken(3, false, false, 0));
+
+        // 12: positive OR zero, i.e. non-negative
+        assert!(bc_taken(12, false, false, 0x0000));
+        assert!(bc_taken(12, false, false, 0x7FFF));
+        assert!(!bc_taken(12, false, false, 0xFFFF));
+    }
+
+    #[test]
+    fn test_bsc_skips_on_zero_condition() {
+        let mut cpu = Cpu::new();
+        // BSC zero; WAIT; WAIT
+        let bsc = (OpCode::BSC as u16) << 8 | u16::from(CONDITION_ZERO) << CONDITION_SHIFT;
+        cpu.write_memory_range(0x0100, &[bsc, 0xB000, 0xB000])
+            .unwrap();
+
+        cpu.set_iar(0x0100);
+        cpu.step().unwrap();
+        assert_eq!(cpu.get_iar(), 0x0102);
+
+        cpu.set_acc(1);
+        cpu.set_iar(0x0100);
+        cpu.step().unwrap();
+        assert_eq!(cpu.get_iar(), 0x0101);
+    }
+
+    #[test]
+    fn test_bsc_skips_data_word() {
+        let mut cpu = Cpu::new();
+        // BSC (unconditional) over a word that is not an instruction, and
+        // over a long-format LD
+        let bsc = (OpCode::BSC as u16) << 8;
+        cpu.write_memory_range(0x0100, &[bsc, 0xFFFF, bsc, 0x6000, 0x0200])
+            .unwrap();
+
+        cpu.set_iar(0x0100);
+        cpu.step().unwrap();
+        assert_eq!(cpu.get_iar(), 0x0102);
+        cpu.step().unwrap();
+        assert_eq!(cpu.get_iar(), 0x0105);
     }
 }
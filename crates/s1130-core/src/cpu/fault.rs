@@ -0,0 +1,112 @@
+//! Fault Context
+//!
+//! When `Cpu::run` stops on a fault, the `StopReason` alone says what went
+//! wrong but not where. `FaultContext` records the instruction that was
+//! executing, so a debugger can show e.g.
+//!
+//! ```text
+//! Fault at 0x0123: DC   0xff00 (invalid instruction)
+//! ```
+
+use super::{Cpu, StopReason};
+use crate::disassembler::Disassembler;
+use crate::instructions::OpCode;
+use std::fmt;
+
+/// The instruction a run faulted on, from `Cpu::last_fault`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FaultContext {
+    /// Address of the faulting instruction
+    pub iar: u16,
+    /// Instruction words at `iar`: one, or two for long format (empty if
+    /// `iar` is outside memory)
+    pub words: Vec<u16>,
+    /// Disassembly of `words` (`DC` for an invalid opcode)
+    pub disassembly: String,
+    /// Why the run stopped
+    pub reason: StopReason,
+}
+
+impl fmt::Display for FaultContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let detail = match &self.reason {
+            StopReason::InvalidInstruction(_) => "invalid instruction".to_string(),
+            StopReason::MemoryViolation(address) => {
+                format!("memory violation at {:#06x}", address)
+            }
+            StopReason::DeviceError(message) | StopReason::Error(message) => message.clone(),
+            other => other.name().to_string(),
+        };
+        if self.words.is_empty() {
+            write!(f, "Fault at {:#06x}: {}", self.iar, detail)
+        } else {
+            write!(
+                f,
+                "Fault at {:#06x}: {} ({})",
+                self.iar, self.disassembly, detail
+            )
+        }
+    }
+}
+
+impl Cpu {
+    /// Describe the instruction at `iar` that stopped a run with `reason`
+    pub(super) fn fault_context(&self, iar: u16, reason: StopReason) -> FaultContext {
+        let mut words = Vec::new();
+        let mut disassembly = String::new();
+        if let Ok(word1) = self.read_memory(iar as usize) {
+            words.push(word1);
+            let word2 = self.read_memory(iar as usize + 1).ok();
+            if let (Ok(opcode), Some(word2)) = (OpCode::from_word(word1), word2) {
+                if opcode.is_long_format() {
+                    words.push(word2);
+                }
+            }
+            disassembly = Disassembler::new().disassemble_instruction(word1, word2, iar, None);
+        }
+        FaultContext {
+            iar,
+            words,
+            disassembly,
+            reason,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_invalid_opcode_fault_context() {
+        let mut cpu = Cpu::new();
+        cpu.write_memory_range(0x0120, &[0x2001, 0x2001, 0x2001, 0xFF00])
+            .unwrap();
+        cpu.set_iar(0x0120);
+
+        let (steps, reason) = cpu.run(10);
+
+        assert_eq!((steps, reason), (3, StopReason::InvalidInstruction(0x0123)));
+        let fault = cpu.last_fault().unwrap();
+        assert_eq!(fault.iar, 0x0123);
+        assert_eq!(fault.words, [0xFF00]);
+        assert_eq!(
+            fault.to_string(),
+            "Fault at 0x0123: DC   0xff00 (invalid instruction)"
+        );
+    }
+
+    #[test]
+    fn test_long_instruction_fault_keeps_both_words() {
+        let mut cpu = Cpu::with_memory_size(0x0200).unwrap();
+        cpu.write_memory_range(0x0100, &[0x6000, 0x0300]).unwrap(); // LD 0x0300
+        cpu.set_iar(0x0100);
+
+        let (_, reason) = cpu.run(10);
+
+        assert_eq!(reason, StopReason::MemoryViolation(0x0300));
+        let fault = cpu.last_fault().unwrap();
+        assert_eq!(fault.words, [0x6000, 0x0300]);
+        assert_eq!(fault.disassembly, "LD   0x0300");
+    }
+}
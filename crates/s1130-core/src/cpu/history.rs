@@ -0,0 +1,125 @@
+//! Step-Back History
+//!
+//! `StateHistory` keeps the registers as they were before each of the most
+//! recent steps, so `Cpu::step_back` can undo them one at a time. Memory
+//! is not recorded: undoing a store restores the registers but leaves the
+//! stored word, and `step_back` reports that with
+//! `CpuError::HistoryMemoryModified`.
+
+use super::state::CpuState;
+use crate::instructions::{InstructionInfo, OpCode};
+use std::collections::VecDeque;
+
+/// Default number of steps kept by a `StateHistory`
+pub const DEFAULT_HISTORY_CAPACITY: usize = 1000;
+
+/// Registers before one step
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HistoryEntry {
+    /// Registers, flags and counters before the step
+    pub state: CpuState,
+    /// The instruction stored to memory, which undoing cannot revert
+    pub wrote_memory: bool,
+}
+
+/// Bounded stack of pre-step register states, newest last
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StateHistory {
+    entries: VecDeque<HistoryEntry>,
+    capacity: usize,
+}
+
+impl StateHistory {
+    /// Create a history keeping the last `capacity` steps
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Maximum number of steps kept
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Number of steps that can be undone
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Check whether there is nothing to undo
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Record the state before `instruction` executed, dropping the oldest when full
+    pub fn push(&mut self, state: CpuState, instruction: &InstructionInfo) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(HistoryEntry {
+            state,
+            wrote_memory: writes_memory(instruction),
+        });
+    }
+
+    /// Remove and return the most recent entry
+    pub fn pop(&mut self) -> Option<HistoryEntry> {
+        self.entries.pop_back()
+    }
+
+    /// Discard all entries
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+/// Stores, BSI's return address, MDX on memory and XIO, whose device may
+/// read into memory
+fn writes_memory(instruction: &InstructionInfo) -> bool {
+    match instruction.opcode {
+        OpCode::STO | OpCode::STD | OpCode::STX | OpCode::STS | OpCode::BSI | OpCode::XIO => true,
+        OpCode::MDX => instruction.tag == 0,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn instruction(word1: u16) -> InstructionInfo {
+        InstructionInfo::decode(word1, Some(0x0200)).unwrap()
+    }
+
+    #[test]
+    fn test_capacity_drops_oldest() {
+        let mut history = StateHistory::new(2);
+        for iar in 1..=3 {
+            let state = CpuState {
+                iar,
+                ..CpuState::default()
+            };
+            history.push(state, &instruction(0x2001));
+        }
+
+        assert_eq!(history.len(), 2);
+        assert_eq!(history.pop().unwrap().state.iar, 3);
+        assert_eq!(history.pop().unwrap().state.iar, 2);
+        assert!(history.pop().is_none());
+    }
+
+    #[test]
+    fn test_memory_writing_instructions() {
+        assert!(writes_memory(&instruction(0x7000))); // STO
+        assert!(writes_memory(&instruction(0x5440))); // STX 1
+        assert!(writes_memory(&instruction(0x4800))); // BSI
+        assert!(!writes_memory(&instruction(0x6000))); // LD
+        assert!(!writes_memory(&instruction(0x5840))); // MDX 1
+        assert!(writes_memory(&instruction(0x581F))); // MDX 0 modifies memory
+    }
+}
@@ -0,0 +1,194 @@
+//! Machine Dump
+//!
+//! `Cpu::inspect` prints the whole visible machine state for debugging and
+//! test failure output:
+//!
+//! ```text
+//! ACC  1234  (+4660)     EXT  FFFF  (-1)
+//! XR1  0000  (+0)        XR2  0000  (+0)        XR3  0000  (+0)
+//! IAR  0100  Carry 0  Overflow 0  Wait 0  Level -
+//! Instructions: 12  Cycles: 30
+//! Next: 0x0100  LD   0x0200
+//! 00FC   0000 0000 0000 0000>6000 0200 B000 0000
+//! ```
+//!
+//! The last line shows memory around IAR, with `>` before the word at IAR.
+//!
+//! `Cpu::dump_memory_hex` prints a range of memory eight words to a row,
+//! followed by the row's 16 bytes as ASCII (`.` where not printable):
+//!
+//! ```text
+//! 0100: 6000 0108 E000 0109 7000 010A B000 4849  |`.......p.....HI|
+//! ```
+
+use super::Cpu;
+use crate::disassembler::Disassembler;
+use std::fmt::Write;
+
+/// Words shown before IAR in the memory line
+const CONTEXT_BEFORE: u16 = 4;
+/// Words shown in the memory line
+const CONTEXT_WORDS: u16 = 8;
+
+/// Words per row of `dump_memory_hex`
+const DUMP_WORDS_PER_ROW: usize = 8;
+
+/// `NAME  hhhh  (+d)`: hex and signed decimal
+fn register(name: &str, value: u16) -> String {
+    format!("{}  {:04X}  ({:+})", name, value, value as i16)
+}
+
+impl Cpu {
+    /// Formatted multi-line dump of registers, flags, the next instruction
+    /// and the memory around IAR
+    pub fn inspect(&self) -> String {
+        let state = self.get_state();
+        let mut dump = String::new();
+
+        let _ = writeln!(
+            dump,
+            "{:<22}{}",
+            register("ACC", state.acc),
+            register("EXT", state.ext)
+        );
+        let _ = writeln!(
+            dump,
+            "{:<22}{:<22}{}",
+            register("XR1", state.xr1),
+            register("XR2", state.xr2),
+            register("XR3", state.xr3)
+        );
+        let level = state
+            .current_interrupt_level
+            .map_or_else(|| "-".to_string(), |level| level.to_string());
+        let _ = writeln!(
+            dump,
+            "IAR  {:04X}  Carry {}  Overflow {}  Wait {}  Level {}",
+            state.iar,
+            u8::from(state.carry),
+            u8::from(state.overflow),
+            u8::from(state.wait),
+            level
+        );
+        let _ = writeln!(
+            dump,
+            "Instructions: {}  Cycles: {}",
+            state.instruction_count, state.cycle_count
+        );
+
+        match self.read_memory(state.iar as usize) {
+            Ok(word) => {
+                let next = self.read_memory(state.iar as usize + 1).ok();
+                let text = Disassembler::new().disassemble_word(word, next, state.iar, None);
+                let _ = writeln!(dump, "Next: {}", text);
+            }
+            Err(_) => {
+                let _ = writeln!(dump, "Next: IAR outside memory");
+            }
+        }
+
+        let start = state.iar.saturating_sub(CONTEXT_BEFORE);
+        let _ = write!(dump, "{:04X}  ", start);
+        for address in start..start.saturating_add(CONTEXT_WORDS) {
+            let Ok(word) = self.read_memory(address as usize) else {
+                break;
+            };
+            let marker = if address == state.iar { '>' } else { ' ' };
+            let _ = write!(dump, "{}{:04X}", marker, word);
+        }
+        dump.push('\n');
+        dump
+    }
+
+    /// Hex and ASCII dump of memory from `start` up to (not including) `end`
+    ///
+    /// Rows begin at `start` and hold eight words; a short last row is
+    /// padded so its ASCII column lines up. Each word shows as two bytes,
+    /// high byte first. The range is clipped to the end of memory.
+    pub fn dump_memory_hex(&self, start: u16, end: u16) -> String {
+        let words: Vec<u16> = (start..end)
+            .map_while(|address| self.read_memory(address as usize).ok())
+            .collect();
+
+        let mut dump = String::new();
+        for (row, chunk) in words.chunks(DUMP_WORDS_PER_ROW).enumerate() {
+            let address = start as usize + row * DUMP_WORDS_PER_ROW;
+            let _ = write!(dump, "{:04X}:", address);
+            for word in chunk {
+                let _ = write!(dump, " {:04X}", word);
+            }
+            let padding = (DUMP_WORDS_PER_ROW - chunk.len()) * 5;
+            let _ = write!(dump, "{:padding$}  |", "", padding = padding);
+            for byte in chunk.iter().flat_map(|word| word.to_be_bytes()) {
+                let printable = byte.is_ascii_graphic() || byte == b' ';
+                dump.push(if printable { byte as char } else { '.' });
+            }
+            dump.push_str("|\n");
+        }
+        dump
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inspect_shows_registers_and_next_instruction() {
+        let mut cpu = Cpu::new();
+        cpu.write_memory_range(0x0100, &[0x6000, 0x0200, 0xB000])
+            .unwrap();
+        cpu.set_iar(0x0100);
+        cpu.set_acc(0x1234);
+        cpu.set_ext(0xFFFF);
+        cpu.set_carry(true);
+
+        let dump = cpu.inspect();
+
+        assert!(dump.contains("ACC  1234  (+4660)"), "{}", dump);
+        assert!(dump.contains("EXT  FFFF  (-1)"), "{}", dump);
+        assert!(dump.contains("Carry 1  Overflow 0"), "{}", dump);
+        assert!(dump.contains("Instructions: 0  Cycles: 0"), "{}", dump);
+        assert!(dump.contains("Next: 0x0100  LD   0x0200"), "{}", dump);
+        assert!(
+            dump.ends_with("00FC   0000 0000 0000 0000>6000 0200 B000 0000\n"),
+            "{}",
+            dump
+        );
+    }
+
+    #[test]
+    fn test_dump_memory_hex_format() {
+        let mut cpu = Cpu::new();
+        cpu.write_memory_range(
+            0x0100,
+            &[
+                0x6000, 0x0108, 0xE000, 0x0109, 0x7000, 0x010A, 0xB000, 0x4849, 0x2141,
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(
+            cpu.dump_memory_hex(0x0100, 0x0109),
+            "0100: 6000 0108 E000 0109 7000 010A B000 4849  |`.......p.....HI|\n\
+             0108: 2141                                     |!A|\n"
+        );
+        assert_eq!(cpu.dump_memory_hex(0x0100, 0x0100), "");
+
+        let cpu = Cpu::with_memory_size(0x0100).unwrap();
+        assert_eq!(
+            cpu.dump_memory_hex(0x00FE, 0x0200),
+            "00FE: 0000 0000                                |....|\n"
+        );
+    }
+
+    #[test]
+    fn test_inspect_near_end_of_memory() {
+        let mut cpu = Cpu::with_memory_size(0x0100).unwrap();
+        cpu.set_iar(0x00FF);
+        assert!(cpu.inspect().contains("Next: 0x00ff"));
+
+        cpu.set_iar(0x0200);
+        assert!(cpu.inspect().contains("Next: IAR outside memory"));
+    }
+}
@@ -0,0 +1,285 @@
+//! Interrupt Level Management
+//!
+//! The IBM 1130 has five maskable interrupt levels (0-4). Level 0 has the
+//! highest priority. A pending level is serviced only when it is unmasked
+//! and outranks every level currently being serviced, so interrupts nest:
+//! level 0 can interrupt a level 1 handler, but not the other way round.
+//!
+//! Memory layout used by the CPU when servicing a level:
+//! - `0x0008 + level`: interrupt vector (address of the handler's entry word)
+//! - `0x0040 + level`: Interrupt Level Status Word (ILSW) for that level
+
+/// Number of interrupt levels
+pub const INTERRUPT_LEVELS: u8 = 5;
+
+/// Memory address of the level 0 interrupt vector
+pub const INTERRUPT_VECTOR_BASE: u16 = 0x0008;
+
+/// Memory address of the level 0 Interrupt Level Status Word
+pub const ILSW_BASE: u16 = 0x0040;
+
+/// Pending, masked and active interrupt levels
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct InterruptController {
+    /// Pending requests (bit n = level n)
+    pending: u8,
+    /// Interrupt mask register (bit n set = level n disabled)
+    mask: u8,
+    /// Levels currently being serviced (bit n = level n)
+    active: u8,
+    /// Accumulated ILSW bits per level, stored to memory on entry
+    ilsw: [u16; INTERRUPT_LEVELS as usize],
+    /// Handler entry word per active level (holds the return address)
+    entry_points: [u16; INTERRUPT_LEVELS as usize],
+}
+
+impl InterruptController {
+    /// Create a controller with nothing pending, masked or active
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Check whether `level` is a valid interrupt level (0-4)
+    pub fn is_valid_level(level: u8) -> bool {
+        level < INTERRUPT_LEVELS
+    }
+
+    /// Raise a request on `level`, OR-ing `status` into its ILSW
+    ///
+    /// Invalid levels are ignored.
+    pub fn request(&mut self, level: u8, status: u16) {
+        if Self::is_valid_level(level) {
+            self.pending |= 1 << level;
+            self.ilsw[level as usize] |= status;
+        }
+    }
+
+    /// Withdraw a pending request on `level`
+    pub fn clear(&mut self, level: u8) {
+        if Self::is_valid_level(level) {
+            self.pending &= !(1 << level);
+            self.ilsw[level as usize] = 0;
+        }
+    }
+
+    /// Get the pending-request bitmask
+    pub fn pending(&self) -> u8 {
+        self.pending
+    }
+
+    /// Get the interrupt mask register
+    pub fn mask(&self) -> u8 {
+        self.mask
+    }
+
+    /// Set the interrupt mask register (bit n set = level n disabled)
+    pub fn set_mask(&mut self, mask: u8) {
+        self.mask = mask & Self::all_levels();
+    }
+
+    /// Check whether `level` is masked
+    pub fn is_masked(&self, level: u8) -> bool {
+        self.mask & (1 << level) != 0
+    }
+
+    /// Get the bitmask of levels currently being serviced
+    pub fn active(&self) -> u8 {
+        self.active
+    }
+
+    /// Highest-priority level currently being serviced
+    pub fn current_level(&self) -> Option<u8> {
+        lowest_level(self.active)
+    }
+
+    /// Level that should be serviced next, if any
+    ///
+    /// Returns the highest-priority unmasked pending level that outranks
+    /// the level currently being serviced.
+    pub fn next_level(&self) -> Option<u8> {
+        let level = lowest_level(self.pending & !self.mask)?;
+        match self.current_level() {
+            Some(current) if current <= level => None,
+            _ => Some(level),
+        }
+    }
+
+    /// Start servicing `level`
+    ///
+    /// Clears its pending bit, marks it active and returns the ILSW bits
+    /// collected for it.
+    pub fn enter(&mut self, level: u8, entry_point: u16) -> u16 {
+        let index = level as usize;
+        let ilsw = self.ilsw[index];
+        self.pending &= !(1 << level);
+        self.ilsw[index] = 0;
+        self.active |= 1 << level;
+        self.entry_points[index] = entry_point;
+        ilsw
+    }
+
+    /// Finish servicing the current level
+    ///
+    /// Returns the level that was exited and its handler entry word.
+    pub fn exit(&mut self) -> Option<(u8, u16)> {
+        let level = self.current_level()?;
+        self.active &= !(1 << level);
+        Some((level, self.entry_points[level as usize]))
+    }
+
+    /// Make `level` the highest-priority level being serviced
+    ///
+    /// Levels that outrank it stop being active and `level` becomes
+    /// active; `None` leaves no level active. Used to restore a saved
+    /// `current_interrupt_level`; outranked active levels and entry words
+    /// are left as they are.
+    pub fn set_current_level(&mut self, level: Option<u8>) {
+        match level.filter(|&level| Self::is_valid_level(level)) {
+            Some(level) => {
+                let outranking = (1u8 << level) - 1;
+                self.active = (self.active & !outranking) | (1 << level);
+            }
+            None => self.active = 0,
+        }
+    }
+
+    /// Save the controller for a CPU snapshot
+    ///
+    /// Layout: pending, mask and active bitmasks, then the ILSW and the
+    /// entry word of each level.
+    pub fn save_state(&self) -> Vec<u16> {
+        let mut data = vec![
+            u16::from(self.pending),
+            u16::from(self.mask),
+            u16::from(self.active),
+        ];
+        data.extend_from_slice(&self.ilsw);
+        data.extend_from_slice(&self.entry_points);
+        data
+    }
+
+    /// Restore state returned by `save_state`
+    ///
+    /// Missing words read as 0 and bits beyond the valid levels are
+    /// dropped.
+    pub fn restore_state(&mut self, data: &[u16]) {
+        let word = |index: usize| data.get(index).copied().unwrap_or(0);
+        let levels = |index: usize| (word(index) as u8) & Self::all_levels();
+        self.pending = levels(0);
+        self.mask = levels(1);
+        self.active = levels(2);
+        let count = INTERRUPT_LEVELS as usize;
+        for level in 0..count {
+            self.ilsw[level] = word(3 + level);
+            self.entry_points[level] = word(3 + count + level);
+        }
+    }
+
+    /// Clear all pending, masked and active levels
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    fn all_levels() -> u8 {
+        (1 << INTERRUPT_LEVELS) - 1
+    }
+}
+
+/// Lowest set bit index within the valid level range
+fn lowest_level(bits: u8) -> Option<u8> {
+    let bits = bits & InterruptController::all_levels();
+    if bits == 0 {
+        None
+    } else {
+        Some(bits.trailing_zeros() as u8)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_and_next_level() {
+        let mut ic = InterruptController::new();
+        assert_eq!(ic.next_level(), None);
+
+        ic.request(3, 0);
+        ic.request(1, 0);
+        assert_eq!(ic.pending(), 0b01010);
+        assert_eq!(ic.next_level(), Some(1));
+    }
+
+    #[test]
+    fn test_state_round_trip() {
+        let mut ic = InterruptController::new();
+        ic.set_mask(0b10000);
+        ic.request(2, 0x4000);
+        ic.request(4, 0x0001);
+        ic.enter(3, 0x0300);
+
+        let mut restored = InterruptController::new();
+        restored.restore_state(&ic.save_state());
+        assert_eq!(restored, ic);
+
+        restored.restore_state(&[]);
+        assert_eq!(restored, InterruptController::new());
+    }
+
+    #[test]
+    fn test_invalid_level_ignored() {
+        let mut ic = InterruptController::new();
+        ic.request(5, 0x8000);
+        assert_eq!(ic.pending(), 0);
+        assert_eq!(ic.next_level(), None);
+    }
+
+    #[test]
+    fn test_masked_level_not_serviced() {
+        let mut ic = InterruptController::new();
+        ic.set_mask(0b00010);
+        ic.request(1, 0);
+        assert!(ic.is_masked(1));
+        assert_eq!(ic.next_level(), None);
+
+        ic.set_mask(0);
+        assert_eq!(ic.next_level(), Some(1));
+    }
+
+    #[test]
+    fn test_only_higher_priority_nests() {
+        let mut ic = InterruptController::new();
+        ic.request(2, 0);
+        ic.enter(2, 0x0300);
+        assert_eq!(ic.current_level(), Some(2));
+
+        // Same and lower priority wait
+        ic.request(2, 0);
+        ic.request(4, 0);
+        assert_eq!(ic.next_level(), None);
+
+        // Higher priority nests
+        ic.request(0, 0);
+        assert_eq!(ic.next_level(), Some(0));
+        ic.enter(0, 0x0400);
+        assert_eq!(ic.current_level(), Some(0));
+
+        assert_eq!(ic.exit(), Some((0, 0x0400)));
+        assert_eq!(ic.current_level(), Some(2));
+        assert_eq!(ic.exit(), Some((2, 0x0300)));
+        assert_eq!(ic.current_level(), None);
+        assert_eq!(ic.next_level(), Some(2));
+    }
+
+    #[test]
+    fn test_enter_returns_and_clears_ilsw() {
+        let mut ic = InterruptController::new();
+        ic.request(4, 0x8000);
+        ic.request(4, 0x0100);
+        assert_eq!(ic.enter(4, 0x0200), 0x8100);
+        assert_eq!(ic.pending(), 0);
+
+        ic.request(4, 0);
+        assert_eq!(ic.enter(4, 0x0200), 0);
+    }
+}
@@ -2,28 +2,72 @@
 //!
 //! This module handles memory operations in isolation.
 //! All memory access goes through bounds-checked methods.
+//!
+//! Regions can be write-protected, like the 1130's storage protect
+//! feature: `write`, the path instructions store through, refuses to
+//! change a protected word, and `device_transfer` undoes and refuses a
+//! device transfer that changed one. Bulk host operations (`write_range`, `fill`,
+//! `restore_region`) are how programs and snapshots get loaded and are
+//! not checked.
+//!
+//! Every change bumps a generation counter and keeps a count of non-zero
+//! words, so views can tell cheaply whether memory changed and how much of
+//! it is in use. Raw access through `as_mut_slice` (device transfers)
+//! can't be tracked word by word; it bumps the generation and drops the
+//! count, which the next `used_word_count` rebuilds with one scan.
 
 use crate::error::{CpuError, Result};
+use std::cell::Cell;
+use std::ops::Range;
 
 /// IBM 1130 Memory
 ///
 /// Word-addressable memory with configurable size.
 /// Default size is 32K words (32,768 = 0x8000).
+#[derive(Clone)]
 pub struct Memory {
     data: Vec<u16>,
+    /// Write protection per word (empty until something is protected)
+    protected: Vec<bool>,
+    /// Bumped by every change to the contents
+    generation: u64,
+    /// Non-zero words, or `None` after untracked raw access
+    used_words: Cell<Option<usize>>,
 }
 
+/// Default memory size in words
+pub const DEFAULT_MEMORY_SIZE: usize = 32768;
+
+/// Largest memory a 16-bit address can reach
+pub const MAX_MEMORY_SIZE: usize = 0x10000;
+
 impl Memory {
     /// Create memory with default size (32K words)
     pub fn new() -> Self {
-        Self::with_size(32768)
+        Self {
+            data: vec![0; DEFAULT_MEMORY_SIZE],
+            protected: Vec::new(),
+            generation: 0,
+            used_words: Cell::new(Some(0)),
+        }
     }
 
     /// Create memory with specific size in words
-    pub fn with_size(size: usize) -> Self {
-        Self {
-            data: vec![0; size],
+    ///
+    /// # Errors
+    ///
+    /// Returns `CpuError::InvalidMemorySize` for zero words or more than
+    /// a 16-bit address can reach (`MAX_MEMORY_SIZE`)
+    pub fn with_size(size: usize) -> Result<Self> {
+        if size == 0 || size > MAX_MEMORY_SIZE {
+            return Err(CpuError::InvalidMemorySize(size));
         }
+        Ok(Self {
+            data: vec![0; size],
+            protected: Vec::new(),
+            generation: 0,
+            used_words: Cell::new(Some(0)),
+        })
     }
 
     /// Get memory size in words
@@ -47,10 +91,14 @@ impl Memory {
     ///
     /// # Errors
     ///
-    /// Returns `CpuError::MemoryViolation` if address is out of bounds
+    /// Returns `CpuError::MemoryViolation` if address is out of bounds or
+    /// write-protected
     pub fn write(&mut self, address: usize, value: u16) -> Result<()> {
+        if self.is_protected(address) {
+            return Err(CpuError::MemoryViolation(address as u16));
+        }
         if address < self.data.len() {
-            self.data[address] = value;
+            self.modify(address..address + 1, |word| word[0] = value);
             Ok(())
         } else {
             Err(CpuError::MemoryViolation(address as u16))
@@ -83,13 +131,223 @@ impl Memory {
 
         let end = (address + values.len()).min(self.data.len());
         let count = end - address;
-        self.data[address..end].copy_from_slice(&values[..count]);
+        self.modify(address..end, |region| {
+            region.copy_from_slice(&values[..count]);
+        });
         Ok(())
     }
 
+    /// Copy out `len` words starting at `start` for a later `restore_region`
+    ///
+    /// # Errors
+    ///
+    /// Returns `CpuError::MemoryViolation` if any part of the region is out of bounds
+    pub fn snapshot_region(&self, start: usize, len: usize) -> Result<Vec<u16>> {
+        let end = self.checked_region_end(start, len)?;
+        Ok(self.data[start..end].to_vec())
+    }
+
+    /// Write back a region saved by `snapshot_region`
+    ///
+    /// Unlike `write_range`, nothing is written unless the whole region fits.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CpuError::MemoryViolation` if any part of the region is out of bounds
+    pub fn restore_region(&mut self, start: usize, values: &[u16]) -> Result<()> {
+        let end = self.checked_region_end(start, values.len())?;
+        self.modify(start..end, |region| region.copy_from_slice(values));
+        Ok(())
+    }
+
+    /// End (exclusive) of a region, or the first out-of-bounds address
+    fn checked_region_end(&self, start: usize, len: usize) -> Result<usize> {
+        let end = start.saturating_add(len);
+        if start > self.data.len() || end > self.data.len() {
+            let first_bad = start.max(self.data.len());
+            return Err(CpuError::MemoryViolation(first_bad as u16));
+        }
+        Ok(end)
+    }
+
+    /// Write-protect every word from `start` up to (not including) `end`
+    ///
+    /// # Errors
+    ///
+    /// Returns `CpuError::MemoryViolation` if the range is out of bounds or
+    /// `end` is below `start`; nothing changes then
+    pub fn protect(&mut self, start: usize, end: usize) -> Result<()> {
+        self.set_protection(start, end, true)
+    }
+
+    /// Remove write protection from `start` up to (not including) `end`
+    ///
+    /// # Errors
+    ///
+    /// As for `protect`
+    pub fn unprotect(&mut self, start: usize, end: usize) -> Result<()> {
+        self.set_protection(start, end, false)
+    }
+
+    /// Check whether the word at `address` is write-protected
+    pub fn is_protected(&self, address: usize) -> bool {
+        self.protected.get(address).copied().unwrap_or(false)
+    }
+
+    fn set_protection(&mut self, start: usize, end: usize, protect: bool) -> Result<()> {
+        if end < start {
+            return Err(CpuError::MemoryViolation(start as u16));
+        }
+        let end = self.checked_region_end(start, end - start)?;
+        if self.protected.is_empty() {
+            if !protect {
+                return Ok(());
+            }
+            self.protected = vec![false; self.data.len()];
+        }
+        self.protected[start..end].fill(protect);
+        Ok(())
+    }
+
+    /// Set every word from `start` up to (not including) `end` to `value`
+    ///
+    /// If `end` is below `start` the range wraps past the top of memory
+    /// to address 0, so `fill(size - 2, 2, v)` sets four words.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CpuError::MemoryViolation` if either bound is out of range;
+    /// nothing is written then
+    pub fn fill(&mut self, start: usize, end: usize, value: u16) -> Result<()> {
+        if start <= end {
+            let end = self.checked_region_end(start, end - start)?;
+            self.modify(start..end, |region| region.fill(value));
+        } else {
+            let size = self.data.len();
+            if start >= size {
+                return Err(CpuError::MemoryViolation(start as u16));
+            }
+            let end = self.checked_region_end(0, end)?;
+            self.modify(start..size, |region| region.fill(value));
+            self.modify(0..end, |region| region.fill(value));
+        }
+        Ok(())
+    }
+
+    /// First address at or after `start` where `pattern` matches exactly
+    ///
+    /// An empty pattern matches at `start`.
+    pub fn search(&self, pattern: &[u16], start: usize) -> Option<usize> {
+        self.search_mask(pattern, &[], start)
+    }
+
+    /// Like `search`, comparing only the bits set in each `mask` word
+    ///
+    /// `mask[i]` applies to `pattern[i]`; pattern words without a mask word
+    /// are compared in full. E.g. a mask of `0xF800` matches any instruction
+    /// with the pattern's opcode, whatever its tag and displacement.
+    pub fn search_mask(&self, pattern: &[u16], mask: &[u16], start: usize) -> Option<usize> {
+        if pattern.is_empty() {
+            return Some(start);
+        }
+
+        let matches_at = |window: &[u16]| {
+            window
+                .iter()
+                .zip(pattern)
+                .enumerate()
+                .all(|(i, (&word, &want))| {
+                    let bits = mask.get(i).copied().unwrap_or(0xFFFF);
+                    word & bits == want & bits
+                })
+        };
+        self.data
+            .get(start..)?
+            .windows(pattern.len())
+            .position(matches_at)
+            .map(|offset| start + offset)
+    }
+
+    /// Check whether two memories have the same size and contents
+    pub fn compare(&self, other: &Memory) -> bool {
+        self.data == other.data
+    }
+
     /// Clear all memory to zero
     pub fn clear(&mut self) {
-        self.data.fill(0);
+        let size = self.data.len();
+        self.modify(0..size, |region| region.fill(0));
+    }
+
+    /// Counter bumped by every change to memory contents
+    ///
+    /// Equal generations mean nothing was written in between.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Number of non-zero words
+    ///
+    /// O(1) while every change goes through the tracked methods; after
+    /// `as_mut_slice` the first call rescans memory and caches the result.
+    pub fn used_word_count(&self) -> usize {
+        self.used_words.get().unwrap_or_else(|| {
+            let used = count_used(&self.data);
+            self.used_words.set(Some(used));
+            used
+        })
+    }
+
+    /// Apply `change` to `range`, keeping the generation and count current
+    fn modify(&mut self, range: Range<usize>, change: impl FnOnce(&mut [u16])) {
+        let region = &mut self.data[range];
+        let before = count_used(region);
+        change(region);
+        let after = count_used(region);
+        self.generation += 1;
+        if let Some(used) = self.used_words.get_mut() {
+            *used = *used - before + after;
+        }
+    }
+
+    /// Let a device transfer data through the raw slice, honouring
+    /// write protection
+    ///
+    /// Device word-count conventions differ, so the transfer isn't checked
+    /// up front; instead protected words are put back afterwards.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CpuError::MemoryViolation` at the first protected word the
+    /// transfer changed, or the transfer's own error
+    pub fn device_transfer<T>(
+        &mut self,
+        transfer: impl FnOnce(&mut [u16]) -> Result<T>,
+    ) -> Result<T> {
+        if self.protected.is_empty() {
+            return transfer(self.as_mut_slice());
+        }
+        let saved: Vec<(usize, u16)> = self
+            .protected
+            .iter()
+            .enumerate()
+            .filter(|&(_, &protect)| protect)
+            .map(|(address, _)| (address, self.data[address]))
+            .collect();
+
+        let result = transfer(self.as_mut_slice());
+
+        let mut violation = None;
+        for &(address, value) in &saved {
+            if self.data[address] != value {
+                self.data[address] = value;
+                violation.get_or_insert(address);
+            }
+        }
+        match violation {
+            Some(address) => Err(CpuError::MemoryViolation(address as u16)),
+            None => result,
+        }
     }
 
     /// Get direct slice reference (for performance-critical operations)
@@ -101,12 +359,19 @@ impl Memory {
 
     /// Get direct mutable slice reference (for performance-critical operations)
     ///
-    /// Use with caution - bypasses bounds checking
+    /// Use with caution - bypasses bounds checking and write protection,
+    /// and drops the used-word count until it is next asked for
     pub fn as_mut_slice(&mut self) -> &mut [u16] {
+        self.generation += 1;
+        self.used_words.set(None);
         &mut self.data
     }
 }
 
+fn count_used(words: &[u16]) -> usize {
+    words.iter().filter(|&&word| word != 0).count()
+}
+
 impl Default for Memory {
     fn default() -> Self {
         Self::new()
@@ -117,6 +382,115 @@ impl Default for Memory {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_memory_size_validation() {
+        assert_eq!(
+            Memory::with_size(0).err(),
+            Some(CpuError::InvalidMemorySize(0))
+        );
+        assert!(Memory::with_size(MAX_MEMORY_SIZE).is_ok());
+        assert!(Memory::with_size(MAX_MEMORY_SIZE + 1).is_err());
+    }
+
+    #[test]
+    fn test_memory_fill_at_boundary() {
+        let mut mem = Memory::with_size(16).unwrap();
+        mem.fill(12, 16, 0xFFFF).unwrap();
+        assert_eq!(
+            mem.read_range(11, 5),
+            vec![0, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF]
+        );
+
+        assert_eq!(mem.fill(12, 17, 1), Err(CpuError::MemoryViolation(16)));
+        assert_eq!(mem.read(12).unwrap(), 0xFFFF);
+    }
+
+    #[test]
+    fn test_memory_fill_wraps_around() {
+        let mut mem = Memory::with_size(16).unwrap();
+        mem.fill(14, 2, 0x1234).unwrap();
+        assert_eq!(mem.read_range(0, 3), vec![0x1234, 0x1234, 0]);
+        assert_eq!(mem.read_range(13, 3), vec![0, 0x1234, 0x1234]);
+
+        assert_eq!(mem.fill(16, 2, 1), Err(CpuError::MemoryViolation(16)));
+    }
+
+    #[test]
+    fn test_memory_search() {
+        let mut mem = Memory::with_size(16).unwrap();
+        mem.write_range(0, &[1, 2, 3]).unwrap();
+        mem.write_range(6, &[7, 8]).unwrap();
+        mem.write_range(14, &[7, 9]).unwrap();
+
+        assert_eq!(mem.search(&[1, 2, 3], 0), Some(0));
+        assert_eq!(mem.search(&[7, 8], 0), Some(6));
+        assert_eq!(mem.search(&[7, 9], 0), Some(14));
+        assert_eq!(mem.search(&[7], 7), Some(14));
+        assert_eq!(mem.search(&[8, 7], 0), None);
+        assert_eq!(mem.search(&[9, 0], 0), None);
+        assert_eq!(mem.search(&[], 5), Some(5));
+        assert_eq!(mem.search(&[1], 17), None);
+    }
+
+    #[test]
+    fn test_memory_search_mask() {
+        let mut mem = Memory::with_size(16).unwrap();
+        mem.write_range(4, &[0x6040, 0x0123]).unwrap(); // LD with tag and displacement
+
+        // Opcode-only search ignores the low bits
+        assert_eq!(mem.search_mask(&[0x6000], &[0xF800], 0), Some(4));
+        assert_eq!(mem.search(&[0x6000], 0), None);
+        // Unmasked trailing words compare in full
+        assert_eq!(mem.search_mask(&[0x6000, 0x0123], &[0xF800], 0), Some(4));
+        assert_eq!(mem.search_mask(&[0x6000, 0x0124], &[0xF800], 0), None);
+    }
+
+    #[test]
+    fn test_memory_compare() {
+        let mut a = Memory::with_size(16).unwrap();
+        let b = Memory::with_size(16).unwrap();
+        assert!(a.compare(&b));
+        a.write(3, 1).unwrap();
+        assert!(!a.compare(&b));
+        assert!(!b.compare(&Memory::with_size(8).unwrap()));
+    }
+
+    #[test]
+    fn test_memory_protect() {
+        let mut mem = Memory::with_size(16).unwrap();
+        mem.protect(4, 8).unwrap();
+
+        assert_eq!(mem.write(4, 1), Err(CpuError::MemoryViolation(4)));
+        assert_eq!(mem.write(7, 1), Err(CpuError::MemoryViolation(7)));
+        assert!(mem.write(3, 1).is_ok() && mem.write(8, 1).is_ok());
+        assert_eq!(mem.read(4).unwrap(), 0);
+
+        // Loading is a host operation and ignores protection
+        mem.write_range(4, &[9]).unwrap();
+        assert_eq!(mem.read(4).unwrap(), 9);
+
+        mem.unprotect(4, 6).unwrap();
+        assert!(mem.write(5, 2).is_ok());
+        assert!(mem.is_protected(6) && !mem.is_protected(5));
+
+        assert_eq!(mem.protect(12, 17), Err(CpuError::MemoryViolation(16)));
+        assert!(mem.protect(8, 4).is_err());
+        assert!(!mem.is_protected(12));
+
+        // A device transfer can't get round it either
+        let result = mem.device_transfer(|words| {
+            words[3..8].fill(0xFFFF);
+            Ok(())
+        });
+        assert_eq!(result, Err(CpuError::MemoryViolation(6)));
+        assert_eq!(mem.read_range(3, 5), [0xFFFF, 0xFFFF, 0xFFFF, 0, 0]);
+        let result = mem.device_transfer(|words| {
+            words[3] = 1;
+            Ok(())
+        });
+        assert!(result.is_ok());
+    }
+
     #[test]
     fn test_memory_new() {
         let mem = Memory::new();
@@ -125,7 +499,7 @@ mod tests {
 
     #[test]
     fn test_memory_with_size() {
-        let mem = Memory::with_size(8192);
+        let mem = Memory::with_size(8192).unwrap();
         assert_eq!(mem.size(), 8192);
     }
 
@@ -138,7 +512,7 @@ mod tests {
 
     #[test]
     fn test_memory_read_bounds_check() {
-        let mem = Memory::with_size(100);
+        let mem = Memory::with_size(100).unwrap();
         let result = mem.read(100);
         assert!(result.is_err());
         match result {
@@ -149,7 +523,7 @@ mod tests {
 
     #[test]
     fn test_memory_write_bounds_check() {
-        let mut mem = Memory::with_size(100);
+        let mut mem = Memory::with_size(100).unwrap();
         let result = mem.write(100, 0x1234);
         assert!(result.is_err());
     }
@@ -167,7 +541,7 @@ mod tests {
 
     #[test]
     fn test_memory_read_range_at_boundary() {
-        let mem = Memory::with_size(10);
+        let mem = Memory::with_size(10).unwrap();
         let values = mem.read_range(8, 5); // Request 5, but only 2 available
         assert_eq!(values.len(), 2);
     }
@@ -185,7 +559,7 @@ mod tests {
 
     #[test]
     fn test_memory_write_range_bounds() {
-        let mut mem = Memory::with_size(10);
+        let mut mem = Memory::with_size(10).unwrap();
         let values = vec![1, 2, 3, 4, 5];
         let result = mem.write_range(8, &values); // Only 2 slots available
         assert!(result.is_ok()); // Writes what fits
@@ -195,9 +569,74 @@ mod tests {
         // Values 3, 4, 5 were not written (out of bounds)
     }
 
+    #[test]
+    fn test_memory_used_word_count_and_generation() {
+        let mut mem = Memory::with_size(16).unwrap();
+        assert_eq!((mem.used_word_count(), mem.generation()), (0, 0));
+
+        mem.write(3, 7).unwrap();
+        mem.write(3, 9).unwrap();
+        mem.write_range(10, &[1, 0, 2]).unwrap();
+        assert_eq!(mem.used_word_count(), 3);
+        assert_eq!(mem.generation(), 3);
+
+        mem.fill(14, 2, 5).unwrap(); // wraps: 14, 15, 0, 1
+        assert_eq!(mem.used_word_count(), 7);
+        mem.write(3, 0).unwrap();
+        assert_eq!(mem.used_word_count(), 6);
+
+        // Untracked raw access falls back to a rescan
+        let generation = mem.generation();
+        mem.as_mut_slice()[8] = 4;
+        assert!(mem.generation() > generation);
+        assert_eq!(mem.used_word_count(), 7);
+
+        mem.clear();
+        assert_eq!(mem.used_word_count(), 0);
+    }
+
+    #[test]
+    fn test_memory_snapshot_and_restore_region() {
+        let mut mem = Memory::with_size(16).unwrap();
+        for addr in 0..16 {
+            mem.write(addr, addr as u16).unwrap();
+        }
+
+        let saved = mem.snapshot_region(4, 4).unwrap();
+        assert_eq!(saved, vec![4, 5, 6, 7]);
+
+        for addr in 0..16 {
+            mem.write(addr, 0xFFFF).unwrap();
+        }
+        mem.restore_region(4, &saved).unwrap();
+
+        // Only the region reverts; its neighbours keep the new values
+        assert_eq!(mem.read(3).unwrap(), 0xFFFF);
+        assert_eq!(mem.read_range(4, 4), vec![4, 5, 6, 7]);
+        assert_eq!(mem.read(8).unwrap(), 0xFFFF);
+    }
+
+    #[test]
+    fn test_memory_region_bounds() {
+        let mut mem = Memory::with_size(16).unwrap();
+        assert!(mem.snapshot_region(12, 4).is_ok());
+        assert_eq!(
+            mem.snapshot_region(12, 5),
+            Err(CpuError::MemoryViolation(16))
+        );
+        assert_eq!(
+            mem.snapshot_region(20, 1),
+            Err(CpuError::MemoryViolation(20))
+        );
+
+        // A region that does not fit is not partially written
+        assert!(mem.restore_region(14, &[1, 2, 3]).is_err());
+        assert_eq!(mem.read(14).unwrap(), 0);
+    }
+
     #[test]
     fn test_memory_clear() {
-        let mut mem = Memory::with_size(10);
+        let mut mem = Memory::with_size(10).unwrap();
         mem.write(0, 0x1234).unwrap();
         mem.write(5, 0x5678).unwrap();
         mem.write(9, 0xABCD).unwrap();
@@ -211,7 +650,7 @@ mod tests {
 
     #[test]
     fn test_memory_as_slice() {
-        let mut mem = Memory::with_size(5);
+        let mut mem = Memory::with_size(5).unwrap();
         mem.write(0, 1).unwrap();
         mem.write(1, 2).unwrap();
         mem.write(2, 3).unwrap();
@@ -224,7 +663,7 @@ mod tests {
 
     #[test]
     fn test_memory_as_mut_slice() {
-        let mut mem = Memory::with_size(5);
+        let mut mem = Memory::with_size(5).unwrap();
 
         let slice = mem.as_mut_slice();
         slice[0] = 0x1111;
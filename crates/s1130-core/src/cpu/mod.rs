@@ -3,27 +3,160 @@
 //! This module orchestrates the CPU components:
 //! - Registers (accumulator, extension, index registers, flags)
 //! - Memory (word-addressable, 32K default)
+//! - Interrupt levels (five prioritized, maskable levels)
 //! - State snapshots for external observation
+//! - Lockstep comparison of two runs (`compare_execution`)
+//! - Event hooks observing execution, memory access and I/O
 
+pub mod breakpoints;
+pub mod compare;
+pub mod events;
 pub mod executor;
+mod fault;
+pub mod history;
+mod inspect;
+pub mod interrupts;
 pub mod memory;
+pub mod options;
 pub mod registers;
+pub mod reset;
+mod stall;
 pub mod state;
-
+pub mod trace;
+
+pub use breakpoints::{BreakpointSet, RegisterId, RunResult};
+pub use compare::{compare_execution, Divergence};
+pub use events::{CpuEventHook, NoOpHook, TraceLogHook};
+pub use fault::FaultContext;
+pub use history::{HistoryEntry, StateHistory};
+pub use interrupts::InterruptController;
 pub use memory::Memory;
+pub use options::{CpuOptions, MemoryMode};
 pub use registers::{IndexRegisters, StatusFlags};
-pub use state::CpuState;
+pub use reset::ResetScope;
+pub use state::{CpuSnapshot, CpuState, DeviceSnapshot, FieldDiff};
+pub use trace::{StepInfo, TraceBuffer, TraceEntry, TraceEvent};
+
+use crate::assembler::{AssembledProgram, Assembler};
+use crate::devices::console_switches::CONSOLE_SWITCHES_ADDRESS;
+use crate::devices::{
+    ConsoleSwitches, Device, DeviceConsoleKeyboard, DeviceConsolePrinter, DeviceHandle,
+    DeviceManager, Iocc, ReplacementCallback, StandardDevice,
+};
+use crate::error::{CpuError, LoadError, Result};
+use crate::instructions::{divide_extra_cycles, InstructionInfo, OpCode};
+use stall::StallDetector;
+use std::collections::{HashMap, VecDeque};
+#[cfg(feature = "std")]
+use std::sync::mpsc;
+use std::sync::Arc;
+
+/// A word skipped because it did not decode as an instruction
+///
+/// Recorded instead of faulting when `Cpu::set_skip_invalid` is on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SkippedWord {
+    /// Address of the word
+    pub address: u16,
+    /// The word itself
+    pub word: u16,
+}
+
+/// Why `Cpu::run` stopped
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StopReason {
+    /// The program executed WAIT
+    Wait,
+    /// `max_steps` instructions executed without stopping
+    MaxStepsReached,
+    /// The word at this address is not a valid instruction
+    InvalidInstruction(u16),
+    /// Memory access outside the machine at this address
+    MemoryViolation(u16),
+    /// An I/O operation failed
+    DeviceError(String),
+    /// Stopped at an instruction breakpoint or register trigger (IAR)
+    Breakpoint(u16),
+    /// Stopped after a write to a watched address
+    Watchpoint(u16),
+    /// The program kept sensing a device whose status never changed
+    /// (see `Cpu::set_stall_threshold`)
+    DeviceStall {
+        /// Device code of the stalled device
+        code: u8,
+    },
+    /// Any other error, with its message
+    Error(String),
+}
+
+impl StopReason {
+    /// Classify the error that ended a run, with `iar` at the failing step
+    pub fn from_error(error: CpuError, iar: u16) -> Self {
+        match error {
+            CpuError::WaitState => StopReason::Wait,
+            CpuError::StepLimitReached => StopReason::MaxStepsReached,
+            CpuError::InvalidInstruction(address) => StopReason::InvalidInstruction(address),
+            CpuError::NoInstructionLoaded => StopReason::InvalidInstruction(iar),
+            CpuError::MemoryViolation(address) => StopReason::MemoryViolation(address),
+            CpuError::Breakpoint(address) => StopReason::Breakpoint(address),
+            CpuError::Watchpoint(address) => StopReason::Watchpoint(address),
+            CpuError::DeviceError(message) => StopReason::DeviceError(message),
+            CpuError::InvalidDevice(_) => StopReason::DeviceError(error.to_string()),
+            CpuError::InvalidInterruptLevel(_)
+            | CpuError::InvalidSnapshot(_)
+            | CpuError::HistoryEmpty
+            | CpuError::HistoryMemoryModified(_)
+            | CpuError::InvalidMemorySize(_) => StopReason::Error(error.to_string()),
+        }
+    }
+
+    /// Check whether the run stopped on an error rather than a normal stop
+    pub fn is_fault(&self) -> bool {
+        matches!(
+            self,
+            StopReason::InvalidInstruction(_)
+                | StopReason::MemoryViolation(_)
+                | StopReason::DeviceError(_)
+                | StopReason::Error(_)
+        )
+    }
+
+    /// Short camelCase name, e.g. for JSON
+    pub fn name(&self) -> &'static str {
+        match self {
+            StopReason::Wait => "wait",
+            StopReason::MaxStepsReached => "maxStepsReached",
+            StopReason::InvalidInstruction(_) => "invalidInstruction",
+            StopReason::MemoryViolation(_) => "memoryViolation",
+            StopReason::DeviceError(_) => "deviceError",
+            StopReason::Breakpoint(_) => "breakpoint",
+            StopReason::Watchpoint(_) => "watchpoint",
+            StopReason::DeviceStall { .. } => "deviceStall",
+            StopReason::Error(_) => "error",
+        }
+    }
+}
 
-use crate::devices::{Device, Iocc};
-use crate::error::{CpuError, Result};
-use crate::instructions::{InstructionInfo, OpCode};
-use std::collections::HashMap;
+/// Outcome of `Cpu::run_collecting_output`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RunReport {
+    /// Instructions executed
+    pub steps: u64,
+    /// The run ended in the wait state
+    pub halted: bool,
+    /// Everything the console printer printed, if one is attached
+    pub printer_output: Option<String>,
+}
 
 /// IBM 1130 Central Processing Unit
 ///
 /// The CPU coordinates execution of instructions, manages registers,
 /// and provides access to memory. It maintains minimal state and delegates
 /// responsibilities to focused submodules.
+///
+/// Cloning produces an independent machine (memory, registers and device
+/// state included), e.g. for speculative "run ahead and compare" debugging.
+#[derive(Clone)]
 pub struct Cpu {
     /// Main accumulator (16-bit)
     acc: u16,
@@ -43,34 +176,181 @@ pub struct Cpu {
     /// Main memory
     memory: Memory,
 
+    /// Whether 0x0001-0x0003 mirror the index registers
+    memory_mode: MemoryMode,
+
     /// Instruction execution counter
     instruction_count: u64,
 
-    /// Attached I/O devices (device_code -> device)
-    devices: HashMap<u8, Box<dyn Device>>,
+    /// Storage cycles used by executed instructions (see `CYCLE_COUNTS`)
+    cycle_count: u64,
+
+    /// Attached I/O devices, indexed by device code
+    devices: DeviceManager,
+
+    /// Console switches are attached, so they own address 0
+    switches_attached: bool,
 
     /// Last decoded IOCC (for XIO instruction)
     iocc: Option<Iocc>,
+
+    /// Interrupt levels (pending, masked, being serviced)
+    interrupts: InterruptController,
+
+    /// Addresses of recently executed instructions (oldest first)
+    iar_history: VecDeque<u16>,
+
+    /// Registers before recent steps, for `step_back` (None = disabled)
+    state_history: Option<StateHistory>,
+
+    /// Maximum number of addresses kept in `iar_history` (0 = disabled)
+    iar_history_capacity: usize,
+
+    /// Execution count per instruction address (None = profiling disabled)
+    profile: Option<HashMap<u16, u64>>,
+
+    /// Debugger breakpoints, watchpoints and register triggers
+    breakpoints: BreakpointSet,
+
+    /// Breakpoint address just reported, allowed to execute on the next step
+    resume_address: Option<u16>,
+
+    /// Watched address written during the current step
+    watch_hit: Option<u16>,
+
+    /// Execution trace (None = tracing disabled)
+    trace: Option<TraceBuffer>,
+
+    /// Receives one event per executed instruction (None = no channel)
+    #[cfg(feature = "std")]
+    trace_sender: Option<mpsc::Sender<TraceEvent>>,
+
+    /// Decoded instructions by address, only kept during `run_fast`
+    decode_cache: Option<HashMap<u16, InstructionInfo>>,
+
+    /// Treat invalid opcodes as data and continue
+    skip_invalid: bool,
+
+    /// Words skipped in `skip_invalid` mode, oldest first
+    skipped_words: Vec<SkippedWord>,
+
+    /// Repeated XIO Sense tracking for `StopReason::DeviceStall`
+    stall: StallDetector,
+
+    /// Instruction the last run faulted on
+    last_fault: Option<FaultContext>,
+
+    /// User notes by address, shown in disassembly (not stored in memory)
+    annotations: HashMap<u16, String>,
+
+    /// Observer of execution, memory access and I/O (shared by clones)
+    event_hook: Option<Arc<dyn CpuEventHook>>,
+
+    /// An instruction is executing, so memory access is reported to the hook
+    executing: bool,
 }
 
 impl Cpu {
     /// Create a new CPU with default configuration (32K memory)
     pub fn new() -> Self {
-        Self::with_memory_size(32768)
+        Self::with_options(CpuOptions::default()).expect("default options are valid")
     }
 
     /// Create a CPU with specific memory size (in words)
-    pub fn with_memory_size(size: usize) -> Self {
-        Self {
+    ///
+    /// # Errors
+    ///
+    /// Returns `CpuError::InvalidMemorySize` if `Memory::with_size` rejects the size
+    pub fn with_memory_size(size: usize) -> Result<Self> {
+        Self::with_options(CpuOptions {
+            memory_size: size,
+            ..CpuOptions::default()
+        })
+    }
+
+    /// Create a CPU from a full set of options
+    ///
+    /// # Errors
+    ///
+    /// Returns `CpuError::InvalidMemorySize` if `Memory::with_size` rejects
+    /// `options.memory_size`
+    pub fn with_options(options: CpuOptions) -> Result<Self> {
+        let mut cpu = Self {
             acc: 0,
             ext: 0,
             iar: 0,
             index_registers: IndexRegisters::new(),
             status_flags: StatusFlags::new(),
-            memory: Memory::with_size(size),
+            memory: Memory::with_size(options.memory_size)?,
+            memory_mode: options.memory_mode,
             instruction_count: 0,
-            devices: HashMap::new(),
+            cycle_count: 0,
+            devices: DeviceManager::new(),
+            switches_attached: false,
             iocc: None,
+            interrupts: InterruptController::new(),
+            iar_history: VecDeque::new(),
+            iar_history_capacity: 0,
+            state_history: None,
+            profile: None,
+            breakpoints: BreakpointSet::new(),
+            resume_address: None,
+            watch_hit: None,
+            trace: None,
+            #[cfg(feature = "std")]
+            trace_sender: None,
+            decode_cache: None,
+            skip_invalid: options.skip_invalid,
+            skipped_words: Vec::new(),
+            stall: StallDetector::new(options.stall_threshold),
+            last_fault: None,
+            annotations: HashMap::new(),
+            event_hook: None,
+            executing: false,
+        };
+
+        if let Some(capacity) = options.enable_history {
+            cpu.enable_iar_history(capacity);
+        }
+        cpu.enable_profiling(options.enable_profiling);
+
+        if options.standard_devices {
+            let standard: [Box<dyn Device>; 2] = [
+                Box::new(DeviceConsoleKeyboard::new()),
+                Box::new(DeviceConsolePrinter::new()),
+            ];
+            for device in standard {
+                cpu.devices
+                    .attach(device)
+                    .expect("standard device codes are distinct");
+            }
+        }
+
+        Ok(cpu)
+    }
+
+    /// Get the index register memory mapping mode
+    pub fn memory_mode(&self) -> MemoryMode {
+        self.memory_mode
+    }
+
+    /// Check whether 0x0001-0x0003 mirror the index registers
+    pub fn memory_mapped_registers_enabled(&self) -> bool {
+        self.memory_mode == MemoryMode::MappedIndexRegisters
+    }
+
+    /// Map XR1-XR3 at 0x0001-0x0003 (the default) or use plain memory there
+    ///
+    /// Enabling copies the current index registers into those words so both
+    /// views agree from then on.
+    pub fn set_memory_mapping(&mut self, enabled: bool) {
+        if !enabled {
+            self.memory_mode = MemoryMode::Plain;
+            return;
+        }
+        self.memory_mode = MemoryMode::MappedIndexRegisters;
+        for tag in 1..=3 {
+            self.set_index_register(tag, self.index_registers.get(tag));
         }
     }
 
@@ -78,13 +358,57 @@ impl Cpu {
     ///
     /// Clears all registers and flags, but preserves memory contents
     pub fn reset(&mut self) {
-        self.acc = 0;
-        self.ext = 0;
-        self.iar = 0;
-        self.index_registers.reset();
-        self.status_flags.reset();
-        self.instruction_count = 0;
-        // Memory is NOT cleared - programs remain loaded
+        self.reset_with_scope(ResetScope::default());
+    }
+
+    /// Reset only the state groups in `scope`
+    ///
+    /// Memory is never cleared. Profiling, history and tracing stay enabled;
+    /// only their collected data is dropped.
+    pub fn reset_with_scope(&mut self, scope: ResetScope) {
+        if scope.contains(ResetScope::REGISTERS) {
+            self.acc = 0;
+            self.ext = 0;
+            self.iar = 0;
+            self.index_registers.reset();
+            self.status_flags.reset();
+            self.interrupts.reset();
+            self.stall.clear();
+            self.last_fault = None;
+        }
+        if scope.contains(ResetScope::COUNTERS) {
+            self.instruction_count = 0;
+            self.cycle_count = 0;
+        }
+        if scope.contains(ResetScope::PROFILING) {
+            if let Some(profile) = self.profile.as_mut() {
+                profile.clear();
+            }
+        }
+        if scope.contains(ResetScope::HISTORY) {
+            self.iar_history.clear();
+            if let Some(history) = self.state_history.as_mut() {
+                history.clear();
+            }
+            if let Some(trace) = self.trace.as_mut() {
+                trace.clear();
+            }
+        }
+        if scope.contains(ResetScope::DEVICES) {
+            for (_, device) in self.devices.iter_mut() {
+                device.reset();
+            }
+        }
+        if scope.contains(ResetScope::ANNOTATIONS) {
+            self.annotations.clear();
+        }
+    }
+
+    /// Reset every state group, including devices and annotations
+    ///
+    /// Memory is kept, as with every reset.
+    pub fn reset_hard(&mut self) {
+        self.reset_with_scope(ResetScope::ALL);
     }
 
     /// Get current CPU state snapshot
@@ -100,10 +424,80 @@ impl Cpu {
             overflow: self.status_flags.overflow,
             wait: self.status_flags.wait,
             instruction_count: self.instruction_count,
-            current_interrupt_level: None, // TODO: implement interrupt system
+            cycle_count: self.cycle_count,
+            current_interrupt_level: self.interrupts.current_level(),
         }
     }
 
+    /// Capture registers, memory and device state for later restore
+    pub fn save_snapshot(&self) -> CpuSnapshot {
+        let devices_state: Vec<DeviceSnapshot> = self
+            .devices
+            .iter()
+            .map(|(device_code, device)| DeviceSnapshot {
+                device_code,
+                data: device.save_state(),
+            })
+            .collect();
+
+        CpuSnapshot {
+            state: self.get_state(),
+            memory: self.memory.as_slice().to_vec(),
+            devices_state,
+            interrupts: self.interrupts.save_state(),
+        }
+    }
+
+    /// Restore a snapshot taken by `save_snapshot`
+    ///
+    /// The snapshot's memory must match this CPU's memory size, otherwise
+    /// `CpuError::MemoryViolation` is returned and nothing is changed.
+    /// Device state is applied to devices attached at the same codes;
+    /// snapshots for devices not attached are ignored. A snapshot without
+    /// interrupt controller state only restores the current level.
+    pub fn restore_snapshot(&mut self, snapshot: CpuSnapshot) -> Result<()> {
+        if snapshot.memory.len() != self.memory.size() {
+            return Err(CpuError::MemoryViolation(
+                snapshot.memory.len().min(u16::MAX as usize) as u16,
+            ));
+        }
+
+        self.memory.write_range(0, &snapshot.memory)?;
+        self.apply_state(&snapshot.state);
+        if !snapshot.interrupts.is_empty() {
+            self.interrupts.restore_state(&snapshot.interrupts);
+        }
+
+        for device_state in &snapshot.devices_state {
+            if let Some(device) = self.devices.get_mut(device_state.device_code) {
+                device.restore_state(&device_state.data);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Set registers, flags, the instruction count and the interrupt level
+    /// from `state`
+    ///
+    /// Index registers go through `set_index_register`, so their
+    /// memory-mapped words follow.
+    fn apply_state(&mut self, state: &CpuState) {
+        self.acc = state.acc;
+        self.ext = state.ext;
+        self.iar = state.iar;
+        self.set_index_register(1, state.xr1);
+        self.set_index_register(2, state.xr2);
+        self.set_index_register(3, state.xr3);
+        self.status_flags.carry = state.carry;
+        self.status_flags.overflow = state.overflow;
+        self.status_flags.wait = state.wait;
+        self.instruction_count = state.instruction_count;
+        self.cycle_count = state.cycle_count;
+        self.interrupts
+            .set_current_level(state.current_interrupt_level);
+    }
+
     // === Accumulator Methods ===
 
     pub fn get_acc(&self) -> u16 {
@@ -156,21 +550,32 @@ impl Cpu {
         self.index_registers.get(tag)
     }
 
+    /// Set an index register
+    ///
+    /// In `MappedIndexRegisters` mode the register's word at 0x0001-0x0003
+    /// is updated too, even if it is write-protected: it is the register
+    /// itself, and refusing the write would leave the two out of step.
     pub fn set_index_register(&mut self, tag: u8, value: u16) {
         self.index_registers.set(tag, value);
 
+        if self.memory_mode != MemoryMode::MappedIndexRegisters || !(1..=3).contains(&tag) {
+            return;
+        }
+
         // Update memory-mapped locations (0x0001-0x0003)
-        match tag {
-            1 => {
-                let _ = self.memory.write(0x0001, value);
-            }
-            2 => {
-                let _ = self.memory.write(0x0002, value);
-            }
-            3 => {
-                let _ = self.memory.write(0x0003, value);
-            }
-            _ => {}
+        self.invalidate_decoded(tag as u16);
+        let _ = self.memory.write_range(tag as usize, &[value]);
+    }
+
+    /// Read a register by identifier
+    pub fn get_register(&self, register: RegisterId) -> u16 {
+        match register {
+            RegisterId::Acc => self.acc,
+            RegisterId::Ext => self.ext,
+            RegisterId::Iar => self.iar,
+            RegisterId::Xr1 => self.index_registers.xr1,
+            RegisterId::Xr2 => self.index_registers.xr2,
+            RegisterId::Xr3 => self.index_registers.xr3,
         }
     }
 
@@ -200,16 +605,59 @@ impl Cpu {
         self.status_flags.wait = value;
     }
 
+    /// Carry and overflow packed as LDS/STS see them (bit 15 carry,
+    /// bit 14 overflow)
+    pub fn flags_word(&self) -> u16 {
+        self.status_flags.to_word()
+    }
+
+    /// Set carry and overflow from a packed word, as LDS does
+    ///
+    /// The wait state is not part of the word and is left unchanged.
+    pub fn set_flags_word(&mut self, word: u16) {
+        let flags = StatusFlags::from_word(word);
+        self.status_flags.carry = flags.carry;
+        self.status_flags.overflow = flags.overflow;
+    }
+
     // === Memory Methods ===
 
     /// Read word from memory with bounds checking
+    ///
+    /// With console switches attached, address 0 reads the switch word.
     pub fn read_memory(&self, address: usize) -> Result<u16> {
-        self.memory.read(address)
+        if address == CONSOLE_SWITCHES_ADDRESS && self.switches_attached {
+            if let Some(switches) = self.console_switches_device() {
+                return Ok(switches.value());
+            }
+        }
+        let value = self.memory.read(address)?;
+        if let Some(hook) = self.active_event_hook() {
+            hook.on_memory_read(address as u16, value);
+        }
+        Ok(value)
     }
 
     /// Write word to memory with bounds checking and memory-mapped register handling
+    ///
+    /// With console switches attached, writes to address 0 are ignored.
     pub fn write_memory(&mut self, address: usize, value: u16) -> Result<()> {
+        if address == CONSOLE_SWITCHES_ADDRESS && self.switches_attached {
+            return Ok(());
+        }
         self.memory.write(address, value)?;
+        self.invalidate_decoded(address as u16);
+        if let Some(hook) = self.active_event_hook() {
+            hook.on_memory_write(address as u16, value);
+        }
+
+        if self.breakpoints.has_watchpoint(address as u16) {
+            self.watch_hit = Some(address as u16);
+        }
+
+        if self.memory_mode != MemoryMode::MappedIndexRegisters {
+            return Ok(());
+        }
 
         // Handle memory-mapped index registers
         match address {
@@ -222,16 +670,216 @@ impl Cpu {
         Ok(())
     }
 
+    /// Counter bumped by every change to memory contents
+    ///
+    /// Views can compare it with the value they last rendered to skip work
+    /// when nothing was written.
+    pub fn memory_generation(&self) -> u64 {
+        self.memory.generation()
+    }
+
+    /// Number of non-zero memory words
+    ///
+    /// O(1) except right after a device transfer, when it rescans once.
+    pub fn used_word_count(&self) -> usize {
+        self.memory.used_word_count()
+    }
+
     /// Read multiple words from memory
     pub fn read_memory_range(&self, address: usize, count: usize) -> Vec<u16> {
         self.memory.read_range(address, count)
     }
 
     /// Write multiple words to memory
+    ///
+    /// With console switches attached, a word for address 0 is skipped.
     pub fn write_memory_range(&mut self, address: usize, values: &[u16]) -> Result<()> {
+        let (address, values) = match values.split_first() {
+            Some((_, rest)) if address == CONSOLE_SWITCHES_ADDRESS && self.switches_attached => {
+                (address + 1, rest)
+            }
+            _ => (address, values),
+        };
         self.memory.write_range(address, values)?;
+        self.sync_mapped_registers(address, values);
+        Ok(())
+    }
+
+    /// Read the word at IAR and advance IAR, like the console Examine key
+    ///
+    /// IAR wraps to 0 past the top of memory, so repeated examines walk
+    /// through memory.
+    pub fn examine_word(&mut self) -> Result<u16> {
+        let value = self.read_memory(self.iar as usize)?;
+        self.advance_console_address();
+        Ok(value)
+    }
+
+    /// Write `value` at IAR and advance IAR, like the console Deposit key
+    ///
+    /// Goes through `write_memory`, so in `MappedIndexRegisters` mode
+    /// depositing into 0x0001-0x0003 loads the index registers.
+    pub fn deposit_word(&mut self, value: u16) -> Result<()> {
+        self.write_memory(self.iar as usize, value)?;
+        self.advance_console_address();
+        Ok(())
+    }
+
+    fn advance_console_address(&mut self) {
+        self.iar = ((self.iar as usize + 1) % self.memory.size()) as u16;
+    }
+
+    /// Load an assembled program and point IAR at its entry
+    ///
+    /// Writes every segment to memory, then sets IAR to the entry point
+    /// (or the origin if the program has none).
+    ///
+    /// # Errors
+    ///
+    /// Returns `CpuError::MemoryViolation` with the first address past the
+    /// end of memory if any segment does not fit. Nothing is written then.
+    pub fn load_program(&mut self, program: &AssembledProgram) -> Result<()> {
+        let size = self.memory.size();
+        for segment in &program.segments {
+            let start = segment.origin as usize;
+            if start + segment.words.len() > size {
+                return Err(CpuError::MemoryViolation(start.max(size) as u16));
+            }
+        }
+
+        for segment in &program.segments {
+            self.write_memory_range(segment.origin as usize, &segment.words)?;
+        }
+        self.iar = program.entry_point.unwrap_or(program.origin);
+        Ok(())
+    }
+
+    /// Assemble `source` and load it with `load_program`
+    ///
+    /// Returns the assembled program, e.g. for its symbols.
+    ///
+    /// # Errors
+    ///
+    /// Returns `LoadError::Assembly` if the source does not assemble and
+    /// `LoadError::Memory` if the program does not fit in memory.
+    pub fn load_source(
+        &mut self,
+        source: &str,
+    ) -> std::result::Result<AssembledProgram, LoadError> {
+        let program = Assembler::new().assemble(source)?;
+        self.load_program(&program)?;
+        Ok(program)
+    }
+
+    /// Load a program with `load_program`, then `run` it
+    ///
+    /// Returns the number of instructions executed.
+    pub fn load_and_run(&mut self, program: &AssembledProgram, max_steps: u64) -> Result<u64> {
+        self.load_program(program)?;
+        Ok(self.run_silent(max_steps))
+    }
+
+    /// Save `len` words starting at `start` (bounds-checked)
+    pub fn snapshot_region(&self, start: usize, len: usize) -> Result<Vec<u16>> {
+        self.memory.snapshot_region(start, len)
+    }
+
+    /// Restore a region saved by `snapshot_region`
+    ///
+    /// Fails without writing anything if the region does not fit in memory.
+    pub fn restore_region(&mut self, start: usize, values: &[u16]) -> Result<()> {
+        self.memory.restore_region(start, values)?;
+        self.sync_mapped_registers(start, values);
+        Ok(())
+    }
+
+    /// First address at or after `start` holding `pattern` (see `Memory::search`)
+    pub fn search_memory(&self, pattern: &[u16], start: u16) -> Option<u16> {
+        self.memory
+            .search(pattern, start as usize)
+            .map(|address| address as u16)
+    }
+
+    /// Masked pattern search (see `Memory::search_mask`)
+    pub fn search_memory_masked(&self, pattern: &[u16], mask: &[u16], start: u16) -> Option<u16> {
+        self.memory
+            .search_mask(pattern, mask, start as usize)
+            .map(|address| address as u16)
+    }
+
+    /// Set memory from `start` up to (not including) `end` to `value`
+    ///
+    /// Wraps past the top of memory when `end` is below `start`; see
+    /// `Memory::fill`. Fails without writing if either bound is out of range.
+    pub fn fill_memory(&mut self, start: u16, end: u16, value: u16) -> Result<()> {
+        self.memory.fill(start as usize, end as usize, value)?;
+
+        if let Some(cache) = self.decode_cache.as_mut() {
+            cache.clear();
+        }
+        let mapped = self.memory.read_range(0x0001, 3);
+        self.sync_mapped_registers(0x0001, &mapped);
+        Ok(())
+    }
+
+    /// Write-protect memory from `start` up to (not including) `end`
+    ///
+    /// Instructions storing into the range fault with
+    /// `CpuError::MemoryViolation`, as does `write_memory`. Loading a
+    /// program or restoring a snapshot still writes it, and no reset
+    /// clears the protection.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CpuError::MemoryViolation` if the range is out of bounds
+    pub fn protect_memory_range(&mut self, start: u16, end: u16) -> Result<()> {
+        self.memory.protect(start as usize, end as usize)
+    }
+
+    /// Remove write protection from `start` up to (not including) `end`
+    ///
+    /// # Errors
+    ///
+    /// Returns `CpuError::MemoryViolation` if the range is out of bounds
+    pub fn unprotect_memory_range(&mut self, start: u16, end: u16) -> Result<()> {
+        self.memory.unprotect(start as usize, end as usize)
+    }
+
+    /// Check whether the word at `address` is write-protected
+    pub fn is_memory_protected(&self, address: u16) -> bool {
+        self.memory.is_protected(address as usize)
+    }
+
+    /// Write a batch of `(address, value)` pairs
+    ///
+    /// All addresses are checked before anything is written, so a failing
+    /// patch leaves memory untouched. Later pairs win for repeated addresses.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CpuError::MemoryViolation` with the first out-of-bounds or
+    /// write-protected address
+    pub fn apply_patch(&mut self, patches: &[(u16, u16)]) -> Result<()> {
+        let size = self.memory.size();
+        if let Some(&(address, _)) = patches
+            .iter()
+            .find(|&&(addr, _)| addr as usize >= size || self.memory.is_protected(addr as usize))
+        {
+            return Err(CpuError::MemoryViolation(address));
+        }
+
+        for &(address, value) in patches {
+            self.write_memory(address as usize, value)?;
+        }
+        Ok(())
+    }
+
+    /// Update memory-mapped index registers after a bulk write
+    fn sync_mapped_registers(&mut self, address: usize, values: &[u16]) {
+        if self.memory_mode != MemoryMode::MappedIndexRegisters {
+            return;
+        }
 
-        // Update memory-mapped registers if affected
         for (offset, &value) in values.iter().enumerate() {
             match address + offset {
                 0x0001 => self.index_registers.xr1 = value,
@@ -240,8 +888,6 @@ impl Cpu {
                 _ => {}
             }
         }
-
-        Ok(())
     }
 
     // === Performance Methods ===
@@ -255,6 +901,383 @@ impl Cpu {
         self.instruction_count += 1;
     }
 
+    /// Reset the instruction counter without touching registers or memory
+    ///
+    /// Lets a host sample `get_instruction_count` over its own timer and
+    /// measure per-interval throughput (instructions per second).
+    pub fn reset_instruction_count(&mut self) {
+        self.instruction_count = 0;
+    }
+
+    /// Storage cycles used since the last reset of the counters
+    ///
+    /// Multiply by the cycle time (3.6 µs on a 1130 model 2) for the
+    /// simulated wall time.
+    pub fn get_cycle_count(&self) -> u64 {
+        self.cycle_count
+    }
+
+    /// Reset the cycle counter without touching registers or memory
+    pub fn reset_cycle_count(&mut self) {
+        self.cycle_count = 0;
+    }
+
+    // === Interrupt Methods ===
+
+    /// Request an interrupt on `level` (0 = highest priority, 4 = lowest)
+    ///
+    /// The request is serviced at the start of the next `step` once the
+    /// level is unmasked and outranks any level already being serviced.
+    pub fn request_interrupt(&mut self, level: u8) -> Result<()> {
+        self.request_interrupt_with_status(level, 0)
+    }
+
+    /// Request an interrupt, OR-ing `status` into the level's ILSW
+    ///
+    /// Devices use the status bits to identify themselves; the handler
+    /// finds the accumulated word at `ILSW_BASE + level`.
+    pub fn request_interrupt_with_status(&mut self, level: u8, status: u16) -> Result<()> {
+        if !InterruptController::is_valid_level(level) {
+            return Err(CpuError::InvalidInterruptLevel(level));
+        }
+        self.interrupts.request(level, status);
+        Ok(())
+    }
+
+    /// Get the interrupt mask register (bit n set = level n disabled)
+    pub fn get_interrupt_mask(&self) -> u8 {
+        self.interrupts.mask()
+    }
+
+    /// Set the interrupt mask register (bit n set = level n disabled)
+    pub fn set_interrupt_mask(&mut self, mask: u8) {
+        self.interrupts.set_mask(mask);
+    }
+
+    /// Highest-priority interrupt level currently being serviced
+    pub fn current_interrupt_level(&self) -> Option<u8> {
+        self.interrupts.current_level()
+    }
+
+    /// Access the interrupt controller
+    pub fn interrupts(&self) -> &InterruptController {
+        &self.interrupts
+    }
+
+    /// Return from the interrupt level currently being serviced
+    ///
+    /// Branches through the return address saved in the handler's entry
+    /// word and turns the level off, like the 1130's BOSC instruction.
+    ///
+    /// # Returns
+    /// The level that was exited, or `None` if no level was active
+    pub fn return_from_interrupt(&mut self) -> Result<Option<u8>> {
+        let Some((level, entry_point)) = self.interrupts.exit() else {
+            return Ok(None);
+        };
+        let return_address = self.read_memory(entry_point as usize)?;
+        self.set_iar(return_address);
+        Ok(Some(level))
+    }
+
+    /// Vector to the highest-priority serviceable interrupt, if any
+    ///
+    /// Raises the requests of attached devices first. Stores the level's
+    /// ILSW at `ILSW_BASE + level`, then performs a forced BSI through the
+    /// vector at `INTERRUPT_VECTOR_BASE + level`. Devices requesting the
+    /// level are told it was acknowledged. Returns whether an interrupt was
+    /// taken.
+    fn service_interrupts(&mut self) -> Result<bool> {
+        for (_, device) in self.devices.iter() {
+            if let Some((level, status)) = device.interrupt_request() {
+                self.interrupts.request(level, status);
+            }
+        }
+
+        let Some(level) = self.interrupts.next_level() else {
+            return Ok(false);
+        };
+
+        let vector = interrupts::INTERRUPT_VECTOR_BASE + level as u16;
+        let entry_point = self.read_memory(vector as usize)?;
+        let ilsw = self.interrupts.enter(level, entry_point);
+        for (_, device) in self.devices.iter_mut() {
+            if device
+                .interrupt_request()
+                .is_some_and(|(requested, _)| requested == level)
+            {
+                device.acknowledge_interrupt(level);
+            }
+        }
+        self.write_memory((interrupts::ILSW_BASE + level as u16) as usize, ilsw)?;
+
+        let return_address = self.get_iar();
+        self.write_memory(entry_point as usize, return_address)?;
+        self.set_iar(entry_point.wrapping_add(1));
+        Ok(true)
+    }
+
+    // === Breakpoints ===
+
+    /// Break before executing the instruction at `address`
+    pub fn set_breakpoint(&mut self, address: u16) {
+        self.breakpoints.set_breakpoint(address);
+    }
+
+    /// Remove an instruction breakpoint
+    pub fn clear_breakpoint(&mut self, address: u16) -> bool {
+        self.breakpoints.clear_breakpoint(address)
+    }
+
+    /// Break after an instruction writes `address` via `write_memory`
+    ///
+    /// Device transfers into memory are not watched.
+    pub fn set_watchpoint(&mut self, address: u16) {
+        self.breakpoints.set_watchpoint(address);
+    }
+
+    /// Remove a watchpoint
+    pub fn clear_watchpoint(&mut self, address: u16) -> bool {
+        self.breakpoints.clear_watchpoint(address)
+    }
+
+    /// Break after an instruction changes `register` to `value`
+    pub fn set_register_trigger(&mut self, register: RegisterId, value: u16) {
+        self.breakpoints.set_register_trigger(register, value);
+    }
+
+    /// Access the breakpoint set
+    pub fn breakpoints(&self) -> &BreakpointSet {
+        &self.breakpoints
+    }
+
+    /// Mutable access to the breakpoint set
+    pub fn breakpoints_mut(&mut self) -> &mut BreakpointSet {
+        &mut self.breakpoints
+    }
+
+    // === Annotations ===
+
+    /// Attach a note to `address`, replacing any earlier one
+    ///
+    /// Notes are debugging metadata: they survive execution and `reset`,
+    /// and are cleared only by `reset_hard`.
+    pub fn set_annotation(&mut self, address: u16, text: impl Into<String>) {
+        self.annotations.insert(address, text.into());
+    }
+
+    /// Note attached to `address`
+    pub fn annotation(&self, address: u16) -> Option<&str> {
+        self.annotations.get(&address).map(String::as_str)
+    }
+
+    /// Remove the note at `address`, returning it
+    pub fn clear_annotation(&mut self, address: u16) -> Option<String> {
+        self.annotations.remove(&address)
+    }
+
+    /// All notes by address
+    pub fn annotations(&self) -> &HashMap<u16, String> {
+        &self.annotations
+    }
+
+    /// Run until WAIT, a breakpoint/watchpoint, or `max_steps` instructions
+    ///
+    /// Calling again after a breakpoint resumes past it. Errors other than
+    /// breakpoints and WAIT are returned.
+    pub fn run_until_breakpoint(&mut self, max_steps: u64) -> Result<RunResult> {
+        for _ in 0..max_steps {
+            match self.step() {
+                Ok(()) if self.status_flags.wait => return Ok(RunResult::Halted),
+                Ok(()) => {}
+                Err(CpuError::WaitState) => return Ok(RunResult::Halted),
+                Err(CpuError::Breakpoint(address)) => return Ok(RunResult::Breakpoint(address)),
+                Err(CpuError::Watchpoint(address)) => return Ok(RunResult::Watchpoint(address)),
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(RunResult::StepLimitReached)
+    }
+
+    /// Run until IAR reaches `target` ("run to cursor")
+    ///
+    /// At least one instruction executes, so running to the current
+    /// address goes round a loop once. Returns the steps executed. A run
+    /// that stops first reports the steps so far with
+    /// `CpuError::WaitState` on WAIT, `CpuError::StepLimitReached` after
+    /// `max_steps`, or the error that ended it.
+    pub fn run_until_address(
+        &mut self,
+        target: u16,
+        max_steps: u64,
+    ) -> std::result::Result<u64, (u64, CpuError)> {
+        for steps in 1..=max_steps {
+            self.step().map_err(|error| (steps - 1, error))?;
+            if self.iar == target {
+                return Ok(steps);
+            }
+            if self.status_flags.wait {
+                return Err((steps, CpuError::WaitState));
+            }
+        }
+        Err((max_steps, CpuError::StepLimitReached))
+    }
+
+    // === Execution History ===
+
+    /// Record the addresses of the last `capacity` executed instructions
+    ///
+    /// Passing 0 disables recording. Existing history is cleared.
+    pub fn enable_iar_history(&mut self, capacity: usize) {
+        self.iar_history_capacity = capacity;
+        self.iar_history = VecDeque::with_capacity(capacity);
+    }
+
+    /// Addresses of recently executed instructions, oldest first
+    pub fn iar_history(&self) -> &[u16] {
+        // `record_iar_history` keeps the ring contiguous
+        self.iar_history.as_slices().0
+    }
+
+    /// Keep registers from before the last `max_entries` steps for `step_back`
+    ///
+    /// Passing 0 disables step-back. Existing history is cleared.
+    pub fn enable_history(&mut self, max_entries: usize) {
+        self.state_history = (max_entries > 0).then(|| StateHistory::new(max_entries));
+    }
+
+    /// Step-back history (None when disabled)
+    pub fn state_history(&self) -> Option<&StateHistory> {
+        self.state_history.as_ref()
+    }
+
+    /// Undo the most recent step by restoring the registers before it
+    ///
+    /// Memory is not restored. If the undone instruction wrote memory
+    /// (a store, BSI or XIO), the registers are still restored and
+    /// `CpuError::HistoryMemoryModified` names the instruction's address.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CpuError::HistoryEmpty` if history is disabled or has no
+    /// steps left.
+    pub fn step_back(&mut self) -> Result<()> {
+        let entry = self
+            .state_history
+            .as_mut()
+            .and_then(StateHistory::pop)
+            .ok_or(CpuError::HistoryEmpty)?;
+        self.apply_state(&entry.state);
+        self.resume_address = None;
+        if entry.wrote_memory {
+            return Err(CpuError::HistoryMemoryModified(entry.state.iar));
+        }
+        Ok(())
+    }
+
+    /// Record the last `capacity` executed instructions in a trace buffer
+    ///
+    /// Replaces any existing trace. Tracing costs nothing while disabled.
+    pub fn enable_trace(&mut self, capacity: usize) {
+        self.trace = Some(TraceBuffer::new(capacity));
+    }
+
+    /// Stop tracing and discard the trace
+    pub fn disable_trace(&mut self) {
+        self.trace = None;
+    }
+
+    /// Traced instructions, oldest first (empty when tracing is disabled)
+    pub fn get_trace(&self) -> &[TraceEntry] {
+        self.trace.as_ref().map_or(&[], |trace| trace.entries())
+    }
+
+    /// Discard recorded trace entries, keeping tracing enabled
+    pub fn clear_trace(&mut self) {
+        if let Some(trace) = self.trace.as_mut() {
+            trace.clear();
+        }
+    }
+
+    /// Stream execution events to another thread
+    ///
+    /// Every instruction executed from now on is sent on the returned
+    /// receiver, so e.g. a UI thread can follow a CPU running on a worker
+    /// thread. Replaces any earlier channel; the channel closes when the
+    /// receiver is dropped. A cloned CPU sends to the same channel.
+    #[cfg(feature = "std")]
+    pub fn trace_channel(&mut self) -> mpsc::Receiver<TraceEvent> {
+        let (sender, receiver) = mpsc::channel();
+        self.trace_sender = Some(sender);
+        receiver
+    }
+
+    /// Stop sending execution events
+    #[cfg(feature = "std")]
+    pub fn close_trace_channel(&mut self) {
+        self.trace_sender = None;
+    }
+
+    /// Turn per-address execution counting on or off
+    ///
+    /// Enabling starts from an empty profile; disabling discards it.
+    pub fn enable_profiling(&mut self, enabled: bool) {
+        self.profile = enabled.then(HashMap::new);
+    }
+
+    /// Execution count per instruction address, if profiling is enabled
+    pub fn execution_profile(&self) -> Option<&HashMap<u16, u64>> {
+        self.profile.as_ref()
+    }
+
+    /// Skip invalid opcodes instead of faulting
+    ///
+    /// When enabled, a word that does not decode as an instruction advances
+    /// IAR by one and is logged in `skipped_words`, so a run can survey a
+    /// region mixing code and data. Off by default: `step` returns
+    /// `CpuError::InvalidInstruction`.
+    pub fn set_skip_invalid(&mut self, enabled: bool) {
+        self.skip_invalid = enabled;
+    }
+
+    /// Stop runs on a device stall after `threshold` unchanged Senses
+    ///
+    /// A stall is the same XIO Sense storing the same status word
+    /// `threshold` times with no other XIO in between, as when a program
+    /// polls an empty keyboard. `run` then stops with
+    /// `StopReason::DeviceStall` instead of spinning until `max_steps`.
+    /// `None` (the default) disables detection.
+    pub fn set_stall_threshold(&mut self, threshold: Option<u32>) {
+        self.stall.set_threshold(threshold);
+    }
+
+    /// Current device stall threshold
+    pub fn stall_threshold(&self) -> Option<u32> {
+        self.stall.threshold()
+    }
+
+    /// Words skipped as invalid instructions, oldest first
+    pub fn skipped_words(&self) -> &[SkippedWord] {
+        &self.skipped_words
+    }
+
+    /// Forget the skipped-word log
+    pub fn clear_skipped_words(&mut self) {
+        self.skipped_words.clear();
+    }
+
+    /// Append an executed instruction address, dropping the oldest when full
+    fn record_iar_history(&mut self, address: u16) {
+        if self.iar_history_capacity == 0 {
+            return;
+        }
+        if self.iar_history.len() == self.iar_history_capacity {
+            self.iar_history.pop_front();
+        }
+        self.iar_history.push_back(address);
+        self.iar_history.make_contiguous();
+    }
+
     // === Fetch-Decode-Execute Cycle ===
 
     /// Fetch instruction from memory at current IAR
@@ -315,6 +1338,7 @@ impl Cpu {
     /// Execute one instruction at current IAR
     ///
     /// This is the main execution method that:
+    /// 0. Vectors to a pending interrupt, if one can be serviced
     /// 1. Fetches instruction from memory at IAR
     /// 2. Decodes the instruction
     /// 3. Calculates effective address
@@ -325,13 +1349,86 @@ impl Cpu {
     /// # Returns
     /// Ok(()) if instruction executed successfully, Err if execution failed
     pub fn step(&mut self) -> Result<()> {
-        // Check if CPU is in wait state
+        self.step_inner().map(drop)
+    }
+
+    /// Execute up to `n` instructions, calling `hook` after each one
+    ///
+    /// `hook` gets the 1-based step number and the state after the step,
+    /// e.g. to collect frames for animation. Stops early when a step fails
+    /// or after the step that puts the CPU in the wait state. Returns the
+    /// number of steps completed and the error that stopped them, if any.
+    pub fn step_n<F>(&mut self, n: u32, mut hook: F) -> (u32, Option<CpuError>)
+    where
+        F: FnMut(u32, &CpuState),
+    {
+        for completed in 0..n {
+            if let Err(e) = self.step() {
+                return (completed, Some(e));
+            }
+            let state = self.get_state();
+            hook(completed + 1, &state);
+            if state.wait {
+                return (completed + 1, None);
+            }
+        }
+        (n, None)
+    }
+
+    /// Execute one instruction like `step`, returning what was executed
+    ///
+    /// The details are captured during the step, so a debugger does not
+    /// need to decode the instruction again. A word skipped under
+    /// `set_skip_invalid` is recorded and stepped over as usual, but reported
+    /// as `CpuError::InvalidInstruction` since nothing was executed.
+    pub fn step_with_info(&mut self) -> Result<StepInfo> {
+        let iar = self.iar;
+        self.step_inner()?.ok_or(CpuError::InvalidInstruction(iar))
+    }
+
+    /// One step; `None` if an invalid word was skipped
+    fn step_inner(&mut self) -> Result<Option<StepInfo>> {
+        // Registers before any change this step makes, for step_back
+        let state_before = self.state_history.is_some().then(|| self.get_state());
+
+        // Service interrupts before the next instruction; taking one ends
+        // a WAIT, returning to the instruction after it
+        if self.service_interrupts()? {
+            self.status_flags.wait = false;
+        }
         if self.status_flags.wait {
             return Err(CpuError::WaitState);
         }
 
+        // Instruction breakpoints stop once; the following step resumes
+        if self.breakpoints.has_breakpoint(self.iar) && self.resume_address != Some(self.iar) {
+            self.resume_address = Some(self.iar);
+            return Err(CpuError::Breakpoint(self.iar));
+        }
+        self.resume_address = None;
+        self.watch_hit = None;
+
+        let triggers_before: Vec<u16> = self
+            .breakpoints
+            .register_triggers()
+            .iter()
+            .map(|&(register, _)| self.get_register(register))
+            .collect();
+
         // Fetch and decode
-        let mut instr = self.fetch_and_decode()?;
+        let mut instr = match self.decode_at_iar() {
+            Err(CpuError::InvalidInstruction(address)) if self.skip_invalid => {
+                let word = self.read_memory(address as usize)?;
+                self.skipped_words.push(SkippedWord { address, word });
+                self.increment_iar(1);
+                return Ok(None);
+            }
+            decoded => decoded?,
+        };
+        self.record_iar_history(self.iar);
+        if let Some(profile) = self.profile.as_mut() {
+            *profile.entry(self.iar).or_insert(0) += 1;
+        }
 
         // Calculate effective address
         // For index register instructions (LDX, STX, MDX), don't use tag for address calculation
@@ -345,21 +1442,108 @@ impl Cpu {
                 instr.tag = saved_tag;
                 ea
             }
+            OpCode::BC | OpCode::BSC => {
+                // For branches, the tag bits belong to the condition mask, not an index register.
+                // Direct short-format branches are relative to the updated IAR;
+                // indirect branches read the target pointer from an absolute address.
+                let saved_tag = instr.tag;
+                instr.tag = 0;
+                let ea = if instr.indirect {
+                    self.calculate_effective_address(&mut instr)?
+                } else {
+                    let ea = self
+                        .iar
+                        .wrapping_add(instr.size_in_words())
+                        .wrapping_add(instr.signed_displacement() as u16);
+                    instr.effective_address = Some(ea);
+                    ea
+                };
+                instr.tag = saved_tag;
+                ea
+            }
             _ => self.calculate_effective_address(&mut instr)?,
         };
 
+        // Capture trace inputs before IAR and ACC change
+        let trace_start = match self.trace {
+            Some(_) => Some((self.iar, self.fetch_instruction()?, self.acc)),
+            None => None,
+        };
+        let (iar_before, acc_before, flags_before) = (self.iar, self.acc, self.status_flags);
+        let mut cycles = instr.cycle_count();
+        if instr.opcode == OpCode::D {
+            cycles += divide_extra_cycles(self.get_acc_ext() as i32);
+        }
+
         // Increment IAR by instruction size BEFORE execution
         // (branch instructions will override this)
         let instruction_size = instr.size_in_words();
         self.increment_iar(instruction_size);
 
-        // Execute instruction
-        self.execute_instruction(&instr, effective_address)?;
+        // Execute instruction, tracing it even if it faults
+        if let Some(hook) = &self.event_hook {
+            hook.on_instruction_execute(iar_before, instr.opcode);
+        }
+        self.executing = true;
+        let result = self.execute_instruction(&instr, effective_address);
+        self.executing = false;
+        if let (Some((iar, (word1, word2), acc_before)), Some(trace)) =
+            (trace_start, self.trace.as_mut())
+        {
+            trace.push(TraceEntry {
+                iar,
+                instruction_word1: word1,
+                instruction_word2: word2,
+                effective_address,
+                acc_before,
+                acc_after: self.acc,
+            });
+        }
+        result?;
 
-        // Increment instruction counter
+        // Increment instruction and cycle counters
         self.increment_instruction_count();
+        self.cycle_count += u64::from(cycles);
 
-        Ok(())
+        if let (Some(state), Some(history)) = (state_before, self.state_history.as_mut()) {
+            history.push(state, &instr);
+        }
+
+        let info = StepInfo {
+            instruction: instr,
+            effective_address,
+            iar_before,
+            iar_after: self.iar,
+            acc_before,
+            acc_after: self.acc,
+            flags_changed: self.status_flags.carry != flags_before.carry
+                || self.status_flags.overflow != flags_before.overflow,
+        };
+        #[cfg(feature = "std")]
+        if let Some(sender) = &self.trace_sender {
+            if sender.send(info.clone()).is_err() {
+                // Receiver dropped
+                self.trace_sender = None;
+            }
+        }
+
+        // Watchpoints and register triggers stop after the instruction completes
+        if let Some(address) = self.watch_hit.take() {
+            return Err(CpuError::Watchpoint(address));
+        }
+        let triggered = self
+            .breakpoints
+            .register_triggers()
+            .iter()
+            .zip(&triggers_before)
+            .any(|(&(register, value), &before)| {
+                before != value && self.get_register(register) == value
+            });
+        if triggered {
+            return Err(CpuError::Breakpoint(self.iar));
+        }
+
+        Ok(Some(info))
     }
 
     /// Run CPU for a specified number of steps or until WAIT
@@ -368,21 +1552,111 @@ impl Cpu {
     /// * `max_steps` - Maximum number of instructions to execute
     ///
     /// # Returns
-    /// Number of instructions actually executed
-    pub fn run(&mut self, max_steps: u64) -> u64 {
-        let mut steps = 0;
+    /// Number of instructions actually executed and why the run stopped
+    pub fn run(&mut self, max_steps: u64) -> (u64, StopReason) {
+        self.run_with_hook(max_steps, |_| {})
+    }
 
-        for _ in 0..max_steps {
-            match self.step() {
-                Ok(()) => steps += 1,
-                Err(CpuError::WaitState) => break,
-                Err(_) => break,
+    /// The instruction the last `run` faulted on
+    ///
+    /// Set when a run stops with a fault (`StopReason::is_fault`) and
+    /// cleared when the next run starts.
+    pub fn last_fault(&self) -> Option<&FaultContext> {
+        self.last_fault.as_ref()
+    }
+
+    /// Run like `run`, returning only the step count
+    pub fn run_silent(&mut self, max_steps: u64) -> u64 {
+        self.run(max_steps).0
+    }
+
+    /// Run like `run`, calling `between_steps` after every executed instruction
+    ///
+    /// The hook lets the host service devices at a controlled cadence, e.g.
+    /// typing a key into the console keyboard while a program polls for it,
+    /// without the CPU having to block on I/O.
+    pub fn run_with_hook<F>(&mut self, max_steps: u64, mut between_steps: F) -> (u64, StopReason)
+    where
+        F: FnMut(&mut Cpu),
+    {
+        let mut steps = 0;
+        self.last_fault = None;
+
+        while steps < max_steps {
+            let iar = self.iar;
+            if let Err(error) = self.step() {
+                let reason = StopReason::from_error(error, self.iar);
+                if reason.is_fault() {
+                    self.last_fault = Some(self.fault_context(iar, reason.clone()));
+                }
+                return (steps, reason);
             }
+            steps += 1;
+            if let Some(code) = self.stall.take_stall() {
+                return (steps, StopReason::DeviceStall { code });
+            }
+            between_steps(self);
         }
 
+        // A WAIT on the final step still counts as halting
+        let reason = if self.status_flags.wait {
+            StopReason::Wait
+        } else {
+            StopReason::MaxStepsReached
+        };
+        (steps, reason)
+    }
+
+    /// Run like `run`, reporting the console printer output
+    ///
+    /// Intended for headless use: the report carries the decoded printer
+    /// output so callers need not downcast the device themselves.
+    pub fn run_collecting_output(&mut self, max_steps: u64) -> RunReport {
+        let steps = self.run_silent(max_steps);
+        let printer_output = self.get_printer_output();
+
+        RunReport {
+            steps,
+            halted: self.status_flags.wait,
+            printer_output,
+        }
+    }
+
+    /// Run like `run`, reusing decoded instructions across iterations
+    ///
+    /// Decoded instructions are cached by address for the duration of the
+    /// call. Any write to memory drops the cached decode covering that word
+    /// (including the displacement word of a long instruction), and device
+    /// transfers drop the whole cache, so self-modifying code behaves exactly
+    /// as under `run`.
+    pub fn run_fast(&mut self, max_steps: u64) -> u64 {
+        self.decode_cache = Some(HashMap::new());
+        let steps = self.run_silent(max_steps);
+        self.decode_cache = None;
         steps
     }
 
+    /// Decode the instruction at IAR, using the `run_fast` cache if active
+    fn decode_at_iar(&mut self) -> Result<InstructionInfo> {
+        if let Some(instr) = self.decode_cache.as_ref().and_then(|c| c.get(&self.iar)) {
+            return Ok(instr.clone());
+        }
+
+        let instr = self.fetch_and_decode()?;
+        if let Some(cache) = self.decode_cache.as_mut() {
+            cache.insert(self.iar, instr.clone());
+        }
+        Ok(instr)
+    }
+
+    /// Forget cached decodes that include the word at `address`
+    fn invalidate_decoded(&mut self, address: u16) {
+        if let Some(cache) = self.decode_cache.as_mut() {
+            cache.remove(&address);
+            cache.remove(&address.wrapping_sub(1));
+        }
+    }
+
     // === Device Management ===
 
     /// Attach an I/O device to the CPU
@@ -391,86 +1665,168 @@ impl Cpu {
     /// * `device` - The device to attach
     ///
     /// # Returns
-    /// * `Ok(())` if device attached successfully
+    /// * `Ok(DeviceHandle)` for later typed access via `device_as`/`device_as_mut`
     /// * `Err(CpuError)` if device code already in use
-    pub fn attach_device(&mut self, device: Box<dyn Device>) -> Result<()> {
+    pub fn attach_device(&mut self, device: Box<dyn Device>) -> Result<DeviceHandle> {
         let device_code = device.device_code();
-        if self.devices.contains_key(&device_code) {
-            return Err(CpuError::DeviceError(format!(
-                "Device code {} already in use",
-                device_code
-            )));
-        }
-        self.devices.insert(device_code, device);
-        Ok(())
+        self.devices
+            .attach(device)
+            .map_err(|e| CpuError::DeviceError(e.to_string()))?;
+        self.note_switches();
+        Ok(DeviceHandle::new(device_code))
+    }
+
+    /// Detach a device by device code
+    pub fn detach_device(&mut self, device_code: u8) -> Option<Box<dyn Device>> {
+        let device = self.devices.detach(device_code);
+        self.note_switches();
+        device
+    }
+
+    /// Swap the device at `device_code`, returning the old one
+    ///
+    /// Safe while a program is running; see `DeviceManager::replace_device`.
+    pub fn replace_device(
+        &mut self,
+        device_code: u8,
+        device: Box<dyn Device>,
+    ) -> Result<Option<Box<dyn Device>>> {
+        let old_device = self
+            .devices
+            .replace_device(device_code, device)
+            .map_err(|e| CpuError::DeviceError(e.to_string()))?;
+        self.note_switches();
+        Ok(old_device)
+    }
+
+    /// Set the function told the device code after each `replace_device`
+    pub fn set_device_replacement_callback(&mut self, callback: Option<Box<ReplacementCallback>>) {
+        self.devices.set_replacement_callback(callback);
     }
 
-    /// Detach a device by device code
-    pub fn detach_device(&mut self, device_code: u8) -> Option<Box<dyn Device>> {
-        self.devices.remove(&device_code)
+    /// Attached devices in device-code order
+    pub fn attached_devices(&self) -> impl Iterator<Item = &dyn Device> {
+        self.devices.iter().map(|(_, device)| device)
     }
 
     /// Get a reference to a device by device code
     pub fn get_device(&self, device_code: u8) -> Option<&dyn Device> {
-        self.devices.get(&device_code).map(|d| &**d)
+        self.devices.get(device_code)
     }
 
     /// Get a mutable reference to a device by device code (for testing/inspection)
     pub fn get_device_mut_ref(&mut self, device_code: u8) -> Option<&mut Box<dyn Device>> {
-        self.devices.get_mut(&device_code)
+        self.devices.get_mut(device_code)
     }
 
-    // === IOCC Handling ===
+    /// Get a typed reference to an attached device by handle
+    ///
+    /// Returns `None` if the device was detached or is not of type `T`.
+    pub fn device_as<T: Device + 'static>(&self, handle: DeviceHandle) -> Option<&T> {
+        self.devices.get_device_as(handle.device_code())
+    }
 
-    /// Decode an IOCC structure from memory
+    /// Get a typed mutable reference to an attached device by handle
     ///
-    /// This is called by the XIO instruction. The IOCC is a 2-word structure:
-    /// - Word 0 (even address): WCA (Word Count Address)
-    /// - Word 1 (odd address): Device code + Function + Modifiers
+    /// Returns `None` if the device was detached or is not of type `T`.
+    pub fn device_as_mut<T: Device + 'static>(&mut self, handle: DeviceHandle) -> Option<&mut T> {
+        self.devices.get_device_as_mut(handle.device_code())
+    }
+
+    /// Decoded output of the console printer
     ///
-    /// # Arguments
-    /// * `address` - Starting address of IOCC (should be even)
+    /// `None` if no console printer is attached at its standard code.
+    pub fn get_printer_output(&self) -> Option<String> {
+        self.devices
+            .get_device_as::<DeviceConsolePrinter>(StandardDevice::ConsolePrinter.code())
+            .map(DeviceConsolePrinter::get_output)
+    }
+
+    /// Characters typed on the console keyboard and not yet read
     ///
-    /// # Returns
-    /// * `Ok(())` if IOCC decoded successfully
-    /// * `Err(CpuError)` if decode failed
-    pub fn decode_iocc(&mut self, address: u16) -> Result<()> {
-        // Read both words of IOCC
-        let word1 = self.read_memory(address as usize)?;
-        let word2 = self.read_memory((address + 1) as usize)?;
-
-        // Decode IOCC
-        let iocc = Iocc::decode(word1, word2)?;
-        self.iocc = Some(iocc);
+    /// 0 if no console keyboard is attached at its standard code.
+    pub fn get_keyboard_buffer_len(&self) -> usize {
+        self.devices
+            .get_device_as::<DeviceConsoleKeyboard>(StandardDevice::ConsoleKeyboard.code())
+            .map_or(0, DeviceConsoleKeyboard::input_len)
+    }
 
+    /// Set the console toggle switches
+    ///
+    /// Attaches a `ConsoleSwitches` device at code 0 on first use, which
+    /// maps the switch word at address 0 from then on.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CpuError::DeviceError` if another device occupies code 0
+    pub fn set_console_switches(&mut self, value: u16) -> Result<()> {
+        if self.console_switches_device().is_none() {
+            self.attach_device(Box::new(ConsoleSwitches::new()))?;
+        }
+        self.device_as_mut::<ConsoleSwitches>(DeviceHandle::new(
+            StandardDevice::ConsoleSwitches.code(),
+        ))
+        .expect("console switches attached above")
+        .set(value);
         Ok(())
     }
 
-    /// Execute the last decoded IOCC
-    ///
-    /// This is called after decode_iocc to actually execute the I/O operation.
+    /// Current console switch word (0 if no switches are attached)
+    pub fn console_switches(&self) -> u16 {
+        self.console_switches_device()
+            .map_or(0, ConsoleSwitches::value)
+    }
+
+    fn console_switches_device(&self) -> Option<&ConsoleSwitches> {
+        self.device_as::<ConsoleSwitches>(DeviceHandle::new(StandardDevice::ConsoleSwitches.code()))
+    }
+
+    /// Update `switches_attached` after the devices change
+    fn note_switches(&mut self) {
+        self.switches_attached = self.console_switches_device().is_some();
+    }
+
+    // === Event Hooks ===
+
+    /// Report execution, memory access and I/O to `hook`
     ///
-    /// # Returns
-    /// * `Ok(())` if IOCC executed successfully
-    /// * `Err(CpuError)` if execution failed or no device found
-    pub fn execute_iocc(&mut self) -> Result<()> {
-        let iocc = self
-            .iocc
-            .ok_or_else(|| CpuError::DeviceError("No IOCC decoded".to_string()))?;
+    /// Replaces any existing hook. Clones of the CPU share the hook.
+    pub fn set_event_hook(&mut self, hook: Box<dyn CpuEventHook>) {
+        self.event_hook = Some(Arc::from(hook));
+    }
 
-        // Get device code before borrowing
-        let device_code = iocc.device_code;
+    /// Remove the event hook
+    pub fn clear_event_hook(&mut self) {
+        self.event_hook = None;
+    }
 
-        // Check device exists
-        if !self.devices.contains_key(&device_code) {
-            return Err(CpuError::InvalidDevice(device_code));
-        }
+    /// The hook, if set and an instruction is executing
+    fn active_event_hook(&self) -> Option<&dyn CpuEventHook> {
+        self.event_hook.as_deref().filter(|_| self.executing)
+    }
 
-        // Execute command - use explicit field access to allow split borrows
-        let device = self.devices.get_mut(&device_code).unwrap();
-        let memory_slice = self.memory.as_mut_slice();
-        device.execute_iocc(&iocc, memory_slice)?;
+    // === IOCC Handling ===
 
+    /// Decode and execute the IOCC at `address` (the XIO instruction)
+    ///
+    /// The decoded IOCC is kept for `get_iocc`. Devices may transfer data
+    /// anywhere in memory, so cached decodes are dropped afterwards; a
+    /// transfer into protected memory is undone and faults.
+    pub(crate) fn execute_xio_at(&mut self, address: u16) -> Result<()> {
+        let iocc = DeviceManager::decode_iocc(self.memory.as_slice(), address)?;
+        self.iocc = Some(iocc);
+        if let Some(hook) = &self.event_hook {
+            hook.on_device_io(iocc.device_code, iocc.function);
+        }
+        let devices = &mut self.devices;
+        self.memory
+            .device_transfer(|memory| devices.execute_xio(&iocc, memory))?;
+        let status = self.memory.read(iocc.wca as usize).ok();
+        self.stall.observe(address, &iocc, status);
+
+        if let Some(cache) = self.decode_cache.as_mut() {
+            cache.clear();
+        }
         Ok(())
     }
 
@@ -503,7 +1859,7 @@ mod tests {
 
     #[test]
     fn test_cpu_with_custom_memory_size() {
-        let cpu = Cpu::with_memory_size(8192);
+        let cpu = Cpu::with_memory_size(8192).unwrap();
         let state = cpu.get_state();
         // Verify it doesn't panic accessing memory within bounds
         assert_eq!(state.acc, 0);
@@ -597,6 +1953,93 @@ mod tests {
         assert_eq!(cpu.read_memory(0x0002).unwrap(), 0x1234);
     }
 
+    #[test]
+    fn test_console_switches_mapped_at_address_zero() {
+        let mut cpu = Cpu::new();
+        cpu.write_memory(0x0000, 0x1111).unwrap();
+        assert_eq!(cpu.read_memory(0x0000).unwrap(), 0x1111);
+
+        cpu.set_console_switches(0x8001).unwrap();
+        assert_eq!(cpu.console_switches(), 0x8001);
+        assert_eq!(cpu.read_memory(0x0000).unwrap(), 0x8001);
+
+        // A program loading address 0 sees the switches
+        cpu.set_iar(0x0100);
+        cpu.write_memory(0x0100, 0x6000).unwrap(); // LD /0000
+        cpu.write_memory(0x0101, 0x0000).unwrap();
+        cpu.step().unwrap();
+        assert_eq!(cpu.get_acc(), 0x8001);
+
+        // The switches are read-only; memory under them is left alone
+        cpu.write_memory(0x0000, 0x2222).unwrap();
+        cpu.write_memory_range(0x0000, &[0x3333, 0x4444]).unwrap();
+        assert_eq!(cpu.read_memory(0x0000).unwrap(), 0x8001);
+        assert_eq!(cpu.read_memory(0x0001).unwrap(), 0x4444);
+
+        cpu.detach_device(StandardDevice::ConsoleSwitches.code());
+        assert_eq!(cpu.read_memory(0x0000).unwrap(), 0x1111);
+    }
+
+    /// CPU that has run SLA 1; WAIT with profiling and history on
+    fn cpu_after_short_run() -> Cpu {
+        let mut cpu = Cpu::new();
+        cpu.enable_profiling(true);
+        cpu.enable_iar_history(8);
+        cpu.set_iar(0x0100);
+        cpu.set_acc(1);
+        cpu.write_memory(0x0100, 0x2001).unwrap(); // SLA 1
+        cpu.write_memory(0x0101, 0xB000).unwrap(); // WAIT
+        cpu.run(10);
+        cpu
+    }
+
+    #[test]
+    fn test_reset_registers_keeps_profiling() {
+        let mut cpu = cpu_after_short_run();
+
+        cpu.reset_with_scope(ResetScope::REGISTERS);
+
+        assert_eq!(cpu.get_acc(), 0);
+        assert_eq!(cpu.get_iar(), 0);
+        assert!(!cpu.get_wait());
+        assert_eq!(cpu.instruction_count, 2);
+        assert_eq!(cpu.execution_profile().unwrap().get(&0x0100), Some(&1));
+        assert_eq!(cpu.iar_history(), &[0x0100, 0x0101]);
+    }
+
+    #[test]
+    fn test_reset_everything() {
+        let mut cpu = cpu_after_short_run();
+        use crate::devices::card_punch_1442::{Device1442, CONTROL_FEED};
+        use crate::devices::DeviceFunction;
+
+        // Feeding from an empty hopper jams the 1442
+        let mut punch = Device1442::new();
+        let (wca, word) = Iocc::build(
+            StandardDevice::CardPunch1442,
+            DeviceFunction::Control,
+            CONTROL_FEED,
+            0,
+        );
+        assert!(punch
+            .execute_iocc(&Iocc::decode(wca, word).unwrap(), &mut [0; 4])
+            .is_err());
+        let handle = cpu.attach_device(Box::new(punch)).unwrap();
+        assert!(cpu.device_as::<Device1442>(handle).unwrap().is_jammed());
+
+        cpu.set_annotation(0x0100, "shift");
+        cpu.reset_with_scope(ResetScope::ALL);
+
+        assert_eq!(cpu.get_acc(), 0);
+        assert!(cpu.annotations().is_empty());
+        assert_eq!(cpu.instruction_count, 0);
+        assert!(cpu.execution_profile().unwrap().is_empty());
+        assert!(cpu.iar_history().is_empty());
+        assert!(!cpu.device_as::<Device1442>(handle).unwrap().is_jammed());
+        // Memory survives even a full reset
+        assert_eq!(cpu.read_memory(0x0100).unwrap(), 0x2001);
+    }
+
     #[test]
     fn test_reset() {
         let mut cpu = Cpu::new();
@@ -646,6 +2089,53 @@ mod tests {
         assert_eq!(cpu.get_instruction_count(), 2);
     }
 
+    #[test]
+    fn test_cycle_count() {
+        let mut cpu = Cpu::new();
+        cpu.write_memory_range(0x0100, &[0x6000, 0x0200]).unwrap(); // LD 0x0200
+        cpu.write_memory_range(0x0102, &[0x6020, 0x0201]).unwrap(); // LD /0x0201
+        cpu.write_memory_range(0x0104, &[0xF800, 0x0202]).unwrap(); // D 0x0202
+        cpu.write_memory_range(0x0200, &[0x0000, 0x0200, 0x0003])
+            .unwrap();
+        cpu.set_iar(0x0100);
+
+        cpu.step().unwrap();
+        assert_eq!(cpu.get_cycle_count(), 2);
+        cpu.step().unwrap();
+        assert_eq!(cpu.get_cycle_count(), 5);
+
+        // ACC:EXT = 0 from the indirect load: no extra divide cycles
+        cpu.step().unwrap();
+        assert_eq!(cpu.get_cycle_count(), 18);
+
+        let snapshot = cpu.save_snapshot();
+        assert_eq!(snapshot.state.cycle_count, 18);
+        cpu.reset_cycle_count();
+        cpu.restore_snapshot(snapshot).unwrap();
+        assert_eq!(cpu.get_cycle_count(), 18);
+
+        cpu.reset_cycle_count();
+        assert_eq!(cpu.get_cycle_count(), 0);
+        assert_eq!(cpu.get_instruction_count(), 3);
+    }
+
+    #[test]
+    fn test_reset_instruction_count() {
+        let mut cpu = Cpu::new();
+        cpu.set_acc(0x1234);
+        cpu.set_iar(0x0100);
+        cpu.write_memory(0x0200, 0xABCD).unwrap();
+        cpu.increment_instruction_count();
+        cpu.increment_instruction_count();
+
+        cpu.reset_instruction_count();
+
+        assert_eq!(cpu.get_instruction_count(), 0);
+        assert_eq!(cpu.get_acc(), 0x1234);
+        assert_eq!(cpu.get_iar(), 0x0100);
+        assert_eq!(cpu.read_memory(0x0200).unwrap(), 0xABCD);
+    }
+
     #[test]
     fn test_fetch_instruction_short_format() {
         let mut cpu = Cpu::new();
@@ -736,6 +2226,93 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_step_n_calls_hook_each_step() {
+        let mut cpu = Cpu::new();
+        cpu.write_memory_range(0x0100, &[0x2001; 8]).unwrap(); // SLA 1
+        cpu.set_iar(0x0100);
+        cpu.set_acc(1);
+
+        let mut frames = Vec::new();
+        let (steps, error) = cpu.step_n(5, |step, state| frames.push((step, state.acc)));
+
+        assert_eq!((steps, error), (5, None));
+        assert_eq!(frames, [(1, 2), (2, 4), (3, 8), (4, 16), (5, 32)]);
+    }
+
+    #[test]
+    fn test_step_n_stops_at_wait() {
+        let mut cpu = Cpu::new();
+        cpu.write_memory_range(0x0100, &[0x2001, 0xB000, 0x2001])
+            .unwrap(); // SLA 1; WAIT; SLA 1
+        cpu.set_iar(0x0100);
+
+        let mut calls = 0;
+        assert_eq!(cpu.step_n(10, |_, _| calls += 1), (2, None));
+        assert_eq!(calls, 2);
+
+        // Already waiting: nothing runs
+        assert_eq!(
+            cpu.step_n(10, |_, _| calls += 1),
+            (0, Some(CpuError::WaitState))
+        );
+        assert_eq!(calls, 2);
+    }
+
+    #[test]
+    fn test_step_with_info_short_format() {
+        let mut cpu = Cpu::new();
+        cpu.set_iar(0x0100);
+        cpu.set_acc(0x0003);
+        cpu.write_memory(0x0100, 0x2002).unwrap(); // SLA 2
+
+        let info = cpu.step_with_info().unwrap();
+        assert_eq!(info.instruction.opcode, OpCode::SLA);
+        assert_eq!(info.iar_before, 0x0100);
+        assert_eq!(info.iar_after, 0x0101);
+        assert_eq!(info.acc_after, 0x000C);
+    }
+
+    #[test]
+    fn test_step_with_info_add() {
+        let mut cpu = Cpu::new();
+        cpu.set_iar(0x0100);
+        cpu.set_acc(0x0005);
+        cpu.write_memory(0x0100, 0xE000).unwrap(); // A /0200
+        cpu.write_memory(0x0101, 0x0200).unwrap();
+        cpu.write_memory(0x0200, 0x0003).unwrap();
+
+        let info = cpu.step_with_info().unwrap();
+        assert_eq!(info.instruction.opcode, OpCode::A);
+        assert_eq!(info.effective_address, 0x0200);
+        assert_eq!((info.iar_before, info.iar_after), (0x0100, 0x0102));
+        assert_eq!((info.acc_before, info.acc_after), (0x0005, 0x0008));
+        assert!(!info.flags_changed);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_trace_channel_streams_steps_across_threads() {
+        let mut cpu = Cpu::new();
+        cpu.set_iar(0x0100);
+        cpu.set_acc(0x0001);
+        cpu.write_memory(0x0100, 0x2001).unwrap(); // SLA 1
+        cpu.write_memory(0x0101, 0x2002).unwrap(); // SLA 2
+        cpu.write_memory(0x0102, 0xB000).unwrap(); // WAIT
+        let events = cpu.trace_channel();
+
+        let worker = std::thread::spawn(move || cpu.run(10));
+        let (steps, reason) = worker.join().unwrap();
+        assert_eq!((steps, reason), (3, StopReason::Wait));
+
+        let events: Vec<TraceEvent> = events.iter().collect();
+        let addresses: Vec<u16> = events.iter().map(|e| e.iar_before).collect();
+        assert_eq!(addresses, [0x0100, 0x0101, 0x0102]);
+        assert_eq!(events[0].acc_after, 0x0002);
+        assert_eq!(events[1].acc_after, 0x0008);
+        assert_eq!(events[2].instruction.opcode, OpCode::WAIT);
+    }
+
     #[test]
     fn test_run_until_wait() {
         let mut cpu = Cpu::new();
@@ -747,11 +2324,463 @@ mod tests {
         cpu.write_memory(0x0102, 0xB000).unwrap();
 
         // Run for up to 10 steps (should stop at first WAIT)
-        let steps = cpu.run(10);
+        let (steps, reason) = cpu.run(10);
         assert_eq!(steps, 1);
+        assert_eq!(reason, StopReason::Wait);
         assert!(cpu.get_wait());
     }
 
+    #[test]
+    fn test_flags_word_round_trip() {
+        let mut cpu = Cpu::new();
+        cpu.set_carry(true);
+        cpu.set_overflow(true);
+        cpu.set_wait(true);
+        let word = cpu.flags_word();
+        assert_eq!(word, 0xC000);
+
+        cpu.set_flags_word(0);
+        assert!(!cpu.get_carry() && !cpu.get_overflow());
+        assert!(cpu.get_wait());
+
+        cpu.set_flags_word(word);
+        assert!(cpu.get_carry() && cpu.get_overflow());
+        cpu.set_flags_word(0x4000);
+        assert!(!cpu.get_carry() && cpu.get_overflow());
+    }
+
+    #[test]
+    fn test_run_stop_reasons() {
+        let mut cpu = Cpu::with_memory_size(0x0200).unwrap();
+        cpu.set_iar(0x0100);
+        cpu.write_memory(0x0100, 0x2001).unwrap(); // SLA 1
+        cpu.write_memory(0x0101, 0x0000).unwrap(); // not an opcode
+
+        assert_eq!(cpu.run(1), (1, StopReason::MaxStepsReached));
+        assert_eq!(cpu.run(10), (0, StopReason::InvalidInstruction(0x0101)));
+
+        cpu.set_iar(0x0300);
+        assert_eq!(cpu.run(10), (0, StopReason::MemoryViolation(0x0300)));
+        let fault = cpu.last_fault().unwrap();
+        assert!(fault.words.is_empty());
+        assert_eq!(
+            fault.to_string(),
+            "Fault at 0x0300: memory violation at 0x0300"
+        );
+
+        cpu.set_iar(0x0100);
+        cpu.run(1);
+        assert!(cpu.last_fault().is_none());
+    }
+
+    #[test]
+    fn test_stop_reason_from_error() {
+        let reason = StopReason::from_error(CpuError::InvalidDevice(9), 0);
+        assert_eq!(reason.name(), "deviceError");
+
+        let reason = StopReason::from_error(CpuError::InvalidInterruptLevel(7), 0);
+        assert_eq!(reason.name(), "error");
+        assert!(reason.is_fault());
+    }
+
+    #[test]
+    fn test_restore_region_reverts_only_region() {
+        let mut cpu = Cpu::new();
+        cpu.write_memory_range(0x0200, &[1, 2, 3, 4]).unwrap();
+
+        let saved = cpu.snapshot_region(0x0201, 2).unwrap();
+        cpu.write_memory_range(0x0200, &[9, 9, 9, 9]).unwrap();
+        cpu.restore_region(0x0201, &saved).unwrap();
+
+        assert_eq!(cpu.read_memory_range(0x0200, 4), vec![9, 2, 3, 9]);
+    }
+
+    #[test]
+    fn test_restore_region_syncs_index_registers() {
+        let mut cpu = Cpu::new();
+        cpu.set_index_register(1, 0x0011);
+        let saved = cpu.snapshot_region(0x0000, 4).unwrap();
+
+        cpu.set_index_register(1, 0x0099);
+        cpu.restore_region(0x0000, &saved).unwrap();
+
+        assert_eq!(cpu.get_index_register(1), 0x0011);
+    }
+
+    #[test]
+    fn test_set_memory_mapping() {
+        let mut cpu = Cpu::new();
+        assert!(cpu.memory_mapped_registers_enabled());
+
+        cpu.set_memory_mapping(false);
+        cpu.write_memory(0x0002, 0x1234).unwrap();
+        assert_eq!(cpu.get_index_register(2), 0);
+
+        cpu.set_memory_mapping(true);
+        assert_eq!(cpu.read_memory(0x0002).unwrap(), 0);
+        cpu.write_memory(0x0002, 0x5678).unwrap();
+        assert_eq!(cpu.get_index_register(2), 0x5678);
+    }
+
+    #[test]
+    fn test_with_options_applies_settings() {
+        let mut cpu = Cpu::with_options(CpuOptions {
+            memory_size: 4096,
+            memory_mode: MemoryMode::Plain,
+            enable_history: Some(4),
+            enable_profiling: true,
+            standard_devices: true,
+            skip_invalid: false,
+            stall_threshold: Some(8),
+        })
+        .unwrap();
+
+        assert_eq!(cpu.memory.size(), 4096);
+        assert_eq!(cpu.memory_mode(), MemoryMode::Plain);
+        assert!(cpu.get_device(1).is_some());
+        assert!(cpu.get_device(2).is_some());
+        assert_eq!(cpu.stall_threshold(), Some(8));
+
+        // Plain memory: writing 0x0002 leaves XR2 alone
+        cpu.write_memory(0x0002, 0x1234).unwrap();
+        assert_eq!(cpu.get_index_register(2), 0);
+
+        cpu.set_iar(0x0100);
+        cpu.write_memory(0x0100, 0x2001).unwrap(); // SLA 1
+        cpu.write_memory(0x0101, 0xB000).unwrap(); // WAIT
+        cpu.run(10);
+
+        assert_eq!(cpu.iar_history(), &[0x0100, 0x0101]);
+        let profile = cpu.execution_profile().unwrap();
+        assert_eq!(profile.get(&0x0100), Some(&1));
+        assert_eq!(profile.get(&0x0101), Some(&1));
+    }
+
+    #[test]
+    fn test_deposit_then_examine_auto_increments() {
+        let mut cpu = Cpu::new();
+        cpu.set_iar(0x0100);
+        for value in [0x1111, 0x2222, 0x3333] {
+            cpu.deposit_word(value).unwrap();
+        }
+        assert_eq!(cpu.get_iar(), 0x0103);
+
+        cpu.set_iar(0x0100);
+        assert_eq!(cpu.examine_word().unwrap(), 0x1111);
+        assert_eq!(cpu.examine_word().unwrap(), 0x2222);
+        assert_eq!(cpu.examine_word().unwrap(), 0x3333);
+        assert_eq!(cpu.get_iar(), 0x0103);
+    }
+
+    #[test]
+    fn test_deposit_wraps_and_maps_index_registers() {
+        let mut cpu = Cpu::with_memory_size(0x0100).unwrap();
+        cpu.set_iar(0x00FF);
+        cpu.deposit_word(0xAAAA).unwrap();
+        assert_eq!(cpu.get_iar(), 0x0000);
+
+        cpu.deposit_word(0x0000).unwrap();
+        cpu.deposit_word(0x0042).unwrap(); // 0x0001 mirrors XR1
+        assert_eq!(cpu.get_index_register(1), 0x0042);
+
+        let mut plain = Cpu::with_options(CpuOptions {
+            memory_mode: MemoryMode::Plain,
+            ..CpuOptions::default()
+        })
+        .unwrap();
+        plain.set_iar(0x0001);
+        plain.deposit_word(0x0042).unwrap();
+        assert_eq!(plain.get_index_register(1), 0);
+    }
+
+    #[test]
+    fn test_with_memory_size_rejects_zero() {
+        assert_eq!(
+            Cpu::with_memory_size(0).err(),
+            Some(CpuError::InvalidMemorySize(0))
+        );
+    }
+
+    #[test]
+    fn test_fill_memory_updates_mapped_index_registers() {
+        let mut cpu = Cpu::with_memory_size(0x0100).unwrap();
+        cpu.fill_memory(0x00FE, 0x0003, 0x0042).unwrap();
+
+        assert_eq!(cpu.read_memory(0x00FF).unwrap(), 0x0042);
+        assert_eq!(cpu.get_index_register(1), 0x0042);
+        assert_eq!(cpu.get_index_register(2), 0x0042);
+        assert_eq!(cpu.get_index_register(3), 0);
+        assert!(cpu.fill_memory(0x0000, 0x0101, 0).is_err());
+    }
+
+    #[test]
+    fn test_set_index_register_writes_through_protection() {
+        let mut cpu = Cpu::new();
+        cpu.protect_memory_range(0, 4).unwrap();
+
+        cpu.set_index_register(1, 0x1234);
+        assert_eq!(cpu.get_index_register(1), 0x1234);
+        assert_eq!(cpu.read_memory(0x0001).unwrap(), 0x1234);
+        // Program stores to the mapped word are still refused
+        assert!(cpu.write_memory(0x0002, 0x5678).is_err());
+        assert_eq!(cpu.get_index_register(2), 0);
+    }
+
+    #[test]
+    fn test_default_options_match_new() {
+        let cpu = Cpu::with_options(CpuOptions::default()).unwrap();
+        assert_eq!(cpu.memory.size(), 32768);
+        assert_eq!(cpu.memory_mode(), MemoryMode::MappedIndexRegisters);
+        assert!(cpu.execution_profile().is_none());
+        assert!(cpu.get_device(1).is_none());
+    }
+
+    #[test]
+    fn test_snapshot_restore_round_trip() {
+        use crate::devices::{DeviceConsoleKeyboard, DeviceConsolePrinter};
+
+        let mut cpu = Cpu::new();
+        let mut keyboard = DeviceConsoleKeyboard::new();
+        keyboard.type_string("AB");
+        let kb = cpu.attach_device(Box::new(keyboard)).unwrap();
+        cpu.attach_device(Box::new(DeviceConsolePrinter::new()))
+            .unwrap();
+
+        cpu.set_iar(0x0100);
+        cpu.set_acc(0x0001);
+        cpu.set_index_register(2, 0x0042);
+        cpu.write_memory(0x0100, 0x2001).unwrap(); // SLA 1
+        cpu.write_memory(0x0101, 0x2001).unwrap(); // SLA 1
+        cpu.step().unwrap();
+
+        let snapshot = cpu.save_snapshot();
+        let saved_state = cpu.get_state();
+        assert_eq!(snapshot.devices_state.len(), 2);
+        assert_eq!(snapshot.devices_state[0].device_code, 1);
+
+        // Diverge registers, memory and device state
+        cpu.step().unwrap();
+        cpu.write_memory(0x0200, 0xBEEF).unwrap();
+        cpu.device_as_mut::<DeviceConsoleKeyboard>(kb)
+            .unwrap()
+            .type_char(b'C' as u16);
+
+        cpu.restore_snapshot(snapshot).unwrap();
+
+        assert_eq!(cpu.get_state(), saved_state);
+        assert_eq!(cpu.read_memory(0x0200).unwrap(), 0);
+        assert_eq!(cpu.read_memory(0x0002).unwrap(), 0x0042);
+        assert_eq!(
+            cpu.device_as::<DeviceConsoleKeyboard>(kb)
+                .unwrap()
+                .input_len(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_snapshot_restores_interrupt_controller() {
+        let mut cpu = Cpu::new();
+        cpu.interrupts.set_mask(0b00001);
+        cpu.interrupts.request(4, 0x1000);
+        cpu.interrupts.enter(2, 0x0300);
+        let saved = cpu.interrupts;
+
+        let snapshot = cpu.save_snapshot();
+        cpu.interrupts.reset();
+        cpu.restore_snapshot(snapshot.clone()).unwrap();
+        assert_eq!(cpu.interrupts, saved);
+
+        // Older snapshots only carry the current level
+        cpu.interrupts.reset();
+        cpu.restore_snapshot(CpuSnapshot {
+            interrupts: Vec::new(),
+            ..snapshot
+        })
+        .unwrap();
+        assert_eq!(cpu.interrupts.current_level(), Some(2));
+        assert_eq!(cpu.interrupts.pending(), 0);
+    }
+
+    #[test]
+    fn test_restore_snapshot_rejects_memory_size_mismatch() {
+        let small = Cpu::with_memory_size(1024).unwrap();
+        let snapshot = small.save_snapshot();
+
+        let mut cpu = Cpu::new();
+        cpu.set_acc(0x1234);
+        assert_eq!(
+            cpu.restore_snapshot(snapshot),
+            Err(CpuError::MemoryViolation(1024))
+        );
+        assert_eq!(cpu.get_acc(), 0x1234);
+    }
+
+    #[test]
+    fn test_apply_patch() {
+        let mut cpu = Cpu::with_memory_size(0x0200).unwrap();
+        cpu.apply_patch(&[(0x0100, 0x1111), (0x0150, 0x2222)])
+            .unwrap();
+        assert_eq!(cpu.read_memory(0x0100).unwrap(), 0x1111);
+        assert_eq!(cpu.read_memory(0x0150).unwrap(), 0x2222);
+    }
+
+    #[test]
+    fn test_apply_patch_out_of_bounds_writes_nothing() {
+        let mut cpu = Cpu::with_memory_size(0x0200).unwrap();
+        assert_eq!(
+            cpu.apply_patch(&[(0x0100, 0x1111), (0x0200, 0x2222)]),
+            Err(CpuError::MemoryViolation(0x0200))
+        );
+        assert_eq!(cpu.read_memory(0x0100).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_clone_is_independent() {
+        use crate::devices::DeviceConsoleKeyboard;
+
+        let mut cpu = Cpu::new();
+        let mut keyboard = DeviceConsoleKeyboard::new();
+        keyboard.type_string("AB");
+        let handle = cpu.attach_device(Box::new(keyboard)).unwrap();
+
+        cpu.set_iar(0x0100);
+        cpu.write_memory(0x0100, 0x2001).unwrap(); // SLA 1
+        cpu.write_memory(0x0101, 0x2001).unwrap(); // SLA 1
+        cpu.write_memory(0x0102, 0xB000).unwrap(); // WAIT
+        cpu.set_acc(0x0001);
+        cpu.step().unwrap();
+
+        let before = cpu.get_state();
+        let mut clone = cpu.clone();
+        assert_eq!(clone.get_state(), before);
+
+        // Run the clone ahead and diverge its memory and devices
+        clone.step().unwrap();
+        clone.write_memory(0x0200, 0xBEEF).unwrap();
+        clone
+            .device_as_mut::<DeviceConsoleKeyboard>(handle)
+            .unwrap()
+            .type_char(b'C' as u16);
+        assert_eq!(clone.get_acc(), 0x0004);
+
+        // Original is untouched
+        assert_eq!(cpu.get_state(), before);
+        assert_eq!(cpu.read_memory(0x0200).unwrap(), 0);
+        assert_eq!(
+            cpu.device_as::<DeviceConsoleKeyboard>(handle)
+                .unwrap()
+                .input_len(),
+            2
+        );
+
+        // ...and still steppable on its own
+        cpu.step().unwrap();
+        assert_eq!(cpu.get_acc(), 0x0004);
+        assert_eq!(cpu.get_iar(), 0x0102);
+    }
+
+    #[test]
+    fn test_trace_records_executed_instructions() {
+        let mut cpu = Cpu::new();
+        cpu.enable_trace(16);
+        cpu.set_iar(0x0100);
+        cpu.set_acc(0x0001);
+
+        cpu.write_memory(0x0100, 0x2001).unwrap(); // SLA 1
+        cpu.write_memory(0x0101, 0x7000).unwrap(); // STO 0x0200
+        cpu.write_memory(0x0102, 0x0200).unwrap();
+        cpu.write_memory(0x0103, 0xB000).unwrap(); // WAIT
+        cpu.run(10);
+
+        let trace = cpu.get_trace();
+        assert_eq!(trace.len(), 3);
+        assert_eq!(
+            trace[0],
+            TraceEntry {
+                iar: 0x0100,
+                instruction_word1: 0x2001,
+                instruction_word2: None,
+                effective_address: 0x0001,
+                acc_before: 0x0001,
+                acc_after: 0x0002,
+            }
+        );
+        assert_eq!(trace[1].iar, 0x0101);
+        assert_eq!(trace[1].instruction_word2, Some(0x0200));
+        assert_eq!(trace[1].effective_address, 0x0200);
+        assert_eq!(trace[2].iar, 0x0103);
+
+        cpu.clear_trace();
+        assert!(cpu.get_trace().is_empty());
+    }
+
+    #[test]
+    fn test_trace_includes_faulting_instruction() {
+        let mut cpu = Cpu::with_memory_size(0x0300).unwrap();
+        cpu.enable_trace(4);
+        cpu.set_iar(0x0100);
+
+        // LD from beyond the end of memory
+        cpu.write_memory(0x0100, 0x6000).unwrap();
+        cpu.write_memory(0x0101, 0x0400).unwrap();
+
+        assert!(cpu.step().is_err());
+        assert_eq!(cpu.get_trace().len(), 1);
+        assert_eq!(cpu.get_trace()[0].effective_address, 0x0400);
+    }
+
+    #[test]
+    fn test_trace_disabled_by_default() {
+        let mut cpu = Cpu::new();
+        cpu.set_iar(0x0100);
+        cpu.write_memory(0x0100, 0xB000).unwrap();
+        cpu.run(10);
+        assert!(cpu.get_trace().is_empty());
+    }
+
+    #[test]
+    fn test_iar_history_records_branch_target() {
+        let mut cpu = Cpu::new();
+        cpu.enable_iar_history(8);
+        cpu.set_iar(0x0100);
+
+        cpu.write_memory(0x0100, 0x2001).unwrap(); // SLA 1
+        cpu.write_memory(0x0101, 0x4002).unwrap(); // BC +2 -> 0x0104
+        cpu.write_memory(0x0102, 0xB000).unwrap(); // WAIT (skipped)
+        cpu.write_memory(0x0104, 0xB000).unwrap(); // WAIT
+
+        cpu.run(10);
+
+        assert_eq!(cpu.iar_history(), &[0x0100, 0x0101, 0x0104]);
+    }
+
+    #[test]
+    fn test_iar_history_is_bounded() {
+        let mut cpu = Cpu::new();
+        cpu.enable_iar_history(2);
+        cpu.set_iar(0x0100);
+
+        cpu.write_memory(0x0100, 0x2001).unwrap(); // SLA 1
+        cpu.write_memory(0x0101, 0x2001).unwrap(); // SLA 1
+        cpu.write_memory(0x0102, 0xB000).unwrap(); // WAIT
+
+        cpu.run(10);
+
+        assert_eq!(cpu.iar_history(), &[0x0101, 0x0102]);
+    }
+
+    #[test]
+    fn test_iar_history_disabled_by_default() {
+        let mut cpu = Cpu::new();
+        cpu.set_iar(0x0100);
+        cpu.write_memory(0x0100, 0xB000).unwrap();
+
+        cpu.run(10);
+
+        assert!(cpu.iar_history().is_empty());
+    }
+
     #[test]
     fn test_fetch_instruction_invalid_opcode() {
         let mut cpu = Cpu::new();
@@ -0,0 +1,55 @@
+//! CPU Construction Options
+//!
+//! `CpuOptions` gathers the settings used to build a `Cpu` so new features
+//! extend one struct instead of adding constructors.
+
+use super::memory::DEFAULT_MEMORY_SIZE;
+
+/// How the low memory words 0x0001-0x0003 relate to the index registers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MemoryMode {
+    /// Words 0x0001-0x0003 mirror XR1-XR3, as on the hardware
+    #[default]
+    MappedIndexRegisters,
+    /// Words 0x0001-0x0003 are ordinary memory
+    Plain,
+}
+
+/// Configuration for `Cpu::with_options`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CpuOptions {
+    /// Memory size in words
+    pub memory_size: usize,
+
+    /// Index register memory mapping
+    pub memory_mode: MemoryMode,
+
+    /// Record the last N executed addresses (see `Cpu::iar_history`)
+    pub enable_history: Option<usize>,
+
+    /// Count executions per instruction address (see `Cpu::execution_profile`)
+    pub enable_profiling: bool,
+
+    /// Attach the console keyboard and console printer
+    pub standard_devices: bool,
+
+    /// Skip invalid opcodes instead of faulting (see `Cpu::set_skip_invalid`)
+    pub skip_invalid: bool,
+
+    /// Stop runs on a device stall (see `Cpu::set_stall_threshold`)
+    pub stall_threshold: Option<u32>,
+}
+
+impl Default for CpuOptions {
+    fn default() -> Self {
+        Self {
+            memory_size: DEFAULT_MEMORY_SIZE,
+            memory_mode: MemoryMode::default(),
+            enable_history: None,
+            enable_profiling: false,
+            standard_devices: false,
+            skip_invalid: false,
+            stall_threshold: None,
+        }
+    }
+}
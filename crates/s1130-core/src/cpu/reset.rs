@@ -0,0 +1,76 @@
+//! Reset Scope
+//!
+//! `ResetScope` selects what `Cpu::reset_with_scope` clears, so registers can
+//! be reset while keeping accumulated profiling data (or the reverse).
+//! Scopes combine with `|`:
+//!
+//! ```text
+//! cpu.reset_with_scope(ResetScope::REGISTERS | ResetScope::HISTORY);
+//! ```
+
+use std::ops::{BitOr, BitOrAssign};
+
+/// Set of CPU state groups to reset
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ResetScope(u8);
+
+impl ResetScope {
+    /// ACC, EXT, IAR, index registers, status flags and interrupt levels
+    pub const REGISTERS: Self = Self(0x01);
+    /// Instruction and cycle counts
+    pub const COUNTERS: Self = Self(0x02);
+    /// Per-address execution counts (profiling stays enabled)
+    pub const PROFILING: Self = Self(0x04);
+    /// IAR history and execution trace (both stay enabled)
+    pub const HISTORY: Self = Self(0x08);
+    /// Every attached device's `Device::reset`
+    pub const DEVICES: Self = Self(0x10);
+    /// Address annotations
+    pub const ANNOTATIONS: Self = Self(0x20);
+
+    /// Nothing
+    pub const NONE: Self = Self(0);
+    /// Every group
+    pub const ALL: Self = Self(0x3F);
+
+    /// Check whether every group in `other` is included
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+/// Registers and counters, matching `Cpu::reset`
+impl Default for ResetScope {
+    fn default() -> Self {
+        Self::REGISTERS | Self::COUNTERS
+    }
+}
+
+impl BitOr for ResetScope {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+}
+
+impl BitOrAssign for ResetScope {
+    fn bitor_assign(&mut self, other: Self) {
+        self.0 |= other.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scope_combination() {
+        let scope = ResetScope::REGISTERS | ResetScope::HISTORY;
+        assert!(scope.contains(ResetScope::REGISTERS));
+        assert!(scope.contains(ResetScope::HISTORY));
+        assert!(!scope.contains(ResetScope::PROFILING));
+        assert!(ResetScope::ALL.contains(scope));
+        assert!(scope.contains(ResetScope::NONE));
+    }
+}
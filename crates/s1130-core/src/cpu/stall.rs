@@ -0,0 +1,131 @@
+//! Device Stall Detection
+//!
+//! Devices here are synchronous, so a program polling a device that will
+//! never become ready (e.g. sensing an empty keyboard) spins until the run
+//! hits `max_steps`. `StallDetector` counts consecutive executions of the
+//! same XIO Sense that store the same status word; once the count reaches
+//! the threshold, `Cpu::run` stops with `StopReason::DeviceStall` so the
+//! host can ask the user for input.
+//!
+//! Any other XIO, or a Sense whose status changed, starts the count over.
+
+use crate::devices::{DeviceFunction, Iocc};
+
+/// Identity and result of the Sense being repeated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Sense {
+    /// Address of the IOCC
+    iocc_address: u16,
+    device_code: u8,
+    /// Status word the device stored
+    status: u16,
+}
+
+/// Counts repeats of one unchanged XIO Sense
+#[derive(Debug, Clone, Default)]
+pub(crate) struct StallDetector {
+    /// Repeats that count as a stall (None = detection disabled)
+    threshold: Option<u32>,
+    last: Option<Sense>,
+    repeats: u32,
+}
+
+impl StallDetector {
+    pub(crate) fn new(threshold: Option<u32>) -> Self {
+        Self {
+            threshold,
+            ..Self::default()
+        }
+    }
+
+    pub(crate) fn threshold(&self) -> Option<u32> {
+        self.threshold
+    }
+
+    pub(crate) fn set_threshold(&mut self, threshold: Option<u32>) {
+        self.threshold = threshold;
+        self.clear();
+    }
+
+    /// Record an executed XIO; `status` is the word a Sense stored
+    pub(crate) fn observe(&mut self, iocc_address: u16, iocc: &Iocc, status: Option<u16>) {
+        let sense = match (iocc.function, status) {
+            (DeviceFunction::Sense, Some(status)) => Sense {
+                iocc_address,
+                device_code: iocc.device_code,
+                status,
+            },
+            _ => return self.clear(),
+        };
+
+        if self.last == Some(sense) {
+            self.repeats = self.repeats.saturating_add(1);
+        } else {
+            self.last = Some(sense);
+            self.repeats = 1;
+        }
+    }
+
+    /// Device code of a stalled Sense, restarting the count
+    ///
+    /// Restarting lets a resumed run spin another `threshold` times before
+    /// reporting the stall again.
+    pub(crate) fn take_stall(&mut self) -> Option<u8> {
+        let threshold = self.threshold?;
+        let sense = self.last.filter(|_| self.repeats >= threshold)?;
+        self.repeats = 0;
+        Some(sense.device_code)
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.last = None;
+        self.repeats = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::devices::test_support::iocc;
+    use crate::devices::StandardDevice;
+
+    const DEVICE: StandardDevice = StandardDevice::ConsoleKeyboard;
+
+    #[test]
+    fn test_unchanged_sense_stalls_at_threshold() {
+        let mut detector = StallDetector::new(Some(3));
+        let sense = iocc(DEVICE, DeviceFunction::Sense, 0);
+
+        detector.observe(0x0200, &sense, Some(0));
+        detector.observe(0x0200, &sense, Some(0));
+        assert_eq!(detector.take_stall(), None);
+        detector.observe(0x0200, &sense, Some(0));
+        assert_eq!(detector.take_stall(), Some(1));
+        assert_eq!(detector.take_stall(), None);
+    }
+
+    #[test]
+    fn test_changes_restart_the_count() {
+        let mut detector = StallDetector::new(Some(2));
+        let sense = iocc(DEVICE, DeviceFunction::Sense, 0);
+
+        detector.observe(0x0200, &sense, Some(0));
+        detector.observe(0x0200, &sense, Some(1));
+        assert_eq!(detector.take_stall(), None);
+
+        detector.observe(0x0200, &sense, Some(1));
+        detector.observe(0x0202, &iocc(DEVICE, DeviceFunction::Read, 0), None);
+        detector.observe(0x0200, &sense, Some(1));
+        assert_eq!(detector.take_stall(), None);
+    }
+
+    #[test]
+    fn test_disabled_without_threshold() {
+        let mut detector = StallDetector::default();
+        let sense = iocc(DEVICE, DeviceFunction::Sense, 0);
+        for _ in 0..100 {
+            detector.observe(0x0200, &sense, Some(0));
+        }
+        assert_eq!(detector.take_stall(), None);
+    }
+}
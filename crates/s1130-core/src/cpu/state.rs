@@ -3,6 +3,7 @@
 //! This module defines the external view of CPU state for debugging and UI.
 //! The state is a snapshot that can be serialized and sent across boundaries.
 
+use crate::error::{CpuError, Result};
 use serde::{Deserialize, Serialize};
 
 /// Snapshot of CPU state at a point in time
@@ -44,7 +45,12 @@ pub struct CpuState {
     /// Number of instructions executed
     pub instruction_count: u64,
 
-    /// Current interrupt level being serviced (0-5, None if not in interrupt)
+    /// Storage cycles used by executed instructions; 0 in states saved
+    /// before it was captured
+    #[serde(default)]
+    pub cycle_count: u64,
+
+    /// Current interrupt level being serviced (0-4, None if not in interrupt)
     pub current_interrupt_level: Option<u8>,
 }
 
@@ -62,6 +68,7 @@ impl CpuState {
             overflow: false,
             wait: false,
             instruction_count: 0,
+            cycle_count: 0,
             current_interrupt_level: None,
         }
     }
@@ -80,6 +87,60 @@ impl CpuState {
     pub fn has_status_flags(&self) -> bool {
         self.carry || self.overflow
     }
+
+    /// Fields whose values differ from `other`, in declaration order
+    ///
+    /// Registers are shown in hex, the rest as plain values.
+    pub fn diff(&self, other: &CpuState) -> Vec<FieldDiff> {
+        let register = |value: u16| format!("{:#06x}", value);
+        let level = |level: Option<u8>| level.map_or_else(|| "none".to_string(), |l| l.to_string());
+        let fields = [
+            ("acc", register(self.acc), register(other.acc)),
+            ("ext", register(self.ext), register(other.ext)),
+            ("iar", register(self.iar), register(other.iar)),
+            ("xr1", register(self.xr1), register(other.xr1)),
+            ("xr2", register(self.xr2), register(other.xr2)),
+            ("xr3", register(self.xr3), register(other.xr3)),
+            ("carry", self.carry.to_string(), other.carry.to_string()),
+            (
+                "overflow",
+                self.overflow.to_string(),
+                other.overflow.to_string(),
+            ),
+            ("wait", self.wait.to_string(), other.wait.to_string()),
+            (
+                "instruction_count",
+                self.instruction_count.to_string(),
+                other.instruction_count.to_string(),
+            ),
+            (
+                "cycle_count",
+                self.cycle_count.to_string(),
+                other.cycle_count.to_string(),
+            ),
+            (
+                "current_interrupt_level",
+                level(self.current_interrupt_level),
+                level(other.current_interrupt_level),
+            ),
+        ];
+        fields
+            .into_iter()
+            .filter(|(_, left, right)| left != right)
+            .map(|(field, left, right)| FieldDiff { field, left, right })
+            .collect()
+    }
+}
+
+/// One field that differs between two states, from `CpuState::diff`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldDiff {
+    /// Field name as in `CpuState`
+    pub field: &'static str,
+    /// Value in the state `diff` was called on
+    pub left: String,
+    /// Value in the other state
+    pub right: String,
 }
 
 impl Default for CpuState {
@@ -88,6 +149,191 @@ impl Default for CpuState {
     }
 }
 
+/// Saved state of one attached device
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeviceSnapshot {
+    /// Device code the state belongs to
+    pub device_code: u8,
+
+    /// Device-specific state words (see `Device::save_state`)
+    pub data: Vec<u16>,
+}
+
+/// Complete machine snapshot: registers, memory, interrupt and device state
+///
+/// Serializes with serde (e.g. to JSON) or to a compact binary form via
+/// `to_bytes`/`from_bytes`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CpuSnapshot {
+    /// Registers, flags and counters
+    pub state: CpuState,
+
+    /// Full memory contents
+    pub memory: Vec<u16>,
+
+    /// State of each attached device, ordered by device code
+    pub devices_state: Vec<DeviceSnapshot>,
+
+    /// Interrupt controller state (see `InterruptController::save_state`);
+    /// empty in snapshots saved before it was captured
+    #[serde(default)]
+    pub interrupts: Vec<u16>,
+}
+
+/// Magic bytes at the start of a binary snapshot
+const SNAPSHOT_MAGIC: &[u8; 4] = b"S13S";
+
+impl CpuSnapshot {
+    /// Encode as compact little-endian binary
+    ///
+    /// Layout: magic, registers, flags, instruction and cycle counts,
+    /// interrupt level (0xFF = none), then the memory, each device's data and the
+    /// interrupt controller state as length-prefixed word arrays.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let state = &self.state;
+        let mut bytes = Vec::with_capacity(32 + self.memory.len() * 2);
+
+        bytes.extend_from_slice(SNAPSHOT_MAGIC);
+        for word in [
+            state.acc, state.ext, state.iar, state.xr1, state.xr2, state.xr3,
+        ] {
+            bytes.extend_from_slice(&word.to_le_bytes());
+        }
+        let flags = (state.carry as u8) | ((state.overflow as u8) << 1) | ((state.wait as u8) << 2);
+        bytes.push(flags);
+        bytes.extend_from_slice(&state.instruction_count.to_le_bytes());
+        bytes.extend_from_slice(&state.cycle_count.to_le_bytes());
+        bytes.push(state.current_interrupt_level.unwrap_or(0xFF));
+
+        write_words(&mut bytes, &self.memory);
+
+        bytes.extend_from_slice(&(self.devices_state.len() as u16).to_le_bytes());
+        for device in &self.devices_state {
+            bytes.push(device.device_code);
+            write_words(&mut bytes, &device.data);
+        }
+        write_words(&mut bytes, &self.interrupts);
+
+        bytes
+    }
+
+    /// Decode a snapshot produced by `to_bytes`
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut reader = ByteReader { bytes, pos: 0 };
+
+        if reader.take(4)? != SNAPSHOT_MAGIC {
+            return Err(CpuError::InvalidSnapshot("bad magic".to_string()));
+        }
+
+        let acc = reader.u16()?;
+        let ext = reader.u16()?;
+        let iar = reader.u16()?;
+        let xr1 = reader.u16()?;
+        let xr2 = reader.u16()?;
+        let xr3 = reader.u16()?;
+        let flags = reader.u8()?;
+        let instruction_count = reader.u64()?;
+        let cycle_count = reader.u64()?;
+        let level = reader.u8()?;
+
+        let state = CpuState {
+            acc,
+            ext,
+            iar,
+            xr1,
+            xr2,
+            xr3,
+            carry: flags & 0x01 != 0,
+            overflow: flags & 0x02 != 0,
+            wait: flags & 0x04 != 0,
+            instruction_count,
+            cycle_count,
+            current_interrupt_level: (level != 0xFF).then_some(level),
+        };
+
+        let memory = reader.words()?;
+
+        let device_count = reader.u16()?;
+        let mut devices_state = Vec::with_capacity(device_count as usize);
+        for _ in 0..device_count {
+            let device_code = reader.u8()?;
+            let data = reader.words()?;
+            devices_state.push(DeviceSnapshot { device_code, data });
+        }
+        let interrupts = reader.words()?;
+
+        if reader.pos != bytes.len() {
+            return Err(CpuError::InvalidSnapshot("trailing bytes".to_string()));
+        }
+
+        Ok(Self {
+            state,
+            memory,
+            devices_state,
+            interrupts,
+        })
+    }
+}
+
+/// Append a u32 word count followed by the words
+fn write_words(bytes: &mut Vec<u8>, words: &[u16]) {
+    bytes.extend_from_slice(&(words.len() as u32).to_le_bytes());
+    for word in words {
+        bytes.extend_from_slice(&word.to_le_bytes());
+    }
+}
+
+/// Cursor over a binary snapshot
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|&end| end <= self.bytes.len())
+            .ok_or_else(|| CpuError::InvalidSnapshot("unexpected end of data".to_string()))?;
+        let slice = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(slice)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        let b = self.take(2)?;
+        Ok(u16::from_le_bytes([b[0], b[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        let b = self.take(4)?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        let lo = self.u32()? as u64;
+        let hi = self.u32()? as u64;
+        Ok(lo | (hi << 32))
+    }
+
+    fn words(&mut self) -> Result<Vec<u16>> {
+        let len = self.u32()? as usize;
+        let bytes = self.take(
+            len.checked_mul(2)
+                .ok_or_else(|| CpuError::InvalidSnapshot("word count too large".to_string()))?,
+        )?;
+        Ok(bytes
+            .chunks_exact(2)
+            .map(|b| u16::from_le_bytes([b[0], b[1]]))
+            .collect())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -138,6 +384,56 @@ mod tests {
         assert!(!state.has_status_flags());
     }
 
+    fn sample_snapshot() -> CpuSnapshot {
+        let mut state = CpuState::new();
+        state.acc = 0x1234;
+        state.iar = 0x0100;
+        state.xr2 = 0x0042;
+        state.carry = true;
+        state.wait = true;
+        state.instruction_count = 0x1_0000_0005;
+        state.cycle_count = 0x2_0000_0011;
+        state.current_interrupt_level = Some(3);
+
+        CpuSnapshot {
+            state,
+            memory: vec![0, 0x0042, 0xBEEF, 0xFFFF],
+            devices_state: vec![DeviceSnapshot {
+                device_code: 2,
+                data: vec![b'H' as u16, b'I' as u16],
+            }],
+            interrupts: vec![0b00100, 0, 0b01000],
+        }
+    }
+
+    #[test]
+    fn test_snapshot_json_round_trip() {
+        let snapshot = sample_snapshot();
+        let json = serde_json::to_string(&snapshot).unwrap();
+        let restored: CpuSnapshot = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored, snapshot);
+    }
+
+    #[test]
+    fn test_snapshot_binary_round_trip() {
+        let snapshot = sample_snapshot();
+        let bytes = snapshot.to_bytes();
+        assert_eq!(&bytes[..4], b"S13S");
+        assert_eq!(CpuSnapshot::from_bytes(&bytes).unwrap(), snapshot);
+    }
+
+    #[test]
+    fn test_snapshot_binary_rejects_bad_input() {
+        let bytes = sample_snapshot().to_bytes();
+
+        assert!(CpuSnapshot::from_bytes(b"XXXX").is_err());
+        assert!(CpuSnapshot::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+
+        let mut extra = bytes.clone();
+        extra.push(0);
+        assert!(CpuSnapshot::from_bytes(&extra).is_err());
+    }
+
     #[test]
     fn test_cpu_state_serialization() {
         let state = CpuState {
@@ -151,6 +447,7 @@ mod tests {
             overflow: false,
             wait: false,
             instruction_count: 42,
+            cycle_count: 97,
             current_interrupt_level: Some(4),
         };
 
@@ -0,0 +1,161 @@
+//! Execution Trace
+//!
+//! A bounded record of the most recently executed instructions, used for
+//! post-mortem debugging: when a program faults, the trace shows what ran
+//! just before.
+
+use crate::instructions::InstructionInfo;
+use serde::{Deserialize, Serialize};
+
+/// Default number of entries kept by a `TraceBuffer`
+pub const DEFAULT_TRACE_CAPACITY: usize = 1024;
+
+/// One executed instruction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TraceEntry {
+    /// Address the instruction was fetched from
+    pub iar: u16,
+    /// First instruction word
+    pub instruction_word1: u16,
+    /// Displacement word (long format only)
+    pub instruction_word2: Option<u16>,
+    /// Effective address computed for the instruction
+    pub effective_address: u16,
+    /// Accumulator before execution
+    pub acc_before: u16,
+    /// Accumulator after execution
+    pub acc_after: u16,
+}
+
+/// Details of one step, returned by `Cpu::step_with_info`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StepInfo {
+    /// The decoded instruction, with its effective address filled in
+    pub instruction: InstructionInfo,
+    /// Effective address computed for the instruction
+    pub effective_address: u16,
+    /// Address the instruction was fetched from
+    pub iar_before: u16,
+    /// IAR after execution (the next instruction, or a branch target)
+    pub iar_after: u16,
+    /// Accumulator before execution
+    pub acc_before: u16,
+    /// Accumulator after execution
+    pub acc_after: u16,
+    /// The carry or overflow indicator changed
+    pub flags_changed: bool,
+}
+
+/// Event sent on the channel from `Cpu::trace_channel`, one per executed instruction
+pub type TraceEvent = StepInfo;
+
+/// Fixed-capacity ring of trace entries, oldest first
+///
+/// Entries are kept in a buffer of up to twice the capacity and the oldest
+/// half is dropped in one go, so recording stays amortized O(1) while
+/// `entries` can still return a contiguous slice.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceBuffer {
+    entries: Vec<TraceEntry>,
+    capacity: usize,
+}
+
+impl TraceBuffer {
+    /// Create a buffer keeping the last `capacity` entries
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: Vec::new(),
+            capacity,
+        }
+    }
+
+    /// Maximum number of entries kept
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Record an executed instruction, dropping the oldest when full
+    pub fn push(&mut self, entry: TraceEntry) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.entries.len() == self.capacity * 2 {
+            self.entries.drain(..self.capacity);
+        }
+        self.entries.push(entry);
+    }
+
+    /// Recorded entries, oldest first
+    pub fn entries(&self) -> &[TraceEntry] {
+        let skip = self.entries.len().saturating_sub(self.capacity);
+        &self.entries[skip..]
+    }
+
+    /// Number of entries currently kept
+    pub fn len(&self) -> usize {
+        self.entries().len()
+    }
+
+    /// Check whether nothing has been recorded
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Discard all entries
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+impl Default for TraceBuffer {
+    fn default() -> Self {
+        Self::new(DEFAULT_TRACE_CAPACITY)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(iar: u16) -> TraceEntry {
+        TraceEntry {
+            iar,
+            instruction_word1: 0x2001,
+            instruction_word2: None,
+            effective_address: 1,
+            acc_before: 0,
+            acc_after: 0,
+        }
+    }
+
+    #[test]
+    fn test_trace_buffer_keeps_last_entries_in_order() {
+        let mut trace = TraceBuffer::new(3);
+        for iar in 0..10 {
+            trace.push(entry(iar));
+        }
+
+        let iars: Vec<u16> = trace.entries().iter().map(|e| e.iar).collect();
+        assert_eq!(iars, vec![7, 8, 9]);
+        assert_eq!(trace.len(), 3);
+    }
+
+    #[test]
+    fn test_trace_buffer_clear() {
+        let mut trace = TraceBuffer::default();
+        assert_eq!(trace.capacity(), DEFAULT_TRACE_CAPACITY);
+
+        trace.push(entry(1));
+        assert!(!trace.is_empty());
+        trace.clear();
+        assert!(trace.is_empty());
+        assert!(trace.entries().is_empty());
+    }
+
+    #[test]
+    fn test_trace_buffer_zero_capacity_records_nothing() {
+        let mut trace = TraceBuffer::new(0);
+        trace.push(entry(1));
+        assert!(trace.is_empty());
+    }
+}
@@ -10,13 +10,28 @@
 //!    - Device generates interrupt for each character
 //!    - High CPU overhead
 
+pub mod card_punch_1442;
+pub mod card_reader;
+pub mod console_switches;
+pub mod disk_2310;
 pub mod keyboard;
+pub mod manager;
+pub mod paper_tape;
 pub mod printer;
+pub mod printer_1132;
 
+pub use card_punch_1442::Device1442;
+pub use card_reader::{Card, Device2501};
+pub use console_switches::ConsoleSwitches;
+pub use disk_2310::Device2310;
 pub use keyboard::DeviceConsoleKeyboard;
+pub use manager::{DeviceManager, ReplacementCallback};
+pub use paper_tape::DevicePaperTape;
 pub use printer::DeviceConsolePrinter;
+pub use printer_1132::{Device1132, PrinterEncoding};
 
 use crate::error::CpuError;
+use serde::Serialize;
 
 /// Device function codes (3 bits, values 0-7)
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -60,6 +75,84 @@ impl DeviceFunction {
     pub fn to_bits(self) -> u8 {
         self as u8
     }
+
+    /// Look up a function by name (case-insensitive, e.g. `READ`, `InitWrite`)
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_uppercase().as_str() {
+            "SENSE" => Some(DeviceFunction::Sense),
+            "CONTROL" => Some(DeviceFunction::Control),
+            "INITREAD" => Some(DeviceFunction::InitRead),
+            "READ" => Some(DeviceFunction::Read),
+            "INITWRITE" => Some(DeviceFunction::InitWrite),
+            "WRITE" => Some(DeviceFunction::Write),
+            "SENSEILSW" => Some(DeviceFunction::SenseIlsw),
+            _ => None,
+        }
+    }
+}
+
+/// Device codes of the standard IBM 1130 peripherals
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum StandardDevice {
+    /// Console toggle switches
+    ConsoleSwitches = 0,
+    /// Console keyboard
+    ConsoleKeyboard = 1,
+    /// Console printer
+    ConsolePrinter = 2,
+    /// 1442 Card Read Punch
+    CardPunch1442 = 3,
+    /// 2310 Disk Storage Drive
+    Disk2310 = 4,
+    /// 1132 Line Printer
+    LinePrinter1132 = 6,
+    /// Paper Tape Reader/Punch
+    PaperTape = 7,
+    /// 2501 Card Reader
+    CardReader2501 = 9,
+}
+
+impl StandardDevice {
+    /// All standard devices, in device-code order
+    pub const ALL: [StandardDevice; 8] = [
+        StandardDevice::ConsoleSwitches,
+        StandardDevice::ConsoleKeyboard,
+        StandardDevice::ConsolePrinter,
+        StandardDevice::CardPunch1442,
+        StandardDevice::Disk2310,
+        StandardDevice::LinePrinter1132,
+        StandardDevice::PaperTape,
+        StandardDevice::CardReader2501,
+    ];
+
+    /// Get the 5-bit device code
+    pub fn code(self) -> u8 {
+        self as u8
+    }
+
+    /// Look up a standard device by its device code
+    pub fn from_code(code: u8) -> Option<Self> {
+        Self::ALL.into_iter().find(|device| device.code() == code)
+    }
+
+    /// Look up a standard device by name (case-insensitive)
+    ///
+    /// Accepts the variant names (`ConsoleKeyboard`, `CardReader2501`, ...)
+    /// plus the short forms `SWITCHES`, `KEYBOARD` and `PRINTER`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_uppercase().as_str() {
+            "CONSOLESWITCHES" | "SWITCHES" => Some(StandardDevice::ConsoleSwitches),
+            "CONSOLEKEYBOARD" | "KEYBOARD" => Some(StandardDevice::ConsoleKeyboard),
+            "CONSOLEPRINTER" | "PRINTER" => Some(StandardDevice::ConsolePrinter),
+            "CARDPUNCH1442" => Some(StandardDevice::CardPunch1442),
+            "DISK2310" => Some(StandardDevice::Disk2310),
+            "LINEPRINTER1132" => Some(StandardDevice::LinePrinter1132),
+            "PAPERTAPE" => Some(StandardDevice::PaperTape),
+            "CARDREADER2501" => Some(StandardDevice::CardReader2501),
+            _ => None,
+        }
+    }
 }
 
 /// IOCC (I/O Channel Command) structure
@@ -101,6 +194,26 @@ impl Iocc {
         })
     }
 
+    /// Build the two IOCC words for a standard device
+    ///
+    /// Saves hand-computing the device/function bit positions, e.g.
+    /// keyboard read is `Iocc::build(ConsoleKeyboard, Read, 0, wca)`
+    /// rather than `wca` followed by `0x0B00`.
+    pub fn build(
+        device: StandardDevice,
+        function: DeviceFunction,
+        modifiers: u8,
+        wca: u16,
+    ) -> (u16, u16) {
+        Iocc {
+            wca,
+            device_code: device.code(),
+            function,
+            modifiers,
+        }
+        .encode()
+    }
+
     /// Encode IOCC into two memory words
     pub fn encode(&self) -> (u16, u16) {
         let word1 = self.wca;
@@ -111,6 +224,46 @@ impl Iocc {
     }
 }
 
+/// Handle to an attached device
+///
+/// Returned by `Cpu::attach_device` and used with `Cpu::device_as`/
+/// `Cpu::device_as_mut` to reach the concrete device type without
+/// repeating device-code lookups and `as_any` downcasts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DeviceHandle(u8);
+
+impl DeviceHandle {
+    /// Create a handle for the device attached at `device_code`
+    pub fn new(device_code: u8) -> Self {
+        Self(device_code)
+    }
+
+    /// Get the device code this handle refers to
+    pub fn device_code(self) -> u8 {
+        self.0
+    }
+}
+
+/// How a device moves data between memory and the device
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum DeviceKind {
+    /// One word per XIO (keyboard, console printer, 1442)
+    CharacterMode,
+    /// A whole block per XIO, sized by the word count at WCA
+    BlockMode,
+}
+
+/// Description of an attached device, for display
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct DeviceInfo {
+    /// Device code (0-31)
+    pub code: u8,
+    /// Device name
+    pub name: &'static str,
+    /// Character- or block-mode transfers
+    pub kind: DeviceKind,
+}
+
 /// Device trait - all I/O devices must implement this
 pub trait Device: Send + Sync {
     /// Get the device code (5-bit identifier, 0-31)
@@ -136,6 +289,25 @@ pub trait Device: Send + Sync {
     /// Check if device is busy
     fn is_busy(&self) -> bool;
 
+    /// Current device status word, as stored by an XIO Sense
+    ///
+    /// Each device documents its own bits; the default is 0.
+    fn get_status_word(&self) -> u16 {
+        0
+    }
+
+    /// Code, name and transfer mode of this device
+    ///
+    /// The default describes a character-mode device; block-mode devices
+    /// override it.
+    fn device_info(&self) -> DeviceInfo {
+        DeviceInfo {
+            code: self.device_code(),
+            name: self.device_name(),
+            kind: DeviceKind::CharacterMode,
+        }
+    }
+
     /// Reset device to initial state
     fn reset(&mut self);
 
@@ -144,6 +316,58 @@ pub trait Device: Send + Sync {
 
     /// Support mutable downcasting to concrete types
     fn as_any_mut(&mut self) -> &mut dyn std::any::Any;
+
+    /// Clone into a new boxed device with identical state
+    fn clone_box(&self) -> Box<dyn Device>;
+
+    /// Save device-specific state for a CPU snapshot
+    ///
+    /// Devices without meaningful state can rely on the default (empty).
+    fn save_state(&self) -> Vec<u16> {
+        Vec::new()
+    }
+
+    /// Restore state previously returned by `save_state`
+    fn restore_state(&mut self, _data: &[u16]) {}
+
+    /// Interrupt this device is requesting, as `(level, ILSW bit)`
+    ///
+    /// The CPU polls attached devices before each instruction. Devices
+    /// that never interrupt rely on the default.
+    fn interrupt_request(&self) -> Option<(u8, u16)> {
+        None
+    }
+
+    /// Called when the CPU vectors to the level this device requested
+    ///
+    /// The device should lower its request here; a request still raised
+    /// after the handler returns interrupts again.
+    fn acknowledge_interrupt(&mut self, _level: u8) {}
+}
+
+impl Clone for Box<dyn Device> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
+
+/// IOCC fixture shared by the device tests
+#[cfg(test)]
+pub(crate) mod test_support {
+    use super::{DeviceFunction, Iocc, StandardDevice};
+
+    /// Word count address the device tests transfer through
+    pub(crate) const WCA: u16 = 0x0100;
+
+    /// IOCC for `device` with its word count at `WCA`
+    pub(crate) fn iocc(device: StandardDevice, function: DeviceFunction, modifiers: u8) -> Iocc {
+        Iocc {
+            wca: WCA,
+            device_code: device.code(),
+            function,
+            modifiers,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -171,6 +395,61 @@ mod tests {
         assert_eq!(DeviceFunction::Control.to_bits(), 1);
     }
 
+    #[test]
+    fn test_standard_device_codes() {
+        assert_eq!(StandardDevice::ConsoleSwitches.code(), 0);
+        assert_eq!(StandardDevice::ConsoleKeyboard.code(), 1);
+        assert_eq!(StandardDevice::ConsolePrinter.code(), 2);
+        assert_eq!(StandardDevice::CardPunch1442.code(), 3);
+        assert_eq!(StandardDevice::LinePrinter1132.code(), 6);
+        assert_eq!(StandardDevice::PaperTape.code(), 7);
+        assert_eq!(StandardDevice::CardReader2501.code(), 9);
+    }
+
+    #[test]
+    fn test_standard_device_from_code_round_trip() {
+        for device in StandardDevice::ALL {
+            assert_eq!(StandardDevice::from_code(device.code()), Some(device));
+        }
+        assert_eq!(StandardDevice::from_code(5), None);
+        assert_eq!(StandardDevice::from_code(31), None);
+    }
+
+    #[test]
+    fn test_names_resolve() {
+        assert_eq!(
+            StandardDevice::from_name("keyboard"),
+            Some(StandardDevice::ConsoleKeyboard)
+        );
+        assert_eq!(
+            StandardDevice::from_name("CardReader2501"),
+            Some(StandardDevice::CardReader2501)
+        );
+        assert_eq!(StandardDevice::from_name("TAPE"), None);
+
+        assert_eq!(
+            DeviceFunction::from_name("read"),
+            Some(DeviceFunction::Read)
+        );
+        assert_eq!(
+            DeviceFunction::from_name("INITWRITE"),
+            Some(DeviceFunction::InitWrite)
+        );
+        assert_eq!(DeviceFunction::from_name("PUNCH"), None);
+    }
+
+    #[test]
+    fn test_devices_report_standard_codes() {
+        assert_eq!(
+            DeviceConsoleKeyboard::new().device_code(),
+            StandardDevice::ConsoleKeyboard.code()
+        );
+        assert_eq!(
+            DeviceConsolePrinter::new().device_code(),
+            StandardDevice::ConsolePrinter.code()
+        );
+    }
+
     #[test]
     fn test_iocc_decode_encode() {
         // Test IOCC structure:
@@ -191,6 +470,53 @@ mod tests {
         assert_eq!(encoded2, word2);
     }
 
+    #[test]
+    fn test_iocc_build_matches_hand_encoded_words() {
+        // Keyboard read, as hand-coded in the echo tests
+        assert_eq!(
+            Iocc::build(
+                StandardDevice::ConsoleKeyboard,
+                DeviceFunction::Read,
+                0,
+                0x0120
+            ),
+            (0x0120, 0x0B00)
+        );
+
+        // Printer write
+        assert_eq!(
+            Iocc::build(
+                StandardDevice::ConsolePrinter,
+                DeviceFunction::Write,
+                0,
+                0x0120
+            ),
+            (0x0120, 0x1500)
+        );
+
+        // Keyboard sense
+        assert_eq!(
+            Iocc::build(
+                StandardDevice::ConsoleKeyboard,
+                DeviceFunction::Sense,
+                0,
+                0x0200
+            ),
+            (0x0200, 0x0800)
+        );
+    }
+
+    #[test]
+    fn test_iocc_build_includes_modifiers() {
+        let (_, word2) = Iocc::build(
+            StandardDevice::CardReader2501,
+            DeviceFunction::InitRead,
+            0x42,
+            0x1000,
+        );
+        assert_eq!(word2, (9 << 11) | (2 << 8) | 0x42);
+    }
+
     #[test]
     fn test_iocc_decode_all_functions() {
         for func in 0..8 {
@@ -200,4 +526,28 @@ mod tests {
             assert_eq!(iocc.function.to_bits(), func);
         }
     }
+
+    #[test]
+    fn test_device_info_and_status_word() {
+        let mut reader = Device2501::new();
+        assert_eq!(
+            reader.device_info(),
+            DeviceInfo {
+                code: 9,
+                name: "2501 Card Reader",
+                kind: DeviceKind::BlockMode,
+            }
+        );
+        assert_eq!(
+            DeviceConsoleKeyboard::new().device_info().kind,
+            DeviceKind::CharacterMode
+        );
+
+        // Empty hopper: not ready
+        assert_eq!(reader.get_status_word(), 0x0001);
+        reader.load_cards(vec![Card::new(), Card::new(), Card::new()]);
+        assert_eq!(reader.get_status_word(), 3 << 2);
+
+        assert_eq!(DeviceConsolePrinter::new().get_status_word(), 0x0001);
+    }
 }
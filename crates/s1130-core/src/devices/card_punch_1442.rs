@@ -0,0 +1,386 @@
+//! IBM 1442 Card Read Punch
+//!
+//! This device emulates the IBM 1442, which both reads and punches cards.
+//! Reading uses the same hopper of `Card`s as the 2501; punched cards are
+//! collected in an output stacker.
+//!
+//! Device code: 3
+//!
+//! Operations:
+//! - Sense: Return the status word in WCA location (see `STATUS_*`)
+//! - InitRead: Read the next hopper card into the selected stacker;
+//!   memory at WCA holds the word count (at most 80) and the columns are
+//!   stored after it
+//! - InitWrite: Punch a card from the words following the count at WCA
+//! - Control: Feed a card through unread (`CONTROL_FEED`) and/or route
+//!   the next card to the alternate stacker (`CONTROL_STACKER_SELECT`)
+
+use crate::devices::card_reader::{restore_cards, save_cards, Card};
+use crate::devices::{Device, DeviceFunction, Iocc, StandardDevice};
+use crate::error::CpuError;
+use std::collections::VecDeque;
+
+/// Columns per card
+pub const CARD_COLUMNS: usize = 80;
+
+/// Status bit: a card feed was attempted with an empty hopper
+pub const STATUS_JAM: u16 = 0x8000;
+
+/// Status bits holding the number of cards in the hopper (saturating)
+pub const STATUS_HOPPER_COUNT: u16 = 0x00FF;
+
+/// Sense modifier: clear the jam indication
+pub const SENSE_RESET: u8 = 0x01;
+
+/// Control modifier: feed the next hopper card to the stacker unread
+pub const CONTROL_FEED: u8 = 0x01;
+
+/// Control modifier: send the next card to the alternate stacker
+pub const CONTROL_STACKER_SELECT: u8 = 0x02;
+
+/// IBM 1442 Card Read Punch Device
+#[derive(Clone)]
+pub struct Device1442 {
+    /// Cards waiting to be read
+    hopper: VecDeque<Card>,
+
+    /// Cards punched or fed, in order (stacker 1)
+    output_stacker: Vec<Card>,
+
+    /// Cards routed by stacker select (stacker 2)
+    alternate_stacker: Vec<Card>,
+
+    /// Next card goes to the alternate stacker
+    stacker_select: bool,
+
+    /// A feed found the hopper empty
+    jammed: bool,
+
+    /// Device status flags
+    busy: bool,
+}
+
+impl Device1442 {
+    /// Create a new 1442 with empty hopper and stackers
+    pub fn new() -> Self {
+        Self {
+            hopper: VecDeque::new(),
+            output_stacker: Vec::new(),
+            alternate_stacker: Vec::new(),
+            stacker_select: false,
+            jammed: false,
+            busy: false,
+        }
+    }
+
+    /// Add cards to the end of the hopper
+    pub fn load_hopper(&mut self, cards: Vec<Card>) {
+        self.hopper.extend(cards);
+    }
+
+    /// Number of cards waiting in the hopper
+    pub fn hopper_count(&self) -> usize {
+        self.hopper.len()
+    }
+
+    /// Cards in the main output stacker
+    pub fn get_punched_cards(&self) -> &[Card] {
+        &self.output_stacker
+    }
+
+    /// Cards in the alternate stacker
+    pub fn get_alternate_stacker(&self) -> &[Card] {
+        &self.alternate_stacker
+    }
+
+    /// Check whether a feed found the hopper empty
+    pub fn is_jammed(&self) -> bool {
+        self.jammed
+    }
+
+    /// Put a card in the selected stacker
+    fn stack(&mut self, card: Card) {
+        if std::mem::take(&mut self.stacker_select) {
+            self.alternate_stacker.push(card);
+        } else {
+            self.output_stacker.push(card);
+        }
+    }
+
+    /// Take the next hopper card, recording a jam if there is none
+    fn feed(&mut self) -> Result<Card, CpuError> {
+        self.hopper.pop_front().ok_or_else(|| {
+            self.jammed = true;
+            CpuError::DeviceError("1442: Hopper empty".to_string())
+        })
+    }
+
+    /// Word count and buffer start for a transfer at WCA
+    fn transfer_setup(iocc: &Iocc, memory: &[u16]) -> Result<(usize, usize), CpuError> {
+        let wca = iocc.wca as usize;
+        let count = *memory.get(wca).ok_or(CpuError::MemoryViolation(iocc.wca))? as usize;
+        let count = count.min(CARD_COLUMNS);
+
+        let buffer = wca + 1;
+        if buffer + count > memory.len() {
+            return Err(CpuError::MemoryViolation(iocc.wca));
+        }
+        Ok((buffer, count))
+    }
+}
+
+impl Default for Device1442 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Device for Device1442 {
+    fn device_code(&self) -> u8 {
+        StandardDevice::CardPunch1442.code()
+    }
+
+    fn device_name(&self) -> &'static str {
+        "1442 Card Read Punch"
+    }
+
+    fn execute_iocc(&mut self, iocc: &Iocc, memory: &mut [u16]) -> Result<(), CpuError> {
+        match iocc.function {
+            DeviceFunction::Sense => {
+                if iocc.modifiers & SENSE_RESET != 0 {
+                    self.jammed = false;
+                }
+                if (iocc.wca as usize) < memory.len() {
+                    memory[iocc.wca as usize] = self.get_status_word();
+                }
+                Ok(())
+            }
+
+            DeviceFunction::InitRead => {
+                let (buffer, count) = Self::transfer_setup(iocc, memory)?;
+                let card = self.feed()?;
+                memory[buffer..buffer + count].copy_from_slice(&card.columns[..count]);
+                self.stack(card);
+                Ok(())
+            }
+
+            DeviceFunction::InitWrite => {
+                let (buffer, count) = Self::transfer_setup(iocc, memory)?;
+                let card = Card::from_data(&memory[buffer..buffer + count]);
+                self.stack(card);
+                Ok(())
+            }
+
+            DeviceFunction::Control => {
+                if iocc.modifiers & CONTROL_STACKER_SELECT != 0 {
+                    self.stacker_select = true;
+                }
+                if iocc.modifiers & CONTROL_FEED != 0 {
+                    let card = self.feed()?;
+                    self.stack(card);
+                }
+                Ok(())
+            }
+
+            _ => Err(CpuError::DeviceError(format!(
+                "1442: Unsupported function {:?}",
+                iocc.function
+            ))),
+        }
+    }
+
+    fn is_busy(&self) -> bool {
+        self.busy
+    }
+
+    fn get_status_word(&self) -> u16 {
+        let count = self.hopper.len().min(STATUS_HOPPER_COUNT as usize) as u16;
+        let jam = if self.jammed { STATUS_JAM } else { 0 };
+        jam | count
+    }
+
+    fn reset(&mut self) {
+        // Cards stay where they are; only the status clears
+        self.stacker_select = false;
+        self.jammed = false;
+        self.busy = false;
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+
+    fn clone_box(&self) -> Box<dyn Device> {
+        Box::new(self.clone())
+    }
+
+    /// Stacker select and jam flags, then the hopper, the output stacker
+    /// and the alternate stacker
+    fn save_state(&self) -> Vec<u16> {
+        let flags = u16::from(self.stacker_select) | (u16::from(self.jammed) << 1);
+        let mut data = vec![flags];
+        save_cards(&mut data, self.hopper.iter());
+        save_cards(&mut data, self.output_stacker.iter());
+        save_cards(&mut data, self.alternate_stacker.iter());
+        data
+    }
+
+    fn restore_state(&mut self, data: &[u16]) {
+        let flags = data.first().copied().unwrap_or(0);
+        self.stacker_select = flags & 0x01 != 0;
+        self.jammed = flags & 0x02 != 0;
+        self.busy = false;
+        let mut cards = data.get(1..).unwrap_or_default();
+        self.hopper = restore_cards(&mut cards).into();
+        self.output_stacker = restore_cards(&mut cards);
+        self.alternate_stacker = restore_cards(&mut cards);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::card_encoding::ascii_to_hollerith;
+    use crate::devices::test_support::{iocc, WCA};
+
+    const DEVICE: StandardDevice = StandardDevice::CardPunch1442;
+
+    #[test]
+    fn test_read_cards_in_order() {
+        let mut device = Device1442::new();
+        device.load_hopper(vec![Card::from_text("FIRST"), Card::from_text("SECOND")]);
+        let mut memory = vec![0u16; 0x200];
+
+        memory[WCA as usize] = 80;
+        device
+            .execute_iocc(&iocc(DEVICE, DeviceFunction::InitRead, 0), &mut memory)
+            .unwrap();
+        let read = Card::from_data(&memory[WCA as usize + 1..WCA as usize + 81]);
+        assert_eq!(&read.to_text()[..5], "FIRST");
+        assert_eq!(device.hopper_count(), 1);
+
+        device
+            .execute_iocc(&iocc(DEVICE, DeviceFunction::InitRead, 0), &mut memory)
+            .unwrap();
+        let read = Card::from_data(&memory[WCA as usize + 1..WCA as usize + 81]);
+        assert_eq!(&read.to_text()[..6], "SECOND");
+        assert_eq!(device.get_punched_cards().len(), 2);
+    }
+
+    #[test]
+    fn test_read_empty_hopper_jams() {
+        let mut device = Device1442::new();
+        let mut memory = vec![0u16; 0x200];
+        memory[WCA as usize] = 80;
+
+        assert!(device
+            .execute_iocc(&iocc(DEVICE, DeviceFunction::InitRead, 0), &mut memory)
+            .is_err());
+        assert!(device.is_jammed());
+
+        device
+            .execute_iocc(&iocc(DEVICE, DeviceFunction::Sense, 0), &mut memory)
+            .unwrap();
+        assert_eq!(memory[WCA as usize], STATUS_JAM);
+
+        device
+            .execute_iocc(
+                &iocc(DEVICE, DeviceFunction::Sense, SENSE_RESET),
+                &mut memory,
+            )
+            .unwrap();
+        assert_eq!(memory[WCA as usize], 0);
+    }
+
+    #[test]
+    fn test_punch_accumulates_in_stacker() {
+        let mut device = Device1442::new();
+        let mut memory = vec![0u16; 0x200];
+
+        for text in ["CARD1", "CARD2", "CARD3"] {
+            memory[WCA as usize] = text.len() as u16;
+            for (i, ch) in text.chars().enumerate() {
+                memory[WCA as usize + 1 + i] = ascii_to_hollerith(ch);
+            }
+            device
+                .execute_iocc(&iocc(DEVICE, DeviceFunction::InitWrite, 0), &mut memory)
+                .unwrap();
+        }
+
+        let punched = device.get_punched_cards();
+        assert_eq!(punched.len(), 3);
+        assert_eq!(&punched[2].to_text()[..5], "CARD3");
+        // Columns past the word count are blank
+        assert_eq!(punched[0].columns[5], 0);
+    }
+
+    #[test]
+    fn test_state_round_trip() {
+        let mut device = Device1442::new();
+        device.load_hopper(vec![Card::from_text("A"), Card::from_text("B")]);
+        let mut memory = vec![0u16; 0x200];
+        device
+            .execute_iocc(
+                &iocc(DEVICE, DeviceFunction::Control, CONTROL_FEED),
+                &mut memory,
+            )
+            .unwrap();
+        device
+            .execute_iocc(
+                &iocc(DEVICE, DeviceFunction::Control, CONTROL_STACKER_SELECT),
+                &mut memory,
+            )
+            .unwrap();
+
+        let mut restored = Device1442::new();
+        restored.restore_state(&device.save_state());
+        assert_eq!(restored.hopper_count(), 1);
+        assert_eq!(restored.get_punched_cards(), device.get_punched_cards());
+
+        // Stacker select survives too
+        restored
+            .execute_iocc(
+                &iocc(DEVICE, DeviceFunction::Control, CONTROL_FEED),
+                &mut memory,
+            )
+            .unwrap();
+        assert_eq!(&restored.get_alternate_stacker()[0].to_text()[..1], "B");
+    }
+
+    #[test]
+    fn test_control_feed_and_stacker_select() {
+        let mut device = Device1442::new();
+        device.load_hopper(vec![Card::from_text("A"), Card::from_text("B")]);
+        let mut memory = vec![0u16; 0x200];
+
+        device
+            .execute_iocc(
+                &iocc(DEVICE, DeviceFunction::Control, CONTROL_FEED),
+                &mut memory,
+            )
+            .unwrap();
+        device
+            .execute_iocc(
+                &iocc(
+                    DEVICE,
+                    DeviceFunction::Control,
+                    CONTROL_FEED | CONTROL_STACKER_SELECT,
+                ),
+                &mut memory,
+            )
+            .unwrap();
+
+        assert_eq!(device.hopper_count(), 0);
+        assert_eq!(&device.get_punched_cards()[0].to_text()[..1], "A");
+        assert_eq!(&device.get_alternate_stacker()[0].to_text()[..1], "B");
+
+        device
+            .execute_iocc(&iocc(DEVICE, DeviceFunction::Sense, 0), &mut memory)
+            .unwrap();
+        assert_eq!(memory[WCA as usize], 0);
+    }
+}
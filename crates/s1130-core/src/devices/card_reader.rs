@@ -12,13 +12,51 @@
 //! Status word bits:
 //! - 0x1000: Last card (interrupt 4)
 //! - 0x0800: Operation complete (interrupt 4)
+//! - 0x00FC: Cards in the hopper (saturating at 63)
 //! - 0x0002: Busy (read in progress)
 //! - 0x0001: Not ready or busy
-
-use crate::devices::{Device, DeviceFunction, Iocc};
-use crate::error::CpuError;
+//!
+//! The hopper count belongs in the low byte (IBM bits 8-15), but the two
+//! lowest bits already hold the busy flags, so it is cut down to the six
+//! bits above them (IBM bits 8-13) and saturates at 63 cards.
+//!
+//! A completed read requests an interrupt on level 4 (ILSW bit 0x1000)
+//! until the CPU acknowledges it.
+//!
+//! Cards can be loaded as raw columns or as text; text cards stay text in
+//! the hopper and are punched in Hollerith (see `card_encoding`) when read.
+//!
+//! Whole decks load from text files, one card per line. In both formats a
+//! line starting with `~` is a comment:
+//!
+//! ```text
+//! ~ load_from_text: up to 80 characters, an empty line is a blank card
+//! // JOB
+//! ~ load_from_hollerith_hex: 80 columns of 4 hex digits (320 per line)
+//! 9000A000C000...
+//! ```
+
+use crate::card_encoding::{ascii_to_hollerith, hollerith_to_ascii, HOLLERITH_TABLE};
+use crate::devices::{Device, DeviceFunction, DeviceInfo, DeviceKind, Iocc, StandardDevice};
+use crate::error::{CpuError, DeviceError};
 use std::collections::VecDeque;
 
+/// Interrupt level of the operation-complete interrupt
+pub const INTERRUPT_LEVEL: u8 = 4;
+/// ILSW bit identifying the 2501 on level 4
+pub const ILSW_BIT: u16 = 0x1000;
+
+/// Columns on a card
+pub const CARD_COLUMNS: usize = 80;
+
+/// Marks a comment line in a card deck file
+const DECK_COMMENT: char = '~';
+
+/// Status bits holding the number of cards in the hopper, saturating at 63
+///
+/// IBM bits 8-13: the low byte minus the two busy bits below it.
+pub const STATUS_HOPPER_COUNT: u16 = 0x00FC;
+
 /// Card data structure
 ///
 /// IBM 1130 cards hold 80 columns of 16-bit words.
@@ -41,6 +79,59 @@ impl Card {
         card.columns[..len].copy_from_slice(&data[..len]);
         card
     }
+
+    /// Punch `text` in Hollerith, one character per column
+    ///
+    /// Text past column 80 is dropped and short text leaves the remaining
+    /// columns blank. Characters outside the card set punch as blanks.
+    pub fn from_text(text: &str) -> Self {
+        let mut card = Self::new();
+        for (column, ch) in card.columns.iter_mut().zip(text.chars()) {
+            *column = ascii_to_hollerith(ch);
+        }
+        card
+    }
+
+    /// Decode all 80 columns as Hollerith text
+    ///
+    /// Punch patterns outside the card set read as `?`.
+    pub fn to_text(&self) -> String {
+        self.columns
+            .iter()
+            .map(|&column| hollerith_to_ascii(column).unwrap_or('?'))
+            .collect()
+    }
+}
+
+/// A card waiting in the hopper
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum HopperCard {
+    /// Columns as they will be read
+    Columns(Card),
+    /// Text, punched when the card is read
+    Text(String),
+}
+
+impl HopperCard {
+    fn into_card(self) -> Card {
+        match self {
+            HopperCard::Columns(card) => card,
+            HopperCard::Text(text) => Card::from_text(&text),
+        }
+    }
+}
+
+/// Lines of a deck file with their 0-based index, comments dropped
+fn deck_lines(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.starts_with(DECK_COMMENT))
+}
+
+/// Check whether `ch` can be punched (lowercase punches as uppercase)
+fn is_card_character(ch: char) -> bool {
+    let ch = ch.to_ascii_uppercase();
+    HOLLERITH_TABLE.iter().any(|&(c, _)| c == ch)
 }
 
 impl Default for Card {
@@ -49,18 +140,45 @@ impl Default for Card {
     }
 }
 
+/// Append `cards` to saved device state: a two-word count (high word
+/// first), then the columns of each card
+pub(crate) fn save_cards<'a>(data: &mut Vec<u16>, cards: impl ExactSizeIterator<Item = &'a Card>) {
+    let count = cards.len() as u32;
+    data.extend_from_slice(&[(count >> 16) as u16, count as u16]);
+    for card in cards {
+        data.extend_from_slice(&card.columns);
+    }
+}
+
+/// Take cards written by `save_cards` from the front of `data`
+///
+/// Stops at the last complete card if `data` runs out.
+pub(crate) fn restore_cards(data: &mut &[u16]) -> Vec<Card> {
+    let Some((count, rest)) = data.split_first_chunk::<2>() else {
+        *data = &[];
+        return Vec::new();
+    };
+    let count = ((count[0] as usize) << 16) | count[1] as usize;
+    let chunks = rest.chunks_exact(CARD_COLUMNS).take(count);
+    let cards: Vec<Card> = chunks.map(Card::from_data).collect();
+    *data = &rest[cards.len() * CARD_COLUMNS..];
+    cards
+}
+
 /// IBM 2501 Card Reader Device
 ///
 /// This is a block-mode device that reads punched cards.
 /// Cards are queued in a hopper and read one at a time into memory.
+#[derive(Clone)]
 pub struct Device2501 {
     /// Card hopper (cards waiting to be read)
-    hopper: VecDeque<Card>,
+    hopper: VecDeque<HopperCard>,
 
     /// Read operation state
     read_in_progress: bool,
     operation_complete: bool,
     last_card: bool,
+    interrupt_pending: bool,
 
     /// Memory address for current read operation
     read_address: u16,
@@ -77,6 +195,7 @@ impl Device2501 {
             read_in_progress: false,
             operation_complete: false,
             last_card: false,
+            interrupt_pending: false,
             read_address: 0,
             read_count: 0,
         }
@@ -89,7 +208,15 @@ impl Device2501 {
     /// # Arguments
     /// * `card` - The card to load
     pub fn load_card(&mut self, card: Card) {
-        self.hopper.push_back(card);
+        self.hopper.push_back(HopperCard::Columns(card));
+    }
+
+    /// Load a card given as text
+    ///
+    /// The text is kept as is and punched in Hollerith when the card is
+    /// read (see `Card::from_text`).
+    pub fn load_text_card(&mut self, text: &str) {
+        self.hopper.push_back(HopperCard::Text(text.to_string()));
     }
 
     /// Load multiple cards into the hopper
@@ -98,10 +225,81 @@ impl Device2501 {
     /// * `cards` - The cards to load
     pub fn load_cards(&mut self, cards: Vec<Card>) {
         for card in cards {
-            self.hopper.push_back(card);
+            self.load_card(card);
         }
     }
 
+    /// Load a deck given as text, one card per line
+    ///
+    /// Lines longer than 80 characters are truncated and an empty line is
+    /// a blank card; lines starting with `~` are comments. Returns the
+    /// number of cards loaded.
+    ///
+    /// # Errors
+    ///
+    /// Returns `DeviceError::InvalidDeck` for a character outside the card
+    /// set (lowercase letters are accepted as uppercase). Nothing is
+    /// loaded then.
+    pub fn load_from_text(&mut self, text: &str) -> Result<usize, DeviceError> {
+        let mut cards = Vec::new();
+        for (index, line) in deck_lines(text) {
+            let line: String = line.chars().take(CARD_COLUMNS).collect();
+            if let Some(ch) = line.chars().find(|&ch| !is_card_character(ch)) {
+                return Err(DeviceError::InvalidDeck {
+                    line: index + 1,
+                    message: format!("{:?} is not in the card character set", ch),
+                });
+            }
+            cards.push(HopperCard::Text(line));
+        }
+
+        let count = cards.len();
+        self.hopper.extend(cards);
+        Ok(count)
+    }
+
+    /// Load a deck given as hex, one card per line
+    ///
+    /// Each line holds the 80 columns as 4 hex digits apiece (320 digits,
+    /// surrounding whitespace ignored); lines starting with `~` and blank
+    /// lines are skipped. Returns the number of cards loaded.
+    ///
+    /// # Errors
+    ///
+    /// Returns `DeviceError::InvalidDeck` for a line of the wrong length
+    /// or with a non-hex character. Nothing is loaded then.
+    pub fn load_from_hollerith_hex(&mut self, hex: &str) -> Result<usize, DeviceError> {
+        let mut cards = Vec::new();
+        for (index, line) in deck_lines(hex) {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let error = |message: String| DeviceError::InvalidDeck {
+                line: index + 1,
+                message,
+            };
+            if line.len() != CARD_COLUMNS * 4 || !line.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Err(error(format!(
+                    "expected {} hex digits, found {:?}",
+                    CARD_COLUMNS * 4,
+                    line
+                )));
+            }
+
+            let mut card = Card::new();
+            for (column, digits) in card.columns.iter_mut().zip(line.as_bytes().chunks(4)) {
+                let digits = std::str::from_utf8(digits).expect("hex digits are ASCII");
+                *column = u16::from_str_radix(digits, 16).expect("checked hex digits");
+            }
+            cards.push(HopperCard::Columns(card));
+        }
+
+        let count = cards.len();
+        self.hopper.extend(cards);
+        Ok(count)
+    }
+
     /// Check if hopper is empty
     pub fn is_empty(&self) -> bool {
         self.hopper.is_empty()
@@ -129,7 +327,7 @@ impl Device2501 {
         }
 
         // Dequeue the card
-        if let Some(card) = self.hopper.pop_front() {
+        if let Some(card) = self.hopper.pop_front().map(HopperCard::into_card) {
             // Transfer data to memory
             let count = self.read_count.min(80) as usize;
             let addr = self.read_address as usize;
@@ -141,6 +339,7 @@ impl Device2501 {
                 self.last_card = self.hopper.is_empty();
                 self.operation_complete = true;
                 self.read_in_progress = false;
+                self.interrupt_pending = true;
 
                 return true;
             }
@@ -149,8 +348,8 @@ impl Device2501 {
         false
     }
 
-    /// Get device status word
-    fn get_status(&self) -> u16 {
+    /// Status flags, without the hopper count
+    fn status_flags(&self) -> u16 {
         let mut status = 0u16;
 
         // Bit 0x1000: Last card
@@ -192,7 +391,7 @@ impl Default for Device2501 {
 
 impl Device for Device2501 {
     fn device_code(&self) -> u8 {
-        0x09 // 2501 Card Reader
+        StandardDevice::CardReader2501.code()
     }
 
     fn device_name(&self) -> &'static str {
@@ -220,7 +419,7 @@ impl Device for Device2501 {
                     // WCA points to word count in memory
                     let wca = iocc.wca as usize;
                     if wca >= memory.len() {
-                        return Err(CpuError::InvalidAddress(iocc.wca));
+                        return Err(CpuError::MemoryViolation(iocc.wca));
                     }
 
                     // Read word count from memory
@@ -250,10 +449,26 @@ impl Device for Device2501 {
         self.read_in_progress
     }
 
+    fn get_status_word(&self) -> u16 {
+        let shift = STATUS_HOPPER_COUNT.trailing_zeros();
+        let max = (STATUS_HOPPER_COUNT >> shift) as usize;
+        let count = self.hopper.len().min(max) as u16;
+        self.status_flags() | (count << shift)
+    }
+
+    fn device_info(&self) -> DeviceInfo {
+        DeviceInfo {
+            code: self.device_code(),
+            name: self.device_name(),
+            kind: DeviceKind::BlockMode,
+        }
+    }
+
     fn reset(&mut self) {
         self.read_in_progress = false;
         self.operation_complete = false;
         self.last_card = false;
+        self.interrupt_pending = false;
         self.read_address = 0;
         self.read_count = 0;
         // Note: hopper is NOT cleared on reset
@@ -266,4 +481,175 @@ impl Device for Device2501 {
     fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
         self
     }
+
+    fn clone_box(&self) -> Box<dyn Device> {
+        Box::new(self.clone())
+    }
+
+    fn interrupt_request(&self) -> Option<(u8, u16)> {
+        self.interrupt_pending
+            .then_some((INTERRUPT_LEVEL, ILSW_BIT))
+    }
+
+    fn acknowledge_interrupt(&mut self, _level: u8) {
+        self.interrupt_pending = false;
+    }
+
+    /// Status flags, the pending read, then the hopper; text cards are
+    /// saved as the columns they punch
+    fn save_state(&self) -> Vec<u16> {
+        let flags = [
+            self.read_in_progress,
+            self.operation_complete,
+            self.last_card,
+            self.interrupt_pending,
+        ];
+        let flags = flags
+            .iter()
+            .enumerate()
+            .fold(0, |word, (bit, &set)| word | (u16::from(set) << bit));
+        let mut data = vec![flags, self.read_address, self.read_count];
+        let hopper: Vec<Card> = self
+            .hopper
+            .iter()
+            .cloned()
+            .map(HopperCard::into_card)
+            .collect();
+        save_cards(&mut data, hopper.iter());
+        data
+    }
+
+    fn restore_state(&mut self, data: &[u16]) {
+        let word = |index: usize| data.get(index).copied().unwrap_or(0);
+        let flag = |bit: u16| word(0) & (1 << bit) != 0;
+        self.read_in_progress = flag(0);
+        self.operation_complete = flag(1);
+        self.last_card = flag(2);
+        self.interrupt_pending = flag(3);
+        self.read_address = word(1);
+        self.read_count = word(2);
+        let mut cards = data.get(3..).unwrap_or_default();
+        self.hopper = restore_cards(&mut cards)
+            .into_iter()
+            .map(HopperCard::Columns)
+            .collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::card_encoding::HOLLERITH_TABLE;
+
+    #[test]
+    fn test_card_text_round_trip_full_character_set() {
+        let text: String = HOLLERITH_TABLE.iter().map(|&(ch, _)| ch).collect();
+        let card = Card::from_text(&text);
+
+        for (column, &(_, code)) in HOLLERITH_TABLE.iter().enumerate() {
+            assert_eq!(card.columns[column], code);
+        }
+        assert_eq!(card.to_text(), format!("{:<80}", text));
+    }
+
+    #[test]
+    fn test_card_text_truncates_and_marks_unknown_punches() {
+        let card = Card::from_text(&"X".repeat(100));
+        assert_eq!(card.to_text(), "X".repeat(80));
+
+        let card = Card::from_data(&[0xFFF0]);
+        assert!(card.to_text().starts_with("? "));
+    }
+
+    #[test]
+    fn test_load_from_text() {
+        let mut reader = Device2501::new();
+        let deck = format!("// JOB\n~ a comment\n\n{}\n", "9".repeat(CARD_COLUMNS + 10));
+        assert_eq!(reader.load_from_text(&deck), Ok(3));
+        assert_eq!(reader.card_count(), 3);
+
+        let cards: Vec<Card> = reader.hopper.drain(..).map(HopperCard::into_card).collect();
+        assert_eq!(cards[0].to_text().trim_end(), "// JOB");
+        assert_eq!(cards[1], Card::new());
+        assert_eq!(cards[2].to_text(), "9".repeat(CARD_COLUMNS));
+
+        let err = reader.load_from_text("OK\nTAB\there\n").unwrap_err();
+        assert!(
+            matches!(err, DeviceError::InvalidDeck { line: 2, .. }),
+            "{}",
+            err
+        );
+        assert!(reader.is_empty());
+    }
+
+    #[test]
+    fn test_load_from_hollerith_hex() {
+        let mut reader = Device2501::new();
+        let card = Card::from_text("HELLO");
+        let hex: String = card.columns.iter().map(|c| format!("{:04X}", c)).collect();
+        let deck = format!("~ deck\n{}\n\n{}\n", hex, hex.to_lowercase());
+
+        assert_eq!(reader.load_from_hollerith_hex(&deck), Ok(2));
+        assert_eq!(
+            reader.hopper.pop_front().map(HopperCard::into_card),
+            Some(card)
+        );
+
+        let err = reader.load_from_hollerith_hex("0000\n").unwrap_err();
+        assert!(matches!(err, DeviceError::InvalidDeck { line: 1, .. }));
+        let err = reader
+            .load_from_hollerith_hex(&format!("{}\n{}", hex, hex.replace('0', "G")))
+            .unwrap_err();
+        assert!(matches!(err, DeviceError::InvalidDeck { line: 2, .. }));
+        assert_eq!(reader.card_count(), 1);
+    }
+
+    #[test]
+    fn test_text_card_punched_on_read() {
+        let mut reader = Device2501::new();
+        reader.load_text_card("HELLO, 1130");
+        let mut memory = vec![0u16; 0x100];
+        memory[0x10] = (-80i16) as u16;
+
+        let iocc = Iocc {
+            wca: 0x10,
+            device_code: 9,
+            function: DeviceFunction::InitRead,
+            modifiers: 0,
+        };
+        reader.execute_iocc(&iocc, &mut memory).unwrap();
+
+        let read = Card::from_data(&memory[0x11..0x11 + 80]);
+        assert_eq!(read, Card::from_text("HELLO, 1130"));
+        assert_eq!(read.to_text().trim_end(), "HELLO, 1130");
+    }
+
+    #[test]
+    fn test_state_round_trip() {
+        let mut reader = Device2501::new();
+        reader.load_card(Card::from_data(&[1, 2, 3]));
+        reader.load_text_card("TEXT");
+        reader.load_text_card("LAST");
+        let mut memory = vec![0u16; 0x100];
+        memory[0x10] = (-80i16) as u16;
+        let iocc = Iocc {
+            wca: 0x10,
+            device_code: 9,
+            function: DeviceFunction::InitRead,
+            modifiers: 0,
+        };
+        reader.execute_iocc(&iocc, &mut memory).unwrap();
+
+        let mut restored = Device2501::new();
+        restored.restore_state(&reader.save_state());
+        assert_eq!(restored.card_count(), 2);
+        assert_eq!(restored.get_status_word(), reader.get_status_word());
+        assert_eq!(restored.interrupt_request(), reader.interrupt_request());
+
+        restored.execute_iocc(&iocc, &mut memory).unwrap();
+        assert_eq!(memory[0x11..0x11 + 80], Card::from_text("TEXT").columns);
+
+        restored.restore_state(&[]);
+        assert!(restored.is_empty() && restored.interrupt_request().is_none());
+    }
 }
@@ -0,0 +1,140 @@
+//! Console Switches Device
+//!
+//! The 16 console toggle switches (the "Address Entry Switches"). A program
+//! reads them with an XIO Sense, which stores the switch word at the WCA.
+//! The switches are hardware: a program cannot change them, only the
+//! operator (`set`) can.
+//!
+//! Device code: 0
+//!
+//! While attached, the switch word also appears at memory address 0 for
+//! reads (`Cpu::read_memory`), as in some IBM 1130 configurations, and
+//! writes to address 0 are ignored.
+
+use crate::devices::{Device, DeviceFunction, Iocc, StandardDevice};
+use crate::error::CpuError;
+
+/// Memory address the switch word is mapped at
+pub const CONSOLE_SWITCHES_ADDRESS: usize = 0x0000;
+
+/// Console toggle switches
+#[derive(Clone, Default)]
+pub struct ConsoleSwitches {
+    /// Switch positions, bit 0 (MSB) = switch 0
+    switches: u16,
+}
+
+impl ConsoleSwitches {
+    /// Create the device with every switch off
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Current switch word
+    pub fn value(&self) -> u16 {
+        self.switches
+    }
+
+    /// Set the switches, as the operator would
+    pub fn set(&mut self, value: u16) {
+        self.switches = value;
+    }
+}
+
+impl Device for ConsoleSwitches {
+    fn device_code(&self) -> u8 {
+        StandardDevice::ConsoleSwitches.code()
+    }
+
+    fn device_name(&self) -> &'static str {
+        "Console Switches"
+    }
+
+    fn execute_iocc(&mut self, iocc: &Iocc, memory: &mut [u16]) -> Result<(), CpuError> {
+        match iocc.function {
+            DeviceFunction::Sense => {
+                let slot = memory
+                    .get_mut(iocc.wca as usize)
+                    .ok_or(CpuError::MemoryViolation(iocc.wca))?;
+                *slot = self.get_status_word();
+                Ok(())
+            }
+
+            DeviceFunction::Write | DeviceFunction::InitWrite => Err(CpuError::DeviceError(
+                "Console switches are read-only".to_string(),
+            )),
+
+            _ => Err(CpuError::DeviceError(format!(
+                "Console switches: Unsupported function {:?}",
+                iocc.function
+            ))),
+        }
+    }
+
+    fn is_busy(&self) -> bool {
+        false
+    }
+
+    /// The switch settings
+    fn get_status_word(&self) -> u16 {
+        self.switches
+    }
+
+    /// Switches are physical toggles, so a reset leaves them as set
+    fn reset(&mut self) {}
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+
+    fn clone_box(&self) -> Box<dyn Device> {
+        Box::new(self.clone())
+    }
+
+    fn save_state(&self) -> Vec<u16> {
+        vec![self.switches]
+    }
+
+    fn restore_state(&mut self, data: &[u16]) {
+        if let Some(&switches) = data.first() {
+            self.switches = switches;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::devices::test_support::{iocc, WCA};
+
+    const DEVICE: StandardDevice = StandardDevice::ConsoleSwitches;
+
+    #[test]
+    fn test_sense_stores_switches_at_wca() {
+        let mut switches = ConsoleSwitches::new();
+        switches.set(0xA5A5);
+        let mut memory = vec![0u16; 0x200];
+
+        switches
+            .execute_iocc(&iocc(DEVICE, DeviceFunction::Sense, 0), &mut memory)
+            .unwrap();
+        assert_eq!(memory[WCA as usize], 0xA5A5);
+    }
+
+    #[test]
+    fn test_switches_are_read_only() {
+        let mut switches = ConsoleSwitches::new();
+        switches.set(0x0001);
+        let mut memory = vec![0x1234u16; 0x200];
+
+        assert!(switches
+            .execute_iocc(&iocc(DEVICE, DeviceFunction::Write, 0), &mut memory)
+            .is_err());
+        switches.reset();
+        assert_eq!(switches.value(), 0x0001);
+    }
+}
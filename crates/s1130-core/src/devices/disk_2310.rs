@@ -0,0 +1,421 @@
+//! IBM 2310 Disk Storage Drive
+//!
+//! This device emulates an IBM 2310 disk drive with a single cartridge.
+//! It's a block-mode device: one XIO moves a whole sector transfer.
+//!
+//! Device code: 4
+//!
+//! Operations:
+//! - Sense: Write the status word (see `STATUS_*`) to WCA
+//! - Control: Seek - move the heads to the cylinder held in memory at WCA
+//! - InitRead: Transfer sector data into memory
+//! - InitWrite: Transfer memory into a sector
+//!
+//! For reads and writes, memory at WCA holds the word count (at most
+//! `WORDS_PER_SECTOR`) and the data buffer follows it. The sector within
+//! the current cylinder (`surface * SECTORS_PER_TRACK + sector`) is taken
+//! from the disk address register at `DISK_ADDRESS_REGISTER`.
+
+use crate::devices::{Device, DeviceFunction, DeviceInfo, DeviceKind, Iocc, StandardDevice};
+use crate::error::CpuError;
+
+/// Cylinders per cartridge
+pub const CYLINDERS: usize = 512;
+
+/// Recording surfaces per cylinder
+pub const SURFACES: usize = 4;
+
+/// Sectors per track
+pub const SECTORS_PER_TRACK: usize = 8;
+
+/// Words per sector
+pub const WORDS_PER_SECTOR: usize = 321;
+
+/// Sectors addressable within one cylinder
+pub const SECTORS_PER_CYLINDER: usize = SURFACES * SECTORS_PER_TRACK;
+
+/// Words on a full cartridge
+pub const CARTRIDGE_WORDS: usize = CYLINDERS * SECTORS_PER_CYLINDER * WORDS_PER_SECTOR;
+
+/// Memory location of the disk address register (sector within cylinder)
+pub const DISK_ADDRESS_REGISTER: usize = 0x0030;
+
+/// Status bit: the last seek finished
+pub const STATUS_SEEK_COMPLETE: u16 = 0x8000;
+
+/// Status bit: the last transfer was a completed read
+pub const STATUS_READ_COMPLETE: u16 = 0x4000;
+
+/// Status bit: an operation is in progress
+pub const STATUS_BUSY: u16 = 0x2000;
+
+/// Status bits holding the current cylinder
+pub const STATUS_CYLINDER: u16 = 0x03FE;
+
+/// Status bit: the drive is ready
+pub const STATUS_READY: u16 = 0x0001;
+
+/// IBM 2310 Disk Storage Drive
+#[derive(Clone)]
+pub struct Device2310 {
+    /// Cartridge contents, sector after sector
+    cartridge: Vec<u16>,
+
+    /// Cylinder the heads are positioned over
+    cylinder: usize,
+
+    /// Device status flags
+    busy: bool,
+    seek_complete: bool,
+    read_complete: bool,
+}
+
+impl Device2310 {
+    /// Create a drive with a blank cartridge, heads at cylinder 0
+    pub fn new() -> Self {
+        Self {
+            cartridge: vec![0; CARTRIDGE_WORDS],
+            cylinder: 0,
+            busy: false,
+            seek_complete: false,
+            read_complete: false,
+        }
+    }
+
+    /// Cylinder the heads are positioned over
+    pub fn cylinder(&self) -> usize {
+        self.cylinder
+    }
+
+    /// Replace the cartridge contents
+    ///
+    /// Shorter images are padded with zeros; images larger than a
+    /// cartridge are rejected and leave the drive unchanged.
+    pub fn load_image(&mut self, data: &[u16]) -> Result<(), CpuError> {
+        if data.len() > CARTRIDGE_WORDS {
+            return Err(CpuError::DeviceError(format!(
+                "Disk: Image of {} words exceeds cartridge size {}",
+                data.len(),
+                CARTRIDGE_WORDS
+            )));
+        }
+
+        self.cartridge[..data.len()].copy_from_slice(data);
+        self.cartridge[data.len()..].fill(0);
+        Ok(())
+    }
+
+    /// Copy of the full cartridge contents, for persisting
+    pub fn save_image(&self) -> Vec<u16> {
+        self.cartridge.clone()
+    }
+
+    /// Offset of a sector in the current cylinder within the cartridge
+    fn sector_offset(&self, sector: u16) -> Result<usize, CpuError> {
+        let sector = sector as usize;
+        if sector >= SECTORS_PER_CYLINDER {
+            return Err(CpuError::DeviceError(format!(
+                "Disk: Sector {} out of range (0-{})",
+                sector,
+                SECTORS_PER_CYLINDER - 1
+            )));
+        }
+
+        Ok((self.cylinder * SECTORS_PER_CYLINDER + sector) * WORDS_PER_SECTOR)
+    }
+
+    /// Resolve the sector and memory buffer for a read or write
+    ///
+    /// Returns `(cartridge offset, buffer start, word count)`.
+    fn transfer_setup(
+        &self,
+        iocc: &Iocc,
+        memory: &[u16],
+    ) -> Result<(usize, usize, usize), CpuError> {
+        let wca = iocc.wca as usize;
+        let count = *memory
+            .get(wca)
+            .ok_or_else(|| CpuError::DeviceError("Disk: Invalid memory address".to_string()))?
+            as usize;
+
+        if count > WORDS_PER_SECTOR {
+            return Err(CpuError::DeviceError(format!(
+                "Disk: Word count {} exceeds sector size {}",
+                count, WORDS_PER_SECTOR
+            )));
+        }
+
+        let buffer = wca + 1;
+        if buffer + count > memory.len() {
+            return Err(CpuError::DeviceError(
+                "Disk: Transfer buffer exceeds memory".to_string(),
+            ));
+        }
+
+        let sector = memory.get(DISK_ADDRESS_REGISTER).copied().unwrap_or(0);
+        Ok((self.sector_offset(sector)?, buffer, count))
+    }
+}
+
+impl Default for Device2310 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Device for Device2310 {
+    fn device_code(&self) -> u8 {
+        StandardDevice::Disk2310.code()
+    }
+
+    fn device_name(&self) -> &'static str {
+        "2310 Disk Drive"
+    }
+
+    fn execute_iocc(&mut self, iocc: &Iocc, memory: &mut [u16]) -> Result<(), CpuError> {
+        match iocc.function {
+            DeviceFunction::Sense => {
+                if (iocc.wca as usize) < memory.len() {
+                    memory[iocc.wca as usize] = self.get_status_word();
+                }
+                Ok(())
+            }
+
+            DeviceFunction::Control => {
+                // Seek to the absolute cylinder stored at WCA
+                let target = *memory.get(iocc.wca as usize).ok_or_else(|| {
+                    CpuError::DeviceError("Disk: Invalid memory address".to_string())
+                })? as usize;
+
+                if target >= CYLINDERS {
+                    return Err(CpuError::DeviceError(format!(
+                        "Disk: Cylinder {} out of range (0-{})",
+                        target,
+                        CYLINDERS - 1
+                    )));
+                }
+                self.cylinder = target;
+                self.seek_complete = true;
+                self.read_complete = false;
+                Ok(())
+            }
+
+            DeviceFunction::InitRead => {
+                let (offset, buffer, count) = self.transfer_setup(iocc, memory)?;
+                memory[buffer..buffer + count]
+                    .copy_from_slice(&self.cartridge[offset..offset + count]);
+                self.read_complete = true;
+                Ok(())
+            }
+
+            DeviceFunction::InitWrite => {
+                let (offset, buffer, count) = self.transfer_setup(iocc, memory)?;
+                self.cartridge[offset..offset + count]
+                    .copy_from_slice(&memory[buffer..buffer + count]);
+                self.read_complete = false;
+                Ok(())
+            }
+
+            _ => Err(CpuError::DeviceError(format!(
+                "Disk: Unsupported function {:?}",
+                iocc.function
+            ))),
+        }
+    }
+
+    fn is_busy(&self) -> bool {
+        self.busy
+    }
+
+    fn get_status_word(&self) -> u16 {
+        let mut status = STATUS_READY | ((self.cylinder as u16) << 1 & STATUS_CYLINDER);
+        if self.seek_complete {
+            status |= STATUS_SEEK_COMPLETE;
+        }
+        if self.read_complete {
+            status |= STATUS_READ_COMPLETE;
+        }
+        if self.busy {
+            status |= STATUS_BUSY;
+        }
+        status
+    }
+
+    fn device_info(&self) -> DeviceInfo {
+        DeviceInfo {
+            code: self.device_code(),
+            name: self.device_name(),
+            kind: DeviceKind::BlockMode,
+        }
+    }
+
+    fn reset(&mut self) {
+        // The cartridge keeps its data; only the heads return home
+        self.cylinder = 0;
+        self.busy = false;
+        self.seek_complete = false;
+        self.read_complete = false;
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+
+    fn clone_box(&self) -> Box<dyn Device> {
+        Box::new(self.clone())
+    }
+
+    /// Head position only; cartridge contents persist via `save_image`
+    fn save_state(&self) -> Vec<u16> {
+        vec![self.cylinder as u16]
+    }
+
+    fn restore_state(&mut self, data: &[u16]) {
+        self.cylinder = data.first().map_or(0, |&c| c as usize).min(CYLINDERS - 1);
+        self.busy = false;
+        self.seek_complete = false;
+        self.read_complete = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::devices::test_support::{iocc, WCA};
+
+    const DEVICE: StandardDevice = StandardDevice::Disk2310;
+
+    fn seek(disk: &mut Device2310, memory: &mut [u16], cylinder: u16) -> Result<(), CpuError> {
+        memory[WCA as usize] = cylinder;
+        disk.execute_iocc(&iocc(DEVICE, DeviceFunction::Control, 0), memory)
+    }
+
+    fn transfer(
+        disk: &mut Device2310,
+        memory: &mut [u16],
+        function: DeviceFunction,
+        sector: u16,
+        count: u16,
+    ) -> Result<(), CpuError> {
+        memory[DISK_ADDRESS_REGISTER] = sector;
+        memory[WCA as usize] = count;
+        disk.execute_iocc(&iocc(DEVICE, function, 0), memory)
+    }
+
+    #[test]
+    fn test_disk_creation() {
+        let disk = Device2310::new();
+        assert_eq!(disk.device_code(), 4);
+        assert_eq!(disk.device_name(), "2310 Disk Drive");
+        assert_eq!(disk.cylinder(), 0);
+        assert_eq!(disk.save_image().len(), CARTRIDGE_WORDS);
+    }
+
+    #[test]
+    fn test_seek_and_sense() {
+        let mut disk = Device2310::new();
+        let mut memory = vec![0u16; 0x1000];
+
+        seek(&mut disk, &mut memory, 100).unwrap();
+        assert_eq!(disk.cylinder(), 100);
+
+        disk.execute_iocc(&iocc(DEVICE, DeviceFunction::Sense, 0), &mut memory)
+            .unwrap();
+        assert_eq!(
+            memory[WCA as usize],
+            STATUS_SEEK_COMPLETE | (100 << 1) | STATUS_READY
+        );
+
+        assert!(seek(&mut disk, &mut memory, CYLINDERS as u16).is_err());
+        assert_eq!(disk.cylinder(), 100);
+    }
+
+    #[test]
+    fn test_sequential_read() {
+        let mut image = vec![0u16; 4 * WORDS_PER_SECTOR];
+        for sector in 0..4 {
+            image[sector * WORDS_PER_SECTOR] = 0x1000 + sector as u16;
+        }
+        let mut disk = Device2310::new();
+        disk.load_image(&image).unwrap();
+        let mut memory = vec![0u16; 0x1000];
+
+        for sector in 0..4 {
+            transfer(&mut disk, &mut memory, DeviceFunction::InitRead, sector, 2).unwrap();
+            assert_eq!(memory[WCA as usize + 1], 0x1000 + sector);
+            assert_eq!(memory[WCA as usize + 2], 0);
+        }
+    }
+
+    #[test]
+    fn test_status_word_tracks_completion() {
+        let mut disk = Device2310::new();
+        let mut memory = vec![0u16; 0x1000];
+        assert_eq!(disk.get_status_word(), STATUS_READY);
+
+        transfer(&mut disk, &mut memory, DeviceFunction::InitRead, 0, 1).unwrap();
+        assert_eq!(disk.get_status_word(), STATUS_READ_COMPLETE | STATUS_READY);
+
+        seek(&mut disk, &mut memory, 3).unwrap();
+        assert_eq!(
+            disk.get_status_word(),
+            STATUS_SEEK_COMPLETE | (3 << 1) | STATUS_READY
+        );
+
+        disk.reset();
+        assert_eq!(disk.get_status_word(), STATUS_READY);
+    }
+
+    #[test]
+    fn test_write_then_read_on_other_cylinder() {
+        let mut disk = Device2310::new();
+        let mut memory = vec![0u16; 0x1000];
+
+        seek(&mut disk, &mut memory, 7).unwrap();
+        memory[WCA as usize + 1..WCA as usize + 4].copy_from_slice(&[1, 2, 3]);
+        transfer(&mut disk, &mut memory, DeviceFunction::InitWrite, 31, 3).unwrap();
+
+        memory[WCA as usize + 1..WCA as usize + 4].fill(0);
+        transfer(&mut disk, &mut memory, DeviceFunction::InitRead, 31, 3).unwrap();
+        assert_eq!(memory[WCA as usize + 1..WCA as usize + 4], [1, 2, 3]);
+
+        // The data landed in cylinder 7, sector 31 of the image
+        let offset = (7 * SECTORS_PER_CYLINDER + 31) * WORDS_PER_SECTOR;
+        assert_eq!(disk.save_image()[offset..offset + 3], [1, 2, 3]);
+
+        // Cylinder 0 is untouched
+        seek(&mut disk, &mut memory, 0).unwrap();
+        transfer(&mut disk, &mut memory, DeviceFunction::InitRead, 31, 3).unwrap();
+        assert_eq!(memory[WCA as usize + 1..WCA as usize + 4], [0, 0, 0]);
+    }
+
+    #[test]
+    fn test_out_of_bounds_access() {
+        let mut disk = Device2310::new();
+        let mut memory = vec![0u16; 0x1000];
+
+        let sector = SECTORS_PER_CYLINDER as u16;
+        assert!(transfer(&mut disk, &mut memory, DeviceFunction::InitRead, sector, 1).is_err());
+
+        let count = WORDS_PER_SECTOR as u16 + 1;
+        assert!(transfer(&mut disk, &mut memory, DeviceFunction::InitWrite, 0, count).is_err());
+
+        assert!(disk.load_image(&vec![0; CARTRIDGE_WORDS + 1]).is_err());
+    }
+
+    #[test]
+    fn test_state_keeps_cylinder() {
+        let mut disk = Device2310::new();
+        let mut memory = vec![0u16; 0x1000];
+        seek(&mut disk, &mut memory, 42).unwrap();
+
+        let state = disk.save_state();
+        let mut restored = Device2310::new();
+        restored.restore_state(&state);
+        assert_eq!(restored.cylinder(), 42);
+    }
+}
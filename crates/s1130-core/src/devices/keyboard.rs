@@ -8,22 +8,34 @@
 //! Operations:
 //! - Sense: Check if a key is ready
 //! - Read: Read a character from keyboard buffer
+//! - Control: Select the keyboard; keystrokes then request an interrupt
+//!   on level 4 (ILSW bit 0x4000) until the CPU acknowledges it
 
-use crate::devices::{Device, DeviceFunction, Iocc};
+use crate::devices::{Device, DeviceFunction, Iocc, StandardDevice};
 use crate::error::CpuError;
 use std::collections::VecDeque;
 
+/// Interrupt level of the keystroke interrupt
+pub const INTERRUPT_LEVEL: u8 = 4;
+/// ILSW bit identifying the keyboard/console on level 4
+pub const ILSW_BIT: u16 = 0x4000;
+
 /// Console Keyboard Device
 ///
 /// This is a character-mode input device. Programs use XIO to:
 /// 1. Sense if a character is ready
 /// 2. Read characters one at a time
+#[derive(Clone)]
 pub struct DeviceConsoleKeyboard {
     /// Input buffer (characters waiting to be read)
     input_buffer: VecDeque<u16>,
 
     /// Device status flags
     busy: bool,
+
+    /// Keystrokes request interrupts once selected by a Control XIO
+    selected: bool,
+    interrupt_pending: bool,
 }
 
 impl DeviceConsoleKeyboard {
@@ -32,6 +44,8 @@ impl DeviceConsoleKeyboard {
         Self {
             input_buffer: VecDeque::new(),
             busy: false,
+            selected: false,
+            interrupt_pending: false,
         }
     }
 
@@ -44,6 +58,7 @@ impl DeviceConsoleKeyboard {
     /// * `ch` - The character to add (as a 16-bit word, typically ASCII in low byte)
     pub fn type_char(&mut self, ch: u16) {
         self.input_buffer.push_back(ch);
+        self.interrupt_pending |= self.selected;
     }
 
     /// Type a string of characters
@@ -54,7 +69,7 @@ impl DeviceConsoleKeyboard {
     /// * `s` - The string to type
     pub fn type_string(&mut self, s: &str) {
         for ch in s.chars() {
-            self.input_buffer.push_back(ch as u16);
+            self.type_char(ch as u16);
         }
     }
 
@@ -63,6 +78,11 @@ impl DeviceConsoleKeyboard {
         !self.input_buffer.is_empty()
     }
 
+    /// Number of characters waiting in the input buffer
+    pub fn input_len(&self) -> usize {
+        self.input_buffer.len()
+    }
+
     /// Read a character from the buffer
     fn read_char(&mut self) -> Option<u16> {
         self.input_buffer.pop_front()
@@ -77,7 +97,7 @@ impl Default for DeviceConsoleKeyboard {
 
 impl Device for DeviceConsoleKeyboard {
     fn device_code(&self) -> u8 {
-        1 // Console keyboard
+        StandardDevice::ConsoleKeyboard.code()
     }
 
     fn device_name(&self) -> &'static str {
@@ -88,10 +108,8 @@ impl Device for DeviceConsoleKeyboard {
         match iocc.function {
             DeviceFunction::Sense => {
                 // Sense operation: return status in WCA location
-                // Bit 15 (LSB) = 1 if character ready
-                let status = if self.has_char() { 1 } else { 0 };
                 if (iocc.wca as usize) < memory.len() {
-                    memory[iocc.wca as usize] = status;
+                    memory[iocc.wca as usize] = self.get_status_word();
                 }
                 Ok(())
             }
@@ -110,6 +128,11 @@ impl Device for DeviceConsoleKeyboard {
                 }
             }
 
+            DeviceFunction::Control => {
+                self.selected = true;
+                Ok(())
+            }
+
             _ => Err(CpuError::DeviceError(format!(
                 "Keyboard: Unsupported function {:?}",
                 iocc.function
@@ -121,9 +144,16 @@ impl Device for DeviceConsoleKeyboard {
         self.busy
     }
 
+    /// Bit 15 (LSB) = 1 if a character is ready
+    fn get_status_word(&self) -> u16 {
+        u16::from(self.has_char())
+    }
+
     fn reset(&mut self) {
         self.input_buffer.clear();
         self.busy = false;
+        self.selected = false;
+        self.interrupt_pending = false;
     }
 
     fn as_any(&self) -> &dyn std::any::Any {
@@ -133,6 +163,28 @@ impl Device for DeviceConsoleKeyboard {
     fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
         self
     }
+
+    fn clone_box(&self) -> Box<dyn Device> {
+        Box::new(self.clone())
+    }
+
+    fn save_state(&self) -> Vec<u16> {
+        self.input_buffer.iter().copied().collect()
+    }
+
+    fn restore_state(&mut self, data: &[u16]) {
+        self.input_buffer = data.iter().copied().collect();
+        self.busy = false;
+    }
+
+    fn interrupt_request(&self) -> Option<(u8, u16)> {
+        self.interrupt_pending
+            .then_some((INTERRUPT_LEVEL, ILSW_BIT))
+    }
+
+    fn acknowledge_interrupt(&mut self, _level: u8) {
+        self.interrupt_pending = false;
+    }
 }
 
 #[cfg(test)]
@@ -202,4 +254,26 @@ mod tests {
         assert_eq!(memory[50], b'A' as u16);
         assert!(!kb.has_char()); // Buffer should be empty now
     }
+
+    #[test]
+    fn test_selected_keystroke_requests_interrupt() {
+        let mut kb = DeviceConsoleKeyboard::new();
+        let mut memory = vec![0u16; 100];
+
+        kb.type_char(b'A' as u16);
+        assert_eq!(kb.interrupt_request(), None);
+
+        let iocc = Iocc {
+            wca: 50,
+            device_code: 1,
+            function: DeviceFunction::Control,
+            modifiers: 0,
+        };
+        kb.execute_iocc(&iocc, &mut memory).unwrap();
+        kb.type_char(b'B' as u16);
+        assert_eq!(kb.interrupt_request(), Some((4, ILSW_BIT)));
+
+        kb.acknowledge_interrupt(4);
+        assert_eq!(kb.interrupt_request(), None);
+    }
 }
@@ -0,0 +1,303 @@
+//! Device Manager
+//!
+//! Holds the attached I/O devices in a table indexed by device code and
+//! routes XIO commands to them. Device codes are 5 bits, so the table has
+//! one slot for each of the 32 possible codes.
+
+use crate::devices::{Device, Iocc};
+use crate::error::{CpuError, DeviceError};
+use std::sync::Arc;
+
+/// Number of device codes (5-bit field in the IOCC)
+pub const DEVICE_SLOTS: usize = 32;
+
+/// Called with the device code after `replace_device` swaps a slot
+pub type ReplacementCallback = dyn Fn(u8) + Send + Sync;
+
+/// Attached devices, indexed by device code
+#[derive(Clone, Default)]
+pub struct DeviceManager {
+    devices: [Option<Box<dyn Device>>; DEVICE_SLOTS],
+
+    /// Shared by clones, so a cloned machine reports replacements too
+    replacement_callback: Option<Arc<ReplacementCallback>>,
+}
+
+impl DeviceManager {
+    /// Create a manager with no devices attached
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Attach a device at its own device code
+    ///
+    /// Fails if the slot is already occupied or the code is outside 0-31.
+    pub fn attach(&mut self, device: Box<dyn Device>) -> Result<(), DeviceError> {
+        let device_code = device.device_code();
+        let slot = self
+            .devices
+            .get_mut(device_code as usize)
+            .ok_or(DeviceError::InvalidCode(device_code))?;
+        if slot.is_some() {
+            return Err(DeviceError::CodeInUse(device_code));
+        }
+        *slot = Some(device);
+        Ok(())
+    }
+
+    /// Remove and return the device at `code`
+    pub fn detach(&mut self, code: u8) -> Option<Box<dyn Device>> {
+        self.devices.get_mut(code as usize)?.take()
+    }
+
+    /// Swap the device at `code` for `new_device`, returning the old one
+    ///
+    /// Unlike `detach` followed by `attach`, the slot is never empty, so
+    /// this is safe between steps of a running program. Whatever the old
+    /// device had in flight (a pending read, an unacknowledged interrupt)
+    /// goes with it; the replacement callback, if set, is told the code so
+    /// the host can raise an interrupt for the program to notice.
+    ///
+    /// An empty slot is filled and `None` returned. Fails, leaving the
+    /// slot alone, if the code is outside 0-31 or `new_device` reports a
+    /// different device code.
+    pub fn replace_device(
+        &mut self,
+        code: u8,
+        new_device: Box<dyn Device>,
+    ) -> Result<Option<Box<dyn Device>>, DeviceError> {
+        let slot = self
+            .devices
+            .get_mut(code as usize)
+            .ok_or(DeviceError::InvalidCode(code))?;
+        let found = new_device.device_code();
+        if found != code {
+            return Err(DeviceError::CodeMismatch { slot: code, found });
+        }
+        let old_device = slot.replace(new_device);
+        if let Some(callback) = &self.replacement_callback {
+            callback(code);
+        }
+        Ok(old_device)
+    }
+
+    /// Set the function called after each `replace_device`
+    pub fn set_replacement_callback(&mut self, callback: Option<Box<ReplacementCallback>>) {
+        self.replacement_callback = callback.map(Arc::from);
+    }
+
+    /// Get the device at `code`
+    pub fn get(&self, code: u8) -> Option<&dyn Device> {
+        self.devices.get(code as usize)?.as_deref()
+    }
+
+    /// Get the device at `code` mutably
+    pub fn get_mut(&mut self, code: u8) -> Option<&mut Box<dyn Device>> {
+        self.devices.get_mut(code as usize)?.as_mut()
+    }
+
+    /// Get the device at `code` as its concrete type
+    ///
+    /// Returns `None` if nothing is attached there or it is not a `T`.
+    pub fn get_device_as<T: Device + 'static>(&self, code: u8) -> Option<&T> {
+        self.get(code)?.as_any().downcast_ref::<T>()
+    }
+
+    /// Get the device at `code` mutably as its concrete type
+    pub fn get_device_as_mut<T: Device + 'static>(&mut self, code: u8) -> Option<&mut T> {
+        self.get_mut(code)?.as_any_mut().downcast_mut::<T>()
+    }
+
+    /// Check whether a device is attached at `code`
+    pub fn contains(&self, code: u8) -> bool {
+        self.get(code).is_some()
+    }
+
+    /// Attached devices in device code order
+    pub fn iter(&self) -> impl Iterator<Item = (u8, &dyn Device)> {
+        self.devices
+            .iter()
+            .enumerate()
+            .filter_map(|(code, slot)| slot.as_deref().map(|device| (code as u8, device)))
+    }
+
+    /// Attached devices in device code order, mutably
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (u8, &mut Box<dyn Device>)> {
+        self.devices
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(code, slot)| slot.as_mut().map(|device| (code as u8, device)))
+    }
+
+    /// Decode the 2-word IOCC at `address`
+    ///
+    /// Word 0 is the WCA (Word Count Address); word 1 holds the device
+    /// code, function and modifiers.
+    pub fn decode_iocc(memory: &[u16], address: u16) -> Result<Iocc, CpuError> {
+        let word = |offset: u16| {
+            let addr = address.wrapping_add(offset);
+            memory
+                .get(addr as usize)
+                .copied()
+                .ok_or(CpuError::MemoryViolation(addr))
+        };
+        Iocc::decode(word(0)?, word(1)?)
+    }
+
+    /// Execute an IOCC on the device it addresses
+    ///
+    /// Returns `CpuError::InvalidDevice` if nothing is attached at the code.
+    pub fn execute_xio(&mut self, iocc: &Iocc, memory: &mut [u16]) -> Result<(), CpuError> {
+        let device = self
+            .get_mut(iocc.device_code)
+            .ok_or(CpuError::InvalidDevice(iocc.device_code))?;
+        device.execute_iocc(iocc, memory)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::devices::{DeviceConsolePrinter, DeviceFunction, StandardDevice};
+
+    fn printer_code() -> u8 {
+        StandardDevice::ConsolePrinter.code()
+    }
+
+    #[test]
+    fn test_attach_and_detach() {
+        let mut manager = DeviceManager::new();
+        manager
+            .attach(Box::new(DeviceConsolePrinter::new()))
+            .unwrap();
+        assert!(manager.contains(printer_code()));
+        assert_eq!(manager.iter().count(), 1);
+
+        let device = manager.detach(printer_code()).unwrap();
+        assert_eq!(device.device_code(), printer_code());
+        assert!(!manager.contains(printer_code()));
+        assert!(manager.detach(printer_code()).is_none());
+    }
+
+    #[test]
+    fn test_get_device_as() {
+        let mut manager = DeviceManager::new();
+        manager
+            .attach(Box::new(DeviceConsolePrinter::new()))
+            .unwrap();
+
+        assert!(manager
+            .get_device_as::<DeviceConsolePrinter>(printer_code())
+            .is_some());
+        assert!(manager
+            .get_device_as::<crate::devices::DeviceConsoleKeyboard>(printer_code())
+            .is_none());
+        assert!(manager.get_device_as::<DeviceConsolePrinter>(1).is_none());
+        manager
+            .get_device_as_mut::<DeviceConsolePrinter>(printer_code())
+            .unwrap()
+            .clear_output();
+    }
+
+    #[test]
+    fn test_replace_device_returns_old_and_notifies() {
+        use std::sync::Mutex;
+
+        let replaced = Arc::new(Mutex::new(Vec::new()));
+        let mut manager = DeviceManager::new();
+        let log = Arc::clone(&replaced);
+        manager.set_replacement_callback(Some(Box::new(move |code| {
+            log.lock().unwrap().push(code);
+        })));
+
+        // An empty slot is simply filled
+        assert!(manager
+            .replace_device(printer_code(), Box::new(DeviceConsolePrinter::new()))
+            .unwrap()
+            .is_none());
+        let old = manager
+            .replace_device(printer_code(), Box::new(DeviceConsolePrinter::new()))
+            .unwrap()
+            .unwrap();
+        assert_eq!(old.device_code(), printer_code());
+        assert!(manager.contains(printer_code()));
+        assert_eq!(*replaced.lock().unwrap(), [printer_code(), printer_code()]);
+
+        // Clones share the callback
+        let mut clone = manager.clone();
+        clone
+            .replace_device(printer_code(), Box::new(DeviceConsolePrinter::new()))
+            .unwrap();
+        assert_eq!(replaced.lock().unwrap().len(), 3);
+
+        // Neither a bad code nor a mismatched device touches a slot
+        assert_eq!(
+            manager
+                .replace_device(32, Box::new(DeviceConsolePrinter::new()))
+                .err(),
+            Some(DeviceError::InvalidCode(32))
+        );
+        assert_eq!(
+            manager
+                .replace_device(1, Box::new(DeviceConsolePrinter::new()))
+                .err(),
+            Some(DeviceError::CodeMismatch {
+                slot: 1,
+                found: printer_code()
+            })
+        );
+        assert!(!manager.contains(1));
+        assert_eq!(replaced.lock().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_attach_occupied_slot_fails() {
+        let mut manager = DeviceManager::new();
+        manager
+            .attach(Box::new(DeviceConsolePrinter::new()))
+            .unwrap();
+        assert_eq!(
+            manager.attach(Box::new(DeviceConsolePrinter::new())).err(),
+            Some(DeviceError::CodeInUse(printer_code()))
+        );
+    }
+
+    #[test]
+    fn test_execute_xio_dispatches_by_code() {
+        let mut manager = DeviceManager::new();
+        manager
+            .attach(Box::new(DeviceConsolePrinter::new()))
+            .unwrap();
+        let mut memory = vec![0u16; 0x100];
+
+        let (wca, word) = Iocc::build(
+            StandardDevice::ConsolePrinter,
+            DeviceFunction::Sense,
+            0,
+            0x20,
+        );
+        memory[0x10] = wca;
+        memory[0x11] = word;
+        let iocc = DeviceManager::decode_iocc(&memory, 0x10).unwrap();
+        assert_eq!(iocc.device_code, printer_code());
+        manager.execute_xio(&iocc, &mut memory).unwrap();
+
+        let missing = Iocc {
+            device_code: 30,
+            ..iocc
+        };
+        assert_eq!(
+            manager.execute_xio(&missing, &mut memory),
+            Err(CpuError::InvalidDevice(30))
+        );
+    }
+
+    #[test]
+    fn test_decode_iocc_out_of_bounds() {
+        let memory = vec![0u16; 0x10];
+        assert_eq!(
+            DeviceManager::decode_iocc(&memory, 0x0F),
+            Err(CpuError::MemoryViolation(0x10))
+        );
+    }
+}
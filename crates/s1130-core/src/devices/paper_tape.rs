@@ -0,0 +1,338 @@
+//! Paper Tape Reader and Punch
+//!
+//! This device emulates the 1130's paper tape reader and punch, which
+//! loaded programs before disk drives were common. Tape holds 8-bit
+//! frames; each frame moves to or from the low byte of one memory word.
+//!
+//! Device code: 7
+//!
+//! Operations:
+//! - Sense: Return the status word in WCA location (see `STATUS_*`)
+//! - InitRead: Read frames from the tape; memory at WCA holds the frame
+//!   count and the frames are stored after it. A read that runs off the
+//!   end of the tape stops there, leaving the remaining words unchanged
+//! - InitWrite: Punch the low byte of each word following the count at
+//!   WCA onto the output tape
+//!
+//! Transfers complete within the XIO, so the device is never busy.
+
+use crate::devices::{Device, DeviceFunction, DeviceInfo, DeviceKind, Iocc, StandardDevice};
+use crate::error::CpuError;
+
+/// Status bit: no frames are left to read
+pub const STATUS_END_OF_TAPE: u16 = 0x0001;
+
+/// Append a 2-word frame count (high word first), then one frame per word
+fn save_frames(data: &mut Vec<u16>, frames: &[u8]) {
+    let count = frames.len() as u32;
+    data.extend_from_slice(&[(count >> 16) as u16, count as u16]);
+    data.extend(frames.iter().map(|&frame| u16::from(frame)));
+}
+
+/// Take frames written by `save_frames` from the front of `data`
+///
+/// Stops early if `data` runs out.
+fn restore_frames(data: &mut &[u16]) -> Vec<u8> {
+    let Some((count, rest)) = data.split_first_chunk::<2>() else {
+        *data = &[];
+        return Vec::new();
+    };
+    let count = (((count[0] as usize) << 16) | count[1] as usize).min(rest.len());
+    let frames = rest[..count].iter().map(|&word| word as u8).collect();
+    *data = &rest[count..];
+    frames
+}
+
+/// Paper Tape Reader and Punch Device
+#[derive(Debug, Clone, Default)]
+pub struct DevicePaperTape {
+    /// Tape mounted in the reader
+    tape: Vec<u8>,
+
+    /// Index of the next frame to read
+    position: usize,
+
+    /// Frames punched so far
+    punched: Vec<u8>,
+}
+
+impl DevicePaperTape {
+    /// Create a reader with no tape mounted and an empty punch
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a reader with a tape image mounted, e.g. the bytes of a
+    /// binary file
+    pub fn from_binary_file_bytes(data: &[u8]) -> Self {
+        let mut device = Self::new();
+        device.load_tape(data.to_vec());
+        device
+    }
+
+    /// Mount a tape, replacing the current one, and rewind it
+    pub fn load_tape(&mut self, data: Vec<u8>) {
+        self.tape = data;
+        self.position = 0;
+    }
+
+    /// Move the tape back to its first frame
+    pub fn rewind(&mut self) {
+        self.position = 0;
+    }
+
+    /// Index of the next frame to read
+    pub fn get_position(&self) -> usize {
+        self.position
+    }
+
+    /// Check whether every frame has been read
+    pub fn is_at_end(&self) -> bool {
+        self.position >= self.tape.len()
+    }
+
+    /// Frames punched so far, in order
+    pub fn get_punched_tape(&self) -> &[u8] {
+        &self.punched
+    }
+
+    /// Frame count and buffer start for a transfer at WCA
+    fn transfer_setup(iocc: &Iocc, memory: &[u16]) -> Result<(usize, usize), CpuError> {
+        let wca = iocc.wca as usize;
+        let count = *memory.get(wca).ok_or(CpuError::MemoryViolation(iocc.wca))? as usize;
+
+        let buffer = wca + 1;
+        if buffer + count > memory.len() {
+            return Err(CpuError::MemoryViolation(iocc.wca));
+        }
+        Ok((buffer, count))
+    }
+
+    /// Read frames into `words` until it is full or the tape ends
+    fn read_frames(&mut self, words: &mut [u16]) {
+        let frames = &self.tape[self.position.min(self.tape.len())..];
+        for (word, &frame) in words.iter_mut().zip(frames) {
+            *word = u16::from(frame);
+        }
+        let read = words.len().min(frames.len());
+        self.position += read;
+    }
+}
+
+impl Device for DevicePaperTape {
+    fn device_code(&self) -> u8 {
+        StandardDevice::PaperTape.code()
+    }
+
+    fn device_name(&self) -> &'static str {
+        "Paper Tape Reader/Punch"
+    }
+
+    fn execute_iocc(&mut self, iocc: &Iocc, memory: &mut [u16]) -> Result<(), CpuError> {
+        match iocc.function {
+            DeviceFunction::Sense => {
+                if (iocc.wca as usize) < memory.len() {
+                    memory[iocc.wca as usize] = self.get_status_word();
+                }
+                Ok(())
+            }
+
+            DeviceFunction::InitRead => {
+                let (buffer, count) = Self::transfer_setup(iocc, memory)?;
+                if count > 0 && self.is_at_end() {
+                    return Err(CpuError::DeviceError("Paper tape: End of tape".to_string()));
+                }
+                self.read_frames(&mut memory[buffer..buffer + count]);
+                Ok(())
+            }
+
+            DeviceFunction::InitWrite => {
+                let (buffer, count) = Self::transfer_setup(iocc, memory)?;
+                self.punched.extend(
+                    memory[buffer..buffer + count]
+                        .iter()
+                        .map(|&word| (word & 0x00FF) as u8),
+                );
+                Ok(())
+            }
+
+            _ => Err(CpuError::DeviceError(format!(
+                "Paper tape: Unsupported function {:?}",
+                iocc.function
+            ))),
+        }
+    }
+
+    fn is_busy(&self) -> bool {
+        false
+    }
+
+    fn get_status_word(&self) -> u16 {
+        if self.is_at_end() {
+            STATUS_END_OF_TAPE
+        } else {
+            0
+        }
+    }
+
+    fn device_info(&self) -> DeviceInfo {
+        DeviceInfo {
+            code: self.device_code(),
+            name: self.device_name(),
+            kind: DeviceKind::BlockMode,
+        }
+    }
+
+    fn reset(&mut self) {
+        // The tape stays where it is and there is no status to clear
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+
+    fn clone_box(&self) -> Box<dyn Device> {
+        Box::new(self.clone())
+    }
+
+    /// The read position (2 words, high first), then the mounted tape and
+    /// the punched frames
+    fn save_state(&self) -> Vec<u16> {
+        let position = self.position as u32;
+        let mut data = vec![(position >> 16) as u16, position as u16];
+        save_frames(&mut data, &self.tape);
+        save_frames(&mut data, &self.punched);
+        data
+    }
+
+    fn restore_state(&mut self, data: &[u16]) {
+        let position = match data {
+            [high, low, ..] => ((*high as usize) << 16) | *low as usize,
+            _ => 0,
+        };
+        let mut rest = data.get(2..).unwrap_or_default();
+        self.tape = restore_frames(&mut rest);
+        self.punched = restore_frames(&mut rest);
+        self.position = position.min(self.tape.len());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::devices::test_support::{iocc, WCA};
+
+    const DEVICE: StandardDevice = StandardDevice::PaperTape;
+
+    fn sense(device: &mut DevicePaperTape, memory: &mut [u16]) -> u16 {
+        device
+            .execute_iocc(&iocc(DEVICE, DeviceFunction::Sense, 0), memory)
+            .unwrap();
+        memory[WCA as usize]
+    }
+
+    #[test]
+    fn test_read_frames_into_low_bytes() {
+        let mut device = DevicePaperTape::from_binary_file_bytes(&[0x12, 0xFF, 0x00, 0x7F]);
+        let mut memory = vec![0xAA00u16; 0x200];
+
+        memory[WCA as usize] = 3;
+        device
+            .execute_iocc(&iocc(DEVICE, DeviceFunction::InitRead, 0), &mut memory)
+            .unwrap();
+        assert_eq!(
+            &memory[WCA as usize + 1..WCA as usize + 4],
+            [0x0012, 0x00FF, 0x0000]
+        );
+        assert_eq!(device.get_position(), 3);
+        assert_eq!(sense(&mut device, &mut memory), 0);
+    }
+
+    #[test]
+    fn test_end_of_tape() {
+        let mut device = DevicePaperTape::from_binary_file_bytes(&[1, 2]);
+        let mut memory = vec![0u16; 0x200];
+
+        // A read past the end stops there
+        memory[WCA as usize] = 4;
+        memory[WCA as usize + 3] = 0xBEEF;
+        device
+            .execute_iocc(&iocc(DEVICE, DeviceFunction::InitRead, 0), &mut memory)
+            .unwrap();
+        assert_eq!(&memory[WCA as usize + 1..WCA as usize + 4], [1, 2, 0xBEEF]);
+        assert!(device.is_at_end());
+        assert_eq!(sense(&mut device, &mut memory), STATUS_END_OF_TAPE);
+
+        memory[WCA as usize] = 1;
+        let err = device
+            .execute_iocc(&iocc(DEVICE, DeviceFunction::InitRead, 0), &mut memory)
+            .unwrap_err();
+        assert!(err.to_string().contains("End of tape"), "{}", err);
+        assert_eq!(DevicePaperTape::new().get_status_word(), STATUS_END_OF_TAPE);
+    }
+
+    #[test]
+    fn test_rewind_rereads_tape() {
+        let mut device = DevicePaperTape::from_binary_file_bytes(b"TAPE");
+        let mut memory = vec![0u16; 0x200];
+
+        memory[WCA as usize] = 4;
+        device
+            .execute_iocc(&iocc(DEVICE, DeviceFunction::InitRead, 0), &mut memory)
+            .unwrap();
+        assert!(device.is_at_end());
+
+        device.rewind();
+        assert_eq!(device.get_position(), 0);
+        memory[WCA as usize] = 2;
+        device
+            .execute_iocc(&iocc(DEVICE, DeviceFunction::InitRead, 0), &mut memory)
+            .unwrap();
+        assert_eq!(&memory[WCA as usize + 1..WCA as usize + 3], [0x54, 0x41]);
+        assert_eq!(device.get_position(), 2);
+    }
+
+    #[test]
+    fn test_punch_appends_low_bytes() {
+        let mut device = DevicePaperTape::new();
+        let mut memory = vec![0u16; 0x200];
+
+        memory[WCA as usize..WCA as usize + 3].copy_from_slice(&[2, 0x1248, 0x0031]);
+        for _ in 0..2 {
+            device
+                .execute_iocc(&iocc(DEVICE, DeviceFunction::InitWrite, 0), &mut memory)
+                .unwrap();
+        }
+        assert_eq!(device.get_punched_tape(), [0x48, 0x31, 0x48, 0x31]);
+    }
+
+    #[test]
+    fn test_state_round_trip() {
+        let mut device = DevicePaperTape::from_binary_file_bytes(&[0x10, 0x20, 0xFF]);
+        let mut memory = vec![0u16; 0x200];
+
+        memory[WCA as usize..WCA as usize + 3].copy_from_slice(&[2, 0x0041, 0x0042]);
+        device
+            .execute_iocc(&iocc(DEVICE, DeviceFunction::InitRead, 0), &mut memory)
+            .unwrap();
+        device
+            .execute_iocc(&iocc(DEVICE, DeviceFunction::InitWrite, 0), &mut memory)
+            .unwrap();
+
+        let mut restored = DevicePaperTape::new();
+        restored.restore_state(&device.save_state());
+        assert_eq!(restored.get_position(), 2);
+        assert_eq!(restored.get_punched_tape(), [0x10, 0x20]);
+
+        memory[WCA as usize] = 1;
+        restored
+            .execute_iocc(&iocc(DEVICE, DeviceFunction::InitRead, 0), &mut memory)
+            .unwrap();
+        assert_eq!(memory[WCA as usize + 1], 0x00FF);
+        assert!(restored.is_at_end());
+    }
+}
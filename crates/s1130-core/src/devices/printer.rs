@@ -9,7 +9,7 @@
 //! - Sense: Check if printer is ready
 //! - Write: Write a character to printer
 
-use crate::devices::{Device, DeviceFunction, Iocc};
+use crate::devices::{Device, DeviceFunction, Iocc, StandardDevice};
 use crate::error::CpuError;
 
 /// Console Printer Device
@@ -17,6 +17,7 @@ use crate::error::CpuError;
 /// This is a character-mode output device. Programs use XIO to:
 /// 1. Sense if printer is ready
 /// 2. Write characters one at a time
+#[derive(Clone)]
 pub struct DeviceConsolePrinter {
     /// Output buffer (characters that have been printed)
     output_buffer: Vec<u16>,
@@ -68,7 +69,7 @@ impl Default for DeviceConsolePrinter {
 
 impl Device for DeviceConsolePrinter {
     fn device_code(&self) -> u8 {
-        2 // Console printer
+        StandardDevice::ConsolePrinter.code()
     }
 
     fn device_name(&self) -> &'static str {
@@ -79,10 +80,8 @@ impl Device for DeviceConsolePrinter {
         match iocc.function {
             DeviceFunction::Sense => {
                 // Sense operation: return status in WCA location
-                // Bit 15 (LSB) = 1 if printer ready (always ready in this simple impl)
-                let status = 1; // Always ready
                 if (iocc.wca as usize) < memory.len() {
-                    memory[iocc.wca as usize] = status;
+                    memory[iocc.wca as usize] = self.get_status_word();
                 }
                 Ok(())
             }
@@ -111,6 +110,11 @@ impl Device for DeviceConsolePrinter {
         self.busy
     }
 
+    /// Bit 15 (LSB) = 1 if printer ready (always ready in this simple impl)
+    fn get_status_word(&self) -> u16 {
+        0x0001
+    }
+
     fn reset(&mut self) {
         self.output_buffer.clear();
         self.busy = false;
@@ -123,6 +127,19 @@ impl Device for DeviceConsolePrinter {
     fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
         self
     }
+
+    fn clone_box(&self) -> Box<dyn Device> {
+        Box::new(self.clone())
+    }
+
+    fn save_state(&self) -> Vec<u16> {
+        self.output_buffer.clone()
+    }
+
+    fn restore_state(&mut self, data: &[u16]) {
+        self.output_buffer = data.to_vec();
+        self.busy = false;
+    }
 }
 
 #[cfg(test)]
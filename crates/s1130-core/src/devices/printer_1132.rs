@@ -0,0 +1,390 @@
+//! IBM 1132 Line Printer
+//!
+//! This device emulates the IBM 1132, the 1130's main batch output device.
+//! It's a block-mode device: one XIO prints a whole line.
+//!
+//! Device code: 6
+//!
+//! Operations:
+//! - Sense: Return ready status in WCA location
+//! - InitWrite: Print one line; memory at WCA holds the word count and the
+//!   characters follow it (one per word, at most `LINE_WIDTH`)
+//! - Control: Carriage control selected by the modifier bits
+//!   (`CONTROL_SPACE`, `CONTROL_DOUBLE_SPACE`, `CONTROL_SKIP_TO_TOP`)
+//!
+//! Lines are decoded when printed, using the printer's `PrinterEncoding`,
+//! so the output is text whichever encoding the program writes. The
+//! encoding has to match the program: in EBCDIC mode the ASCII code of
+//! `A` (0x41) is unmapped, and in ASCII mode EBCDIC `A` (0xC1) prints as
+//! `Á`. Codes with no character in the encoding print as `?`.
+
+use crate::devices::{Device, DeviceFunction, DeviceInfo, DeviceKind, Iocc, StandardDevice};
+use crate::ebcdic::ebcdic_to_char;
+use crate::error::CpuError;
+
+/// Print positions per line
+pub const LINE_WIDTH: usize = 120;
+
+/// Control modifier: advance the paper one line
+pub const CONTROL_SPACE: u8 = 0x01;
+
+/// Control modifier: advance the paper two lines
+pub const CONTROL_DOUBLE_SPACE: u8 = 0x02;
+
+/// Control modifier: skip to the top of the next form
+pub const CONTROL_SKIP_TO_TOP: u8 = 0x04;
+
+/// Line recorded for a skip to top of form
+pub const FORM_FEED: &str = "\u{000C}";
+
+/// How the printer decodes the character words of a line
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PrinterEncoding {
+    /// Each word is a Unicode code point (ASCII for practical programs)
+    #[default]
+    Ascii,
+    /// The low byte of each word is an EBCDIC code; a word with high bits
+    /// set is unmapped
+    Ebcdic,
+}
+
+impl PrinterEncoding {
+    /// Character printed for `word`, `?` if it has none
+    pub fn decode(self, word: u16) -> char {
+        let decoded = match self {
+            PrinterEncoding::Ascii => char::from_u32(u32::from(word)),
+            PrinterEncoding::Ebcdic => u8::try_from(word).ok().and_then(ebcdic_to_char),
+        };
+        decoded.unwrap_or('?')
+    }
+
+    /// Lower-case name: `ascii` or `ebcdic`
+    pub fn name(self) -> &'static str {
+        match self {
+            PrinterEncoding::Ascii => "ascii",
+            PrinterEncoding::Ebcdic => "ebcdic",
+        }
+    }
+
+    /// Parse an encoding name, ignoring case
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "ascii" => Some(PrinterEncoding::Ascii),
+            "ebcdic" => Some(PrinterEncoding::Ebcdic),
+            _ => None,
+        }
+    }
+}
+
+/// IBM 1132 Line Printer Device
+#[derive(Clone)]
+pub struct Device1132 {
+    /// Printed lines, including blank lines from spacing and form feeds
+    output_lines: Vec<String>,
+
+    /// Decoding of the words of a print line
+    encoding: PrinterEncoding,
+
+    /// Device status flags
+    busy: bool,
+}
+
+impl Device1132 {
+    /// Create a new line printer with no output
+    pub fn new() -> Self {
+        Self {
+            output_lines: Vec::new(),
+            encoding: PrinterEncoding::default(),
+            busy: false,
+        }
+    }
+
+    /// Decoding used for lines printed from now on
+    pub fn encoding(&self) -> PrinterEncoding {
+        self.encoding
+    }
+
+    /// Choose how lines printed from now on are decoded
+    ///
+    /// Lines already printed keep their text.
+    pub fn set_encoding(&mut self, encoding: PrinterEncoding) {
+        self.encoding = encoding;
+    }
+
+    /// Get the printed lines
+    pub fn get_output_lines(&self) -> &[String] {
+        &self.output_lines
+    }
+
+    /// Clear the printed output
+    pub fn clear_output(&mut self) {
+        self.output_lines.clear();
+    }
+
+    /// Get the printed output with lines joined by newlines
+    pub fn print_to_string(&self) -> String {
+        self.output_lines.join("\n")
+    }
+
+    /// Format a print line from memory, dropping trailing blanks
+    fn format_line(&self, words: &[u16]) -> String {
+        let line: String = words
+            .iter()
+            .take(LINE_WIDTH)
+            .map(|&word| self.encoding.decode(word))
+            .collect();
+        line.trim_end().to_string()
+    }
+}
+
+impl Default for Device1132 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Device for Device1132 {
+    fn device_code(&self) -> u8 {
+        StandardDevice::LinePrinter1132.code()
+    }
+
+    fn device_name(&self) -> &'static str {
+        "1132 Line Printer"
+    }
+
+    fn execute_iocc(&mut self, iocc: &Iocc, memory: &mut [u16]) -> Result<(), CpuError> {
+        match iocc.function {
+            DeviceFunction::Sense => {
+                if (iocc.wca as usize) < memory.len() {
+                    memory[iocc.wca as usize] = self.get_status_word();
+                }
+                Ok(())
+            }
+
+            DeviceFunction::InitWrite => {
+                let wca = iocc.wca as usize;
+                let count = *memory.get(wca).ok_or_else(|| {
+                    CpuError::DeviceError("Line printer: Invalid memory address".to_string())
+                })? as usize;
+
+                let start = (wca + 1).min(memory.len());
+                let end = (start + count.min(LINE_WIDTH)).min(memory.len());
+                let line = self.format_line(&memory[start..end]);
+                self.output_lines.push(line);
+                Ok(())
+            }
+
+            DeviceFunction::Control => {
+                if iocc.modifiers & CONTROL_SKIP_TO_TOP != 0 {
+                    self.output_lines.push(FORM_FEED.to_string());
+                } else if iocc.modifiers & CONTROL_DOUBLE_SPACE != 0 {
+                    self.output_lines.push(String::new());
+                    self.output_lines.push(String::new());
+                } else if iocc.modifiers & CONTROL_SPACE != 0 {
+                    self.output_lines.push(String::new());
+                }
+                Ok(())
+            }
+
+            _ => Err(CpuError::DeviceError(format!(
+                "Line printer: Unsupported function {:?}",
+                iocc.function
+            ))),
+        }
+    }
+
+    fn is_busy(&self) -> bool {
+        self.busy
+    }
+
+    /// Bit 15 (LSB) = 1 if printer ready (not busy)
+    fn get_status_word(&self) -> u16 {
+        u16::from(!self.busy)
+    }
+
+    fn device_info(&self) -> DeviceInfo {
+        DeviceInfo {
+            code: self.device_code(),
+            name: self.device_name(),
+            kind: DeviceKind::BlockMode,
+        }
+    }
+
+    fn reset(&mut self) {
+        self.output_lines.clear();
+        self.busy = false;
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+
+    fn clone_box(&self) -> Box<dyn Device> {
+        Box::new(self.clone())
+    }
+
+    /// Lines are saved as their characters, each line ended by a 0xFFFF word
+    /// The encoding (0 ASCII, 1 EBCDIC), then each printed line as its
+    /// length followed by its characters
+    fn save_state(&self) -> Vec<u16> {
+        let encoding = match self.encoding {
+            PrinterEncoding::Ascii => 0,
+            PrinterEncoding::Ebcdic => 1,
+        };
+        let mut data = vec![encoding];
+        for line in &self.output_lines {
+            data.push(line.chars().count() as u16);
+            data.extend(line.chars().map(|ch| ch as u16));
+        }
+        data
+    }
+
+    fn restore_state(&mut self, data: &[u16]) {
+        self.encoding = match data.first() {
+            Some(1) => PrinterEncoding::Ebcdic,
+            _ => PrinterEncoding::Ascii,
+        };
+        self.output_lines.clear();
+        let mut rest = data.get(1..).unwrap_or_default();
+        while let Some((&length, tail)) = rest.split_first() {
+            let (line, tail) = tail.split_at((length as usize).min(tail.len()));
+            self.output_lines.push(
+                line.iter()
+                    .map(|&ch| char::from_u32(ch as u32).unwrap_or('?'))
+                    .collect(),
+            );
+            rest = tail;
+        }
+        self.busy = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::devices::test_support::{iocc, WCA};
+
+    const DEVICE: StandardDevice = StandardDevice::LinePrinter1132;
+
+    fn print_line(printer: &mut Device1132, memory: &mut [u16], text: &str) {
+        memory[WCA as usize] = text.chars().count() as u16;
+        for (offset, ch) in text.chars().enumerate() {
+            memory[WCA as usize + 1 + offset] = ch as u16;
+        }
+        printer
+            .execute_iocc(&iocc(DEVICE, DeviceFunction::InitWrite, 0), memory)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_printer_creation_and_sense() {
+        let mut printer = Device1132::new();
+        assert_eq!(printer.device_code(), 6);
+        assert_eq!(printer.device_name(), "1132 Line Printer");
+
+        let mut memory = vec![0u16; 0x200];
+        printer
+            .execute_iocc(&iocc(DEVICE, DeviceFunction::Sense, 0), &mut memory)
+            .unwrap();
+        assert_eq!(memory[WCA as usize], 1);
+    }
+
+    #[test]
+    fn test_multi_line_output_with_spacing() {
+        let mut printer = Device1132::new();
+        let mut memory = vec![0u16; 0x200];
+
+        print_line(&mut printer, &mut memory, "HEADER");
+        printer
+            .execute_iocc(
+                &iocc(DEVICE, DeviceFunction::Control, CONTROL_DOUBLE_SPACE),
+                &mut memory,
+            )
+            .unwrap();
+        print_line(&mut printer, &mut memory, "TOTAL   42   ");
+
+        assert_eq!(printer.get_output_lines(), ["HEADER", "", "", "TOTAL   42"]);
+        assert_eq!(printer.print_to_string(), "HEADER\n\n\nTOTAL   42");
+    }
+
+    #[test]
+    fn test_skip_to_top_of_form() {
+        let mut printer = Device1132::new();
+        let mut memory = vec![0u16; 0x200];
+
+        print_line(&mut printer, &mut memory, "PAGE 1");
+        printer
+            .execute_iocc(
+                &iocc(DEVICE, DeviceFunction::Control, CONTROL_SKIP_TO_TOP),
+                &mut memory,
+            )
+            .unwrap();
+        print_line(&mut printer, &mut memory, "PAGE 2");
+
+        assert_eq!(printer.print_to_string(), "PAGE 1\n\u{000C}\nPAGE 2");
+    }
+
+    #[test]
+    fn test_line_truncated_at_120_columns() {
+        let mut printer = Device1132::new();
+        let mut memory = vec![0u16; 0x200];
+
+        let long_line: String = "0123456789".repeat(13);
+        print_line(&mut printer, &mut memory, &long_line);
+
+        assert_eq!(printer.get_output_lines()[0].len(), LINE_WIDTH);
+        assert_eq!(printer.get_output_lines()[0], long_line[..LINE_WIDTH]);
+    }
+
+    #[test]
+    fn test_ebcdic_encoding() {
+        let mut printer = Device1132::new();
+        printer.set_encoding(PrinterEncoding::Ebcdic);
+        let mut memory = vec![0u16; 0x200];
+
+        // "HI 42" in EBCDIC, then an ASCII 'A' and a word beyond a byte
+        let words = [0xC8, 0xC9, 0x40, 0xF4, 0xF2, 0x41, 0x01C1];
+        memory[WCA as usize] = words.len() as u16;
+        memory[WCA as usize + 1..][..words.len()].copy_from_slice(&words);
+        printer
+            .execute_iocc(&iocc(DEVICE, DeviceFunction::InitWrite, 0), &mut memory)
+            .unwrap();
+
+        assert_eq!(printer.print_to_string(), "HI 42??");
+        assert_eq!(PrinterEncoding::Ascii.decode(0xC1), 'Á');
+        assert_eq!(
+            PrinterEncoding::from_name("EBCDIC"),
+            Some(PrinterEncoding::Ebcdic)
+        );
+        assert_eq!(PrinterEncoding::from_name("utf8"), None);
+    }
+
+    #[test]
+    fn test_state_round_trip() {
+        let mut printer = Device1132::new();
+        let mut memory = vec![0u16; 0x200];
+        print_line(&mut printer, &mut memory, "A");
+        printer
+            .execute_iocc(
+                &iocc(DEVICE, DeviceFunction::Control, CONTROL_SPACE),
+                &mut memory,
+            )
+            .unwrap();
+
+        // U+FFFF prints from ASCII mode and must not end a line
+        print_line(&mut printer, &mut memory, "B\u{FFFF}C");
+        printer.set_encoding(PrinterEncoding::Ebcdic);
+
+        let mut restored = Device1132::new();
+        restored.restore_state(&printer.save_state());
+        assert_eq!(restored.get_output_lines(), ["A", "", "B\u{FFFF}C"]);
+        assert_eq!(restored.encoding(), PrinterEncoding::Ebcdic);
+
+        printer.clear_output();
+        assert_eq!(printer.print_to_string(), "");
+    }
+}
@@ -0,0 +1,308 @@
+//! IBM 1130 Disassembler
+//!
+//! Converts instruction words back into the assembly syntax accepted by
+//! the assembler, e.g. `0x0102  LD   /VALUE,1` (`/` marks indirect).
+//! Words that do not decode as instructions are shown as `DC` constants.
+
+use crate::cpu::Cpu;
+use crate::instructions::{InstructionFormat, InstructionInfo, OpCode};
+use std::collections::HashMap;
+
+/// Disassembler for IBM 1130 machine code
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Disassembler;
+
+impl Disassembler {
+    /// Create a new disassembler
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Disassemble one instruction located at `address`
+    ///
+    /// `word2` is the following memory word, used only by long-format
+    /// instructions. When `symbols` is given, addresses with a matching
+    /// symbol are shown by name.
+    pub fn disassemble_word(
+        &self,
+        word1: u16,
+        word2: Option<u16>,
+        address: u16,
+        symbols: Option<&HashMap<String, u16>>,
+    ) -> String {
+        format!(
+            "{:#06x}  {}",
+            address,
+            self.disassemble_instruction(word1, word2, address, symbols)
+        )
+    }
+
+    /// Disassemble one instruction like `disassemble_word`, without the
+    /// leading address
+    pub fn disassemble_instruction(
+        &self,
+        word1: u16,
+        word2: Option<u16>,
+        address: u16,
+        symbols: Option<&HashMap<String, u16>>,
+    ) -> String {
+        let names = symbols.map(reverse_symbols).unwrap_or_default();
+
+        let text = match InstructionInfo::decode(word1, word2) {
+            Ok(instr) => format_instruction(&instr, address, &names),
+            Err(_) => format!("DC   {:#06x}", word1),
+        };
+
+        text.trim_end().to_string()
+    }
+
+    /// Disassemble `count` words of memory starting at `start`
+    ///
+    /// Long-format instructions consume the following word as their
+    /// displacement, so a line is produced per instruction rather than
+    /// per word. An address annotated with `Cpu::set_annotation` gets its
+    /// note as a trailing `*` comment.
+    pub fn disassemble_range(
+        &self,
+        cpu: &Cpu,
+        start: u16,
+        count: u16,
+        symbols: Option<&HashMap<String, u16>>,
+    ) -> Vec<String> {
+        let end = start as u32 + count as u32;
+        let mut lines = Vec::new();
+        let mut address = start as u32;
+
+        while address < end {
+            let Ok(word1) = cpu.read_memory(address as usize) else {
+                break;
+            };
+            let word2 = cpu.read_memory(address as usize + 1).ok();
+
+            let mut line = self.disassemble_word(word1, word2, address as u16, symbols);
+            if let Some(note) = cpu.annotation(address as u16) {
+                line = format!("{}  * {}", line, note);
+            }
+            lines.push(line);
+
+            let size = match OpCode::from_word(word1) {
+                Ok(opcode) if opcode.is_long_format() && word2.is_some() => 2,
+                _ => 1,
+            };
+            address += size;
+        }
+
+        lines
+    }
+}
+
+/// Build an address -> name map, picking the alphabetically first name
+/// when several symbols share an address so output is deterministic
+fn reverse_symbols(symbols: &HashMap<String, u16>) -> HashMap<u16, &str> {
+    let mut names: HashMap<u16, &str> = HashMap::new();
+    for (name, &address) in symbols {
+        names
+            .entry(address)
+            .and_modify(|existing| {
+                if name.as_str() < *existing {
+                    *existing = name;
+                }
+            })
+            .or_insert(name);
+    }
+    names
+}
+
+fn format_address(address: u16, names: &HashMap<u16, &str>) -> String {
+    match names.get(&address) {
+        Some(name) => name.to_string(),
+        None => format!("{:#06x}", address),
+    }
+}
+
+fn format_instruction(instr: &InstructionInfo, address: u16, names: &HashMap<u16, &str>) -> String {
+    let mnemonic = instr.opcode.mnemonic();
+    let indirect = if instr.indirect { "/" } else { "" };
+
+    let operand = match instr.opcode {
+        OpCode::WAIT => String::new(),
+
+        // Shift count lives in the displacement field
+        OpCode::SLA | OpCode::SLCA | OpCode::SRA | OpCode::SRT => instr.displacement.to_string(),
+
+        // MDX on memory: "0,address,increment"
+        OpCode::MDX if instr.tag == 0 => format!(
+            "{}0,{},{}",
+            indirect,
+            format_address(instr.displacement, names),
+            instr.signed_modifiers()
+        ),
+
+        // Index register instructions use "tag,address"
+        OpCode::LDX | OpCode::STX | OpCode::MDX => format!(
+            "{}{},{}",
+            indirect,
+            instr.tag,
+            format_address(instr.displacement, names)
+        ),
+
+        // Direct BSC skips the next instruction; only the condition matters
+        OpCode::BSC if !instr.indirect => with_tag(instr.displacement.to_string(), instr.condition),
+
+        // Direct short branches are relative to the next instruction;
+        // the suffix is the condition mask
+        OpCode::BC | OpCode::BSC => {
+            let target = if instr.indirect {
+                instr.displacement
+            } else {
+                address
+                    .wrapping_add(instr.size_in_words())
+                    .wrapping_add(instr.signed_displacement() as u16)
+            };
+            with_tag(
+                format!("{}{}", indirect, format_address(target, names)),
+                instr.condition,
+            )
+        }
+
+        _ => {
+            let target = match instr.format {
+                InstructionFormat::Long => format_address(instr.displacement, names),
+                InstructionFormat::Short => format!("{:#06x}", instr.displacement),
+            };
+            with_tag(format!("{}{}", indirect, target), instr.tag)
+        }
+    };
+
+    format!("{:<4} {}", mnemonic, operand)
+}
+
+fn with_tag(operand: String, tag: u8) -> String {
+    if tag == 0 {
+        operand
+    } else {
+        format!("{},{}", operand, tag)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_disassemble_long_format() {
+        let dis = Disassembler::new();
+        assert_eq!(
+            dis.disassemble_word(0x6000, Some(0x0200), 0x0100, None),
+            "0x0100  LD   0x0200"
+        );
+    }
+
+    #[test]
+    fn test_disassemble_indexed_indirect_with_symbols() {
+        let dis = Disassembler::new();
+        let mut symbols = HashMap::new();
+        symbols.insert("VALUE".to_string(), 0x0200);
+
+        // LD with tag 1 and indirect bit
+        let word1 = 0x6000 | (1 << 6) | 0x20;
+        assert_eq!(
+            dis.disassemble_word(word1, Some(0x0200), 0x0102, Some(&symbols)),
+            "0x0102  LD   /VALUE,1"
+        );
+    }
+
+    #[test]
+    fn test_disassemble_short_formats() {
+        let dis = Disassembler::new();
+        assert_eq!(
+            dis.disassemble_word(0xB000, None, 0x0100, None),
+            "0x0100  WAIT"
+        );
+        assert_eq!(
+            dis.disassemble_word(0x2003, None, 0x0100, None),
+            "0x0100  SLA  3"
+        );
+    }
+
+    #[test]
+    fn test_disassemble_index_register_instruction() {
+        let dis = Disassembler::new();
+        // LDX 2,0x0300
+        let word1 = 0x7400 | (2 << 6);
+        assert_eq!(
+            dis.disassemble_word(word1, Some(0x0300), 0x0100, None),
+            "0x0100  LDX  2,0x0300"
+        );
+    }
+
+    #[test]
+    fn test_disassemble_short_backward_branch() {
+        let dis = Disassembler::new();
+        let mut symbols = HashMap::new();
+        symbols.insert("LOOP".to_string(), 0x0100);
+
+        // BC -3 at 0x0102: target = 0x0103 - 3 = 0x0100
+        assert_eq!(
+            dis.disassemble_word(0x401D, None, 0x0102, None),
+            "0x0102  BC   0x0100"
+        );
+        assert_eq!(
+            dis.disassemble_word(0x401D, None, 0x0102, Some(&symbols)),
+            "0x0102  BC   LOOP"
+        );
+
+        // Conditional (tag 1 = carry) forward branch
+        assert_eq!(
+            dis.disassemble_word(0x4000 | (1 << 6) | 0x02, None, 0x0100, None),
+            "0x0100  BC   0x0103,1"
+        );
+    }
+
+    #[test]
+    fn test_disassemble_bsc_skip() {
+        let dis = Disassembler::new();
+        // Direct BSC with carry condition (tag 1)
+        assert_eq!(
+            dis.disassemble_word(0x5000 | (1 << 6), None, 0x0100, None),
+            "0x0100  BSC  0,1"
+        );
+        // Zero condition uses the opcode byte's low bits
+        assert_eq!(
+            dis.disassemble_word(0x5200, None, 0x0100, None),
+            "0x0100  BSC  0,8"
+        );
+    }
+
+    #[test]
+    fn test_disassemble_mdx_memory_increment() {
+        let dis = Disassembler::new();
+        assert_eq!(
+            dis.disassemble_word(0x581F, Some(0x0200), 0x0100, None),
+            "0x0100  MDX  0,0x0200,-1"
+        );
+    }
+
+    #[test]
+    fn test_disassemble_invalid_opcode_as_constant() {
+        let dis = Disassembler::new();
+        assert_eq!(
+            dis.disassemble_word(0x1234, None, 0x0100, None),
+            "0x0100  DC   0x1234"
+        );
+    }
+
+    #[test]
+    fn test_disassemble_range_handles_long_format() {
+        let mut cpu = Cpu::new();
+        cpu.write_memory_range(0x0100, &[0x6000, 0x0200, 0x2001, 0xB000])
+            .unwrap();
+
+        let lines = Disassembler::new().disassemble_range(&cpu, 0x0100, 4, None);
+
+        assert_eq!(
+            lines,
+            vec!["0x0100  LD   0x0200", "0x0102  SLA  1", "0x0103  WAIT",]
+        );
+    }
+}
@@ -0,0 +1,119 @@
+//! EBCDIC Character Encoding
+//!
+//! IBM 1130 software keeps text in EBCDIC, one 8-bit code per character.
+//! The table covers the same 64-character set as the keypunch (see
+//! `card_encoding`); every other code is unmapped.
+
+/// Character to EBCDIC code for the 64-character set
+pub const EBCDIC_TABLE: [(char, u8); 64] = [
+    (' ', 0x40),
+    ('0', 0xF0),
+    ('1', 0xF1),
+    ('2', 0xF2),
+    ('3', 0xF3),
+    ('4', 0xF4),
+    ('5', 0xF5),
+    ('6', 0xF6),
+    ('7', 0xF7),
+    ('8', 0xF8),
+    ('9', 0xF9),
+    ('A', 0xC1),
+    ('B', 0xC2),
+    ('C', 0xC3),
+    ('D', 0xC4),
+    ('E', 0xC5),
+    ('F', 0xC6),
+    ('G', 0xC7),
+    ('H', 0xC8),
+    ('I', 0xC9),
+    ('J', 0xD1),
+    ('K', 0xD2),
+    ('L', 0xD3),
+    ('M', 0xD4),
+    ('N', 0xD5),
+    ('O', 0xD6),
+    ('P', 0xD7),
+    ('Q', 0xD8),
+    ('R', 0xD9),
+    ('S', 0xE2),
+    ('T', 0xE3),
+    ('U', 0xE4),
+    ('V', 0xE5),
+    ('W', 0xE6),
+    ('X', 0xE7),
+    ('Y', 0xE8),
+    ('Z', 0xE9),
+    ('&', 0x50),
+    ('-', 0x60),
+    ('/', 0x61),
+    ('¢', 0x4A),
+    ('.', 0x4B),
+    ('<', 0x4C),
+    ('(', 0x4D),
+    ('+', 0x4E),
+    ('|', 0x4F),
+    ('!', 0x5A),
+    ('$', 0x5B),
+    ('*', 0x5C),
+    (')', 0x5D),
+    (';', 0x5E),
+    ('¬', 0x5F),
+    ('\\', 0xE0),
+    (',', 0x6B),
+    ('%', 0x6C),
+    ('_', 0x6D),
+    ('>', 0x6E),
+    ('?', 0x6F),
+    (':', 0x7A),
+    ('#', 0x7B),
+    ('@', 0x7C),
+    ('\'', 0x7D),
+    ('=', 0x7E),
+    ('"', 0x7F),
+];
+
+/// Decode an EBCDIC code to its character
+///
+/// Returns `None` for codes outside the character set.
+pub fn ebcdic_to_char(code: u8) -> Option<char> {
+    EBCDIC_TABLE
+        .iter()
+        .find(|&&(_, c)| c == code)
+        .map(|&(ch, _)| ch)
+}
+
+/// Encode a character in EBCDIC
+///
+/// Lowercase letters encode as uppercase; returns `None` for characters
+/// outside the set.
+pub fn char_to_ebcdic(ch: char) -> Option<u8> {
+    let ch = ch.to_ascii_uppercase();
+    EBCDIC_TABLE
+        .iter()
+        .find(|&&(c, _)| c == ch)
+        .map(|&(_, code)| code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::card_encoding::HOLLERITH_TABLE;
+
+    #[test]
+    fn test_known_codes() {
+        assert_eq!(char_to_ebcdic('A'), Some(0xC1));
+        assert_eq!(char_to_ebcdic('z'), Some(0xE9));
+        assert_eq!(char_to_ebcdic(' '), Some(0x40));
+        assert_eq!(ebcdic_to_char(0xF7), Some('7'));
+        assert_eq!(ebcdic_to_char(0x41), None);
+        assert_eq!(char_to_ebcdic('~'), None);
+    }
+
+    #[test]
+    fn test_same_set_as_keypunch() {
+        for &(ch, code) in &EBCDIC_TABLE {
+            assert_eq!(ebcdic_to_char(code), Some(ch));
+            assert!(HOLLERITH_TABLE.iter().any(|&(c, _)| c == ch), "{:?}", ch);
+        }
+    }
+}
@@ -21,13 +21,46 @@ pub enum CpuError {
     #[error("Invalid device code: {0}")]
     InvalidDevice(u8),
 
+    /// Interrupt level outside 0-4
+    #[error("Invalid interrupt level: {0}")]
+    InvalidInterruptLevel(u8),
+
+    /// Snapshot data could not be decoded
+    #[error("Invalid snapshot: {0}")]
+    InvalidSnapshot(String),
+
+    /// Stopped at a breakpoint (address of the next instruction)
+    #[error("Breakpoint at address {0:#06x}")]
+    Breakpoint(u16),
+
+    /// Stopped after a write to a watched address
+    #[error("Watchpoint hit at address {0:#06x}")]
+    Watchpoint(u16),
+
     /// Execution halted by WAIT instruction
     #[error("Execution halted by WAIT instruction")]
     WaitState,
 
+    /// A bounded run executed its step limit without stopping
+    #[error("Step limit reached")]
+    StepLimitReached,
+
+    /// `step_back` with no recorded steps
+    #[error("No execution history to step back through")]
+    HistoryEmpty,
+
+    /// `step_back` undid an instruction at this address that wrote memory,
+    /// which stays modified
+    #[error("Stepped back over a memory write at address {0:#06x}; memory was not restored")]
+    HistoryMemoryModified(u16),
+
     /// No instruction loaded for execution
     #[error("No instruction loaded for execution")]
     NoInstructionLoaded,
+
+    /// Memory size of zero words or beyond 16-bit addressing
+    #[error("Invalid memory size: {0} words")]
+    InvalidMemorySize(usize),
 }
 
 /// Errors that can occur during instruction execution
@@ -50,21 +83,33 @@ pub enum InstructionError {
 #[derive(Debug, Error, Clone, PartialEq, Eq)]
 pub enum AssemblerError {
     /// Syntax error in assembly source
-    #[error("Syntax error on line {line}: {message}")]
+    #[error("Syntax error on line {}: {message}", span.line)]
     SyntaxError {
-        /// Line number (1-indexed)
-        line: usize,
+        /// Where the error is; the line is 1-indexed
+        span: SourceSpan,
         /// Error message
         message: String,
+        /// Text of the offending source line, when known
+        text: Option<String>,
     },
 
     /// Undefined symbol reference
-    #[error("Undefined symbol: {0}")]
-    UndefinedSymbol(String),
+    #[error("Undefined symbol: {name}")]
+    UndefinedSymbol {
+        /// Symbol name
+        name: String,
+        /// Where the symbol is used (line 0 if not known)
+        span: SourceSpan,
+    },
 
     /// Duplicate label definition
-    #[error("Duplicate label: {0}")]
-    DuplicateLabel(String),
+    #[error("Duplicate label: {name}")]
+    DuplicateLabel {
+        /// Label name
+        name: String,
+        /// Where the second definition is (line 0 if not known)
+        span: SourceSpan,
+    },
 
     /// Invalid address
     #[error("Invalid address: {0:#06x}")]
@@ -73,6 +118,139 @@ pub enum AssemblerError {
     /// Value out of range
     #[error("Value out of range: {0}")]
     ValueOutOfRange(i32),
+
+    /// Malformed program image (see `AssembledProgram::from_binary`)
+    #[error("Invalid program binary: {0}")]
+    InvalidBinary(String),
+}
+
+/// Location of an assembler error in the source
+///
+/// Lines and columns are 1-indexed and `column_end` is exclusive, so the
+/// span covers `column_end - column_start` characters. Columns of 0 mean
+/// the whole line; line 0 means the location is not known.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SourceSpan {
+    pub line: usize,
+    pub column_start: usize,
+    pub column_end: usize,
+}
+
+impl SourceSpan {
+    /// Span of `column_start..column_end` on `line`
+    pub fn new(line: usize, column_start: usize, column_end: usize) -> Self {
+        Self {
+            line,
+            column_start,
+            column_end,
+        }
+    }
+
+    /// Span of a whole line, columns unknown
+    pub fn whole_line(line: usize) -> Self {
+        Self::new(line, 0, 0)
+    }
+
+    /// Check whether the span narrows the line down to columns
+    pub fn has_columns(&self) -> bool {
+        self.column_start > 0 && self.column_end > self.column_start
+    }
+}
+
+impl AssemblerError {
+    /// Syntax error on a whole `line`, without its source text
+    pub fn syntax(line: usize, message: impl Into<String>) -> Self {
+        Self::syntax_at(SourceSpan::whole_line(line), message)
+    }
+
+    /// Syntax error at `span`, without its source text
+    pub fn syntax_at(span: SourceSpan, message: impl Into<String>) -> Self {
+        AssemblerError::SyntaxError {
+            span,
+            message: message.into(),
+            text: None,
+        }
+    }
+
+    /// Undefined symbol `name` at an unknown location
+    pub fn undefined_symbol(name: impl Into<String>) -> Self {
+        AssemblerError::UndefinedSymbol {
+            name: name.into(),
+            span: SourceSpan::default(),
+        }
+    }
+
+    /// Second definition of `name` at an unknown location
+    pub fn duplicate_label(name: impl Into<String>) -> Self {
+        AssemblerError::DuplicateLabel {
+            name: name.into(),
+            span: SourceSpan::default(),
+        }
+    }
+
+    /// Fill in the source text of a syntax error's line from `source`
+    ///
+    /// Other errors, and syntax errors that already carry text or have no
+    /// line number, are returned unchanged.
+    pub fn with_source_text(self, source: &str) -> Self {
+        match self {
+            AssemblerError::SyntaxError {
+                span,
+                message,
+                text: None,
+            } if span.line > 0 => {
+                let text = source
+                    .lines()
+                    .nth(span.line - 1)
+                    .map(|l| l.trim_end().to_string());
+                AssemblerError::SyntaxError {
+                    span,
+                    message,
+                    text,
+                }
+            }
+            other => other,
+        }
+    }
+
+    /// Line number of the error, if it has a known location
+    pub fn line(&self) -> Option<usize> {
+        self.span().map(|span| span.line)
+    }
+
+    /// Location of the error, if known
+    pub fn span(&self) -> Option<SourceSpan> {
+        match self {
+            AssemblerError::SyntaxError { span, .. }
+            | AssemblerError::UndefinedSymbol { span, .. }
+            | AssemblerError::DuplicateLabel { span, .. }
+                if span.line > 0 =>
+            {
+                Some(*span)
+            }
+            _ => None,
+        }
+    }
+
+    /// Source text of the line a syntax error refers to
+    pub fn source_text(&self) -> Option<&str> {
+        match self {
+            AssemblerError::SyntaxError { text, .. } => text.as_deref(),
+            _ => None,
+        }
+    }
+}
+
+/// Errors from `Cpu::load_source`, by the stage that failed
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum LoadError {
+    /// The source did not assemble
+    #[error("Assembly failed: {0}")]
+    Assembly(#[from] AssemblerError),
+
+    /// The assembled program did not fit in memory
+    #[error("Load failed: {0}")]
+    Memory(#[from] CpuError),
 }
 
 /// Errors that can occur during device operations
@@ -89,6 +267,22 @@ pub enum DeviceError {
     /// I/O error
     #[error("I/O error: {0}")]
     IoError(String),
+
+    /// Another device is already attached at this code
+    #[error("Device code {0} already in use")]
+    CodeInUse(u8),
+
+    /// Device code outside 0-31
+    #[error("Invalid device code: {0}")]
+    InvalidCode(u8),
+
+    /// A device put in a slot other than its own device code
+    #[error("Device with code {found} cannot go in slot {slot}")]
+    CodeMismatch { slot: u8, found: u8 },
+
+    /// A card deck file that cannot be loaded (1-based line number)
+    #[error("Invalid card deck line {line}: {message}")]
+    InvalidDeck { line: usize, message: String },
 }
 
 /// Result type for CPU operations
@@ -116,10 +310,31 @@ mod tests {
 
     #[test]
     fn test_assembler_syntax_error() {
-        let err = AssemblerError::SyntaxError {
-            line: 42,
-            message: "Missing operand".to_string(),
-        };
+        let err = AssemblerError::syntax(42, "Missing operand");
         assert_eq!(err.to_string(), "Syntax error on line 42: Missing operand");
     }
+
+    #[test]
+    fn test_syntax_error_source_text() {
+        let err = AssemblerError::syntax(2, "Bad operand").with_source_text("A\nB   \nC");
+        assert_eq!(err.line(), Some(2));
+        assert_eq!(err.source_text(), Some("B"));
+
+        // No line number, no text
+        let err = AssemblerError::syntax(0, "Bad operand").with_source_text("A");
+        assert_eq!(err.source_text(), None);
+    }
+
+    #[test]
+    fn test_error_spans() {
+        let err = AssemblerError::syntax_at(SourceSpan::new(3, 10, 14), "Bad operand");
+        assert_eq!(err.to_string(), "Syntax error on line 3: Bad operand");
+        assert_eq!(err.span(), Some(SourceSpan::new(3, 10, 14)));
+        assert!(err.span().unwrap().has_columns());
+        assert!(!SourceSpan::whole_line(3).has_columns());
+
+        let err = AssemblerError::undefined_symbol("SUB");
+        assert_eq!(err.to_string(), "Undefined symbol: SUB");
+        assert_eq!(err.span(), None);
+    }
 }
@@ -86,7 +86,10 @@ pub enum OpCode {
 impl OpCode {
     /// Decode opcode from instruction word
     ///
-    /// The opcode is in bits 0-7 (upper byte) of the instruction word
+    /// The opcode is in bits 0-7 (upper byte) of the instruction word. BC
+    /// and BSC keep the high half of their condition mask in the opcode
+    /// byte's two low bits (see `CONDITION_SHIFT`), so 0x40-0x43 are all BC
+    /// and 0x50-0x53 all BSC.
     pub fn from_word(word: u16) -> Result<Self> {
         let opcode = (word >> 8) as u8;
 
@@ -109,8 +112,8 @@ impl OpCode {
             0x30 => Ok(OpCode::SRA),
             0x38 => Ok(OpCode::SRT),
             0x48 => Ok(OpCode::BSI),
-            0x40 => Ok(OpCode::BC),
-            0x50 => Ok(OpCode::BSC),
+            0x40..=0x43 => Ok(OpCode::BC),
+            0x50..=0x53 => Ok(OpCode::BSC),
             0x74 => Ok(OpCode::LDX),
             0x54 => Ok(OpCode::STX),
             0x58 => Ok(OpCode::MDX),
@@ -123,6 +126,40 @@ impl OpCode {
         }
     }
 
+    /// Get the assembler mnemonic for this opcode
+    pub fn mnemonic(self) -> &'static str {
+        match self {
+            OpCode::LD => "LD",
+            OpCode::LDD => "LDD",
+            OpCode::STO => "STO",
+            OpCode::STD => "STD",
+            OpCode::A => "A",
+            OpCode::AD => "AD",
+            OpCode::S => "S",
+            OpCode::SD => "SD",
+            OpCode::M => "M",
+            OpCode::D => "D",
+            OpCode::AND => "AND",
+            OpCode::OR => "OR",
+            OpCode::EOR => "EOR",
+            OpCode::SLA => "SLA",
+            OpCode::SLCA => "SLCA",
+            OpCode::SRA => "SRA",
+            OpCode::SRT => "SRT",
+            OpCode::BSI => "BSI",
+            OpCode::BC => "BC",
+            OpCode::BSC => "BSC",
+            OpCode::LDX => "LDX",
+            OpCode::STX => "STX",
+            OpCode::MDX => "MDX",
+            OpCode::WAIT => "WAIT",
+            OpCode::LDS => "LDS",
+            OpCode::STS => "STS",
+            OpCode::XIO => "XIO",
+            OpCode::SDS => "SDS",
+        }
+    }
+
     /// Check if this instruction requires long format (displacement)
     pub fn is_long_format(self) -> bool {
         matches!(
@@ -151,6 +188,55 @@ impl OpCode {
     }
 }
 
+/// Storage cycles per instruction, indexed by opcode byte (0 = invalid)
+///
+/// A simplified model of the 1130's documented timings, for comparing
+/// programs rather than exact reproduction. Indirect addressing adds one
+/// cycle (see `InstructionInfo::cycle_count`) and divide adds cycles for
+/// larger dividends (see `divide_extra_cycles`).
+pub const CYCLE_COUNTS: [u16; 256] = cycle_counts();
+
+const fn cycle_counts() -> [u16; 256] {
+    let mut table = [0; 256];
+    table[OpCode::LD as usize] = 2;
+    table[OpCode::LDD as usize] = 3;
+    table[OpCode::STO as usize] = 2;
+    table[OpCode::STD as usize] = 3;
+    table[OpCode::A as usize] = 2;
+    table[OpCode::AD as usize] = 3;
+    table[OpCode::S as usize] = 2;
+    table[OpCode::SD as usize] = 3;
+    table[OpCode::M as usize] = 17;
+    table[OpCode::D as usize] = 13;
+    table[OpCode::AND as usize] = 2;
+    table[OpCode::OR as usize] = 2;
+    table[OpCode::EOR as usize] = 2;
+    table[OpCode::SLA as usize] = 2;
+    table[OpCode::SLCA as usize] = 2;
+    table[OpCode::SRA as usize] = 2;
+    table[OpCode::SRT as usize] = 2;
+    table[OpCode::BSI as usize] = 2;
+    table[OpCode::BC as usize] = 1;
+    table[OpCode::BSC as usize] = 1;
+    table[OpCode::LDX as usize] = 2;
+    table[OpCode::STX as usize] = 2;
+    table[OpCode::MDX as usize] = 2;
+    table[OpCode::WAIT as usize] = 1;
+    table[OpCode::LDS as usize] = 1;
+    table[OpCode::STS as usize] = 2;
+    table[OpCode::XIO as usize] = 3;
+    table[OpCode::SDS as usize] = 2;
+    table
+}
+
+/// Extra divide cycles for `dividend`: one per four significant bits
+///
+/// Small dividends finish early, so D takes 13 to 21 cycles.
+pub fn divide_extra_cycles(dividend: i32) -> u16 {
+    let significant_bits = 32 - dividend.unsigned_abs().leading_zeros();
+    significant_bits.div_ceil(4) as u16
+}
+
 /// Instruction format (short or long)
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum InstructionFormat {
@@ -160,6 +246,22 @@ pub enum InstructionFormat {
     Long,
 }
 
+/// BC/BSC condition bit: carry is set
+pub const CONDITION_CARRY: u8 = 0x01;
+/// BC/BSC condition bit: overflow is set
+pub const CONDITION_OVERFLOW: u8 = 0x02;
+/// BC/BSC condition bit: ACC is positive and non-zero
+pub const CONDITION_POSITIVE: u8 = 0x04;
+/// BC/BSC condition bit: ACC is zero
+pub const CONDITION_ZERO: u8 = 0x08;
+
+/// Position of the 4-bit BC/BSC condition mask in the first word
+///
+/// The mask occupies bits 6-9: its low two bits share the tag field, which
+/// branches do not use for indexing, and its high two bits the low bits of
+/// the opcode byte. Masks 0-3 therefore encode exactly as the tag did.
+pub const CONDITION_SHIFT: u16 = 6;
+
 /// Decoded instruction information
 ///
 /// Contains all fields extracted from the instruction word(s):
@@ -184,6 +286,16 @@ pub struct InstructionInfo {
     /// Displacement (long format) or direct address (short format)
     pub displacement: u16,
 
+    /// Low five bits of the first word of a long-format instruction (0 in
+    /// short format); MDX with tag 0 adds them, signed, to memory
+    #[serde(default)]
+    pub modifiers: u16,
+
+    /// Condition mask of a BC or BSC (see `CONDITION_*`; 0 is
+    /// unconditional), 0 for other opcodes
+    #[serde(default)]
+    pub condition: u8,
+
     /// Effective address (calculated during execution)
     pub effective_address: Option<u16>,
 }
@@ -206,6 +318,11 @@ impl InstructionInfo {
         // Extract indirect flag (bit 10)
         let indirect = (word1 & 0x20) != 0;
 
+        let condition = match opcode {
+            OpCode::BC | OpCode::BSC => ((word1 >> CONDITION_SHIFT) & 0x0F) as u8,
+            _ => 0,
+        };
+
         if opcode.is_long_format() {
             // Long format: requires displacement word
             let displacement = word2.ok_or(InstructionError::MissingDisplacement)?;
@@ -216,6 +333,8 @@ impl InstructionInfo {
                 tag,
                 indirect,
                 displacement,
+                modifiers: word1 & 0x1F,
+                condition,
                 effective_address: None,
             })
         } else {
@@ -228,6 +347,8 @@ impl InstructionInfo {
                 tag,
                 indirect,
                 displacement,
+                modifiers: 0,
+                condition,
                 effective_address: None,
             })
         }
@@ -268,6 +389,23 @@ impl InstructionInfo {
         Ok(address)
     }
 
+    /// Get the short-format displacement as a signed value
+    ///
+    /// Short-format branches address relative to the updated IAR, so their
+    /// 5-bit displacement field is sign-extended (-16..=15). Long-format
+    /// displacements are returned unchanged as a 16-bit two's complement value.
+    pub fn signed_displacement(&self) -> i16 {
+        match self.format {
+            InstructionFormat::Short => ((self.displacement as i16) << 11) >> 11,
+            InstructionFormat::Long => self.displacement as i16,
+        }
+    }
+
+    /// Get the modifier bits as a signed value (-16..=15)
+    pub fn signed_modifiers(&self) -> i16 {
+        ((self.modifiers as i16) << 11) >> 11
+    }
+
     /// Get the size of this instruction in words
     pub fn size_in_words(&self) -> u16 {
         match self.format {
@@ -275,6 +413,13 @@ impl InstructionInfo {
             InstructionFormat::Long => 2,
         }
     }
+
+    /// Storage cycles from `CYCLE_COUNTS`, plus one for indirect addressing
+    ///
+    /// Divide's data-dependent cycles are added by the CPU when it runs.
+    pub fn cycle_count(&self) -> u16 {
+        CYCLE_COUNTS[self.opcode as usize] + u16::from(self.indirect)
+    }
 }
 
 #[cfg(test)]
@@ -342,6 +487,22 @@ mod tests {
         assert!(instr.indirect);
     }
 
+    #[test]
+    fn test_decode_branch_condition_mask() {
+        // BC +2 on positive or zero: mask 0b1100 in bits 6-9
+        let instr = InstructionInfo::decode(0x4302, None).unwrap();
+        assert_eq!(instr.opcode, OpCode::BC);
+        assert_eq!(instr.condition, CONDITION_POSITIVE | CONDITION_ZERO);
+        assert_eq!(instr.displacement, 2);
+
+        let instr = InstructionInfo::decode(0x5040, None).unwrap();
+        assert_eq!(instr.opcode, OpCode::BSC);
+        assert_eq!(instr.condition, CONDITION_CARRY);
+
+        // Other opcodes have no condition
+        assert_eq!(InstructionInfo::decode(0x20C0, None).unwrap().condition, 0);
+    }
+
     #[test]
     fn test_decode_long_format() {
         // LD 0x1234: word1=0x6000, word2=0x1234
@@ -372,6 +533,17 @@ mod tests {
         assert_eq!(instr.displacement, 0xABCD);
     }
 
+    #[test]
+    fn test_decode_long_format_modifiers() {
+        // MDX 0,0x0200,-1: increment in the low five bits of word1
+        let instr = InstructionInfo::decode(0x581F, Some(0x0200)).unwrap();
+        assert_eq!(instr.modifiers, 0x1F);
+        assert_eq!(instr.signed_modifiers(), -1);
+
+        let short = InstructionInfo::decode(0x401F, None).unwrap();
+        assert_eq!(short.modifiers, 0);
+    }
+
     #[test]
     fn test_decode_long_format_missing_displacement() {
         // LD without second word should fail
@@ -437,6 +609,37 @@ mod tests {
         assert_eq!(ea, 0x0300);
     }
 
+    #[test]
+    fn test_signed_displacement_short_format() {
+        // BC with displacement 0x1B (-5)
+        let instr = InstructionInfo::decode(0x401B, None).unwrap();
+        assert_eq!(instr.signed_displacement(), -5);
+
+        // BC with displacement 0x0F (+15)
+        let instr = InstructionInfo::decode(0x400F, None).unwrap();
+        assert_eq!(instr.signed_displacement(), 15);
+
+        // BC with displacement 0x10 (-16)
+        let instr = InstructionInfo::decode(0x4010, None).unwrap();
+        assert_eq!(instr.signed_displacement(), -16);
+    }
+
+    #[test]
+    fn test_cycle_count() {
+        let ld = InstructionInfo::decode(0x6000, Some(0x0100)).unwrap();
+        assert_eq!(ld.cycle_count(), 2);
+        let ld_indirect = InstructionInfo::decode(0x6020, Some(0x0100)).unwrap();
+        assert_eq!(ld_indirect.cycle_count(), 3);
+        let m = InstructionInfo::decode(0xF000, Some(0x0100)).unwrap();
+        assert_eq!(m.cycle_count(), 17);
+        assert_eq!(CYCLE_COUNTS[0xFF], 0);
+
+        assert_eq!(divide_extra_cycles(0), 0);
+        assert_eq!(divide_extra_cycles(-15), 1);
+        assert_eq!(divide_extra_cycles(0x10000), 5);
+        assert_eq!(divide_extra_cycles(i32::MIN), 8);
+    }
+
     #[test]
     fn test_size_in_words() {
         let short = InstructionInfo::decode(0xB000, None).unwrap();
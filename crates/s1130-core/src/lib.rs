@@ -9,6 +9,7 @@
 //! - **Memory**: 32K words (configurable)
 //! - **Instructions**: Complete 28-instruction set
 //! - **Assembler**: Two-pass assembler with full IBM 1130 syntax support
+//! - **Disassembler**: Converts memory words back to assembler syntax
 //! - **Devices**: I/O device emulation (card reader, disk, etc.)
 //!
 //! # Example
@@ -22,12 +23,19 @@
 //! ```
 
 pub mod assembler;
+pub mod card_encoding;
 pub mod cpu;
 pub mod devices;
+pub mod disassembler;
+pub mod ebcdic;
 pub mod error;
 pub mod instructions;
+pub mod loader;
 
 // Re-export commonly used types
-pub use cpu::{Cpu, CpuState};
-pub use error::{AssemblerError, CpuError, DeviceError, InstructionError, Result};
+pub use cpu::{Cpu, CpuOptions, CpuSnapshot, CpuState, StopReason};
+pub use disassembler::Disassembler;
+pub use error::{
+    AssemblerError, CpuError, DeviceError, InstructionError, LoadError, Result, SourceSpan,
+};
 pub use instructions::{InstructionFormat, InstructionInfo, OpCode};
@@ -0,0 +1,198 @@
+//! Absolute Binary Card Decks
+//!
+//! Programs were distributed as decks of binary cards, each holding a run
+//! of words for a fixed load address. A card column holds a whole 16-bit
+//! word here, so every field takes one column:
+//!
+//! ```text
+//! column 0      card type: 0x0000 data, 0x0001 transfer
+//! column 1      data: load address of the first word; transfer: entry point
+//! column 2      number of data words (0-76)
+//! columns 3-78  data words, unused columns zero
+//! column 79     checksum: 16-bit wrapping sum of columns 0-78
+//! ```
+//!
+//! A transfer card ends the program and sets its entry point; cards after
+//! it are ignored, as the hardware loader would have started the program.
+
+use crate::assembler::{AssembledProgram, Segment};
+use crate::devices::card_reader::Card;
+use crate::error::AssemblerError;
+
+/// Card type of a card carrying data words
+pub const DATA_CARD: u16 = 0x0000;
+/// Card type of the card carrying the entry point
+pub const TRANSFER_CARD: u16 = 0x0001;
+/// Data words per card
+pub const WORDS_PER_CARD: usize = 76;
+
+const TYPE_COLUMN: usize = 0;
+const ADDRESS_COLUMN: usize = 1;
+const COUNT_COLUMN: usize = 2;
+const DATA_COLUMN: usize = 3;
+const CHECKSUM_COLUMN: usize = 79;
+
+/// Checksum of a card: the wrapping sum of every column before the checksum
+pub fn checksum(card: &Card) -> u16 {
+    card.columns[..CHECKSUM_COLUMN]
+        .iter()
+        .fold(0u16, |sum, &column| sum.wrapping_add(column))
+}
+
+/// Check whether `card` is a well-formed binary card
+///
+/// A blank card is not, even though its zero checksum matches.
+pub fn is_binary_card(card: &Card) -> bool {
+    let kind = card.columns[TYPE_COLUMN];
+    (kind == DATA_CARD || kind == TRANSFER_CARD)
+        && (card.columns[COUNT_COLUMN] as usize) <= WORDS_PER_CARD
+        && card.columns[CHECKSUM_COLUMN] == checksum(card)
+        && card.columns.iter().any(|&column| column != 0)
+}
+
+/// Rebuild a program from an absolute binary deck
+///
+/// Cards that load consecutive addresses join into one segment.
+///
+/// # Errors
+///
+/// Returns `AssemblerError::SyntaxError` with the 1-based card number as
+/// the line for a checksum mismatch, an unknown card type or a word count
+/// over 76.
+pub fn load_absolute_binary(cards: &[Card]) -> Result<AssembledProgram, AssemblerError> {
+    let mut segments: Vec<Segment> = Vec::new();
+    let mut entry_point = None;
+
+    for (index, card) in cards.iter().enumerate() {
+        let card_number = index + 1;
+        let error = |message: String| AssemblerError::syntax(card_number, message);
+
+        let punched = card.columns[CHECKSUM_COLUMN];
+        let computed = checksum(card);
+        if punched != computed {
+            return Err(error(format!(
+                "Checksum mismatch: punched {:#06x}, computed {:#06x}",
+                punched, computed
+            )));
+        }
+
+        let address = card.columns[ADDRESS_COLUMN];
+        match card.columns[TYPE_COLUMN] {
+            DATA_CARD => {
+                let count = card.columns[COUNT_COLUMN] as usize;
+                if count > WORDS_PER_CARD {
+                    return Err(error(format!(
+                        "Word count {} exceeds {} per card",
+                        count, WORDS_PER_CARD
+                    )));
+                }
+                let words = &card.columns[DATA_COLUMN..DATA_COLUMN + count];
+                match segments.last_mut() {
+                    Some(segment)
+                        if segment.origin as usize + segment.words.len() == address as usize =>
+                    {
+                        segment.words.extend_from_slice(words);
+                    }
+                    _ => segments.push(Segment {
+                        origin: address,
+                        words: words.to_vec(),
+                    }),
+                }
+            }
+            TRANSFER_CARD => {
+                entry_point = Some(address);
+                break;
+            }
+            kind => return Err(error(format!("Unknown card type {:#06x}", kind))),
+        }
+    }
+
+    Ok(AssembledProgram::from_segments(segments, entry_point))
+}
+
+/// Punch a program as an absolute binary deck
+///
+/// Each segment is split into cards of up to 76 words. A transfer card
+/// follows if the program has an entry point.
+pub fn punch_absolute_binary(program: &AssembledProgram) -> Vec<Card> {
+    let mut cards = Vec::new();
+    for segment in &program.segments {
+        for (index, words) in segment.words.chunks(WORDS_PER_CARD).enumerate() {
+            let address = segment.origin.wrapping_add((index * WORDS_PER_CARD) as u16);
+            cards.push(binary_card(DATA_CARD, address, words));
+        }
+    }
+    if let Some(entry) = program.entry_point {
+        cards.push(binary_card(TRANSFER_CARD, entry, &[]));
+    }
+    cards
+}
+
+fn binary_card(kind: u16, address: u16, words: &[u16]) -> Card {
+    let mut card = Card::new();
+    card.columns[TYPE_COLUMN] = kind;
+    card.columns[ADDRESS_COLUMN] = address;
+    card.columns[COUNT_COLUMN] = words.len() as u16;
+    card.columns[DATA_COLUMN..DATA_COLUMN + words.len()].copy_from_slice(words);
+    card.columns[CHECKSUM_COLUMN] = checksum(&card);
+    card
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assembler::Assembler;
+
+    const SOURCE: &str = "
+        ORG  /0100
+START   LD   VALUE
+        WAIT
+VALUE   DC   42
+        ORG  /0200
+TABLE   BSS  100
+        END  START
+";
+
+    #[test]
+    fn test_deck_round_trip() {
+        let program = Assembler::new().assemble(SOURCE).unwrap();
+        let cards = punch_absolute_binary(&program);
+
+        // 4 words at 0x0100, 100 words at 0x0200 over two cards, transfer
+        assert_eq!(cards.len(), 4);
+        assert!(cards.iter().all(is_binary_card));
+
+        let loaded = load_absolute_binary(&cards).unwrap();
+        assert_eq!(loaded.segments, program.segments);
+        assert_eq!(loaded.entry_point, Some(0x0100));
+        assert_eq!(loaded.origin, 0x0100);
+        assert_eq!(loaded.footprint().total_words, 104);
+    }
+
+    #[test]
+    fn test_checksum_mismatch_names_card() {
+        let program = Assembler::new().assemble(SOURCE).unwrap();
+        let mut cards = punch_absolute_binary(&program);
+        cards[1].columns[DATA_COLUMN] ^= 0x0001;
+
+        let err = load_absolute_binary(&cards).unwrap_err();
+        assert_eq!(err.line(), Some(2));
+        assert!(err.to_string().contains("Checksum mismatch"), "{}", err);
+    }
+
+    #[test]
+    fn test_transfer_card_ends_deck() {
+        let cards = [
+            binary_card(TRANSFER_CARD, 0x0300, &[]),
+            binary_card(DATA_CARD, 0x0100, &[0xB000]),
+        ];
+        let program = load_absolute_binary(&cards).unwrap();
+        assert!(program.segments.is_empty());
+        assert_eq!(program.entry_point, Some(0x0300));
+    }
+
+    #[test]
+    fn test_blank_card_is_not_binary() {
+        assert!(!is_binary_card(&Card::new()));
+    }
+}
@@ -0,0 +1,118 @@
+//! Card Deck Loader
+//!
+//! Loads a program from a deck of cards, which may be either an absolute
+//! binary object deck or assembler source punched one line per card. The
+//! first card decides: a well-formed binary card (see `absolute_binary`)
+//! starts a binary deck, anything else is read as Hollerith source.
+
+pub mod absolute_binary;
+
+pub use absolute_binary::{load_absolute_binary, punch_absolute_binary};
+
+use crate::assembler::{AssembledProgram, Assembler};
+use crate::card_encoding::hollerith_to_ascii;
+use crate::devices::card_reader::Card;
+use crate::error::AssemblerError;
+
+/// Format of a card deck
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeckFormat {
+    /// Absolute binary object deck
+    AbsoluteBinary,
+    /// Assembler source in Hollerith, one line per card
+    Source,
+}
+
+/// Loads programs from card decks of either format
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Loader;
+
+impl Loader {
+    /// Create a loader
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Format of `cards`, judged by the first card
+    pub fn detect_format(&self, cards: &[Card]) -> DeckFormat {
+        match cards.first() {
+            Some(card) if absolute_binary::is_binary_card(card) => DeckFormat::AbsoluteBinary,
+            _ => DeckFormat::Source,
+        }
+    }
+
+    /// Load a program from a binary or source deck
+    ///
+    /// # Errors
+    ///
+    /// Binary decks report bad cards by card number; source decks report
+    /// assembly errors by line, which is also the card number.
+    pub fn load(&self, cards: &[Card]) -> Result<AssembledProgram, AssemblerError> {
+        match self.detect_format(cards) {
+            DeckFormat::AbsoluteBinary => load_absolute_binary(cards),
+            DeckFormat::Source => Assembler::new().assemble(&source_text(cards)),
+        }
+    }
+}
+
+/// Decode source cards to text, one line per card without trailing blanks
+///
+/// Columns with no valid Hollerith code read as blanks.
+fn source_text(cards: &[Card]) -> String {
+    cards
+        .iter()
+        .map(|card| {
+            let line: String = card
+                .columns
+                .iter()
+                .map(|&column| hollerith_to_ascii(column).unwrap_or(' '))
+                .collect();
+            line.trim_end().to_string()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str =
+        "        ORG  /0100\nSTART   LD   VALUE\n        WAIT\nVALUE   DC   7\n        END  START";
+
+    fn source_deck(source: &str) -> Vec<Card> {
+        source.lines().map(Card::from_text).collect()
+    }
+
+    #[test]
+    fn test_loads_source_deck() {
+        let cards = source_deck(SOURCE);
+        let loader = Loader::new();
+        assert_eq!(loader.detect_format(&cards), DeckFormat::Source);
+
+        let program = loader.load(&cards).unwrap();
+        let expected = Assembler::new().assemble(SOURCE).unwrap();
+        assert_eq!(program.segments, expected.segments);
+        assert_eq!(program.symbols, expected.symbols);
+        assert_eq!(program.entry_point, Some(0x0100));
+    }
+
+    #[test]
+    fn test_loads_binary_deck() {
+        let assembled = Assembler::new().assemble(SOURCE).unwrap();
+        let cards = punch_absolute_binary(&assembled);
+        let loader = Loader::new();
+        assert_eq!(loader.detect_format(&cards), DeckFormat::AbsoluteBinary);
+
+        let program = loader.load(&cards).unwrap();
+        assert_eq!(program.segments, assembled.segments);
+        assert_eq!(program.entry_point, assembled.entry_point);
+    }
+
+    #[test]
+    fn test_source_error_reports_card_number() {
+        let cards = source_deck("        ORG  /0100\n        LD   NOWHERE");
+        let err = Loader::new().load(&cards).unwrap_err();
+        assert_eq!(err.line(), Some(2));
+    }
+}
@@ -0,0 +1,112 @@
+//! Property tests for arithmetic instructions
+//!
+//! Random operands for A, AD, S, SD, M and D, checked against Rust's own
+//! integer arithmetic. Boundary values around the sign bit are where the
+//! hand-written cases in `instruction_execution_tests.rs` are thinnest.
+
+use proptest::prelude::*;
+use s1130_core::Cpu;
+
+const A: u16 = 0xE000;
+const AD: u16 = 0xE800;
+const S: u16 = 0xC000;
+const SD: u16 = 0xC800;
+const M: u16 = 0xF000;
+const D: u16 = 0xF800;
+
+/// Execute `opcode /0200` with ACC:EXT = `acc_ext` and `operand` at /0200
+fn execute(opcode: u16, acc_ext: u32, operand: &[u16]) -> Cpu {
+    let mut cpu = Cpu::with_memory_size(0x0400).unwrap();
+    cpu.set_iar(0x0100);
+    cpu.set_acc_ext(acc_ext);
+    cpu.write_memory(0x0100, opcode).unwrap();
+    cpu.write_memory(0x0101, 0x0200).unwrap();
+    cpu.write_memory_range(0x0200, operand).unwrap();
+    cpu.step().unwrap();
+    cpu
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(10_000))]
+
+    #[test]
+    fn prop_add(acc: u16, operand: u16) {
+        let cpu = execute(A, u32::from(acc) << 16, &[operand]);
+
+        let (sum, overflow) = (acc as i16).overflowing_add(operand as i16);
+        prop_assert_eq!(cpu.get_acc(), sum as u16);
+        prop_assert_eq!(cpu.get_carry(), u32::from(acc) + u32::from(operand) > 0xFFFF);
+        prop_assert_eq!(cpu.get_overflow(), overflow);
+    }
+
+    #[test]
+    fn prop_subtract(acc: u16, operand: u16) {
+        let cpu = execute(S, u32::from(acc) << 16, &[operand]);
+
+        let (difference, overflow) = (acc as i16).overflowing_sub(operand as i16);
+        prop_assert_eq!(cpu.get_acc(), difference as u16);
+        prop_assert_eq!(cpu.get_carry(), acc < operand);
+        prop_assert_eq!(cpu.get_overflow(), overflow);
+    }
+
+    #[test]
+    fn prop_add_double(acc_ext: u32, operand: u32) {
+        let cpu = execute(AD, acc_ext, &[(operand >> 16) as u16, operand as u16]);
+
+        let (sum, overflow) = (acc_ext as i32).overflowing_add(operand as i32);
+        prop_assert_eq!(cpu.get_acc_ext(), sum as u32);
+        prop_assert_eq!(cpu.get_carry(), acc_ext.checked_add(operand).is_none());
+        prop_assert_eq!(cpu.get_overflow(), overflow);
+    }
+
+    #[test]
+    fn prop_subtract_double(acc_ext: u32, operand: u32) {
+        let cpu = execute(SD, acc_ext, &[(operand >> 16) as u16, operand as u16]);
+
+        let (difference, overflow) = (acc_ext as i32).overflowing_sub(operand as i32);
+        prop_assert_eq!(cpu.get_acc_ext(), difference as u32);
+        prop_assert_eq!(cpu.get_carry(), acc_ext < operand);
+        prop_assert_eq!(cpu.get_overflow(), overflow);
+    }
+
+    #[test]
+    fn prop_multiply(acc: u16, operand: u16) {
+        let cpu = execute(M, u32::from(acc) << 16, &[operand]);
+
+        let product = i32::from(acc as i16) * i32::from(operand as i16);
+        prop_assert_eq!(cpu.get_acc_ext() as i32, product);
+    }
+
+    #[test]
+    fn prop_divide(dividend: i32, divisor: i16) {
+        let cpu = execute(D, dividend as u32, &[divisor as u16]);
+
+        match dividend.checked_div(i32::from(divisor)) {
+            Some(quotient) if i16::try_from(quotient).is_ok() => {
+                prop_assert_eq!(cpu.get_acc(), quotient as u16);
+                prop_assert_eq!(cpu.get_ext(), (dividend % i32::from(divisor)) as u16);
+                prop_assert!(!cpu.get_overflow());
+            }
+            // Divide by zero or a quotient too large for ACC
+            _ => prop_assert!(cpu.get_overflow()),
+        }
+    }
+}
+
+/// Boundary cases random operands are unlikely to hit
+#[test]
+fn test_double_word_sign_boundaries() {
+    // -2^31 + -2^31 wraps to 0: carry and overflow
+    let cpu = execute(AD, 0x8000_0000, &[0x8000, 0x0000]);
+    assert_eq!(cpu.get_acc_ext(), 0);
+    assert!(cpu.get_carry() && cpu.get_overflow());
+
+    // 0 - (-2^31) is not representable
+    let cpu = execute(SD, 0, &[0x8000, 0x0000]);
+    assert_eq!(cpu.get_acc_ext(), 0x8000_0000);
+    assert!(cpu.get_overflow());
+
+    // -2^31 / -1 overflows the quotient instead of panicking
+    let cpu = execute(D, 0x8000_0000, &[0xFFFF]);
+    assert!(cpu.get_overflow());
+}
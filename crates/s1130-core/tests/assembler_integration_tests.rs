@@ -2,7 +2,9 @@
 //!
 //! Tests complete programs to verify end-to-end assembly functionality
 
-use s1130_core::assembler::Assembler;
+use s1130_core::assembler::{Assembler, Segment};
+use s1130_core::devices::DeviceConsoleKeyboard;
+use s1130_core::{Cpu, CpuError, LoadError, StopReason};
 
 #[test]
 fn test_simple_addition_program() {
@@ -39,7 +41,7 @@ C       DC   0
         Some(0x0100),
         "Entry point should be 0x0100"
     );
-    assert!(program.words.len() > 0, "Should generate code");
+    assert!(!program.segments.is_empty(), "Should generate code");
 
     // Verify symbols were defined (addresses depend on instruction sizes)
     assert!(program.symbols.contains_key("A"), "Symbol A should exist");
@@ -68,10 +70,11 @@ fn test_hex_constants() {
     let program = result.unwrap();
 
     assert_eq!(program.origin, 0x0200);
-    assert_eq!(program.words.len(), 3);
-    assert_eq!(program.words[0], 0xFFFF);
-    assert_eq!(program.words[1], 0x1234);
-    assert_eq!(program.words[2], 0xABCD);
+    assert_eq!(program.word_count(), 3);
+    let (_, words) = program.flat_words(0);
+    assert_eq!(words[0], 0xFFFF);
+    assert_eq!(words[1], 0x1234);
+    assert_eq!(words[2], 0xABCD);
 }
 
 #[test]
@@ -90,10 +93,11 @@ fn test_decimal_constants() {
     assert!(result.is_ok(), "Assembly should succeed");
     let program = result.unwrap();
 
-    assert_eq!(program.words.len(), 3);
-    assert_eq!(program.words[0], 0);
-    assert_eq!(program.words[1], 100);
-    assert_eq!(program.words[2], 65535);
+    assert_eq!(program.word_count(), 3);
+    let (_, words) = program.flat_words(0);
+    assert_eq!(words[0], 0);
+    assert_eq!(words[1], 100);
+    assert_eq!(words[2], 65535);
 }
 
 #[test]
@@ -112,10 +116,11 @@ fn test_octal_constants() {
     assert!(result.is_ok(), "Assembly should succeed");
     let program = result.unwrap();
 
-    assert_eq!(program.words.len(), 3);
-    assert_eq!(program.words[0], 0o777); // 511 decimal
-    assert_eq!(program.words[1], 0o100); // 64 decimal
-    assert_eq!(program.words[2], 0o1234); // 668 decimal
+    assert_eq!(program.word_count(), 3);
+    let (_, words) = program.flat_words(0);
+    assert_eq!(words[0], 0o777); // 511 decimal
+    assert_eq!(words[1], 0o100); // 64 decimal
+    assert_eq!(words[2], 0o1234); // 668 decimal
 }
 
 #[test]
@@ -135,9 +140,10 @@ OCT     DC   0377
     let program = result.unwrap();
 
     // All three should equal 255
-    assert_eq!(program.words[0], 255, "Hex /00FF should be 255");
-    assert_eq!(program.words[1], 255, "Decimal 255 should be 255");
-    assert_eq!(program.words[2], 255, "Octal 0377 should be 255");
+    let (_, words) = program.flat_words(0);
+    assert_eq!(words[0], 255, "Hex /00FF should be 255");
+    assert_eq!(words[1], 255, "Decimal 255 should be 255");
+    assert_eq!(words[2], 255, "Octal 0377 should be 255");
 }
 
 #[test]
@@ -213,10 +219,11 @@ S       DC   /FFFF
     let c_addr = *program.symbols.get("C").unwrap() as usize - program.origin as usize;
     let s_addr = *program.symbols.get("S").unwrap() as usize - program.origin as usize;
 
-    assert_eq!(program.words[a_addr], 5, "A should be 5");
-    assert_eq!(program.words[b_addr], 3, "B should be 3");
-    assert_eq!(program.words[c_addr], 0, "C should be 0");
-    assert_eq!(program.words[s_addr], 0xFFFF, "S should be 0xFFFF");
+    let (_, words) = program.flat_words(0);
+    assert_eq!(words[a_addr], 5, "A should be 5");
+    assert_eq!(words[b_addr], 3, "B should be 3");
+    assert_eq!(words[c_addr], 0, "C should be 0");
+    assert_eq!(words[s_addr], 0xFFFF, "S should be 0xFFFF");
 }
 
 #[test]
@@ -235,13 +242,14 @@ END_BUF DC   /ABCD
     assert!(result.is_ok(), "Assembly should succeed");
     let program = result.unwrap();
 
-    assert_eq!(program.words.len(), 12); // 1 + 10 + 1
-    assert_eq!(program.words[0], 0x1234);
+    assert_eq!(program.word_count(), 12); // 1 + 10 + 1
+    let (_, words) = program.flat_words(0);
+    assert_eq!(words[0], 0x1234);
     // BSS allocates 10 zeros
-    for i in 1..11 {
-        assert_eq!(program.words[i], 0, "BSS word {} should be 0", i);
+    for (i, &word) in words.iter().enumerate().take(11).skip(1) {
+        assert_eq!(word, 0, "BSS word {} should be 0", i);
     }
-    assert_eq!(program.words[11], 0xABCD);
+    assert_eq!(words[11], 0xABCD);
 }
 
 #[test]
@@ -363,7 +371,6 @@ PAST    DC   /5678
 }
 
 #[test]
-#[ignore] // EQU not fully implemented yet
 fn test_equ_pseudo_op() {
     let source = r#"
 CONST   EQU  /0100
@@ -373,18 +380,15 @@ CONST   EQU  /0100
 "#;
 
     let mut assembler = Assembler::new();
-    let result = assembler.assemble(source);
+    let program = assembler.assemble(source).unwrap();
 
-    // EQU may not be fully implemented yet
-    // This test documents expected behavior for future implementation
-    if let Ok(program) = result {
-        assert_eq!(program.origin, 0x0100);
-        assert_eq!(program.words[0], 0x0100);
-    }
+    assert_eq!(program.symbols.get("CONST"), Some(&0x0100));
+    assert_eq!(program.origin, 0x0100);
+    let (_, words) = program.flat_words(0);
+    assert_eq!(words[0], 0x0100);
 }
 
 #[test]
-#[ignore] // Multiple ORGs not fully supported yet
 fn test_multiple_org_directives() {
     let source = r#"
         ORG  /0100
@@ -397,15 +401,114 @@ fn test_multiple_org_directives() {
 "#;
 
     let mut assembler = Assembler::new();
-    let result = assembler.assemble(source);
+    let program = assembler.assemble(source).unwrap();
 
-    // Multiple ORGs in one program may not be supported yet
-    // This test documents the expected/desired behavior for future implementation
-    if result.is_ok() {
-        let program = result.unwrap();
-        // First ORG should set origin
-        assert_eq!(program.origin, 0x0100);
-    }
+    // First ORG sets origin; each ORG starts its own segment
+    assert_eq!(program.origin, 0x0100);
+    assert_eq!(
+        program.segments,
+        [
+            Segment {
+                origin: 0x0100,
+                words: vec![0x1111]
+            },
+            Segment {
+                origin: 0x0200,
+                words: vec![0x2222]
+            },
+        ]
+    );
+    assert_eq!(program.word_count(), 2);
+
+    // Flattening fills the gap between segments
+    let (start, words) = program.flat_words(0xFFFF);
+    assert_eq!(start, 0x0100);
+    assert_eq!(words.len(), 0x0101);
+    assert_eq!(words[0], 0x1111);
+    assert!(words[1..0x0100].iter().all(|&w| w == 0xFFFF));
+    assert_eq!(words[0x0100], 0x2222);
+}
+
+#[test]
+fn test_load_program_with_separate_segments() {
+    let source = r#"
+        ORG  /0300
+START   LD   DATA
+        STO  RESULT
+        WAIT
+RESULT  DC   0
+
+        ORG  /0080
+DATA    DC   /0042
+        END  START
+"#;
+
+    let program = Assembler::new().assemble(source).unwrap();
+    assert_eq!(program.segments.len(), 2);
+    assert_eq!(program.segments[1].origin, 0x0080);
+
+    let mut cpu = Cpu::new();
+    cpu.write_memory(0x0100, 0x5555).unwrap();
+    cpu.load_program(&program).unwrap();
+
+    // Both segments are in place and the gap between them is untouched
+    assert_eq!(cpu.read_memory(0x0080).unwrap(), 0x0042);
+    assert_eq!(cpu.read_memory(0x0300).unwrap(), 0x6000);
+    assert_eq!(cpu.read_memory(0x0100).unwrap(), 0x5555);
+
+    assert_eq!(cpu.get_iar(), 0x0300); // END START
+    cpu.run(10);
+    assert_eq!(cpu.read_memory(0x0305).unwrap(), 0x0042);
+}
+
+#[test]
+fn test_load_program_sets_iar_to_origin_without_entry() {
+    let program = Assembler::new()
+        .assemble("        ORG  /0200\n        WAIT\n")
+        .unwrap();
+
+    let mut cpu = Cpu::new();
+    cpu.load_program(&program).unwrap();
+    assert_eq!(cpu.get_iar(), 0x0200);
+}
+
+#[test]
+fn test_load_and_run() {
+    let source = r#"
+        ORG  /0100
+        LD   SIX
+        A    SIX
+        WAIT
+SIX     DC   6
+"#;
+    let program = Assembler::new().assemble(source).unwrap();
+
+    let mut cpu = Cpu::new();
+    let steps = cpu.load_and_run(&program, 100).unwrap();
+
+    assert_eq!(steps, 3);
+    assert_eq!(cpu.get_acc(), 12);
+}
+
+#[test]
+fn test_load_program_rejects_program_past_memory_end() {
+    let source = r#"
+        ORG  /0100
+        DC   1
+        ORG  /0FFF
+        DC   2
+        DC   3
+"#;
+    let program = Assembler::new().assemble(source).unwrap();
+
+    let mut cpu = Cpu::with_memory_size(4096).unwrap();
+    assert_eq!(
+        cpu.load_program(&program),
+        Err(CpuError::MemoryViolation(0x1000))
+    );
+    // Nothing was written
+    assert_eq!(cpu.read_memory(0x0100).unwrap(), 0);
+    assert!(cpu.load_and_run(&program, 10).is_err());
 }
 
 #[test]
@@ -451,3 +554,239 @@ fn test_error_invalid_hex() {
 
     assert!(result.is_err(), "Invalid hex digits should cause error");
 }
+
+#[test]
+fn test_dmes_message_constants() {
+    let source = r#"
+        ORG  /0300
+EVEN    DMES 'AB'
+ODD     DMES 'ABC'
+QUOTE   DMES 'IT''S'
+        END
+"#;
+
+    let program = Assembler::new().assemble(source).unwrap();
+
+    assert_eq!(
+        program.segments,
+        vec![Segment {
+            origin: 0x0300,
+            words: vec![0x4142, 0x4142, 0x4300, 0x4954, 0x2753],
+        }]
+    );
+    assert_eq!(program.symbols.get("EVEN"), Some(&0x0300));
+    assert_eq!(program.symbols.get("ODD"), Some(&0x0301));
+    assert_eq!(program.symbols.get("QUOTE"), Some(&0x0303));
+
+    let result = Assembler::new().assemble("        DMES 'CAF\u{c9}'\n");
+    assert!(result.is_err(), "Non-ASCII message text should cause error");
+}
+
+#[test]
+fn test_negative_and_additive_constants() {
+    let source = r#"
+        ORG  /0400
+LABEL   DC   -1
+        DC   0-1
+        DC   LABEL+1
+        DC   LABEL-2
+        DC   100+50
+        DC   -32768
+        DC   -5
+        DC   /FFFB
+        DC   65531
+        END
+"#;
+
+    let mut assembler = Assembler::new();
+    let program = assembler.assemble(source).unwrap();
+
+    assert_eq!(
+        program.segments[0].words,
+        vec![0xFFFF, 0xFFFF, 0x0401, 0x03FE, 150, 0x8000, 0xFFFB, 0xFFFB, 0xFFFB]
+    );
+    assert!(assembler.warnings().is_empty());
+}
+
+#[test]
+fn test_out_of_range_constant_wraps_with_warning() {
+    let source = "        DC   65535+2\n        DC   70000\n        DC   65536*65536\n";
+
+    let mut assembler = Assembler::new();
+    let program = assembler.assemble(source).unwrap();
+
+    assert_eq!(program.segments[0].words, vec![0x0001, 0x1170, 0xFFFF]);
+    let warnings = assembler.warnings();
+    assert_eq!(warnings.len(), 3);
+    assert_eq!(warnings[2].line, 3);
+    assert_eq!(warnings[0].line, 1);
+    assert_eq!(
+        warnings[0].message,
+        "Value out of range: 65537, wrapped to 0x0001"
+    );
+}
+
+#[test]
+fn test_load_source_reports_failing_stage() {
+    let mut cpu = Cpu::new();
+    let program = cpu
+        .load_source("        ORG  /0100\nSTART   WAIT\n        END  START\n")
+        .unwrap();
+    assert_eq!(program.symbols.get("START"), Some(&0x0100));
+    assert_eq!(cpu.get_iar(), 0x0100);
+
+    // Syntax errors come from the assembler
+    let err = cpu.load_source("        BOGUS 1\n").unwrap_err();
+    assert!(matches!(err, LoadError::Assembly(_)), "{:?}", err);
+
+    // A program past the end of a tiny memory fails to load
+    let mut tiny = Cpu::with_memory_size(0x0100).unwrap();
+    let err = tiny
+        .load_source("        ORG  /00FF\n        DC   1\n        DC   2\n")
+        .unwrap_err();
+    assert_eq!(err, LoadError::Memory(CpuError::MemoryViolation(0x0100)));
+}
+
+#[test]
+fn test_incremental_assembly_shares_symbols() {
+    let library = "
+        ORG  /0200
+SUB     DC   0
+        SLA  1
+        WAIT
+";
+    let main = "
+        ORG  /0100
+START   LD   VALUE
+        BSI  SUB
+VALUE   DC   21
+        END  START
+";
+
+    let mut assembler = Assembler::new();
+    assert_eq!(
+        assembler.assemble_incremental(library).unwrap(),
+        [0x0000, 0x2001, 0xB000]
+    );
+    // BSI reaches SUB in the first file
+    assert_eq!(
+        assembler.assemble_incremental(main).unwrap(),
+        [0x6000, 0x0104, 0x4800, 0x0200, 21]
+    );
+
+    let program = assembler.finalize().unwrap();
+    assert_eq!(program.origin, 0x0200);
+    assert_eq!(program.entry_point, Some(0x0100));
+    assert_eq!(program.symbols.get("SUB"), Some(&0x0200));
+    assert_eq!(program.footprint().total_words, 8);
+
+    let mut cpu = Cpu::new();
+    cpu.load_program(&program).unwrap();
+    cpu.run(10);
+    assert_eq!(cpu.get_acc(), 42);
+
+    // finalize starts a new program
+    assert!(assembler.finalize().unwrap().segments.is_empty());
+}
+
+#[test]
+fn test_incremental_assembly_rejects_duplicate_labels() {
+    let mut assembler = Assembler::new();
+    assembler
+        .assemble_incremental("        ORG  /0100\nLOOP    WAIT\n")
+        .unwrap();
+
+    let err = assembler
+        .assemble_incremental("        ORG  /0200\nNEXT    WAIT\nLOOP    WAIT\n")
+        .unwrap_err();
+    assert_eq!(err.line(), Some(3));
+    assert!(err.to_string().contains("LOOP"), "{}", err);
+
+    // The failed file left nothing behind
+    assembler
+        .assemble_incremental("        ORG  /0200\nNEXT    WAIT\n")
+        .unwrap();
+    let program = assembler.finalize().unwrap();
+    assert_eq!(program.footprint().total_words, 2);
+}
+
+#[test]
+fn test_incremental_failure_leaves_no_entries_or_origin() {
+    let mut assembler = Assembler::new();
+    assembler
+        .assemble_incremental("        ORG  /0100\nMAIN    WAIT\n")
+        .unwrap();
+
+    // ENT, EXTRN, ORG, END and TITLE before the error are all undone
+    let failed = "        TITLE BROKEN\n        ENT  FOO\n        EXTRN BAR\n        ORG  /0300\nFOO     WAIT\n        END  FOO\n        LD   UNDEFINED\n";
+    assert!(assembler.assemble_incremental(failed).is_err());
+
+    let program = assembler.finalize().unwrap();
+    assert!(program.entries.is_empty());
+    assert_eq!(program.origin, 0x0100);
+    assert_eq!(program.entry_point, None);
+    assert_eq!(program.listing_title, None);
+    assert!(!program.symbols.contains_key("BAR"));
+}
+
+#[test]
+fn test_protected_code_rejects_stores() {
+    let mut cpu = Cpu::new();
+    let program = cpu
+        .load_source(
+            "        ORG  /0100
+START   LD   VALUE
+        STO  START
+        WAIT
+VALUE   DC   7
+        END  START
+",
+        )
+        .unwrap();
+    let footprint = program.footprint();
+    cpu.protect_memory_range(footprint.origin, footprint.last_address + 1)
+        .unwrap();
+
+    // The program tries to overwrite its own first instruction
+    let (steps, reason) = cpu.run(10);
+    assert_eq!(steps, 1);
+    assert_eq!(reason, StopReason::MemoryViolation(0x0100));
+    assert_eq!(cpu.read_memory(0x0100).unwrap(), 0x6000);
+    assert_eq!(
+        cpu.write_memory(0x0105, 0),
+        Err(CpuError::MemoryViolation(0x0105))
+    );
+
+    cpu.reset_hard();
+    assert!(cpu.is_memory_protected(0x0100));
+
+    cpu.unprotect_memory_range(0x0100, 0x0106).unwrap();
+    cpu.set_iar(0x0100);
+    assert_eq!(cpu.run(10).1, StopReason::Wait);
+    assert_eq!(cpu.read_memory(0x0100).unwrap(), 7);
+}
+
+#[test]
+fn test_protected_memory_rejects_device_transfers() {
+    let mut cpu = Cpu::new();
+    let mut keyboard = DeviceConsoleKeyboard::new();
+    keyboard.type_char(u16::from(b'A'));
+    cpu.attach_device(Box::new(keyboard)).unwrap();
+    cpu.load_source(
+        "        ORG  /0100
+        XIO  KREAD
+        WAIT
+KREAD   DC   CHAR
+        DC   /0B00
+CHAR    DC   7
+        END  /0100
+",
+    )
+    .unwrap();
+    cpu.protect_memory_range(0x0105, 0x0106).unwrap();
+
+    let (steps, reason) = cpu.run(10);
+    assert_eq!(steps, 0);
+    assert_eq!(reason, StopReason::MemoryViolation(0x0105));
+    assert_eq!(cpu.read_memory(0x0105).unwrap(), 7);
+}
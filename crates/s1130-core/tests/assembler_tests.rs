@@ -2,8 +2,8 @@
 //!
 //! These tests verify end-to-end assembly of IBM 1130 programs.
 
-use s1130_core::assembler::Assembler;
-use s1130_core::Cpu;
+use s1130_core::assembler::{link, Assembler, CharPacking, ExternalReference, MemoryFootprint};
+use s1130_core::{AssemblerError, Cpu, SourceSpan};
 
 #[test]
 fn test_assemble_simple_program() {
@@ -23,7 +23,7 @@ RESULT  BSS 1
     let program = assembler.assemble(source).unwrap();
 
     // Check that we got some code
-    assert!(!program.words.is_empty());
+    assert!(!program.segments.is_empty());
 
     // Check symbol table
     assert!(program.symbols.contains_key("START"));
@@ -56,9 +56,7 @@ RESULT  BSS 1
 
     // Load program into CPU
     let mut cpu = Cpu::new();
-    cpu.write_memory_range(program.origin as usize, &program.words)
-        .unwrap();
-    cpu.set_iar(program.origin);
+    cpu.load_program(&program).unwrap();
 
     // Execute until WAIT
     cpu.run(100);
@@ -101,6 +99,15 @@ fn test_assemble_hex_literals() {
     assert!(program.is_ok());
 }
 
+#[test]
+fn test_slash_and_c_style_hex_assemble_identically() {
+    let assemble = |source: &str| Assembler::new().assemble(source).unwrap().flat_words(0).1;
+    assert_eq!(
+        assemble("        LD  /100\n        DC  /FFFF\n"),
+        assemble("        LD  0x100\n        DC  0xFFFF\n")
+    );
+}
+
 #[test]
 fn test_assemble_octal_literals() {
     let source = r#"
@@ -126,6 +133,27 @@ PTR     DC  0x100
     assert!(program.is_ok());
 }
 
+#[test]
+fn test_slash_hex_word_is_literal_even_with_matching_label() {
+    let source = r#"
+        ORG /0100
+        LD  / A
+        LD  *BEEF,1
+        LD  /C
+        LD  /A
+        WAIT
+A       DC  /0200
+BEEF    DC  /0300
+    "#;
+
+    let program = Assembler::new().assemble(source).unwrap();
+    // `/A` is the literal 0x000A whether or not label `A` exists
+    assert_eq!(
+        program.segments[0].words[..8],
+        [0x6020, 0x0109, 0x6060, 0x010A, 0x6000, 0x000C, 0x6000, 0x000A]
+    );
+}
+
 #[test]
 fn test_assemble_indexed_addressing() {
     let source = r#"
@@ -168,10 +196,64 @@ fn test_assemble_all_instructions() {
     assert!(program.is_ok());
 
     // Should have assembled all instructions
-    let words = program.unwrap().words;
+    let words = program.unwrap().flat_words(0).1;
     assert!(words.len() > 17); // At least one word per instruction
 }
 
+#[test]
+fn test_assemble_short_backward_branch() {
+    let source = r#"
+        ORG  0x100
+LOOP    A    ONE
+        BC   LOOP
+        WAIT
+ONE     DC   1
+    "#;
+
+    let mut assembler = Assembler::new();
+    let program = assembler.assemble(source).unwrap();
+
+    // BC at 0x0102, next instruction at 0x0103, LOOP at 0x0100: offset -3
+    let (_, words) = program.flat_words(0);
+    assert_eq!(words[2], 0x4000 | 0x001D);
+}
+
+#[test]
+fn test_assemble_short_branch_out_of_range() {
+    let source = r#"
+        ORG  0x100
+        BC   FAR
+        BSS  20
+FAR     WAIT
+    "#;
+
+    let mut assembler = Assembler::new();
+    let err = assembler.assemble(source).unwrap_err();
+    assert!(err.to_string().contains("indirectly"), "{}", err);
+}
+
+#[test]
+fn test_assemble_mdx_memory_increment() {
+    let source = r#"
+        ORG  0x100
+LOOP    MDX  0,COUNT,-1
+        BC   LOOP
+        WAIT
+COUNT   DC   3
+    "#;
+
+    let program = Assembler::new().assemble(source).unwrap();
+    let (_, words) = program.flat_words(0);
+    assert_eq!(&words[..2], &[0x581F, 0x0104]);
+
+    assert!(Assembler::new()
+        .assemble("        MDX  1,COUNT,-1\nCOUNT DC 3")
+        .is_err());
+    assert!(Assembler::new()
+        .assemble("        MDX  0,COUNT,16\nCOUNT DC 3")
+        .is_err());
+}
+
 #[test]
 fn test_assemble_bss_pseudo_op() {
     let source = r#"
@@ -205,9 +287,63 @@ DATA3   DC  300
     let program = assembler.assemble(source).unwrap();
 
     assert_eq!(program.origin, 0x200);
-    assert_eq!(program.words[0], 100);
-    assert_eq!(program.words[1], 200);
-    assert_eq!(program.words[2], 300);
+    let (_, words) = program.flat_words(0);
+    assert_eq!(words[0], 100);
+    assert_eq!(words[1], 200);
+    assert_eq!(words[2], 300);
+}
+
+#[test]
+fn test_character_constant_packing_orders() {
+    let source = "        ORG 0x200\nTEXT    DC  'AB'\nNEXT    DC  'XYZ'\nLAST    DC  1\n";
+
+    let mut assembler = Assembler::new();
+    assert_eq!(assembler.char_packing(), CharPacking::HighFirst);
+    let program = assembler.assemble(source).unwrap();
+    assert_eq!(program.flat_words(0).1, vec![0x4142, 0x5859, 0x5A20, 1]);
+    assert_eq!(program.symbols["LAST"], 0x203);
+
+    assembler.set_char_packing(CharPacking::LowFirst);
+    let program = assembler.assemble(source).unwrap();
+    assert_eq!(program.flat_words(0).1, vec![0x4241, 0x5958, 0x205A, 1]);
+}
+
+#[test]
+fn test_character_constant_doubled_quote() {
+    let source = "        ORG 0x200\nTEXT    DC  'IT''S'\nLAST    DC  1\n";
+    let program = Assembler::new().assemble(source).unwrap();
+    assert_eq!(program.flat_words(0).1, vec![0x4954, 0x2753, 1]);
+    assert_eq!(program.symbols["LAST"], 0x202);
+}
+
+#[test]
+fn test_character_constant_errors() {
+    for source in [
+        "        DC  'AB',1\n",
+        "        DC  'A'*X\n",
+        "        DC  'CAF\u{c9}'\n",
+    ] {
+        assert!(
+            matches!(
+                Assembler::new().assemble(source),
+                Err(AssemblerError::SyntaxError { .. })
+            ),
+            "{source}"
+        );
+    }
+    // Whitespace-separated text after the string is remarks
+    let program = Assembler::new()
+        .assemble("        DC  'AB' remarks\n")
+        .unwrap();
+    assert_eq!(program.flat_words(0).1, vec![0x4142]);
+}
+
+#[test]
+fn test_empty_character_constant_is_one_blank_word() {
+    let source = "        ORG 0x200\nTEXT    DC  ''\nLAST    DC  1\n";
+    let program = Assembler::new().assemble(source).unwrap();
+    assert_eq!(program.flat_words(0).1, vec![0x2020, 1]);
+    assert_eq!(program.symbols["LAST"], 0x201);
 }
 
 #[test]
@@ -231,7 +367,7 @@ fn test_assemble_empty_program() {
 
     let mut assembler = Assembler::new();
     let program = assembler.assemble(source).unwrap();
-    assert!(program.words.is_empty());
+    assert!(program.segments.is_empty());
 }
 
 #[test]
@@ -257,3 +393,855 @@ fn test_undefined_symbol_error() {
     let result = assembler.assemble(source);
     assert!(result.is_err());
 }
+
+#[test]
+fn test_memory_reference_requires_operand() {
+    let err = Assembler::new().assemble("        LD\n").unwrap_err();
+    assert_eq!(err.line(), Some(1));
+    assert!(err.to_string().contains("LD requires an operand"));
+
+    // WAIT and shifts take no operand
+    let program = Assembler::new()
+        .assemble("        SLA\n        WAIT\n")
+        .unwrap();
+    assert_eq!(program.flat_words(0).1, vec![0x2000, 0xB000]);
+}
+
+#[test]
+fn test_assemble_with_mapping_indexed_load() {
+    let source = "        ORG  0x100\n* load through XR1\n        LD   100,1\n        WAIT\n";
+
+    let mut assembler = Assembler::new();
+    let (program, mapping) = assembler.assemble_with_mapping(source).unwrap();
+
+    // LD is on source line 3 (the comment on line 2 produces no entry)
+    let (_, ld_words) = mapping.iter().find(|(line, _)| *line == 3).unwrap();
+
+    // Word 1: opcode 0x60 << 8, tag 1 in bits 6-7, no indirect bit
+    // Word 2: displacement 100
+    assert_eq!(ld_words, &vec![0x6000 | (1 << 6), 100]);
+
+    // ORG emits nothing, WAIT emits one word
+    assert_eq!(mapping[0], (1, vec![]));
+    assert_eq!(mapping.last().unwrap(), &(4, vec![0xB000]));
+
+    // The flattened program matches the concatenated mapping
+    let flattened: Vec<u16> = mapping.iter().flat_map(|(_, w)| w.clone()).collect();
+    let (_, words) = program.flat_words(0);
+    assert_eq!(words, flattened);
+}
+
+#[test]
+fn test_source_map_links_addresses_and_lines() {
+    let source = "        ORG  /0100\nLOOP    LD   VAL\n        WAIT\nVAL     DC   7\nBUF     BSS  4\n        DC   9\n";
+
+    let (program, map) = Assembler::new().assemble_with_source_map(source).unwrap();
+
+    // Both words of the long LD come from line 2
+    assert_eq!(map.line_to_addresses(2), [0x0100, 0x0101]);
+    assert_eq!(map.address_to_line(0x0101), Some(2));
+    assert_eq!(map.address_to_line(0x0102), Some(3));
+    assert_eq!(map.line_to_addresses(5), [0x0104, 0x0105, 0x0106, 0x0107]);
+    assert_eq!(map.address_to_line(0x0108), Some(6));
+    assert_eq!(map.address_to_line(0x0109), None);
+    // ORG generates nothing
+    assert!(map.line_to_addresses(1).is_empty());
+    assert_eq!(map.entries.len(), program.word_count());
+}
+
+#[test]
+fn test_assemble_iocc_pseudo_op() {
+    // Hand-coded IOCC pairs from the echo program
+    let hand_coded = r#"
+        ORG 0x100
+KREAD   DC  CHAR
+        DC  0x0B00
+PWRITE  DC  CHAR
+        DC  0x1500
+CHAR    BSS 1
+    "#;
+
+    let with_iocc = r#"
+        ORG 0x100
+KREAD   IOCC CHAR,1,READ,0
+PWRITE  IOCC CHAR,PRINTER,WRITE,0
+CHAR    BSS 1
+    "#;
+
+    let expected = Assembler::new().assemble(hand_coded).unwrap();
+    let program = Assembler::new().assemble(with_iocc).unwrap();
+
+    assert_eq!(program.segments, expected.segments);
+    let (_, words) = program.flat_words(0);
+    assert_eq!(words[..4], [0x0104, 0x0B00, 0x0104, 0x1500]);
+    assert_eq!(program.symbols.get("PWRITE"), Some(&0x0102));
+    assert_eq!(program.symbols.get("CHAR"), Some(&0x0104));
+}
+
+#[test]
+fn test_assemble_iocc_numeric_fields() {
+    let source = "        IOCC 0x200,9,2,0x42\n";
+
+    let program = Assembler::new().assemble(source).unwrap();
+
+    let (_, words) = program.flat_words(0);
+    assert_eq!(words, vec![0x0200, (9 << 11) | (2 << 8) | 0x42]);
+}
+
+#[test]
+fn test_assemble_iocc_requires_four_fields() {
+    let source = "        IOCC CHAR,KEYBOARD,READ\nCHAR    BSS 1\n";
+
+    let result = Assembler::new().assemble(source);
+
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_assemble_bsc_skip_keeps_condition() {
+    let source = "        ORG 0x100\n        BSC 0,1\n        WAIT\n";
+
+    let program = Assembler::new().assemble(source).unwrap();
+
+    // Direct BSC is a skip: no relative displacement, tag carries the condition
+    let (_, words) = program.flat_words(0);
+    assert_eq!(words, vec![0x5040, 0xB000]);
+}
+
+#[test]
+fn test_assemble_branch_condition_mask() {
+    let source = "
+        ORG  0x100
+        LD   VALUE
+        BC   DONE,12
+        WAIT
+DONE    BSC  0,8
+        WAIT
+        WAIT
+VALUE   DC   0
+";
+    let program = Assembler::new().assemble(source).unwrap();
+    let (_, words) = program.flat_words(0);
+    // Positive-or-zero (12) and zero (8) need the bits above the tag
+    assert_eq!(words[2], 0x4301);
+    assert_eq!(words[4], 0x5200);
+    assert!(Assembler::new().assemble("        BC   0,16\n").is_err());
+
+    // ACC is zero: BC branches, then BSC skips the first WAIT
+    let mut cpu = Cpu::new();
+    cpu.load_program(&program).unwrap();
+    for _ in 0..4 {
+        cpu.step().unwrap();
+    }
+    assert!(cpu.get_wait());
+    assert_eq!(cpu.get_iar(), 0x0107);
+}
+
+#[test]
+fn test_label_shadowing_mnemonic_resolves_to_label_with_warning() {
+    let source = r#"
+        ORG  0x100
+        LD   S
+        WAIT
+S       DC   7
+        END
+"#;
+
+    let mut assembler = Assembler::new();
+    let program = assembler.assemble(source).unwrap();
+
+    // The operand S is the label, not the subtract mnemonic
+    assert_eq!(program.symbols.get("S"), Some(&0x0103));
+    let (_, words) = program.flat_words(0);
+    assert_eq!(words[..2], [0x6000, 0x0103]);
+
+    let warnings = assembler.warnings();
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(warnings[0].line, 5);
+    assert!(warnings[0].message.contains("shadows"));
+}
+
+#[test]
+fn test_ordinary_labels_produce_no_warnings() {
+    let mut assembler = Assembler::new();
+    assembler
+        .assemble("        LD   VALUE\nVALUE   DC   1\n")
+        .unwrap();
+
+    assert!(assembler.warnings().is_empty());
+}
+
+#[test]
+fn test_expression_operands() {
+    let source = r#"
+        ORG  0x100
+START   LD   A+B-1
+        WAIT
+A       DC   4
+B       DC   (LAST-START)/2
+LAST    DC   *+4
+        END
+"#;
+
+    let program = Assembler::new().assemble(source).unwrap();
+
+    // A = 0x103, B = 0x104, LAST = 0x105
+    let (_, words) = program.flat_words(0);
+    assert_eq!(words[1], 0x0103 + 0x0104 - 1);
+    assert_eq!(words[4], (0x0105 - 0x0100) / 2);
+    assert_eq!(words[5], 0x0105 + 4);
+}
+
+#[test]
+fn test_tabs_between_operand_components() {
+    let spaced = "        LD   100, 1\n        LDX  2, 100\n        MDX  0, 100, -2\n";
+    let tabbed = "\tLD\t100,\t1\n\tLDX\t2\t,\t100\n\tMDX\t0,\t100,\t-2\n";
+
+    let expected = Assembler::new().assemble(spaced).unwrap();
+    let program = Assembler::new().assemble(tabbed).unwrap();
+
+    // Displacement 100 with tag 1, tag 2 and the MDX increment
+    let (_, words) = program.flat_words(0);
+    assert_eq!(words[..2], [0x6040, 100]);
+    assert_eq!(words[2..4], [0x7480, 100]);
+    assert_eq!(words, expected.flat_words(0).1);
+}
+
+#[test]
+fn test_current_address_operand_is_not_indirect() {
+    let source = "        ORG 0x100\n        LD   *+4\n        LD   *VAL\nVAL     DC   1\n";
+
+    let program = Assembler::new().assemble(source).unwrap();
+
+    // LD *+4 is direct, LD *VAL is indirect through VAL
+    let (_, words) = program.flat_words(0);
+    assert_eq!(words[..2], [0x6000, 0x0104]);
+    assert_eq!(words[2..4], [0x6020, 0x0104]);
+}
+
+#[test]
+fn test_equ_in_instruction_operand() {
+    let source = r#"
+PORT    EQU  /0200
+        ORG  0x100
+        LD   PORT
+        STO  PORT+1
+"#;
+
+    let program = Assembler::new().assemble(source).unwrap();
+
+    let (_, words) = program.flat_words(0);
+    assert_eq!(words, vec![0x6000, 0x0200, 0x7000, 0x0201]);
+    // An equate does not occupy memory
+    assert_eq!(program.symbols.get("PORT"), Some(&0x0200));
+}
+
+#[test]
+fn test_equ_forward_reference() {
+    let source = r#"
+        ORG  0x100
+SIZE    EQU  LAST-FIRST+1
+        DC   SIZE
+FIRST   DC   1
+        DC   2
+LAST    DC   3
+"#;
+
+    let program = Assembler::new().assemble(source).unwrap();
+
+    assert_eq!(program.symbols.get("SIZE"), Some(&3));
+    let (_, words) = program.flat_words(0);
+    assert_eq!(words[0], 3);
+}
+
+#[test]
+fn test_equ_chain_and_current_address() {
+    let source = r#"
+        ORG  0x100
+HERE    EQU  *
+TWICE   EQU  ONCE*2
+ONCE    EQU  HERE+1
+        DC   TWICE
+"#;
+
+    let program = Assembler::new().assemble(source).unwrap();
+
+    assert_eq!(program.symbols.get("HERE"), Some(&0x0100));
+    let (_, words) = program.flat_words(0);
+    assert_eq!(words, vec![0x0202]);
+}
+
+#[test]
+fn test_duplicate_equ_is_error() {
+    let source = "VALUE   EQU  1\nVALUE   EQU  2\n";
+
+    let result = Assembler::new().assemble(source);
+
+    assert!(matches!(
+        result,
+        Err(AssemblerError::DuplicateLabel {
+            span: SourceSpan { line: 2, .. },
+            ..
+        })
+    ));
+}
+
+#[test]
+fn test_equ_undefined_symbol_is_error() {
+    let result = Assembler::new().assemble("VALUE   EQU  MISSING+1\n");
+
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_address_labels_for_sample_program() {
+    let source = r#"
+        ORG  /0100
+        LD   A
+        A    B
+        STO  C
+        WAIT
+
+A       DC   /0005
+B       DC   /0003
+C       DC   0
+SUM     EQU  C
+        END  /0100
+"#;
+
+    let program = Assembler::new().assemble(source).unwrap();
+    let labels = program.address_labels();
+
+    assert_eq!(labels.get(&0x0107).map(String::as_str), Some("A"));
+    assert_eq!(labels.get(&0x0108).map(String::as_str), Some("B"));
+    // C and SUM share 0x0109; the alphabetically first name wins
+    assert_eq!(labels.get(&0x0109).map(String::as_str), Some("C"));
+    assert_eq!(labels.len(), 3);
+}
+
+#[test]
+fn test_assemble_with_listing() {
+    let source = "* listing test\n        ORG  /0100\nSTART   LD   VALUE\n        WAIT\n\nVALUE   DC   42\nSIZE    EQU  VALUE-START\nBUF     BSS  4\n";
+
+    let mut assembler = Assembler::new();
+    let (program, listing) = assembler.assemble_with_listing(source).unwrap();
+    let (_, words) = program.flat_words(0);
+    assert_eq!(words[0], 0x6000);
+
+    let lines: Vec<&str> = listing.lines().collect();
+    assert_eq!(lines[0], "                  * listing test");
+    assert_eq!(lines[1], "                          ORG  /0100");
+    assert_eq!(lines[2], "0100  6000 0103   START   LD   VALUE");
+    assert_eq!(lines[3], "0102  B000                WAIT");
+    assert_eq!(lines[4], "");
+    assert_eq!(lines[5], "0103  002A        VALUE   DC   42");
+    assert_eq!(lines[6], "                  SIZE    EQU  VALUE-START");
+    assert_eq!(lines[7], "0104              BUF     BSS  4");
+
+    // Symbol table follows the source, sorted by name
+    let table: Vec<&str> = lines[lines.iter().position(|l| *l == "SYMBOL TABLE").unwrap()..]
+        .iter()
+        .filter(|l| !l.is_empty())
+        .copied()
+        .collect();
+    assert_eq!(
+        table,
+        [
+            "SYMBOL TABLE",
+            "NAME      VALUE  TYPE",
+            "BUF       0104   LABEL",
+            "SIZE      0003   EQU",
+            "START     0100   LABEL",
+            "VALUE     0103   LABEL",
+        ]
+    );
+}
+
+#[test]
+fn test_assemble_with_listing_reports_errors() {
+    let mut assembler = Assembler::new();
+    assert!(assembler
+        .assemble_with_listing("        LD   NOWHERE\n")
+        .is_err());
+}
+
+#[test]
+fn test_assemble_with_outputs_matches_separate_calls() {
+    let source = "        ORG  /0100\nSTART   LD   VALUE\n        WAIT\nVALUE   DC   5\n";
+
+    let outputs = Assembler::new().assemble_with_outputs(source).unwrap();
+    let (program, listing) = Assembler::new().assemble_with_listing(source).unwrap();
+    let (_, source_map) = Assembler::new().assemble_with_source_map(source).unwrap();
+    let (_, crossref) = Assembler::new().assemble_with_crossref(source).unwrap();
+
+    assert_eq!(outputs.program.segments, program.segments);
+    assert_eq!(outputs.listing, listing);
+    assert_eq!(outputs.source_map, source_map);
+    assert_eq!(outputs.crossref, crossref);
+    assert!(Assembler::new()
+        .assemble_with_outputs("        LD   NOWHERE\n")
+        .is_err());
+}
+
+#[test]
+fn test_title_sets_listing_heading() {
+    let source = "        TITLE 'MY PROGRAM'\n        ORG  /0100\n        WAIT\n";
+
+    let mut assembler = Assembler::new();
+    let (program, listing) = assembler.assemble_with_listing(source).unwrap();
+
+    assert_eq!(program.listing_title.as_deref(), Some("MY PROGRAM"));
+    assert_eq!(program.segments[0].words, vec![0xB000]);
+    assert!(listing.starts_with("MY PROGRAM\n\n"));
+}
+
+#[test]
+fn test_hdng_without_quotes() {
+    let program = Assembler::new()
+        .assemble("        HDNG PAYROLL RUN\n        WAIT\n")
+        .unwrap();
+
+    assert_eq!(program.listing_title.as_deref(), Some("PAYROLL RUN"));
+    assert_eq!(program.word_count(), 1);
+}
+
+// === Macros ===
+
+#[test]
+fn test_macro_without_parameters() {
+    let source = "HALT    MACRO\n        WAIT\n        MEND\n        ORG  /0100\n        HALT\n        HALT\n";
+
+    let program = Assembler::new().assemble(source).unwrap();
+    assert_eq!(program.segments[0].words, vec![0xB000, 0xB000]);
+}
+
+#[test]
+fn test_macro_with_one_parameter() {
+    let source = r#"
+LOAD    MACRO ADDR
+        LD    ADDR
+        MEND
+        ORG   /0100
+        LOAD  VAL
+VAL     DC    7
+"#;
+
+    let program = Assembler::new().assemble(source).unwrap();
+    assert_eq!(program.segments[0].words, vec![0x6000, 0x0102, 7]);
+}
+
+#[test]
+fn test_macro_with_multiple_parameters_and_label() {
+    let source = r#"
+ADDTO   MACRO SRC,DST
+        LD    DST
+        A     SRC
+        STO   DST
+        MEND
+
+        ORG   /0100
+START   ADDTO ONE,TOTAL
+        ADDTO ONE,TOTAL
+        WAIT
+ONE     DC    1
+TOTAL   DC    40
+"#;
+
+    let program = Assembler::new().assemble(source).unwrap();
+    assert_eq!(program.symbols.get("START"), Some(&0x0100));
+
+    let mut cpu = Cpu::new();
+    cpu.load_program(&program).unwrap();
+    cpu.run(100);
+    let total = *program.symbols.get("TOTAL").unwrap() as usize;
+    assert_eq!(cpu.read_memory(total).unwrap(), 42);
+}
+
+#[test]
+fn test_nested_macro_calls_with_local_labels() {
+    let source = r#"
+HALT    MACRO
+        WAIT
+        MEND
+SKIP    MACRO
+        LD    L$OUT
+        HALT
+L$OUT   DC    0
+        MEND
+
+        ORG   /0100
+        SKIP
+        SKIP
+"#;
+
+    let program = Assembler::new().assemble(source).unwrap();
+
+    // Each expansion gets its own copy of the local label
+    assert_eq!(program.symbols.get("L$001OUT"), Some(&0x0103));
+    assert_eq!(program.symbols.get("L$003OUT"), Some(&0x0107));
+    assert_eq!(
+        program.segments[0].words,
+        vec![0x6000, 0x0103, 0xB000, 0, 0x6000, 0x0107, 0xB000, 0]
+    );
+}
+
+#[test]
+fn test_macro_mexit_ends_expansion() {
+    let source = r#"
+TWO     MACRO
+        DC    1
+        MEXIT
+        DC    2
+        MEND
+        TWO
+"#;
+
+    let program = Assembler::new().assemble(source).unwrap();
+    assert_eq!(program.segments[0].words, vec![1]);
+}
+
+#[test]
+fn test_macro_definition_errors() {
+    let unterminated = "M1      MACRO\n        WAIT\n";
+    assert!(matches!(
+        Assembler::new().assemble(unterminated),
+        Err(AssemblerError::SyntaxError {
+            span: SourceSpan { line: 1, .. },
+            ..
+        })
+    ));
+
+    let stray = "        WAIT\n        MEND\n";
+    assert!(matches!(
+        Assembler::new().assemble(stray),
+        Err(AssemblerError::SyntaxError {
+            span: SourceSpan { line: 2, .. },
+            ..
+        })
+    ));
+
+    let recursive = "LOOP    MACRO\n        LOOP\n        MEND\n        LOOP\n";
+    assert!(matches!(
+        Assembler::new().assemble(recursive),
+        Err(AssemblerError::SyntaxError {
+            span: SourceSpan { line: 4, .. },
+            ..
+        })
+    ));
+}
+
+#[test]
+fn test_error_carries_source_line_text() {
+    let source = "        ORG  /0100\n        LD   MISSING\n        WAIT\n";
+
+    let err = Assembler::new().assemble(source).unwrap_err();
+
+    assert_eq!(err.line(), Some(2));
+    assert_eq!(err.source_text(), Some("        LD   MISSING"));
+}
+
+#[test]
+fn test_error_in_macro_reports_invocation_line() {
+    let source = "\
+LOADX   MACRO ADDR
+        LD   ADDR
+        MEND
+        WAIT
+        LOADX NOWHERE
+";
+
+    let err = Assembler::new().assemble(source).unwrap_err();
+
+    assert_eq!(err.line(), Some(5));
+    assert_eq!(err.source_text(), Some("        LOADX NOWHERE"));
+}
+
+#[test]
+fn test_error_spans_point_at_the_token() {
+    // Undefined symbol inside an indexed operand
+    let source = "        ORG  /0100\n        LD   TABLE+MISSING,1\nTABLE   DC   0\n";
+    let err = Assembler::new().assemble(source).unwrap_err();
+    assert_eq!(err.span(), Some(SourceSpan::new(2, 20, 27)));
+    assert!(
+        err.to_string().contains("Undefined symbol: MISSING"),
+        "{}",
+        err
+    );
+
+    // Second definition of a label
+    let source = "LOOP    WAIT\nLOOP    WAIT\n";
+    let err = Assembler::new().assemble(source).unwrap_err();
+    assert_eq!(err.span(), Some(SourceSpan::new(2, 1, 5)));
+
+    // An error in a repeated expression is found where it is used
+    let source = "        IOCC 2*3,6,2,2*\n";
+    let err = Assembler::new().assemble(source).unwrap_err();
+    assert_eq!(err.span(), Some(SourceSpan::new(1, 22, 24)));
+
+    // ENT and EXTRN names
+    let source = "        ENT  START,NOWHERE\nSTART   WAIT\n";
+    let err = Assembler::new().assemble(source).unwrap_err();
+    assert_eq!(err.span(), Some(SourceSpan::new(1, 20, 27)));
+    let source = "X       WAIT\n        EXTRN Y,X\n";
+    let err = Assembler::new().assemble(source).unwrap_err();
+    assert!(matches!(err, AssemblerError::DuplicateLabel { .. }));
+    assert_eq!(err.span(), Some(SourceSpan::new(2, 17, 18)));
+
+    // Columns of a macro expansion are unknown; the line still is
+    let source = "LOADX   MACRO ADDR\n        LD   ADDR\n        MEND\n        LOADX NOWHERE\n";
+    let err = Assembler::new().assemble(source).unwrap_err();
+    assert_eq!(err.span(), Some(SourceSpan::whole_line(4)));
+}
+
+#[test]
+fn test_footprint_counts_code_data_and_bss() {
+    let source = "
+        ORG  /0200
+START   LD   VALUE
+        WAIT
+VALUE   DC   42
+BUF     BSS  10
+";
+    // LD is two words, WAIT one
+
+    let program = Assembler::new().assemble(source).unwrap();
+    let footprint = program.footprint();
+
+    assert_eq!(
+        footprint,
+        MemoryFootprint {
+            origin: 0x0200,
+            last_address: 0x020D,
+            total_words: 14,
+            code_words: 3,
+            data_words: 1,
+            bss_words: 10,
+        }
+    );
+    assert_eq!(footprint.total_words, program.word_count());
+}
+
+#[test]
+fn test_ent_records_entry_symbols() {
+    let source = "
+        ENT  START
+        ORG  /0100
+START   WAIT
+";
+    let program = Assembler::new().assemble(source).unwrap();
+    assert_eq!(program.entries, ["START"]);
+    assert_eq!(program.symbols.get("START"), Some(&0x0100));
+
+    // An entry must be defined in the module itself
+    let err = Assembler::new()
+        .assemble("        ENT  NOWHERE\n")
+        .unwrap_err();
+    assert!(matches!(err, AssemblerError::UndefinedSymbol { name, .. } if name == "NOWHERE"));
+}
+
+#[test]
+fn test_extrn_reference_records_fixup() {
+    let source = "
+        EXTRN SUB
+        ORG  /0100
+        BSI  SUB
+        LD   /SUB,1
+        DC   SUB
+";
+    let program = Assembler::new().assemble(source).unwrap();
+
+    // Placeholders assemble as zero and stay out of the symbol table
+    assert_eq!(program.segments[0].words[1], 0x0000);
+    assert!(!program.symbols.contains_key("SUB"));
+    assert_eq!(
+        program.external_refs,
+        [
+            ExternalReference {
+                symbol: "SUB".to_string(),
+                address: 0x0101,
+            },
+            ExternalReference {
+                symbol: "SUB".to_string(),
+                address: 0x0103,
+            },
+            ExternalReference {
+                symbol: "SUB".to_string(),
+                address: 0x0104,
+            },
+        ]
+    );
+
+    // A linker can only patch a whole word holding the bare address
+    for invalid in ["        SLA  SUB", "        LD   SUB+1", "        BSS  SUB"] {
+        let source = format!("        EXTRN SUB\n{}\n", invalid);
+        assert!(
+            Assembler::new().assemble(&source).is_err(),
+            "{} should be rejected",
+            invalid
+        );
+    }
+}
+
+#[test]
+fn test_link_resolves_call_into_another_module() {
+    let module_a = "
+        ENT  SUB
+        ORG  /0100
+SUB     DC   0
+        WAIT
+";
+    let module_b = "
+        EXTRN SUB
+        ORG  /0200
+START   BSI  SUB
+        WAIT
+        END  START
+";
+    let a = Assembler::new().assemble(module_a).unwrap();
+    let b = Assembler::new().assemble(module_b).unwrap();
+    let program = link(&[a, b]).unwrap();
+
+    // BSI's address word now holds SUB's address in module A
+    let mut cpu = Cpu::new();
+    cpu.load_program(&program).unwrap();
+    assert_eq!(cpu.read_memory(0x0201).unwrap(), 0x0100);
+    assert_eq!(program.entry_point, Some(0x0200));
+    assert_eq!(program.symbols.get("SUB"), Some(&0x0100));
+    assert!(program.external_refs.is_empty());
+
+    // BSI stores the return address at SUB and continues after it
+    cpu.set_iar(0x0200);
+    cpu.step().unwrap();
+    assert_eq!(cpu.read_memory(0x0100).unwrap(), 0x0202);
+    assert_eq!(cpu.get_iar(), 0x0101);
+}
+
+#[test]
+fn test_link_moves_modules_without_org() {
+    let caller = "
+        EXTRN SUB
+START   BSI  SUB
+BACK    DC   START
+        WAIT
+        END  START
+";
+    let library = "
+        ENT  SUB
+SUB     DC   0
+PTR     DC   SUB
+LEN     DC   PTR-SUB
+        WAIT
+";
+    let library = Assembler::new().assemble(library).unwrap();
+    assert!(library.relocatable);
+    assert_eq!(library.relocations, [0x0001]);
+
+    let program = link(&[Assembler::new().assemble(caller).unwrap(), library]).unwrap();
+
+    // The library follows the caller, and its own address words move too
+    let mut cpu = Cpu::new();
+    cpu.load_program(&program).unwrap();
+    assert_eq!(program.symbols.get("SUB"), Some(&0x0004));
+    assert_eq!(cpu.read_memory(0x0001).unwrap(), 0x0004);
+    assert_eq!(cpu.read_memory(0x0002).unwrap(), 0x0000);
+    assert_eq!(cpu.read_memory(0x0005).unwrap(), 0x0004);
+    assert_eq!(cpu.read_memory(0x0006).unwrap(), 0x0001);
+    assert_eq!(program.entry_point, Some(0x0000));
+    assert_eq!(program.footprint().total_words, 8);
+}
+
+#[test]
+fn test_libf_and_call_assemble_as_bsi() {
+    let source = "
+        ORG  /0100
+        CALL SUB
+        LIBF SQRT
+        DC   ARG
+        WAIT
+SUB     DC   0
+        BSC  *SUB
+ARG     DC   9
+";
+    let program = Assembler::new().assemble(source).unwrap();
+
+    // CALL: direct BSI to SUB; LIBF: BSI through SQRT's transfer vector
+    // word, followed by its argument list
+    assert_eq!(
+        program.segments[0].words[..6],
+        [0x4800, 0x0106, 0x4820, 0x0000, 0x0108, 0xB000]
+    );
+    assert_eq!(program.footprint().code_words, 6);
+    assert!(!program.symbols.contains_key("SQRT"));
+    assert_eq!(
+        program.external_refs,
+        [ExternalReference {
+            symbol: "SQRT".to_string(),
+            address: 0x0103,
+        }]
+    );
+
+    for invalid in ["        CALL", "        LIBF SUB+1", "        CALL 'SUB'"] {
+        assert!(
+            Assembler::new().assemble(invalid).is_err(),
+            "{} should be rejected",
+            invalid
+        );
+    }
+}
+
+#[test]
+fn test_libf_links_through_transfer_vector() {
+    let library = "
+        ENT  SQRT
+        ORG  /0300
+SQRT    DC   ENTRY
+ENTRY   DC   0
+        WAIT
+";
+    let caller = "
+        ORG  /0100
+START   LIBF SQRT
+        WAIT
+        END  START
+";
+    let program = link(&[
+        Assembler::new().assemble(library).unwrap(),
+        Assembler::new().assemble(caller).unwrap(),
+    ])
+    .unwrap();
+
+    let mut cpu = Cpu::new();
+    cpu.load_program(&program).unwrap();
+    assert_eq!(cpu.read_memory(0x0101).unwrap(), 0x0300);
+
+    // BSI I reaches the routine at ENTRY and stores the return address there
+    cpu.set_iar(0x0100);
+    cpu.step().unwrap();
+    assert_eq!(cpu.read_memory(0x0301).unwrap(), 0x0102);
+    assert_eq!(cpu.get_iar(), 0x0302);
+}
+
+#[test]
+fn test_crossref_lists_every_reference() {
+    let source = "
+        ORG  /0100
+START   LD   COUNT
+        A    COUNT
+        STO  COUNT
+        BSC  START
+COUNT   DC   COUNT-START
+";
+    let (program, crossref) = Assembler::new().assemble_with_crossref(source).unwrap();
+
+    // LD, A and STO are two words each, BSC one
+    assert_eq!(program.symbols["COUNT"], 0x0107);
+    assert_eq!(
+        crossref.references_to("COUNT"),
+        [0x0100, 0x0102, 0x0104, 0x0107]
+    );
+    assert_eq!(crossref.references_to("START"), [0x0106, 0x0107]);
+    assert_eq!(crossref.definitions["START"], 0x0100);
+    assert!(crossref
+        .format()
+        .contains("COUNT   0107   0100 0102 0104 0107\n"));
+}
@@ -0,0 +1,153 @@
+//! Breakpoint, watchpoint, register trigger and annotation tests
+
+use s1130_core::cpu::{RegisterId, RunResult};
+use s1130_core::{Cpu, CpuError, Disassembler};
+
+/// Program at 0x0100:
+///   0x0100  SLA 1
+///   0x0101  STO 0x0200
+///   0x0103  SLA 1
+///   0x0104  WAIT
+fn setup_cpu() -> Cpu {
+    let mut cpu = Cpu::new();
+    cpu.write_memory_range(0x0100, &[0x2001, 0x7000, 0x0200, 0x2001, 0xB000])
+        .unwrap();
+    cpu.set_acc(0x0001);
+    cpu.set_iar(0x0100);
+    cpu
+}
+
+#[test]
+fn test_step_stops_at_breakpoint_then_resumes() {
+    let mut cpu = setup_cpu();
+    cpu.set_breakpoint(0x0101);
+
+    cpu.step().unwrap();
+    assert_eq!(cpu.step(), Err(CpuError::Breakpoint(0x0101)));
+    assert_eq!(cpu.get_iar(), 0x0101);
+    assert_eq!(cpu.get_instruction_count(), 1);
+
+    // Next step executes the instruction under the breakpoint
+    cpu.step().unwrap();
+    assert_eq!(cpu.get_iar(), 0x0103);
+}
+
+#[test]
+fn test_run_until_breakpoint() {
+    let mut cpu = setup_cpu();
+    cpu.set_breakpoint(0x0103);
+
+    assert_eq!(
+        cpu.run_until_breakpoint(100),
+        Ok(RunResult::Breakpoint(0x0103))
+    );
+    assert_eq!(cpu.read_memory(0x0200).unwrap(), 0x0002);
+
+    // Resuming runs to the WAIT
+    assert_eq!(cpu.run_until_breakpoint(100), Ok(RunResult::Halted));
+    assert_eq!(cpu.get_acc(), 0x0004);
+}
+
+#[test]
+fn test_run_until_breakpoint_step_limit() {
+    let mut cpu = setup_cpu();
+    assert_eq!(cpu.run_until_breakpoint(2), Ok(RunResult::StepLimitReached));
+    assert_eq!(cpu.get_iar(), 0x0103);
+}
+
+#[test]
+fn test_cleared_breakpoint_does_not_stop() {
+    let mut cpu = setup_cpu();
+    cpu.set_breakpoint(0x0103);
+    assert!(cpu.clear_breakpoint(0x0103));
+
+    assert_eq!(cpu.run_until_breakpoint(100), Ok(RunResult::Halted));
+}
+
+#[test]
+fn test_watchpoint_stops_after_write() {
+    let mut cpu = setup_cpu();
+    cpu.set_watchpoint(0x0200);
+
+    assert_eq!(
+        cpu.run_until_breakpoint(100),
+        Ok(RunResult::Watchpoint(0x0200))
+    );
+
+    // The STO completed before stopping
+    assert_eq!(cpu.read_memory(0x0200).unwrap(), 0x0002);
+    assert_eq!(cpu.get_iar(), 0x0103);
+    assert_eq!(cpu.get_instruction_count(), 2);
+
+    assert_eq!(cpu.run_until_breakpoint(100), Ok(RunResult::Halted));
+}
+
+#[test]
+fn test_register_trigger() {
+    let mut cpu = setup_cpu();
+    cpu.set_register_trigger(RegisterId::Acc, 0x0004);
+
+    assert_eq!(cpu.step(), Ok(()));
+    assert_eq!(cpu.step(), Ok(()));
+    assert_eq!(cpu.step(), Err(CpuError::Breakpoint(0x0104)));
+    assert_eq!(cpu.get_acc(), 0x0004);
+}
+
+#[test]
+fn test_register_trigger_fires_on_change_only() {
+    let mut cpu = setup_cpu();
+    // ACC already holds 1 and the first SLA changes it away
+    cpu.set_register_trigger(RegisterId::Acc, 0x0001);
+
+    assert_eq!(cpu.run_until_breakpoint(100), Ok(RunResult::Halted));
+}
+
+#[test]
+fn test_run_until_address_reaches_target() {
+    let mut cpu = setup_cpu();
+    assert_eq!(cpu.run_until_address(0x0101, 10), Ok(1));
+
+    let mut cpu = setup_cpu();
+    assert_eq!(cpu.run_until_address(0x0104, 10), Ok(3));
+    assert_eq!(cpu.get_iar(), 0x0104);
+    assert_eq!(cpu.read_memory(0x0200).unwrap(), 0x0002);
+}
+
+#[test]
+fn test_run_until_address_stops_short_of_target() {
+    // WAIT comes first
+    let mut cpu = setup_cpu();
+    assert_eq!(
+        cpu.run_until_address(0x0300, 10),
+        Err((4, CpuError::WaitState))
+    );
+
+    // Out of steps
+    let mut cpu = setup_cpu();
+    assert_eq!(
+        cpu.run_until_address(0x0300, 2),
+        Err((2, CpuError::StepLimitReached))
+    );
+    assert_eq!(cpu.get_iar(), 0x0103);
+}
+
+#[test]
+fn test_annotations_survive_stepping_until_hard_reset() {
+    let mut cpu = setup_cpu();
+    cpu.set_annotation(0x0101, "save doubled value");
+    assert_eq!(cpu.annotation(0x0101), Some("save doubled value"));
+    assert_eq!(cpu.annotation(0x0100), None);
+
+    cpu.step().unwrap();
+    cpu.step().unwrap();
+    cpu.reset();
+    assert_eq!(cpu.annotation(0x0101), Some("save doubled value"));
+
+    let lines = Disassembler::new().disassemble_range(&cpu, 0x0100, 3, None);
+    assert_eq!(lines[1], "0x0101  STO  0x0200  * save doubled value");
+
+    cpu.reset_hard();
+    assert_eq!(cpu.annotation(0x0101), None);
+    // Annotations are not memory
+    assert_eq!(cpu.read_memory(0x0101).unwrap(), 0x7000);
+}
@@ -5,7 +5,7 @@
 
 use s1130_core::assembler::Assembler;
 use s1130_core::devices::{DeviceConsoleKeyboard, DeviceConsolePrinter};
-use s1130_core::Cpu;
+use s1130_core::{Cpu, StopReason};
 
 #[test]
 fn test_simple_echo_hello() {
@@ -71,27 +71,19 @@ CHAR    BSS 1           * Character buffer
     let program = assembler.assemble(source).unwrap();
 
     // Load program into memory
-    cpu.write_memory_range(program.origin as usize, &program.words)
-        .unwrap();
-    cpu.set_iar(program.origin);
+    cpu.load_program(&program).unwrap();
 
     // Run program (max 1000 steps to prevent infinite loop)
-    let steps = cpu.run(1000);
+    let (steps, _) = cpu.run(1000);
 
     println!("Executed {} steps", steps);
 
-    // Get printer output by downcasting
-    let printer_device = cpu.get_device_mut_ref(2).unwrap();
-    let printer = printer_device
-        .as_any()
-        .downcast_ref::<DeviceConsolePrinter>()
-        .expect("Expected DeviceConsolePrinter");
-
-    let output = printer.get_output();
+    let output = cpu.get_printer_output().expect("console printer attached");
     println!("Printer output: {:?}", output);
 
-    // Verify output
+    // Verify output, with every typed character consumed
     assert_eq!(output, "hello\n");
+    assert_eq!(cpu.get_keyboard_buffer_len(), 0);
 }
 
 #[test]
@@ -132,19 +124,12 @@ CHAR    BSS 1
     let mut assembler = Assembler::new();
     let program = assembler.assemble(source).unwrap();
 
-    cpu.write_memory_range(program.origin as usize, &program.words)
-        .unwrap();
-    cpu.set_iar(program.origin);
+    cpu.load_program(&program).unwrap();
 
+    assert_eq!(cpu.get_keyboard_buffer_len(), 2);
     cpu.run(1000);
 
-    let printer_device = cpu.get_device_mut_ref(2).unwrap();
-    let printer = printer_device
-        .as_any()
-        .downcast_ref::<DeviceConsolePrinter>()
-        .unwrap();
-
-    assert_eq!(printer.get_output(), "X\n");
+    assert_eq!(cpu.get_printer_output().as_deref(), Some("X\n"));
 }
 
 #[test]
@@ -176,9 +161,7 @@ RESULT  BSS 1
     let mut assembler = Assembler::new();
     let program = assembler.assemble(source).unwrap();
 
-    cpu.write_memory_range(program.origin as usize, &program.words)
-        .unwrap();
-    cpu.set_iar(program.origin);
+    cpu.load_program(&program).unwrap();
 
     cpu.run(100);
 
@@ -187,3 +170,184 @@ RESULT  BSS 1
 
     assert_eq!(result, 1); // Character is ready
 }
+
+#[test]
+fn test_echo_via_device_handle() {
+    let mut cpu = Cpu::new();
+
+    let mut keyboard = DeviceConsoleKeyboard::new();
+    keyboard.type_char(b'Z' as u16);
+
+    let keyboard_handle = cpu.attach_device(Box::new(keyboard)).unwrap();
+    let printer_handle = cpu
+        .attach_device(Box::new(DeviceConsolePrinter::new()))
+        .unwrap();
+
+    let source = r#"
+        ORG 0x100
+
+        XIO KREAD
+        LD  CHAR
+        XIO PWRITE
+        WAIT
+
+KREAD   IOCC CHAR,KEYBOARD,READ,0
+PWRITE  IOCC CHAR,PRINTER,WRITE,0
+
+CHAR    BSS 1
+    "#;
+
+    let mut assembler = Assembler::new();
+    let program = assembler.assemble(source).unwrap();
+
+    cpu.load_program(&program).unwrap();
+
+    cpu.run(100);
+
+    // Single typed call through the handle, no manual downcast
+    let printer = cpu
+        .device_as::<DeviceConsolePrinter>(printer_handle)
+        .unwrap();
+    assert_eq!(printer.get_output(), "Z");
+
+    // Wrong type through a valid handle yields None
+    assert!(cpu
+        .device_as::<DeviceConsolePrinter>(keyboard_handle)
+        .is_none());
+
+    // Mutable access through the handle
+    cpu.device_as_mut::<DeviceConsolePrinter>(printer_handle)
+        .unwrap()
+        .clear_output();
+    assert_eq!(
+        cpu.device_as::<DeviceConsolePrinter>(printer_handle)
+            .unwrap()
+            .get_output(),
+        ""
+    );
+}
+
+#[test]
+fn test_run_with_hook_injects_key_mid_run() {
+    let mut cpu = Cpu::new();
+    let keyboard = cpu
+        .attach_device(Box::new(DeviceConsoleKeyboard::new()))
+        .unwrap();
+    let printer = cpu
+        .attach_device(Box::new(DeviceConsolePrinter::new()))
+        .unwrap();
+
+    // Poll the keyboard until a character is ready, then echo it.
+    // Adding 0x7FFF to the sense status overflows only when it is 1.
+    let source = r#"
+        ORG  0x100
+POLL    XIO  KSENSE
+        LD   STATUS
+        A    MAXP
+        BC   GOT,2
+        BC   POLL
+GOT     XIO  KREAD
+        XIO  PWRITE
+        WAIT
+KSENSE  IOCC STATUS,KEYBOARD,SENSE,0
+KREAD   IOCC CHAR,KEYBOARD,READ,0
+PWRITE  IOCC CHAR,PRINTER,WRITE,0
+STATUS  DC   0
+MAXP    DC   0x7FFF
+CHAR    DC   0
+"#;
+    let program = Assembler::new().assemble(source).unwrap();
+    cpu.load_program(&program).unwrap();
+
+    let mut hook_calls = 0;
+    let (steps, _) = cpu.run_with_hook(1000, |cpu| {
+        hook_calls += 1;
+        if hook_calls == 50 {
+            cpu.device_as_mut::<DeviceConsoleKeyboard>(keyboard)
+                .unwrap()
+                .type_char('K' as u16);
+        }
+    });
+
+    // The program spun before the key arrived, then consumed it
+    assert!(steps > 50);
+    assert!(cpu.get_wait());
+    let printer = cpu.device_as::<DeviceConsolePrinter>(printer).unwrap();
+    assert_eq!(printer.get_output(), "K");
+}
+
+#[test]
+fn test_run_collecting_output_reports_echo() {
+    let mut cpu = Cpu::new();
+    let mut keyboard = DeviceConsoleKeyboard::new();
+    keyboard.type_string("OK\n");
+    cpu.attach_device(Box::new(keyboard)).unwrap();
+    cpu.attach_device(Box::new(DeviceConsolePrinter::new()))
+        .unwrap();
+
+    let source = r#"
+        ORG 0x100
+        XIO KREAD
+        XIO PWRITE
+        XIO KREAD
+        XIO PWRITE
+        XIO KREAD
+        XIO PWRITE
+        WAIT
+
+KREAD   IOCC CHAR,KEYBOARD,READ,0
+PWRITE  IOCC CHAR,PRINTER,WRITE,0
+CHAR    BSS 1
+    "#;
+
+    let program = Assembler::new().assemble(source).unwrap();
+    cpu.load_program(&program).unwrap();
+
+    let report = cpu.run_collecting_output(100);
+
+    assert!(report.halted);
+    assert_eq!(report.steps, 7); // six XIOs and the WAIT
+    assert_eq!(report.printer_output.as_deref(), Some("OK\n"));
+}
+
+#[test]
+fn test_run_collecting_output_without_printer() {
+    let mut cpu = Cpu::new();
+    cpu.write_memory(0x0000, 0xB000).unwrap(); // WAIT
+
+    let report = cpu.run_collecting_output(10);
+
+    assert!(report.halted);
+    assert_eq!(report.printer_output, None);
+}
+
+#[test]
+fn test_polling_empty_keyboard_reports_device_stall() {
+    let source = r#"
+        ORG 0x100
+
+* Poll the keyboard forever: nothing is ever typed
+LOOP    XIO KSENSE
+        BC  LOOP
+
+KSENSE  DC  STATUS      * WCA for sense
+        DC  0x0800      * Device 1 (0x0800), Function 0 (Sense)
+STATUS  BSS 1
+    "#;
+    let program = Assembler::new().assemble(source).unwrap();
+
+    let mut cpu = Cpu::new();
+    cpu.attach_device(Box::new(DeviceConsoleKeyboard::new()))
+        .unwrap();
+    cpu.load_program(&program).unwrap();
+
+    // Without a threshold the loop runs until max_steps
+    assert_eq!(cpu.run(1000), (1000, StopReason::MaxStepsReached));
+
+    // The 50th unchanged sense is the 99th step (XIO, BC, XIO, ...)
+    cpu.set_iar(0x100);
+    cpu.set_stall_threshold(Some(50));
+    assert_eq!(cpu.run(10_000), (99, StopReason::DeviceStall { code: 1 }));
+    let status = program.symbols["STATUS"] as usize;
+    assert_eq!(cpu.read_memory(status).unwrap(), 0);
+}
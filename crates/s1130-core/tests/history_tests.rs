@@ -0,0 +1,108 @@
+//! Step-back history tests
+
+use s1130_core::{Cpu, CpuError};
+
+/// Program at 0x0100:
+///   0x0100  LD  0x0200   (ACC = 5)
+///   0x0102  SLA 1        (ACC = 10)
+///   0x0103  STO 0x0201
+///   0x0105  SLA 1        (ACC = 20)
+///   0x0106  WAIT
+fn setup_cpu() -> Cpu {
+    let mut cpu = Cpu::new();
+    cpu.write_memory_range(
+        0x0100,
+        &[0x6000, 0x0200, 0x2001, 0x7000, 0x0201, 0x2001, 0xB000],
+    )
+    .unwrap();
+    cpu.write_memory(0x0200, 5).unwrap();
+    cpu.set_iar(0x0100);
+    cpu.enable_history(1000);
+    cpu
+}
+
+#[test]
+fn test_three_steps_forward_two_back() {
+    let mut cpu = setup_cpu();
+    cpu.write_memory_range(0x0103, &[0x2001, 0x2001]).unwrap(); // no store
+
+    for _ in 0..3 {
+        cpu.step().unwrap();
+    }
+    assert_eq!((cpu.get_iar(), cpu.get_acc()), (0x0104, 20));
+
+    cpu.step_back().unwrap();
+    cpu.step_back().unwrap();
+    assert_eq!(cpu.get_iar(), 0x0102);
+    assert_eq!(cpu.get_acc(), 5);
+    assert_eq!(cpu.get_instruction_count(), 1);
+
+    // Re-executing from the restored point gives the same results
+    cpu.step().unwrap();
+    assert_eq!((cpu.get_iar(), cpu.get_acc()), (0x0103, 10));
+}
+
+#[test]
+fn test_step_back_over_store_warns_memory_kept() {
+    let mut cpu = setup_cpu();
+    for _ in 0..3 {
+        cpu.step().unwrap();
+    }
+    assert_eq!(cpu.read_memory(0x0201).unwrap(), 10);
+
+    assert_eq!(
+        cpu.step_back(),
+        Err(CpuError::HistoryMemoryModified(0x0103))
+    );
+    // Registers are restored even so; the stored word is not
+    assert_eq!(cpu.get_iar(), 0x0103);
+    assert_eq!(cpu.read_memory(0x0201).unwrap(), 10);
+
+    cpu.step_back().unwrap();
+    cpu.step_back().unwrap();
+    assert_eq!(cpu.get_iar(), 0x0100);
+    assert_eq!(cpu.step_back(), Err(CpuError::HistoryEmpty));
+}
+
+#[test]
+fn test_history_disabled_and_capped() {
+    let mut cpu = setup_cpu();
+    cpu.enable_history(0);
+    cpu.step().unwrap();
+    assert_eq!(cpu.step_back(), Err(CpuError::HistoryEmpty));
+
+    cpu.enable_history(1);
+    cpu.step().unwrap();
+    cpu.step().unwrap();
+    assert_eq!(cpu.state_history().unwrap().len(), 1);
+}
+
+#[test]
+fn test_step_back_restores_mapped_index_register() {
+    let mut cpu = setup_cpu();
+    // LDX 1,0x0200 with 0x1234 at 0x0200
+    cpu.write_memory_range(0x0100, &[0x7440, 0x0200]).unwrap();
+    cpu.write_memory(0x0200, 0x1234).unwrap();
+
+    cpu.step().unwrap();
+    assert_eq!(cpu.read_memory(0x0001).unwrap(), 0x1234);
+
+    cpu.step_back().unwrap();
+    assert_eq!(cpu.get_index_register(1), 0);
+    assert_eq!(cpu.read_memory(0x0001).unwrap(), 0);
+}
+
+#[test]
+fn test_step_back_leaves_interrupt_level() {
+    let mut cpu = setup_cpu();
+    cpu.write_memory(0x000A, 0x0300).unwrap(); // level 2 handler entry
+    cpu.write_memory(0x0301, 0x2000).unwrap(); // SLA 0
+
+    cpu.request_interrupt(2).unwrap();
+    cpu.step().unwrap();
+    assert_eq!(cpu.current_interrupt_level(), Some(2));
+
+    cpu.step_back().unwrap();
+    assert_eq!(cpu.current_interrupt_level(), None);
+    assert_eq!(cpu.get_iar(), 0x0100);
+}
@@ -2,7 +2,8 @@
 //!
 //! These tests verify that each instruction correctly modifies CPU state.
 
-use s1130_core::Cpu;
+use s1130_core::cpu::SkippedWord;
+use s1130_core::{Cpu, CpuError};
 
 // === Load/Store Instructions ===
 
@@ -39,6 +40,21 @@ fn test_ldd_basic() {
     assert_eq!(cpu.get_ext(), 0x5678);
 }
 
+#[test]
+fn test_ldd_at_last_word_is_memory_violation() {
+    let mut cpu = Cpu::with_memory_size(0x0200).unwrap();
+    cpu.set_iar(0x0100);
+    cpu.set_acc(0xAAAA);
+
+    // Setup: LDD 0x01FF, whose second word is past the end of memory
+    cpu.write_memory(0x0100, 0x6800).unwrap(); // LDD opcode
+    cpu.write_memory(0x0101, 0x01FF).unwrap(); // address
+    cpu.write_memory(0x01FF, 0x1234).unwrap();
+
+    assert_eq!(cpu.step(), Err(CpuError::MemoryViolation(0x01FF)));
+    assert_eq!(cpu.get_acc(), 0xAAAA);
+}
+
 #[test]
 fn test_sto_basic() {
     let mut cpu = Cpu::new();
@@ -71,6 +87,20 @@ fn test_std_basic() {
     assert_eq!(cpu.read_memory(0x0201).unwrap(), 0x2222);
 }
 
+#[test]
+fn test_std_at_last_word_writes_nothing() {
+    let mut cpu = Cpu::with_memory_size(0x0200).unwrap();
+    cpu.set_iar(0x0100);
+    cpu.set_acc(0x1111);
+
+    // Setup: STD 0x01FF
+    cpu.write_memory(0x0100, 0x7800).unwrap(); // STD opcode
+    cpu.write_memory(0x0101, 0x01FF).unwrap(); // address
+
+    assert_eq!(cpu.step(), Err(CpuError::MemoryViolation(0x01FF)));
+    assert_eq!(cpu.read_memory(0x01FF).unwrap(), 0);
+}
+
 // === Arithmetic Instructions ===
 
 #[test]
@@ -159,6 +189,31 @@ fn test_subtract_with_borrow() {
     assert!(cpu.get_carry()); // Borrow occurred
 }
 
+/// Run `S` with ACC = `acc` and the operand `operand`, returning (ACC, carry, overflow)
+fn subtract(acc: u16, operand: u16) -> (u16, bool, bool) {
+    let mut cpu = Cpu::new();
+    cpu.set_iar(0x0100);
+    cpu.set_acc(acc);
+    cpu.write_memory(0x0100, 0xC000).unwrap();
+    cpu.write_memory(0x0101, 0x0200).unwrap();
+    cpu.write_memory(0x0200, operand).unwrap();
+
+    cpu.step().unwrap();
+    (cpu.get_acc(), cpu.get_carry(), cpu.get_overflow())
+}
+
+#[test]
+fn test_subtract_carry_is_unsigned_borrow() {
+    // 0x8000 is 32768 unsigned: no borrow, but -32768 - 1 overflows
+    assert_eq!(subtract(0x8000, 0x0001), (0x7FFF, false, true));
+
+    // 0 - 1 borrows; -1 is representable
+    assert_eq!(subtract(0x0000, 0x0001), (0xFFFF, true, false));
+
+    // Equal operands with the sign bit set: neither borrow nor overflow
+    assert_eq!(subtract(0x8000, 0x8000), (0x0000, false, false));
+}
+
 #[test]
 fn test_multiply_basic() {
     let mut cpu = Cpu::new();
@@ -384,6 +439,41 @@ fn test_slca_basic() {
     assert_eq!(cpu.get_ext(), 0x0000);
 }
 
+/// Run `SLCA count` on ACC:EXT = `acc_ext`, returning (ACC:EXT, carry)
+fn slca(acc_ext: u32, count: u8, carry: bool) -> (u32, bool) {
+    let mut cpu = Cpu::new();
+    cpu.set_iar(0x0100);
+    cpu.set_acc((acc_ext >> 16) as u16);
+    cpu.set_ext(acc_ext as u16);
+    cpu.set_carry(carry);
+    cpu.write_memory(0x0100, 0x2800 | count as u16).unwrap(); // SLCA count
+
+    cpu.step().unwrap();
+    (
+        ((cpu.get_acc() as u32) << 16) | cpu.get_ext() as u32,
+        cpu.get_carry(),
+    )
+}
+
+#[test]
+fn test_slca_shift_counts() {
+    // Zero count changes nothing, including carry
+    assert_eq!(slca(0x1234_5678, 0, true), (0x1234_5678, true));
+
+    assert_eq!(slca(0x8000_0001, 1, false), (0x0000_0002, true));
+    assert_eq!(slca(0x0002_0001, 15, false), (0x0000_8000, true));
+    assert_eq!(slca(0x1235_5678, 16, false), (0x5678_0000, true));
+    assert_eq!(slca(0x1234_5678, 16, true), (0x5678_0000, false));
+    assert_eq!(slca(0x0000_8001, 17, false), (0x0002_0000, true));
+    assert_eq!(slca(0x0000_0003, 31, false), (0x8000_0000, true));
+}
+
+#[test]
+fn test_slca_count_wraps_at_32() {
+    assert_eq!(slca(0x8000_0001, 32, false), (0x8000_0001, false));
+    assert_eq!(slca(0x8000_0001, 33, false), (0x0000_0002, true));
+}
+
 #[test]
 fn test_srt_basic() {
     let mut cpu = Cpu::new();
@@ -399,6 +489,63 @@ fn test_srt_basic() {
     assert_eq!(cpu.get_acc_ext(), 0x00123456);
 }
 
+/// Run a short-format shift `opcode | count` on ACC:EXT = `acc_ext`,
+/// returning (ACC:EXT, carry)
+fn shift(opcode: u16, acc_ext: u32, count: u8, carry: bool) -> (u32, bool) {
+    let mut cpu = Cpu::new();
+    cpu.set_iar(0x0100);
+    cpu.set_acc_ext(acc_ext);
+    cpu.set_carry(carry);
+    cpu.write_memory(0x0100, opcode | count as u16).unwrap();
+
+    cpu.step().unwrap();
+    (cpu.get_acc_ext(), cpu.get_carry())
+}
+
+#[test]
+fn test_srt_zero_fills_high_bit() {
+    const SRT: u16 = 0x3800;
+
+    // The vacated high bits are zero even with the sign bit set
+    assert_eq!(shift(SRT, 0x8000_0000, 1, false), (0x4000_0000, false));
+    assert_eq!(shift(SRT, 0x8000_0000, 16, false), (0x0000_8000, false));
+    assert_eq!(shift(SRT, 0xFFFF_FFFF, 31, false), (0x0000_0001, true));
+
+    // Carry is the last bit shifted out of EXT
+    assert_eq!(shift(SRT, 0x8000_0001, 1, false), (0x4000_0000, true));
+    assert_eq!(shift(SRT, 0x8000_0008, 4, false), (0x0800_0000, true));
+    assert_eq!(shift(SRT, 0x8000_0008, 3, true), (0x1000_0001, false));
+
+    // Zero count changes nothing, including carry
+    assert_eq!(shift(SRT, 0x8000_0001, 0, true), (0x8000_0001, true));
+}
+
+#[test]
+fn test_sra_sign_extends_unlike_srt() {
+    const SRA: u16 = 0x3000;
+    const SRT: u16 = 0x3800;
+
+    // SRA shifts ACC alone, copying the sign bit; EXT is untouched
+    assert_eq!(shift(SRA, 0x8000_1234, 4, false), (0xF800_1234, false));
+    assert_eq!(shift(SRT, 0x8000_1234, 4, false), (0x0800_0123, false));
+}
+
+#[test]
+fn test_sla_sra_counts_of_16_and_more() {
+    const SLA: u16 = 0x2000;
+    const SRA: u16 = 0x3000;
+
+    // SLA clears ACC; carry is the old low bit at 16, zero beyond
+    assert_eq!(shift(SLA, 0x8001_1234, 16, false), (0x0000_1234, true));
+    assert_eq!(shift(SLA, 0xFFFF_1234, 17, true), (0x0000_1234, false));
+    assert_eq!(shift(SLA, 0xFFFF_1234, 31, true), (0x0000_1234, false));
+
+    // SRA fills ACC with the sign bit, which is also the last bit out
+    assert_eq!(shift(SRA, 0x8000_1234, 16, false), (0xFFFF_1234, true));
+    assert_eq!(shift(SRA, 0x8000_1234, 17, false), (0xFFFF_1234, true));
+    assert_eq!(shift(SRA, 0x7FFF_1234, 31, true), (0x0000_1234, false));
+}
+
 // === Branch Instructions ===
 
 #[test]
@@ -464,6 +611,113 @@ fn test_bc_carry_false() {
     assert_eq!(cpu.get_iar(), 0x0101); // Short format, advances by 1
 }
 
+#[test]
+fn test_bc_short_backward_branch() {
+    let mut cpu = Cpu::new();
+    cpu.set_iar(0x0105);
+
+    // Setup: BC with tag=0, direct, displacement 0x1B (-5)
+    // Target is relative to the updated IAR: 0x0106 - 5 = 0x0101
+    cpu.write_memory(0x0105, 0x4000 | 0x001B).unwrap();
+
+    cpu.step().unwrap();
+
+    assert_eq!(cpu.get_iar(), 0x0101);
+}
+
+#[test]
+fn test_bc_short_forward_branch() {
+    let mut cpu = Cpu::new();
+    cpu.set_iar(0x0100);
+
+    // Setup: BC with tag=0, direct, displacement +4
+    cpu.write_memory(0x0100, 0x4004).unwrap();
+
+    cpu.step().unwrap();
+
+    assert_eq!(cpu.get_iar(), 0x0105);
+}
+
+#[test]
+fn test_bc_short_backward_branch_ignores_index_register() {
+    let mut cpu = Cpu::new();
+    cpu.set_iar(0x0105);
+    cpu.set_carry(true);
+    cpu.set_index_register(1, 0x0040);
+
+    // Setup: BC with tag=1 (branch if carry), direct, displacement -5
+    // The tag selects the condition; XR1 must not be added to the target
+    cpu.write_memory(0x0105, 0x4040 | 0x001B).unwrap();
+
+    cpu.step().unwrap();
+
+    assert_eq!(cpu.get_iar(), 0x0101);
+}
+
+#[test]
+fn test_bsc_short_skips_when_condition_holds() {
+    let mut cpu = Cpu::new();
+    cpu.set_iar(0x0100);
+    cpu.set_carry(true);
+
+    // BSC tag=1 (skip if carry), direct
+    cpu.write_memory(0x0100, 0x5040).unwrap();
+    cpu.write_memory(0x0101, 0x2001).unwrap(); // SLA 1 (skipped)
+    cpu.write_memory(0x0102, 0xB000).unwrap(); // WAIT
+
+    cpu.step().unwrap();
+
+    assert_eq!(cpu.get_iar(), 0x0102);
+}
+
+#[test]
+fn test_bsc_short_does_not_skip_when_condition_fails() {
+    let mut cpu = Cpu::new();
+    cpu.set_iar(0x0100);
+    cpu.set_carry(false);
+
+    // BSC tag=1 (skip if carry), direct
+    cpu.write_memory(0x0100, 0x5040).unwrap();
+    cpu.write_memory(0x0101, 0x2001).unwrap(); // SLA 1
+    cpu.write_memory(0x0102, 0xB000).unwrap(); // WAIT
+
+    cpu.step().unwrap();
+
+    assert_eq!(cpu.get_iar(), 0x0101);
+}
+
+#[test]
+fn test_bsc_short_skips_whole_long_instruction() {
+    let mut cpu = Cpu::new();
+    cpu.set_iar(0x0100);
+
+    // Unconditional BSC skips a two-word LD
+    cpu.write_memory(0x0100, 0x5000).unwrap();
+    cpu.write_memory(0x0101, 0x6000).unwrap(); // LD
+    cpu.write_memory(0x0102, 0x0200).unwrap();
+    cpu.write_memory(0x0103, 0xB000).unwrap(); // WAIT
+
+    cpu.step().unwrap();
+
+    assert_eq!(cpu.get_iar(), 0x0103);
+}
+
+#[test]
+fn test_bsc_indirect_branches_and_stores() {
+    let mut cpu = Cpu::new();
+    cpu.set_iar(0x0100);
+    cpu.set_carry(true);
+
+    // BSC tag=1, indirect through 0x10 -> subroutine at 0x0300
+    cpu.write_memory(0x0100, 0x5060 | 0x0010).unwrap();
+    cpu.write_memory(0x0010, 0x0300).unwrap();
+
+    cpu.step().unwrap();
+
+    assert_eq!(cpu.read_memory(0x0300).unwrap(), 0x0101);
+    assert_eq!(cpu.get_iar(), 0x0301);
+}
+
 // === Index Register Instructions ===
 
 #[test]
@@ -533,6 +787,41 @@ fn test_mdx_with_skip() {
     assert_eq!(cpu.get_iar(), 0x0103); // Skipped one instruction
 }
 
+#[test]
+fn test_mdx_tag0_modifies_memory_no_skip() {
+    let mut cpu = Cpu::new();
+    cpu.set_iar(0x0100);
+    cpu.set_index_register(1, 7);
+
+    // MDX 0,0x0200,-1: decrement the word at 0x0200
+    cpu.write_memory(0x0100, 0x581F).unwrap();
+    cpu.write_memory(0x0101, 0x0200).unwrap();
+    cpu.write_memory(0x0200, 3).unwrap();
+
+    cpu.step().unwrap();
+
+    assert_eq!(cpu.read_memory(0x0200).unwrap(), 2);
+    assert_eq!(cpu.get_index_register(1), 7); // No index register involved
+    assert_eq!(cpu.get_iar(), 0x0102);
+}
+
+#[test]
+fn test_mdx_tag0_modifies_memory_with_skip() {
+    let mut cpu = Cpu::new();
+    cpu.set_iar(0x0100);
+
+    // MDX 0,0x0200,+2 on -2: result zero skips the next instruction
+    cpu.write_memory(0x0100, 0x5802).unwrap();
+    cpu.write_memory(0x0101, 0x0200).unwrap();
+    cpu.write_memory(0x0102, 0xB000).unwrap(); // This should be skipped
+    cpu.write_memory(0x0200, (-2i16) as u16).unwrap();
+
+    cpu.step().unwrap();
+
+    assert_eq!(cpu.read_memory(0x0200).unwrap(), 0);
+    assert_eq!(cpu.get_iar(), 0x0103);
+}
+
 // === Status Instructions ===
 
 #[test]
@@ -568,3 +857,44 @@ fn test_sts_basic() {
     assert_eq!(status_word & 0x8000, 0x8000); // Carry bit set
     assert_eq!(status_word & 0x4000, 0); // Overflow bit clear
 }
+
+// === Invalid Instructions ===
+
+#[test]
+fn test_invalid_opcode_errors_by_default() {
+    let mut cpu = Cpu::new();
+    cpu.set_iar(0x0100);
+    cpu.write_memory(0x0100, 0x0000).unwrap(); // not an opcode
+
+    assert!(matches!(
+        cpu.step(),
+        Err(CpuError::InvalidInstruction(0x0100))
+    ));
+    assert_eq!(cpu.get_iar(), 0x0100);
+    assert!(cpu.skipped_words().is_empty());
+}
+
+#[test]
+fn test_skip_invalid_continues_past_data_word() {
+    let mut cpu = Cpu::new();
+    cpu.set_skip_invalid(true);
+    cpu.set_iar(0x0100);
+    cpu.set_acc(1);
+
+    cpu.write_memory(0x0100, 0x2001).unwrap(); // SLA 1
+    cpu.write_memory(0x0101, 0x1234).unwrap(); // data, not an opcode
+    cpu.write_memory(0x0102, 0x2001).unwrap(); // SLA 1
+    cpu.write_memory(0x0103, 0xB000).unwrap(); // WAIT
+
+    cpu.run(10);
+
+    assert!(cpu.get_wait());
+    assert_eq!(cpu.get_acc(), 4); // both shifts ran
+    assert_eq!(
+        cpu.skipped_words(),
+        [SkippedWord {
+            address: 0x0101,
+            word: 0x1234
+        }]
+    );
+}
@@ -0,0 +1,292 @@
+//! Interrupt system tests
+//!
+//! These tests verify that interrupt requests vector through the level
+//! vectors, nest by priority, respect the mask register and return to the
+//! interrupted program.
+
+use s1130_core::cpu::interrupts::ILSW_BASE;
+use s1130_core::devices::card_reader::{self, Card, Device2501};
+use s1130_core::{Cpu, CpuError};
+
+/// SLA 0 - used as a one-word no-op
+const NOP: u16 = 0x2000;
+
+/// Build a CPU with a main program at 0x0100 and a handler for each
+/// level `n` at `0x0300 + n * 0x0100` (entry word followed by no-ops).
+fn setup_cpu() -> Cpu {
+    let mut cpu = Cpu::new();
+
+    for address in 0x0100..0x0110 {
+        cpu.write_memory(address, NOP).unwrap();
+    }
+
+    for level in 0..5u16 {
+        let entry = 0x0300 + level * 0x0100;
+        cpu.write_memory((0x0008 + level) as usize, entry).unwrap();
+        for offset in 1..0x10 {
+            cpu.write_memory((entry + offset) as usize, NOP).unwrap();
+        }
+    }
+
+    cpu.set_iar(0x0100);
+    cpu
+}
+
+#[test]
+fn test_interrupt_vectors_to_handler() {
+    let mut cpu = setup_cpu();
+    cpu.step().unwrap(); // main: 0x0100
+
+    cpu.request_interrupt(2).unwrap();
+    cpu.step().unwrap(); // vector to level 2, execute 0x0501
+
+    assert_eq!(cpu.read_memory(0x0500).unwrap(), 0x0101); // return address
+    assert_eq!(cpu.get_iar(), 0x0502);
+    assert_eq!(cpu.current_interrupt_level(), Some(2));
+    assert_eq!(cpu.get_state().current_interrupt_level, Some(2));
+}
+
+#[test]
+fn test_return_from_interrupt() {
+    let mut cpu = setup_cpu();
+    cpu.step().unwrap();
+
+    cpu.request_interrupt(4).unwrap();
+    cpu.step().unwrap();
+    cpu.step().unwrap();
+    assert_eq!(cpu.get_iar(), 0x0703);
+
+    assert_eq!(cpu.return_from_interrupt().unwrap(), Some(4));
+    assert_eq!(cpu.get_iar(), 0x0101);
+    assert_eq!(cpu.current_interrupt_level(), None);
+
+    // Main program resumes where it left off
+    cpu.step().unwrap();
+    assert_eq!(cpu.get_iar(), 0x0102);
+
+    // Nothing left to return from
+    assert_eq!(cpu.return_from_interrupt().unwrap(), None);
+}
+
+#[test]
+fn test_nested_interrupts() {
+    let mut cpu = setup_cpu();
+    cpu.step().unwrap(); // main: 0x0100
+
+    // Enter level 2
+    cpu.request_interrupt(2).unwrap();
+    cpu.step().unwrap();
+    assert_eq!(cpu.get_iar(), 0x0502);
+
+    // Lower priority level 3 must wait
+    cpu.request_interrupt(3).unwrap();
+    cpu.step().unwrap();
+    assert_eq!(cpu.get_iar(), 0x0503);
+    assert_eq!(cpu.current_interrupt_level(), Some(2));
+
+    // Higher priority level 0 nests inside level 2
+    cpu.request_interrupt(0).unwrap();
+    cpu.step().unwrap();
+    assert_eq!(cpu.read_memory(0x0300).unwrap(), 0x0503);
+    assert_eq!(cpu.get_iar(), 0x0302);
+    assert_eq!(cpu.current_interrupt_level(), Some(0));
+
+    // Leaving level 0 resumes the level 2 handler
+    assert_eq!(cpu.return_from_interrupt().unwrap(), Some(0));
+    assert_eq!(cpu.get_iar(), 0x0503);
+    assert_eq!(cpu.current_interrupt_level(), Some(2));
+
+    // Level 3 still cannot preempt level 2
+    cpu.step().unwrap();
+    assert_eq!(cpu.get_iar(), 0x0504);
+
+    // Leaving level 2 lets the pending level 3 in before main resumes
+    assert_eq!(cpu.return_from_interrupt().unwrap(), Some(2));
+    assert_eq!(cpu.get_iar(), 0x0101);
+    cpu.step().unwrap();
+    assert_eq!(cpu.read_memory(0x0600).unwrap(), 0x0101);
+    assert_eq!(cpu.get_iar(), 0x0602);
+    assert_eq!(cpu.current_interrupt_level(), Some(3));
+
+    assert_eq!(cpu.return_from_interrupt().unwrap(), Some(3));
+    assert_eq!(cpu.get_iar(), 0x0101);
+    assert_eq!(cpu.current_interrupt_level(), None);
+}
+
+#[test]
+fn test_masked_interrupt_is_deferred() {
+    let mut cpu = setup_cpu();
+    cpu.set_interrupt_mask(1 << 1);
+
+    cpu.request_interrupt(1).unwrap();
+    cpu.step().unwrap();
+    cpu.step().unwrap();
+    assert_eq!(cpu.get_iar(), 0x0102);
+    assert_eq!(cpu.current_interrupt_level(), None);
+    assert_eq!(cpu.interrupts().pending(), 1 << 1);
+
+    // Unmasking lets the pending request through
+    cpu.set_interrupt_mask(0);
+    cpu.step().unwrap();
+    assert_eq!(cpu.read_memory(0x0400).unwrap(), 0x0102);
+    assert_eq!(cpu.get_iar(), 0x0402);
+    assert_eq!(cpu.current_interrupt_level(), Some(1));
+}
+
+#[test]
+fn test_mask_does_not_block_other_levels() {
+    let mut cpu = setup_cpu();
+    cpu.set_interrupt_mask(1 << 0);
+
+    cpu.request_interrupt(0).unwrap();
+    cpu.request_interrupt(3).unwrap();
+    cpu.step().unwrap();
+
+    assert_eq!(cpu.current_interrupt_level(), Some(3));
+}
+
+#[test]
+fn test_ilsw_stored_on_entry() {
+    let mut cpu = setup_cpu();
+
+    cpu.request_interrupt_with_status(4, 0x8000).unwrap();
+    cpu.request_interrupt_with_status(4, 0x0400).unwrap();
+    cpu.step().unwrap();
+
+    assert_eq!(cpu.read_memory((ILSW_BASE + 4) as usize).unwrap(), 0x8400);
+}
+
+#[test]
+fn test_invalid_interrupt_level() {
+    let mut cpu = setup_cpu();
+    assert_eq!(
+        cpu.request_interrupt(5),
+        Err(CpuError::InvalidInterruptLevel(5))
+    );
+}
+
+#[test]
+fn test_reset_clears_interrupts() {
+    let mut cpu = setup_cpu();
+    cpu.request_interrupt(2).unwrap();
+    cpu.step().unwrap();
+    cpu.request_interrupt(1).unwrap();
+    cpu.set_interrupt_mask(0x1F);
+
+    cpu.reset();
+
+    assert_eq!(cpu.current_interrupt_level(), None);
+    assert_eq!(cpu.interrupts().pending(), 0);
+    assert_eq!(cpu.get_interrupt_mask(), 0);
+}
+
+#[test]
+fn test_acknowledged_device_does_not_reinterrupt() {
+    let mut cpu = setup_cpu();
+    let mut reader = Device2501::new();
+    reader.load_card(Card::from_data(&[0x1111, 0x2222]));
+    cpu.attach_device(Box::new(reader)).unwrap();
+
+    // XIO InitRead of 2 words into 0x0211 via the IOCC at 0x0200
+    cpu.write_memory_range(0x0100, &[0x4400, 0x0200]).unwrap();
+    cpu.write_memory_range(0x0200, &[0x0210, 0x4A00]).unwrap();
+    cpu.write_memory(0x0210, (-2i16) as u16).unwrap();
+
+    cpu.step().unwrap(); // XIO completes the read at once
+    assert_eq!(cpu.read_memory(0x0211).unwrap(), 0x1111);
+
+    cpu.step().unwrap(); // vector to level 4, execute 0x0701
+    assert_eq!(cpu.current_interrupt_level(), Some(4));
+    assert_eq!(
+        cpu.read_memory((ILSW_BASE + 4) as usize).unwrap(),
+        card_reader::ILSW_BIT
+    );
+    let reader = cpu.get_device(9).unwrap();
+    assert_eq!(reader.interrupt_request(), None);
+
+    assert_eq!(cpu.return_from_interrupt().unwrap(), Some(4));
+    cpu.step().unwrap();
+    cpu.step().unwrap();
+
+    // The handler ran once; the main program carries on uninterrupted
+    assert_eq!(cpu.current_interrupt_level(), None);
+    assert_eq!(cpu.get_iar(), 0x0104);
+    assert_eq!(cpu.interrupts().pending(), 0);
+}
+
+#[test]
+fn test_device_interrupt_ends_wait() {
+    let mut cpu = setup_cpu();
+    let mut reader = Device2501::new();
+    reader.load_card(Card::from_data(&[0x1111]));
+    cpu.attach_device(Box::new(reader)).unwrap();
+
+    // XIO InitRead of 1 word; the completion interrupt is then pending
+    cpu.write_memory_range(0x0100, &[0x4400, 0x0200]).unwrap();
+    cpu.write_memory_range(0x0200, &[0x0210, 0x4A00]).unwrap();
+    cpu.write_memory(0x0210, (-1i16) as u16).unwrap();
+    cpu.step().unwrap();
+
+    // A CPU waiting for the read is woken by its interrupt
+    cpu.set_wait(true);
+    cpu.step().unwrap();
+    assert!(!cpu.get_wait());
+    assert_eq!(cpu.current_interrupt_level(), Some(4));
+    assert_eq!(cpu.read_memory(0x0700).unwrap(), 0x0102);
+
+    // With nothing pending a waiting CPU stays put
+    cpu.return_from_interrupt().unwrap();
+    cpu.set_wait(true);
+    assert!(matches!(cpu.step(), Err(CpuError::WaitState)));
+    assert_eq!(cpu.get_iar(), 0x0102);
+}
+
+#[test]
+fn test_replaced_card_reader_starts_clean() {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    let mut cpu = setup_cpu();
+    let mut reader = Device2501::new();
+    reader.load_cards(vec![
+        Card::from_data(&[0x1111, 0x2222]),
+        Card::from_data(&[0x3333, 0x4444]),
+    ]);
+    cpu.attach_device(Box::new(reader)).unwrap();
+
+    let replaced = Arc::new(AtomicBool::new(false));
+    let flag = Arc::clone(&replaced);
+    cpu.set_device_replacement_callback(Some(Box::new(move |code| {
+        assert_eq!(code, 9);
+        flag.store(true, Ordering::SeqCst);
+    })));
+
+    // XIO InitRead of 2 words into 0x0211 via the IOCC at 0x0200, twice
+    cpu.write_memory_range(0x0100, &[0x4400, 0x0200, 0x4400, 0x0200])
+        .unwrap();
+    cpu.write_memory_range(0x0200, &[0x0210, 0x4A00]).unwrap();
+    cpu.write_memory(0x0210, (-2i16) as u16).unwrap();
+    cpu.step().unwrap();
+
+    // Swap readers while the first one's completion interrupt is pending
+    let mut fresh = Device2501::new();
+    fresh.load_card(Card::from_data(&[0x5555, 0x6666]));
+    let old = cpu.replace_device(9, Box::new(fresh)).unwrap().unwrap();
+    assert!(replaced.load(Ordering::SeqCst));
+    assert!(old.interrupt_request().is_some());
+
+    let reader = cpu.get_device(9).unwrap();
+    assert_eq!(reader.interrupt_request(), None);
+    assert!(!reader.is_busy());
+    assert_eq!(
+        reader.get_status_word() & card_reader::STATUS_HOPPER_COUNT,
+        1 << 2
+    );
+
+    // The old interrupt went with the old reader; the next XIO reads the
+    // new deck
+    cpu.step().unwrap();
+    assert_eq!(cpu.current_interrupt_level(), None);
+    assert_eq!(cpu.read_memory(0x0211).unwrap(), 0x5555);
+    assert_eq!(cpu.read_memory(0x0212).unwrap(), 0x6666);
+}
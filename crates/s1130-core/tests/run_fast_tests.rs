@@ -0,0 +1,81 @@
+//! Decode-cached execution tests
+//!
+//! `run_fast` must produce exactly the same machine state as `run`, even
+//! when a program rewrites instructions it has already executed.
+
+use s1130_core::assembler::Assembler;
+use s1130_core::Cpu;
+
+fn load(source: &str) -> Cpu {
+    let program = Assembler::new().assemble(source).unwrap();
+    let mut cpu = Cpu::new();
+    cpu.load_program(&program).unwrap();
+    cpu
+}
+
+fn assert_same_execution(source: &str) -> Cpu {
+    let mut slow = load(source);
+    let mut fast = slow.clone();
+
+    let slow_steps = slow.run_silent(1000);
+    let fast_steps = fast.run_fast(1000);
+
+    assert_eq!(fast_steps, slow_steps);
+    assert_eq!(fast.get_state(), slow.get_state());
+    assert_eq!(
+        fast.read_memory_range(0, 0x200),
+        slow.read_memory_range(0, 0x200)
+    );
+    fast
+}
+
+#[test]
+fn test_run_fast_sees_rewritten_displacement_word() {
+    // The second pass through LOOP must use the patched MDX operand
+    let cpu = assert_same_execution(
+        r#"
+        ORG  0x100
+        LDX  1,COUNT
+LOOP    MDX  2,ONE
+        LD   NEWADR
+        STO  LOOP+1
+        MDX  1,MINUS1
+        BC   LOOP
+        WAIT
+COUNT   DC   2
+ONE     DC   1
+TEN     DC   10
+MINUS1  DC   -1
+NEWADR  DC   TEN
+"#,
+    );
+
+    assert_eq!(cpu.get_state().xr2, 11);
+}
+
+#[test]
+fn test_run_fast_sees_rewritten_opcode_word() {
+    // SLA 1 is replaced by SLA 2 after the first pass
+    let cpu = assert_same_execution(
+        r#"
+        ORG  0x100
+        LDX  1,COUNT
+        LD   ONE
+LOOP    SLA  1
+        STO  SAVE
+        LD   NEWOP
+        STO  LOOP
+        LD   SAVE
+        MDX  1,MINUS1
+        BC   LOOP
+        WAIT
+COUNT   DC   2
+ONE     DC   1
+MINUS1  DC   -1
+NEWOP   DC   0x2002
+SAVE    DC   0
+"#,
+    );
+
+    assert_eq!(cpu.get_acc(), 1 << 3);
+}
@@ -38,12 +38,10 @@ CHAR    BSS 1
 
     println!("Program assembled successfully");
     println!("Origin: 0x{:04X}", program.origin);
-    println!("Words: {:?}", program.words);
+    println!("Segments: {:?}", program.segments);
     println!("Symbols: {:?}", program.symbols);
 
-    cpu.write_memory_range(program.origin as usize, &program.words)
-        .unwrap();
-    cpu.set_iar(program.origin);
+    cpu.load_program(&program).unwrap();
 
     println!("Starting execution...");
 
@@ -72,13 +70,7 @@ CHAR    BSS 1
     println!("  Steps: {}", state.instruction_count);
 
     // Check printer output
-    let printer_device = cpu.get_device_mut_ref(2).unwrap();
-    let printer = printer_device
-        .as_any()
-        .downcast_ref::<DeviceConsolePrinter>()
-        .unwrap();
-
-    let output = printer.get_output();
+    let output = cpu.get_printer_output().unwrap();
     println!("  Printer output: {:?}", output);
 
     assert_eq!(output, "A");
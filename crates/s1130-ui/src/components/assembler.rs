@@ -2,10 +2,17 @@
 
 use crate::cpu_context::use_cpu;
 use gloo::console;
+use gloo::file::callbacks::FileReader;
+use gloo::file::{Blob, File, ObjectUrl};
 use serde::Deserialize;
-use web_sys::HtmlTextAreaElement;
+use std::collections::BTreeMap;
+use wasm_bindgen::JsCast;
+use web_sys::{HtmlElement, HtmlInputElement, HtmlTextAreaElement};
 use yew::prelude::*;
 
+/// File name offered when saving the assembled program
+const PROGRAM_FILE_NAME: &str = "program.s130";
+
 #[derive(Debug, Deserialize)]
 struct AssemblyResult {
     success: bool,
@@ -13,11 +20,158 @@ struct AssemblyResult {
     origin: Option<u16>,
     #[serde(rename = "entryPoint")]
     entry_point: Option<u16>,
+    /// Emitted words (code and data)
     #[serde(rename = "codeSize")]
     code_size: Option<usize>,
+    /// Whole footprint including BSS, from `AssembledProgram::footprint()`
+    #[serde(rename = "totalWords", default)]
+    total_words: Option<usize>,
     message: String,
     #[serde(default)]
     errors: Vec<String>,
+    /// Source locations of the errors, where known
+    #[serde(default)]
+    spans: Vec<ErrorSpan>,
+}
+
+/// Location of an assembly error; columns are 1-indexed, end exclusive,
+/// and 0 for the whole line
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ErrorSpan {
+    line: usize,
+    column_start: usize,
+    column_end: usize,
+}
+
+impl ErrorSpan {
+    /// Selection range in `code` as UTF-16 offsets, as a textarea counts
+    fn selection(self, code: &str) -> Option<(u32, u32)> {
+        let mut offset = 0;
+        for (index, text) in code.split('\n').enumerate() {
+            if index + 1 == self.line {
+                let (start, end) = if self.column_start > 0 {
+                    (self.column_start - 1, self.column_end - 1)
+                } else {
+                    (0, text.chars().count())
+                };
+                let utf16_len =
+                    |count: usize| text.chars().take(count).map(char::len_utf16).sum::<usize>();
+                return Some((
+                    (offset + utf16_len(start)) as u32,
+                    (offset + utf16_len(end)) as u32,
+                ));
+            }
+            offset += text.encode_utf16().count() + 1;
+        }
+        None
+    }
+}
+
+impl AssemblyResult {
+    /// Text for the Messages tab
+    fn summary(&self) -> String {
+        if !self.success {
+            let mut msg = format!("✗ {}\n\n", self.message);
+            for (i, error) in self.errors.iter().enumerate() {
+                msg.push_str(&format!("{}. {}\n", i + 1, error));
+            }
+            return msg;
+        }
+
+        let mut msg = format!("✓ {}\n\n", self.message);
+        if let Some(origin) = self.origin {
+            msg.push_str(&format!("Origin: 0x{:04X}\n", origin));
+        }
+        if let Some(entry) = self.entry_point {
+            msg.push_str(&format!("Entry Point: 0x{:04X}\n", entry));
+        }
+        if let Some(size) = self.code_size {
+            msg.push_str(&format!("Code Size: {} words\n", size));
+        }
+        if let Some(total) = self.total_words {
+            msg.push_str(&format!("Total Footprint: {} words\n", total));
+        }
+        msg.push_str("\nProgram loaded into memory and ready to execute.");
+        msg
+    }
+}
+
+/// The parts of an `assembleWithOutputs` result the output tabs show
+#[derive(Debug, Deserialize)]
+struct OutputsResult {
+    assembly_result: AssemblyResult,
+    listing: String,
+    crossref: String,
+}
+
+/// Code size stat: emitted words and total footprint
+#[derive(Clone, Copy, PartialEq)]
+struct CodeSize {
+    emitted: usize,
+    total: usize,
+}
+
+impl CodeSize {
+    fn from_result(result: &AssemblyResult) -> Option<Self> {
+        let emitted = result.code_size?;
+        Some(Self {
+            emitted,
+            total: result.total_words.unwrap_or(emitted),
+        })
+    }
+
+    /// Stat text; the total is shown only when BSS makes it larger
+    fn label(self) -> String {
+        if self.total > self.emitted {
+            format!("{} words ({} total)", self.emitted, self.total)
+        } else {
+            format!("{} words", self.emitted)
+        }
+    }
+}
+
+/// State handles showing the outcome of assembling or loading a program
+#[derive(Clone)]
+struct ResultView {
+    output: UseStateHandle<String>,
+    status: UseStateHandle<String>,
+    error_count: UseStateHandle<usize>,
+    success: UseStateHandle<bool>,
+    code_size: UseStateHandle<Option<CodeSize>>,
+}
+
+impl ResultView {
+    fn show(&self, result: &AssemblyResult) {
+        self.success.set(result.success);
+        if result.success {
+            self.code_size.set(CodeSize::from_result(result));
+            self.error_count.set(0);
+            self.status.set("Success".to_string());
+        } else {
+            self.code_size.set(None);
+            self.error_count.set(result.errors.len());
+            self.status.set("Error".to_string());
+        }
+        self.output.set(result.summary());
+    }
+
+    fn show_error(&self, message: String) {
+        self.success.set(false);
+        self.code_size.set(None);
+        self.error_count.set(1);
+        self.status.set("Error".to_string());
+        self.output.set(message);
+    }
+}
+
+/// Output panel tabs
+#[derive(Clone, Copy, PartialEq)]
+enum OutputTab {
+    Messages,
+    Listing,
+    SymbolTable,
+    CrossReference,
 }
 
 #[function_component(AssemblerView)]
@@ -27,12 +181,32 @@ pub fn assembler_view() -> Html {
 
     let code = use_state(|| sample_code.to_string());
     let output = use_state(|| "Ready to assemble...".to_string());
+    let listing = use_state(String::new);
+    let crossref = use_state(String::new);
+    let symbol_filter = use_state(String::new);
+    let active_tab = use_state(|| OutputTab::Messages);
     let status = use_state(|| "Ready".to_string());
     let error_count = use_state(|| 0usize);
     let success = use_state(|| false);
+    let code_size = use_state(|| None::<CodeSize>);
     let editor_ref = use_node_ref();
+    let gutter_ref = use_node_ref();
+    let file_input_ref = use_node_ref();
+    // Kept alive until the read completes / the download has started
+    let file_reader = use_mut_ref(|| None::<FileReader>);
+    let download_url = use_mut_ref(|| None::<ObjectUrl>);
+
+    let view = ResultView {
+        output: output.clone(),
+        status: status.clone(),
+        error_count: error_count.clone(),
+        success: success.clone(),
+        code_size: code_size.clone(),
+    };
 
     let line_count = code.lines().count();
+    // Source line of the instruction at IAR, to highlight in the gutter
+    let current_line = cpu_ctx.cpu.borrow().current_source_line();
 
     let on_code_change = {
         let code = code.clone();
@@ -43,12 +217,27 @@ pub fn assembler_view() -> Html {
         })
     };
 
+    // Keep the line numbers level with the text
+    let on_editor_scroll = {
+        let editor_ref = editor_ref.clone();
+        let gutter_ref = gutter_ref.clone();
+        Callback::from(move |_: Event| {
+            if let (Some(editor), Some(gutter)) = (
+                editor_ref.cast::<HtmlTextAreaElement>(),
+                gutter_ref.cast::<HtmlElement>(),
+            ) {
+                gutter.set_scroll_top(editor.scroll_top());
+            }
+        })
+    };
+
     let on_assemble = {
         let code = code.clone();
-        let output = output.clone();
+        let editor_ref = editor_ref.clone();
+        let listing = listing.clone();
+        let crossref = crossref.clone();
         let status = status.clone();
-        let error_count = error_count.clone();
-        let success = success.clone();
+        let view = view.clone();
         let ctx = cpu_ctx.clone();
 
         Callback::from(move |_: MouseEvent| {
@@ -59,66 +248,44 @@ pub fn assembler_view() -> Html {
             console::log!(format!("[Assembler] Code length: {} chars", code_str.len()));
 
             // Perform assembly
-            console::log!("[Assembler] About to call cpu.assemble()");
+            console::log!("[Assembler] About to call cpu.assemble_with_outputs()");
             let result = {
                 let mut cpu = ctx.cpu.borrow_mut();
                 console::log!("[Assembler] Got mutable borrow of CPU");
-                cpu.assemble(&code_str)
+                cpu.assemble_with_outputs(&code_str)
             };
             console::log!("[Assembler] Assembly call returned");
 
-            match result {
-                Ok(result_value) => {
-                    console::log!("[Assembler] Got Ok result from WASM");
-                    if let Ok(result) =
-                        serde_wasm_bindgen::from_value::<AssemblyResult>(result_value)
+            match serde_wasm_bindgen::from_value::<OutputsResult>(result) {
+                Ok(OutputsResult {
+                    assembly_result: result,
+                    listing: listing_text,
+                    crossref: crossref_text,
+                }) => {
+                    listing.set(listing_text);
+                    crossref.set(crossref_text);
+                    console::log!(format!(
+                        "[Assembler] Deserialized result, success={}",
+                        result.success
+                    ));
+                    // Select the offending token so it stands out in the editor
+                    let selection = result
+                        .spans
+                        .first()
+                        .and_then(|span| span.selection(&code_str));
+                    if let (Some((start, end)), Some(editor)) =
+                        (selection, editor_ref.cast::<HtmlTextAreaElement>())
                     {
-                        console::log!(format!(
-                            "[Assembler] Deserialized result, success={}",
-                            result.success
-                        ));
-                        if result.success {
-                            success.set(true);
-                            error_count.set(0);
-                            status.set("Success".to_string());
-
-                            let mut msg = format!("✓ {}\n\n", result.message);
-                            if let Some(origin) = result.origin {
-                                msg.push_str(&format!("Origin: 0x{:04X}\n", origin));
-                            }
-                            if let Some(entry) = result.entry_point {
-                                msg.push_str(&format!("Entry Point: 0x{:04X}\n", entry));
-                            }
-                            if let Some(size) = result.code_size {
-                                msg.push_str(&format!("Code Size: {} words\n", size));
-                            }
-                            msg.push_str("\nProgram loaded into memory and ready to execute.");
-                            output.set(msg);
-                        } else {
-                            success.set(false);
-                            error_count.set(result.errors.len());
-                            status.set("Error".to_string());
-
-                            let mut msg = format!("✗ {}\n\n", result.message);
-                            for (i, error) in result.errors.iter().enumerate() {
-                                msg.push_str(&format!("{}. {}\n", i + 1, error));
-                            }
-                            output.set(msg);
-                        }
-                    } else {
-                        console::log!("[Assembler] Failed to deserialize result");
-                        success.set(false);
-                        error_count.set(1);
-                        status.set("Error".to_string());
-                        output.set("Failed to deserialize assembly result".to_string());
+                        let _ = editor.focus();
+                        let _ = editor.set_selection_range(start, end);
                     }
+                    view.show(&result);
                 }
                 Err(e) => {
-                    console::log!(format!("[Assembler] Got Err result: {:?}", e));
-                    success.set(false);
-                    error_count.set(1);
-                    status.set("Error".to_string());
-                    output.set(format!("✗ Assembly failed\n\n{:?}", e));
+                    console::log!(format!("[Assembler] Failed to deserialize result: {:?}", e));
+                    listing.set(String::new());
+                    crossref.set(String::new());
+                    view.show_error("Failed to deserialize assembly result".to_string());
                 }
             }
 
@@ -132,19 +299,148 @@ pub fn assembler_view() -> Html {
     let on_clear = {
         let code = code.clone();
         let output = output.clone();
+        let listing = listing.clone();
+        let crossref = crossref.clone();
         let status = status.clone();
         let error_count = error_count.clone();
         let success = success.clone();
+        let code_size = code_size.clone();
 
         Callback::from(move |_: MouseEvent| {
             code.set(String::new());
             output.set("Ready to assemble...".to_string());
+            listing.set(String::new());
+            crossref.set(String::new());
             status.set("Ready".to_string());
             error_count.set(0);
             success.set(false);
+            code_size.set(None);
+        })
+    };
+
+    let on_save = {
+        let ctx = cpu_ctx.clone();
+        let output = output.clone();
+        let download_url = download_url.clone();
+
+        Callback::from(move |_: MouseEvent| {
+            let bytes = ctx.cpu.borrow().download_program();
+            if bytes.is_empty() {
+                output.set("Nothing to save: assemble a program first.".to_string());
+                return;
+            }
+            console::log!(format!("[Assembler] Saving {} bytes", bytes.len()));
+
+            // Download through a temporary link to an object URL
+            let blob = Blob::new_with_options(bytes.as_slice(), Some("application/octet-stream"));
+            let url = ObjectUrl::from(blob);
+            let link = gloo::utils::document()
+                .create_element("a")
+                .ok()
+                .and_then(|link| link.dyn_into::<HtmlElement>().ok());
+            if let Some(link) = link {
+                let _ = link.set_attribute("href", &url);
+                let _ = link.set_attribute("download", PROGRAM_FILE_NAME);
+                link.click();
+            }
+            *download_url.borrow_mut() = Some(url);
         })
     };
 
+    let on_load = {
+        let file_input_ref = file_input_ref.clone();
+        Callback::from(move |_: MouseEvent| {
+            if let Some(input) = file_input_ref.cast::<HtmlInputElement>() {
+                input.click();
+            }
+        })
+    };
+
+    let on_file_chosen = {
+        let listing = listing.clone();
+        let crossref = crossref.clone();
+        let view = view.clone();
+        let ctx = cpu_ctx.clone();
+
+        Callback::from(move |e: Event| {
+            let Some(input) = e.target_dyn_into::<HtmlInputElement>() else {
+                return;
+            };
+            let Some(file) = input.files().and_then(|files| files.get(0)) else {
+                return;
+            };
+            // Let the same file be chosen again later
+            input.set_value("");
+
+            let file = File::from(file);
+            console::log!(format!("[Assembler] Loading {}", file.name()));
+            let listing = listing.clone();
+            let crossref = crossref.clone();
+            let view = view.clone();
+            let ctx = ctx.clone();
+            let reader = gloo::file::callbacks::read_as_bytes(&file, move |bytes| {
+                // A binary has no source to list
+                listing.set(String::new());
+                crossref.set(String::new());
+                let bytes = match bytes {
+                    Ok(bytes) => bytes,
+                    Err(e) => {
+                        view.show_error(format!("Could not read file: {}", e));
+                        return;
+                    }
+                };
+                let result = ctx.cpu.borrow_mut().upload_program(&bytes);
+                match result.map(serde_wasm_bindgen::from_value::<AssemblyResult>) {
+                    Ok(Ok(result)) => view.show(&result),
+                    _ => view.show_error("Failed to load program".to_string()),
+                }
+
+                let mut new_ctx = (*ctx).clone();
+                new_ctx.version += 1;
+                ctx.set(new_ctx);
+            });
+            *file_reader.borrow_mut() = Some(reader);
+        })
+    };
+
+    // Live symbol table: the program's symbols plus any debug symbols
+    let symbols: BTreeMap<String, u16> =
+        serde_wasm_bindgen::from_value(cpu_ctx.cpu.borrow().get_all_symbols()).unwrap_or_default();
+    let on_symbol_filter = {
+        let symbol_filter = symbol_filter.clone();
+        Callback::from(move |e: InputEvent| {
+            if let Some(input) = e.target_dyn_into::<HtmlInputElement>() {
+                symbol_filter.set(input.value());
+            }
+        })
+    };
+
+    let select_tab = |tab: OutputTab| {
+        let active_tab = active_tab.clone();
+        Callback::from(move |_: MouseEvent| active_tab.set(tab))
+    };
+    // Without output for a tab (nothing assembled yet, or errors) messages show
+    let has_output = |tab: OutputTab| match tab {
+        OutputTab::Messages => true,
+        OutputTab::Listing => !listing.is_empty(),
+        OutputTab::SymbolTable => !symbols.is_empty(),
+        OutputTab::CrossReference => !crossref.is_empty(),
+    };
+    let current_tab = if has_output(*active_tab) {
+        *active_tab
+    } else {
+        OutputTab::Messages
+    };
+    let tab_class =
+        |tab: OutputTab| classes!("output-tab", (current_tab == tab).then_some("active"));
+    // Text for the plain-text tabs; the symbol table renders as a table
+    let shown_output = match current_tab {
+        OutputTab::Messages => Some((*output).clone()),
+        OutputTab::Listing => Some((*listing).clone()),
+        OutputTab::SymbolTable => None,
+        OutputTab::CrossReference => Some((*crossref).clone()),
+    };
+
     let status_class = if *success {
         "success"
     } else if *error_count > 0 {
@@ -159,18 +455,33 @@ pub fn assembler_view() -> Html {
                 <div class="editor-toolbar">
                     <button class="toolbar-button primary" onclick={on_assemble}>{"Assemble"}</button>
                     <button class="toolbar-button" onclick={on_clear}>{"Clear"}</button>
-                    <button class="toolbar-button" disabled={true}>{"Load"}</button>
-                    <button class="toolbar-button" disabled={true}>{"Save"}</button>
+                    <button class="toolbar-button" onclick={on_load}>{"Load"}</button>
+                    <button class="toolbar-button" disabled={!*success} onclick={on_save}>{"Save"}</button>
+                    <input
+                        ref={file_input_ref}
+                        type="file"
+                        accept=".s130"
+                        style="display: none"
+                        onchange={on_file_chosen}
+                    />
                     <button class="toolbar-button" disabled={true}>{"Examples ▾"}</button>
                 </div>
 
                 <div class="editor-container">
+                    <div ref={gutter_ref} class="editor-gutter">
+                        { for (1..=line_count.max(1)).map(|line| html! {
+                            <div class={classes!("gutter-line", (current_line == Some(line)).then_some("current"))}>
+                                {line}
+                            </div>
+                        }) }
+                    </div>
                     <textarea
                         ref={editor_ref}
                         class="assembler-editor"
                         placeholder="Enter IBM 1130 assembly code here..."
                         value={(*code).clone()}
                         oninput={on_code_change}
+                        onscroll={on_editor_scroll}
                     />
                 </div>
             </div>
@@ -179,14 +490,56 @@ pub fn assembler_view() -> Html {
                 <h3 class="panel-title">{"Assembler Output"}</h3>
 
                 <div class="output-tabs">
-                    <button class="output-tab active">{"Messages"}</button>
-                    <button class="output-tab" disabled={true}>{"Listing"}</button>
-                    <button class="output-tab" disabled={true}>{"Symbol Table"}</button>
+                    <button class={tab_class(OutputTab::Messages)} onclick={select_tab(OutputTab::Messages)}>
+                        {"Messages"}
+                    </button>
+                    <button
+                        class={tab_class(OutputTab::Listing)}
+                        disabled={!has_output(OutputTab::Listing)}
+                        onclick={select_tab(OutputTab::Listing)}
+                    >
+                        {"Listing"}
+                    </button>
+                    <button
+                        class={tab_class(OutputTab::SymbolTable)}
+                        disabled={!has_output(OutputTab::SymbolTable)}
+                        onclick={select_tab(OutputTab::SymbolTable)}
+                    >
+                        {"Symbol Table"}
+                    </button>
+                    <button
+                        class={tab_class(OutputTab::CrossReference)}
+                        disabled={!has_output(OutputTab::CrossReference)}
+                        onclick={select_tab(OutputTab::CrossReference)}
+                    >
+                        {"Cross Reference"}
+                    </button>
                 </div>
 
                 <div class="output-container">
                     <div class="output-content">
-                        <pre class="output-text">{&*output}</pre>
+                        if let Some(text) = shown_output {
+                            <pre class="output-text">{text}</pre>
+                        } else {
+                            <input
+                                type="search"
+                                class="symbol-search"
+                                placeholder="Search symbols..."
+                                value={(*symbol_filter).clone()}
+                                oninput={on_symbol_filter}
+                            />
+                            <table class="symbol-table">
+                                { for symbols
+                                    .iter()
+                                    .filter(|(name, _)| name.to_uppercase().contains(&symbol_filter.trim().to_uppercase()))
+                                    .map(|(name, address)| html! {
+                                        <tr>
+                                            <td class="symbol-name">{name}</td>
+                                            <td class="symbol-address">{format!("{:04X}", address)}</td>
+                                        </tr>
+                                    }) }
+                            </table>
+                        }
                     </div>
                 </div>
 
@@ -207,7 +560,9 @@ pub fn assembler_view() -> Html {
                     </div>
                     <div class="stat-item">
                         <span class="stat-label">{"Code Size:"}</span>
-                        <span class="stat-value">{"N/A"}</span>
+                        <span class="stat-value">
+                            {code_size.map_or_else(|| "N/A".to_string(), CodeSize::label)}
+                        </span>
                     </div>
                 </div>
             </div>
@@ -1,6 +1,6 @@
 //! Console Panel view - IBM 1130 hardware buttons, switches, and lights
 
-use crate::cpu_context::use_cpu;
+use crate::cpu_context::{use_cpu, RunOutcome};
 use gloo::console;
 use serde::Deserialize;
 use yew::prelude::*;
@@ -14,6 +14,19 @@ struct CpuState {
     overflow: bool,
 }
 
+/// Decoded instruction from `stepWithInfo`
+#[derive(Deserialize)]
+struct StepInstruction {
+    opcode: String,
+}
+
+/// What `stepWithInfo` reports about the executed instruction
+#[derive(Deserialize)]
+struct StepInfo {
+    instruction: StepInstruction,
+    iar_before: u16,
+}
+
 #[function_component(ConsolePanel)]
 pub fn console_panel() -> Html {
     let cpu_ctx = use_cpu();
@@ -31,16 +44,37 @@ pub fn console_panel() -> Html {
         })
     };
 
+    let switches = cpu_ctx.cpu.borrow().console_switches();
+    let toggle_switch = |mask: u16| {
+        let ctx = cpu_ctx.clone();
+        Callback::from(move |_: Event| {
+            {
+                let mut cpu = ctx.cpu.borrow_mut();
+                let value = cpu.console_switches() ^ mask;
+                if let Err(e) = cpu.set_console_switches(value) {
+                    console::log!(format!("[Console Panel] Switch error: {:?}", e));
+                }
+            }
+            let mut new_ctx = (*ctx).clone();
+            new_ctx.version += 1;
+            ctx.set(new_ctx);
+        })
+    };
+
     let on_step = {
         let ctx = cpu_ctx.clone();
         Callback::from(move |_: MouseEvent| {
             console::log!("[Console Panel] INST STEP button clicked");
             {
                 let mut cpu = ctx.cpu.borrow_mut();
-                match cpu.step() {
-                    Ok(_) => {
-                        console::log!("[Console Panel] Step executed successfully");
-                    }
+                match cpu.step_with_info() {
+                    Ok(info) => match serde_wasm_bindgen::from_value::<StepInfo>(info) {
+                        Ok(info) => console::log!(format!(
+                            "[Console Panel] Executed {} at 0x{:04X}",
+                            info.instruction.opcode, info.iar_before
+                        )),
+                        Err(_) => console::log!("[Console Panel] Step executed successfully"),
+                    },
                     Err(e) => {
                         console::log!(format!("[Console Panel] Step error: {:?}", e));
                     }
@@ -60,9 +94,17 @@ pub fn console_panel() -> Html {
             {
                 let mut cpu = ctx.cpu.borrow_mut();
                 match cpu.run(100) {
-                    Ok(_) => {
-                        console::log!("[Console Panel] Run completed successfully");
-                    }
+                    Ok(result) => match RunOutcome::from_js(result) {
+                        Some(outcome) if outcome.fault.is_some() => {
+                            console::warn!(format!("[Console Panel] Run {}", outcome.describe()));
+                        }
+                        Some(outcome) => {
+                            console::log!(format!("[Console Panel] Run {}", outcome.describe()));
+                        }
+                        None => {
+                            console::log!("[Console Panel] Run completed");
+                        }
+                    },
                     Err(e) => {
                         console::log!(format!("[Console Panel] Run error: {:?}", e));
                     }
@@ -145,12 +187,15 @@ pub fn console_panel() -> Html {
                 <h3 class="panel-title">{"Address Entry Switches"}</h3>
                 <div class="address-switches">
                     {for (0..16).map(|bit| {
+                        let mask = 1u16 << (15 - bit);
                         html! {
                             <div class="bit-switch">
                                 <label class="bit-label">{format!("{}", 15 - bit)}</label>
                                 <input
                                     type="checkbox"
                                     class="toggle-switch"
+                                    checked={switches & mask != 0}
+                                    onchange={toggle_switch(mask)}
                                 />
                             </div>
                         }
@@ -1,9 +1,30 @@
 //! I/O Devices view - shows status of all attached devices
 
+use crate::cpu_context::use_cpu;
 use yew::prelude::*;
 
 #[function_component(IoDevicesView)]
 pub fn io_devices_view() -> Html {
+    let cpu_ctx = use_cpu();
+
+    // The 1132 decodes as it prints, so the output is already text
+    let (printer_output, printer_encoding) = {
+        let cpu = cpu_ctx.cpu.borrow();
+        (cpu.get_printer_1132_output(), cpu.printer_encoding())
+    };
+
+    let on_encoding_change = {
+        let ctx = cpu_ctx.clone();
+        Callback::from(move |e: Event| {
+            if let Some(select) = e.target_dyn_into::<web_sys::HtmlSelectElement>() {
+                ctx.cpu.borrow_mut().set_printer_encoding(&select.value());
+                let mut new_ctx = (*ctx).clone();
+                new_ctx.version += 1;
+                ctx.set(new_ctx);
+            }
+        })
+    };
+
     html! {
         <div class="view-panel io-devices-view">
             <div class="panel-section">
@@ -61,6 +82,41 @@ pub fn io_devices_view() -> Html {
                 </div>
             </div>
 
+            <div class="panel-section">
+                <h3 class="panel-title">{"Line Printer"}</h3>
+                <div class="devices-grid">
+                    <div class={classes!("device-card", printer_encoding.is_none().then_some("disabled"))}>
+                        <div class="device-header">
+                            <span class="device-icon">{"🖨️"}</span>
+                            <span class="device-name">{"1132 Line Printer"}</span>
+                            if printer_encoding.is_some() {
+                                <span class="device-status ready">{"Ready"}</span>
+                            } else {
+                                <span class="device-status disabled">{"Not Installed"}</span>
+                            }
+                        </div>
+                        <div class="device-info">
+                            <div class="info-row">
+                                <span>{"Device Code:"}</span>
+                                <span class="mono">{"0x06"}</span>
+                            </div>
+                            <div class="info-row">
+                                <span>{"Encoding:"}</span>
+                                <select
+                                    class="format-select"
+                                    disabled={printer_encoding.is_none()}
+                                    onchange={on_encoding_change}
+                                >
+                                    <option value="ascii" selected={printer_encoding.as_deref() == Some("ascii")}>{"ASCII"}</option>
+                                    <option value="ebcdic" selected={printer_encoding.as_deref() == Some("ebcdic")}>{"EBCDIC"}</option>
+                                </select>
+                            </div>
+                        </div>
+                        <pre class="printer-output">{printer_output}</pre>
+                    </div>
+                </div>
+            </div>
+
             <div class="panel-section">
                 <h3 class="panel-title">{"Card Equipment"}</h3>
                 <div class="devices-grid">
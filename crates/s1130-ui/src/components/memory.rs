@@ -29,6 +29,8 @@ pub fn memory_view() -> Html {
     let base_address = use_state(|| 0u16);
     let format = use_state(|| DisplayFormat::Hexadecimal);
     let address_input_ref = use_node_ref();
+    // (memory generation, used words) as of the last refresh
+    let used_cache = use_mut_ref(|| None::<(u64, usize)>);
 
     // Read memory from CPU
     let memory_lines: Vec<(u16, Vec<u16>)> = {
@@ -42,12 +44,19 @@ pub fn memory_view() -> Html {
             .collect()
     };
 
-    // Count non-zero words for used memory statistic
+    // Used memory statistic, refreshed only when memory has changed
     let used_memory: usize = {
         let cpu = cpu_ctx.cpu.borrow();
-        (0..0x8000u16)
-            .filter(|&addr| cpu.read_memory(addr).unwrap_or(0) != 0)
-            .count()
+        let generation = cpu.memory_generation();
+        let mut cache = used_cache.borrow_mut();
+        match *cache {
+            Some((cached, used)) if cached == generation => used,
+            _ => {
+                let used = cpu.used_word_count();
+                *cache = Some((generation, used));
+                used
+            }
+        }
     };
 
     let on_address_change = {
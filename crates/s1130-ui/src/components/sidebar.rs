@@ -1,6 +1,6 @@
 //! Sidebar component with controls and status
 
-use crate::cpu_context::use_cpu;
+use crate::cpu_context::{use_cpu, RunOutcome};
 use gloo::console;
 use yew::prelude::*;
 
@@ -38,9 +38,17 @@ pub fn sidebar() -> Html {
                 let mut cpu = ctx.cpu.borrow_mut();
                 match cpu.run(100) {
                     // Run 100 instructions
-                    Ok(_) => {
-                        console::log!("[Sidebar] Run completed successfully");
-                    }
+                    Ok(result) => match RunOutcome::from_js(result) {
+                        Some(outcome) if outcome.fault.is_some() => {
+                            console::warn!(format!("[Sidebar] Run {}", outcome.describe()));
+                        }
+                        Some(outcome) => {
+                            console::log!(format!("[Sidebar] Run {}", outcome.describe()));
+                        }
+                        None => {
+                            console::log!("[Sidebar] Run completed");
+                        }
+                    },
                     Err(e) => {
                         console::log!(format!("[Sidebar] Run error: {:?}", e));
                     }
@@ -1,6 +1,7 @@
 //! CPU Context for sharing emulator state across components
 
 use s1130_wasm::WasmCpu;
+use serde::Deserialize;
 use std::cell::RefCell;
 use std::rc::Rc;
 use yew::prelude::*;
@@ -33,6 +34,35 @@ impl PartialEq for CpuContext {
     }
 }
 
+/// Why `WasmCpu::run` stopped
+#[derive(Deserialize)]
+pub struct RunOutcome {
+    #[serde(rename = "stopReason")]
+    pub stop_reason: String,
+    pub fault: Option<RunFault>,
+}
+
+/// The fault a run stopped on
+#[derive(Deserialize)]
+pub struct RunFault {
+    pub message: String,
+}
+
+impl RunOutcome {
+    /// Read the result of `WasmCpu::run`
+    pub fn from_js(result: wasm_bindgen::JsValue) -> Option<Self> {
+        serde_wasm_bindgen::from_value(result).ok()
+    }
+
+    /// One-line description for the log, e.g. "stopped: wait"
+    pub fn describe(&self) -> String {
+        match &self.fault {
+            Some(fault) => format!("stopped: {} ({})", self.stop_reason, fault.message),
+            None => format!("stopped: {}", self.stop_reason),
+        }
+    }
+}
+
 /// Context provider component
 #[derive(Properties, PartialEq)]
 pub struct CpuProviderProps {
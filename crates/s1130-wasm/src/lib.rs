@@ -3,10 +3,19 @@
 //! This crate provides WebAssembly bindings for the s1130-core library,
 //! allowing the emulator to run in web browsers.
 
-use s1130_core::Cpu;
-use serde::Serialize;
+use s1130_core::assembler::{AssembledProgram, SourceMap};
+use s1130_core::devices::card_reader::{Card, Device2501, CARD_COLUMNS};
+use s1130_core::devices::{Device1132, DeviceInfo, PrinterEncoding, StandardDevice};
+use s1130_core::{AssemblerError, Cpu, CpuError, CpuSnapshot, CpuState, Disassembler, SourceSpan};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
 use wasm_bindgen::prelude::*;
 
+pub mod logging;
+
+use logging::wasm_log;
+
 /// Result of assembly operation
 #[derive(Serialize)]
 struct AssemblyResult {
@@ -15,17 +24,184 @@ struct AssemblyResult {
     origin: Option<u16>,
     #[serde(rename = "entryPoint", skip_serializing_if = "Option::is_none")]
     entry_point: Option<u16>,
+    /// Words emitted by instructions and data (BSS excluded)
     #[serde(rename = "codeSize", skip_serializing_if = "Option::is_none")]
     code_size: Option<usize>,
+    /// Whole footprint, BSS included
+    #[serde(rename = "totalWords", skip_serializing_if = "Option::is_none")]
+    total_words: Option<usize>,
     message: String,
     #[serde(skip_serializing_if = "Vec::is_empty")]
     errors: Vec<String>,
+    /// Source location of each entry of `errors` that has one
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    spans: Vec<ErrorSpan>,
+    /// Address -> label, for annotating the Memory view
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    labels: BTreeMap<u16, String>,
+}
+
+/// Source location of an assembly error, for highlighting in the editor
+///
+/// Columns are 1-indexed with `columnEnd` exclusive; both are 0 when the
+/// whole line is meant.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ErrorSpan {
+    line: usize,
+    column_start: usize,
+    column_end: usize,
+}
+
+impl From<SourceSpan> for ErrorSpan {
+    fn from(span: SourceSpan) -> Self {
+        Self {
+            line: span.line,
+            column_start: span.column_start,
+            column_end: span.column_end,
+        }
+    }
+}
+
+impl AssemblyResult {
+    /// Failed result for an assembler error, with its span if known
+    fn assembly_failed(error: &AssemblerError) -> Self {
+        Self {
+            spans: error.span().map(ErrorSpan::from).into_iter().collect(),
+            ..Self::failed(error)
+        }
+    }
+
+    fn failed(error: &dyn std::fmt::Display) -> Self {
+        wasm_log!(Warn, "assemble", "failed: {}", error);
+        Self {
+            success: false,
+            origin: None,
+            entry_point: None,
+            code_size: None,
+            total_words: None,
+            message: "Assembly failed".to_string(),
+            errors: vec![error.to_string()],
+            spans: Vec::new(),
+            labels: BTreeMap::new(),
+        }
+    }
+}
+
+/// Length of a browser animation frame, the unit of `runTimed`
+const FRAME_MS: f64 = 16.0;
+
+/// Instructions executed by `calibrateSpeed`
+const CALIBRATION_STEPS: u32 = 10_000;
+
+/// Instructions that take `FRAME_MS` at `khz` thousand instructions per second
+fn steps_per_frame(khz: f64) -> u32 {
+    (khz * FRAME_MS).round().clamp(1.0, u32::MAX as f64) as u32
+}
+
+/// CPU state after `run`, with the reason it stopped
+#[derive(Serialize)]
+struct RunState {
+    #[serde(flatten)]
+    state: CpuState,
+    #[serde(rename = "stopReason")]
+    stop_reason: &'static str,
+    /// Set when the run stopped on a fault
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fault: Option<FaultInfo>,
+}
+
+/// Result of `stepNWithSnapshots`
+#[derive(Serialize)]
+struct SnapshotSteps {
+    /// State after each completed step
+    snapshots: Vec<CpuState>,
+    /// Why the steps stopped early, if a step failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// The instruction a run faulted on
+#[derive(Serialize)]
+struct FaultInfo {
+    iar: u16,
+    words: Vec<u16>,
+    disassembly: String,
+    /// e.g. "Fault at 0x0123: DC   0xff00 (invalid instruction)"
+    message: String,
+}
+
+/// One entry of `getDevices`
+#[derive(Serialize)]
+struct DeviceStatus {
+    #[serde(flatten)]
+    info: DeviceInfo,
+    status: u16,
+    busy: bool,
+}
+
+/// Result of `assembleWithListing`
+#[derive(Serialize)]
+struct ListingResult {
+    assembly_result: AssemblyResult,
+    listing: String,
+}
+
+/// Result of `assembleWithCrossRef`
+#[derive(Serialize)]
+struct CrossRefResult {
+    assembly_result: AssemblyResult,
+    crossref: String,
+}
+
+/// Result of `assembleWithSourceMap`
+#[derive(Serialize)]
+struct SourceMapResult {
+    assembly_result: AssemblyResult,
+    /// `[address, line]` pairs of `SourceMap::entries`
+    source_map: Vec<(u16, usize)>,
+}
+
+/// Result of `assembleWithOutputs`
+#[derive(Serialize)]
+struct OutputsResult {
+    assembly_result: AssemblyResult,
+    listing: String,
+    crossref: String,
+    /// `[address, line]` pairs of `SourceMap::entries`
+    source_map: Vec<(u16, usize)>,
+}
+
+/// Session written by `saveState`
+#[derive(Serialize, Deserialize)]
+struct SavedSession {
+    /// Registers, memory and device state
+    snapshot: CpuSnapshot,
+    /// Devices attached when the state was saved, for display
+    devices: Vec<DeviceDescriptor>,
+}
+
+/// Code and name of a device in a saved session
+#[derive(Serialize, Deserialize)]
+struct DeviceDescriptor {
+    code: u8,
+    name: String,
 }
 
 /// WASM wrapper for CPU
 #[wasm_bindgen]
 pub struct WasmCpu {
     inner: Cpu,
+    /// State last handed to JS by `getState`/`stateDelta`
+    last_state: RefCell<Option<CpuState>>,
+    /// Program most recently loaded, for `downloadProgram`
+    program: Option<AssembledProgram>,
+    /// Source map of that program, if it was assembled here
+    source_map: Option<SourceMap>,
+    /// Symbols added with `addSymbol`, kept across assemblies
+    debug_symbols: HashMap<String, u16>,
+    /// Speed cap for `run` in kHz (None = unlimited)
+    speed_limit_khz: Option<f64>,
 }
 
 #[wasm_bindgen]
@@ -36,7 +212,19 @@ impl WasmCpu {
         // Set panic hook for better error messages in browser
         console_error_panic_hook::set_once();
 
-        Self { inner: Cpu::new() }
+        let mut inner = Cpu::new();
+        inner
+            .attach_device(Box::new(Device1132::new()))
+            .expect("fresh CPU has no device at the 1132 code");
+
+        Self {
+            inner,
+            last_state: RefCell::new(None),
+            program: None,
+            source_map: None,
+            debug_symbols: HashMap::new(),
+            speed_limit_khz: None,
+        }
     }
 
     /// Reset CPU to initial state
@@ -45,11 +233,119 @@ impl WasmCpu {
         self.inner.reset();
     }
 
+    /// Reset everything but memory, including devices and annotations
+    #[wasm_bindgen(js_name = resetHard)]
+    pub fn reset_hard(&mut self) {
+        self.inner.reset_hard();
+    }
+
     /// Get current CPU state as JSON
     #[wasm_bindgen(js_name = getState)]
     pub fn get_state(&self) -> JsValue {
         let state = self.inner.get_state();
-        serde_wasm_bindgen::to_value(&state).unwrap()
+        let value = serde_wasm_bindgen::to_value(&state).unwrap();
+        *self.last_state.borrow_mut() = Some(state);
+        value
+    }
+
+    /// Get only the state fields changed since the last `getState`/`stateDelta`
+    ///
+    /// The first call returns the full state.
+    #[wasm_bindgen(js_name = stateDelta)]
+    pub fn state_delta(&self) -> JsValue {
+        let state = self.inner.get_state();
+        let previous = self.last_state.replace(Some(state.clone()));
+
+        let current = serde_json::to_value(&state).unwrap();
+        let delta = match (previous, current) {
+            (Some(previous), serde_json::Value::Object(fields)) => {
+                let previous = serde_json::to_value(&previous).unwrap();
+                let changed: serde_json::Map<_, _> = fields
+                    .into_iter()
+                    .filter(|(name, value)| previous.get(name) != Some(value))
+                    .collect();
+                serde_json::Value::Object(changed)
+            }
+            (_, current) => current,
+        };
+
+        delta
+            .serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+            .unwrap()
+    }
+
+    /// Save the complete machine state (registers, memory, devices)
+    ///
+    /// The returned object can be stored (e.g. in `localStorage` after
+    /// `JSON.stringify`) and passed back to `restoreSnapshot`.
+    #[wasm_bindgen(js_name = saveSnapshot)]
+    pub fn save_snapshot(&self) -> JsValue {
+        let snapshot = self.inner.save_snapshot();
+        serde_wasm_bindgen::to_value(&snapshot).unwrap()
+    }
+
+    /// Restore a machine state produced by `saveSnapshot`
+    #[wasm_bindgen(js_name = restoreSnapshot)]
+    pub fn restore_snapshot(&mut self, data: JsValue) -> Result<(), JsValue> {
+        let snapshot: CpuSnapshot = serde_wasm_bindgen::from_value(data)
+            .map_err(|e| JsValue::from_str(&format!("Invalid snapshot: {}", e)))?;
+        self.inner
+            .restore_snapshot(snapshot)
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Save the full machine (registers, memory, devices) as a JSON string
+    ///
+    /// Meant for persisting a session, e.g. in `localStorage`; pass the
+    /// string back to `loadState`.
+    #[wasm_bindgen(js_name = saveState)]
+    pub fn save_state(&self) -> String {
+        let session = SavedSession {
+            snapshot: self.inner.save_snapshot(),
+            devices: self
+                .inner
+                .attached_devices()
+                .map(|device| DeviceDescriptor {
+                    code: device.device_code(),
+                    name: device.device_name().to_string(),
+                })
+                .collect(),
+        };
+        serde_json::to_string(&session).unwrap()
+    }
+
+    /// Restore a session saved by `saveState`
+    ///
+    /// Device state is applied to the devices attached at the same codes;
+    /// state for devices not attached here is ignored.
+    #[wasm_bindgen(js_name = loadState)]
+    pub fn load_state(&mut self, json: &str) -> Result<(), JsValue> {
+        let session: SavedSession = serde_json::from_str(json)
+            .map_err(|e| JsValue::from_str(&format!("Invalid saved state: {}", e)))?;
+        self.inner
+            .restore_snapshot(session.snapshot)
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+        self.last_state.replace(None);
+        // Memory may now hold a different program
+        self.program = None;
+        self.source_map = None;
+        Ok(())
+    }
+
+    /// Write-protect memory from `start` up to (not including) `end`
+    #[wasm_bindgen(js_name = protectMemoryRange)]
+    pub fn protect_memory_range(&mut self, start: u16, end: u16) -> Result<(), JsValue> {
+        self.inner
+            .protect_memory_range(start, end)
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Remove write protection from `start` up to (not including) `end`
+    #[wasm_bindgen(js_name = unprotectMemoryRange)]
+    pub fn unprotect_memory_range(&mut self, start: u16, end: u16) -> Result<(), JsValue> {
+        self.inner
+            .unprotect_memory_range(start, end)
+            .map_err(|e| JsValue::from_str(&e.to_string()))
     }
 
     /// Read memory at address
@@ -68,6 +364,36 @@ impl WasmCpu {
             .map_err(|e| JsValue::from_str(&e.to_string()))
     }
 
+    /// Set the console toggle switches (also readable at address 0)
+    #[wasm_bindgen(js_name = setConsoleSwitches)]
+    pub fn set_console_switches(&mut self, value: u16) -> Result<(), JsValue> {
+        self.inner
+            .set_console_switches(value)
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Current console switch word
+    #[wasm_bindgen(js_name = consoleSwitches)]
+    pub fn console_switches(&self) -> u16 {
+        self.inner.console_switches()
+    }
+
+    /// Read the word at IAR and advance IAR (console Examine)
+    #[wasm_bindgen(js_name = examineWord)]
+    pub fn examine_word(&mut self) -> Result<u16, JsValue> {
+        self.inner
+            .examine_word()
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Write value at IAR and advance IAR (console Deposit)
+    #[wasm_bindgen(js_name = depositWord)]
+    pub fn deposit_word(&mut self, value: u16) -> Result<(), JsValue> {
+        self.inner
+            .deposit_word(value)
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
     /// Read a range of memory
     #[wasm_bindgen(js_name = readMemoryRange)]
     pub fn read_memory_range(&self, address: u16, count: u16) -> Vec<u16> {
@@ -76,71 +402,262 @@ impl WasmCpu {
             .collect()
     }
 
+    /// Read a range of memory, failing if any of it is out of bounds
+    ///
+    /// Unlike `readMemoryRange`, which drops addresses past the top of
+    /// memory, this returns an error instead of a short result.
+    #[wasm_bindgen(js_name = readMemoryRangeChecked)]
+    pub fn read_memory_range_checked(&self, address: u16, count: u16) -> Result<Vec<u16>, JsValue> {
+        self.inner
+            .snapshot_region(address as usize, count as usize)
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Hex and ASCII dump of memory from `start` up to (not including) `end`
+    ///
+    /// Eight words per row with an ASCII column (see `Cpu::dump_memory_hex`).
+    #[wasm_bindgen(js_name = dumpMemory)]
+    pub fn dump_memory(&self, start: u16, end: u16) -> String {
+        self.inner.dump_memory_hex(start, end)
+    }
+
+    /// Counter bumped by every change to memory contents
+    #[wasm_bindgen(js_name = memoryGeneration)]
+    pub fn memory_generation(&self) -> u64 {
+        self.inner.memory_generation()
+    }
+
+    /// Number of non-zero memory words, without scanning memory
+    #[wasm_bindgen(js_name = usedWordCount)]
+    pub fn used_word_count(&self) -> usize {
+        self.inner.used_word_count()
+    }
+
+    /// First address at or after `start` holding `pattern`, if any
+    #[wasm_bindgen(js_name = searchMemory)]
+    pub fn search_memory(&self, pattern: Vec<u16>, start: u16) -> Option<u16> {
+        self.inner.search_memory(&pattern, start)
+    }
+
     /// Assemble source code and load into memory
     #[wasm_bindgen]
     pub fn assemble(&mut self, source: &str) -> Result<JsValue, JsValue> {
-        web_sys::console::log_1(&"[WASM] assemble() called".into());
         use s1130_core::assembler::Assembler;
 
+        wasm_log!(Debug, "assemble", "{} source lines", source.lines().count());
         let mut assembler = Assembler::new();
-        web_sys::console::log_1(&"[WASM] Assembler created, calling assemble()".into());
-        match assembler.assemble(source) {
-            Ok(program) => {
-                web_sys::console::log_1(
-                    &format!(
-                        "[WASM] Assembly successful, loading {} words",
-                        program.words.len()
-                    )
-                    .into(),
-                );
-                // Load program into memory starting at origin
-                for (i, word) in program.words.iter().enumerate() {
-                    let addr = program.origin as usize + i;
-                    if let Err(e) = self.inner.write_memory(addr, *word) {
-                        return Err(JsValue::from_str(&format!("Memory write error: {}", e)));
-                    }
+        let result = match assembler.assemble(source) {
+            Ok(program) => self.load_assembled(&program)?,
+            Err(error) => AssemblyResult::assembly_failed(&error),
+        };
+        Ok(serde_wasm_bindgen::to_value(&result).unwrap())
+    }
+
+    /// Assemble and load like `assemble`, also returning the listing
+    ///
+    /// Returns `{ assembly_result, listing }`; the listing is empty when
+    /// assembly fails.
+    #[wasm_bindgen(js_name = assembleWithListing)]
+    pub fn assemble_with_listing(&mut self, source: &str) -> JsValue {
+        use s1130_core::assembler::Assembler;
+
+        let mut assembler = Assembler::new();
+        let result = match assembler.assemble_with_listing(source) {
+            Ok((program, listing)) => ListingResult {
+                assembly_result: self
+                    .load_assembled(&program)
+                    .unwrap_or_else(|e| AssemblyResult::failed(&e.as_string().unwrap_or_default())),
+                listing,
+            },
+            Err(error) => ListingResult {
+                assembly_result: AssemblyResult::assembly_failed(&error),
+                listing: String::new(),
+            },
+        };
+        serde_wasm_bindgen::to_value(&result).unwrap()
+    }
+
+    /// Assemble and load like `assemble`, also returning the cross-reference
+    ///
+    /// Returns `{ assembly_result, crossref }` where `crossref` is the table
+    /// text of `CrossRefTable::format`, empty when assembly fails.
+    #[wasm_bindgen(js_name = assembleWithCrossRef)]
+    pub fn assemble_with_cross_ref(&mut self, source: &str) -> JsValue {
+        use s1130_core::assembler::Assembler;
+
+        let mut assembler = Assembler::new();
+        let result = match assembler.assemble_with_crossref(source) {
+            Ok((program, table)) => CrossRefResult {
+                assembly_result: self
+                    .load_assembled(&program)
+                    .unwrap_or_else(|e| AssemblyResult::failed(&e.as_string().unwrap_or_default())),
+                crossref: table.format(),
+            },
+            Err(error) => CrossRefResult {
+                assembly_result: AssemblyResult::assembly_failed(&error),
+                crossref: String::new(),
+            },
+        };
+        serde_wasm_bindgen::to_value(&result).unwrap()
+    }
+
+    /// Assemble and load like `assemble`, also returning the source map
+    ///
+    /// Returns `{ assembly_result, source_map }` where `source_map` lists
+    /// `[address, line]` for every generated word, empty when assembly
+    /// fails. The map is kept for `currentSourceLine`.
+    #[wasm_bindgen(js_name = assembleWithSourceMap)]
+    pub fn assemble_with_source_map(&mut self, source: &str) -> JsValue {
+        use s1130_core::assembler::Assembler;
+
+        let mut assembler = Assembler::new();
+        let result = match assembler.assemble_with_source_map(source) {
+            Ok((program, source_map)) => {
+                let assembly_result = self
+                    .load_assembled(&program)
+                    .unwrap_or_else(|e| AssemblyResult::failed(&e.as_string().unwrap_or_default()));
+                let entries = source_map.entries.clone();
+                if assembly_result.success {
+                    self.source_map = Some(source_map);
+                }
+                SourceMapResult {
+                    assembly_result,
+                    source_map: entries,
                 }
+            }
+            Err(error) => SourceMapResult {
+                assembly_result: AssemblyResult::assembly_failed(&error),
+                source_map: Vec::new(),
+            },
+        };
+        serde_wasm_bindgen::to_value(&result).unwrap()
+    }
 
-                // Set IAR to entry point, or origin if not specified
-                let iar_address = program.entry_point.unwrap_or(program.origin);
-                self.inner.set_iar(iar_address);
-                web_sys::console::log_1(
-                    &format!(
-                        "[WASM] Set IAR to 0x{:04X} ({})",
-                        iar_address,
-                        if program.entry_point.is_some() {
-                            "entry point"
-                        } else {
-                            "origin"
-                        }
-                    )
-                    .into(),
-                );
+    /// Assemble and load like `assemble`, returning the listing, the
+    /// cross-reference and the source map from a single assembly
+    ///
+    /// Returns `{ assembly_result, listing, crossref, source_map }`, with
+    /// the fields of `assembleWithListing`, `assembleWithCrossRef` and
+    /// `assembleWithSourceMap`. The map is kept for `currentSourceLine`.
+    #[wasm_bindgen(js_name = assembleWithOutputs)]
+    pub fn assemble_with_outputs(&mut self, source: &str) -> JsValue {
+        use s1130_core::assembler::Assembler;
 
-                // Return assembly result
-                let result = AssemblyResult {
-                    success: true,
-                    origin: Some(program.origin),
-                    entry_point: program.entry_point,
-                    code_size: Some(program.words.len()),
-                    message: "Assembly successful".to_string(),
-                    errors: vec![],
-                };
-                Ok(serde_wasm_bindgen::to_value(&result).unwrap())
+        let mut assembler = Assembler::new();
+        let result = match assembler.assemble_with_outputs(source) {
+            Ok(outputs) => {
+                let assembly_result = self
+                    .load_assembled(&outputs.program)
+                    .unwrap_or_else(|e| AssemblyResult::failed(&e.as_string().unwrap_or_default()));
+                let entries = outputs.source_map.entries.clone();
+                if assembly_result.success {
+                    self.source_map = Some(outputs.source_map);
+                }
+                OutputsResult {
+                    assembly_result,
+                    listing: outputs.listing,
+                    crossref: outputs.crossref.format(),
+                    source_map: entries,
+                }
             }
-            Err(error) => {
-                web_sys::console::log_1(&format!("[WASM] Assembly failed: {}", error).into());
-                let result = AssemblyResult {
-                    success: false,
-                    origin: None,
-                    entry_point: None,
-                    code_size: None,
-                    message: "Assembly failed".to_string(),
-                    errors: vec![error.to_string()],
-                };
-                Ok(serde_wasm_bindgen::to_value(&result).unwrap())
+            Err(error) => OutputsResult {
+                assembly_result: AssemblyResult::assembly_failed(&error),
+                listing: String::new(),
+                crossref: String::new(),
+                source_map: Vec::new(),
+            },
+        };
+        serde_wasm_bindgen::to_value(&result).unwrap()
+    }
+
+    /// Source line (1-indexed) that generated the word at IAR
+    ///
+    /// `None` if the loaded program was not assembled by
+    /// `assembleWithSourceMap` or IAR is outside it.
+    #[wasm_bindgen(js_name = currentSourceLine)]
+    pub fn current_source_line(&self) -> Option<usize> {
+        self.source_map
+            .as_ref()?
+            .address_to_line(self.inner.get_iar())
+    }
+
+    /// The loaded program in the binary format of `AssembledProgram::to_binary`
+    ///
+    /// Empty if nothing has been assembled or uploaded yet.
+    #[wasm_bindgen(js_name = downloadProgram)]
+    pub fn download_program(&self) -> Vec<u8> {
+        self.program
+            .as_ref()
+            .map(AssembledProgram::to_binary)
+            .unwrap_or_default()
+    }
+
+    /// Load a program saved by `downloadProgram`
+    ///
+    /// Returns the same result object as `assemble`.
+    #[wasm_bindgen(js_name = uploadProgram)]
+    pub fn upload_program(&mut self, data: &[u8]) -> Result<JsValue, JsValue> {
+        let result = match AssembledProgram::from_binary(data) {
+            Ok(program) => self.load_assembled(&program)?,
+            Err(error) => AssemblyResult::assembly_failed(&error),
+        };
+        Ok(serde_wasm_bindgen::to_value(&result).unwrap())
+    }
+
+    /// The loaded program's symbols overlaid with the debug symbols
+    fn symbol_table(&self) -> HashMap<String, u16> {
+        let mut symbols = self
+            .program
+            .as_ref()
+            .map(|program| program.symbols.clone())
+            .unwrap_or_default();
+        symbols.extend(
+            self.debug_symbols
+                .iter()
+                .map(|(name, &address)| (name.clone(), address)),
+        );
+        symbols
+    }
+
+    /// Load all segments of an assembled program and point IAR at its entry
+    fn load_assembled(&mut self, program: &AssembledProgram) -> Result<AssemblyResult, JsValue> {
+        wasm_log!(
+            Info,
+            "assemble",
+            "loading {} words in {} segments",
+            program.word_count(),
+            program.segments.len()
+        );
+        // Writes every segment and sets IAR to the entry point or origin
+        self.inner
+            .load_program(program)
+            .map_err(|e| JsValue::from_str(&format!("Memory write error: {}", e)))?;
+        self.program = Some(program.clone());
+        self.source_map = None;
+        wasm_log!(
+            Debug,
+            "assemble",
+            "IAR set to {:#06X} ({})",
+            self.inner.get_iar(),
+            if program.entry_point.is_some() {
+                "entry point"
+            } else {
+                "origin"
             }
-        }
+        );
+
+        let footprint = program.footprint();
+        Ok(AssemblyResult {
+            success: true,
+            origin: Some(program.origin),
+            entry_point: program.entry_point,
+            code_size: Some(footprint.code_words + footprint.data_words),
+            total_words: Some(footprint.total_words),
+            message: "Assembly successful".to_string(),
+            errors: vec![],
+            spans: vec![],
+            labels: program.address_labels(),
+        })
     }
 
     /// Execute one instruction (step)
@@ -149,22 +666,468 @@ impl WasmCpu {
         match self.inner.step() {
             Ok(_) => {
                 let state = self.inner.get_state();
+                wasm_log!(Debug, "step", "IAR now {:#06X}", state.iar);
                 Ok(serde_wasm_bindgen::to_value(&state).unwrap())
             }
-            Err(e) => Err(JsValue::from_str(&e.to_string())),
+            Err(e) => {
+                wasm_log!(Error, "step", "{}", e);
+                Err(JsValue::from_str(&e.to_string()))
+            }
+        }
+    }
+
+    /// Keep registers from before the last `max_entries` steps for `stepBack`
+    #[wasm_bindgen(js_name = enableHistory)]
+    pub fn enable_history(&mut self, max_entries: usize) {
+        self.inner.enable_history(max_entries);
+    }
+
+    /// Undo the most recent step
+    ///
+    /// Returns `{ state, memoryModified }`; `memoryModified` is true when
+    /// the undone instruction wrote memory, which is not restored. Errors
+    /// if there is no history to step back through.
+    #[wasm_bindgen(js_name = stepBack)]
+    pub fn step_back(&mut self) -> Result<JsValue, JsValue> {
+        let memory_modified = match self.inner.step_back() {
+            Ok(()) => false,
+            Err(CpuError::HistoryMemoryModified(address)) => {
+                wasm_log!(Warn, "stepBack", "memory written at {:#06X} kept", address);
+                true
+            }
+            Err(e) => return Err(JsValue::from_str(&e.to_string())),
+        };
+        let outcome = serde_json::json!({
+            "state": self.inner.get_state(),
+            "memoryModified": memory_modified,
+        });
+        Ok(serde_wasm_bindgen::to_value(&outcome).unwrap())
+    }
+
+    /// Execute one instruction, returning what was executed
+    ///
+    /// The JSON carries the decoded `instruction`, `effective_address`, IAR
+    /// and ACC before and after, and `flags_changed`.
+    #[wasm_bindgen(js_name = stepWithInfo)]
+    pub fn step_with_info(&mut self) -> Result<JsValue, JsValue> {
+        match self.inner.step_with_info() {
+            Ok(info) => {
+                wasm_log!(
+                    Debug,
+                    "step",
+                    "{:?} at {:#06X}, IAR now {:#06X}",
+                    info.instruction.opcode,
+                    info.iar_before,
+                    info.iar_after
+                );
+                Ok(serde_wasm_bindgen::to_value(&info).unwrap())
+            }
+            Err(e) => {
+                wasm_log!(Error, "step", "{}", e);
+                Err(JsValue::from_str(&e.to_string()))
+            }
         }
     }
 
-    /// Run N instructions
+    /// Run up to N instructions
+    ///
+    /// With a speed limit set, at most one frame's worth of instructions
+    /// at that speed are run. Returns the CPU state with a `stopReason`
+    /// field naming why the run ended (`wait`, `maxStepsReached`,
+    /// `invalidInstruction`, ...). A run stopped by a fault also has a
+    /// `fault` object: `{ iar, words, disassembly, message }`.
     #[wasm_bindgen]
     pub fn run(&mut self, steps: u32) -> Result<JsValue, JsValue> {
-        for _ in 0..steps {
-            if let Err(e) = self.inner.step() {
-                return Err(JsValue::from_str(&e.to_string()));
+        let steps = match self.speed_limit_khz {
+            Some(khz) => steps.min(steps_per_frame(khz)),
+            None => steps,
+        };
+        let (executed, reason) = self.inner.run(steps as u64);
+        wasm_log!(
+            Debug,
+            "run",
+            "{} steps, stopped ({:?}) at {:#06X}",
+            executed,
+            reason,
+            self.inner.get_iar()
+        );
+        let fault = self.inner.last_fault().map(|fault| FaultInfo {
+            iar: fault.iar,
+            words: fault.words.clone(),
+            disassembly: fault.disassembly.clone(),
+            message: fault.to_string(),
+        });
+        let result = RunState {
+            state: self.inner.get_state(),
+            stop_reason: reason.name(),
+            fault,
+        };
+        // `flatten` goes through a map, which the default serializer
+        // would hand to JavaScript as a `Map` rather than an object
+        result
+            .serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Execute up to `n` instructions, returning the state after each
+    ///
+    /// Unlike `run`, every intermediate state is kept, so the UI can
+    /// animate the steps. Returns `{ snapshots, error }`: `snapshots` is
+    /// shorter than `n` if the CPU reached `WAIT` or a step failed, and
+    /// `error` is the failure's message, absent if no step failed.
+    #[wasm_bindgen(js_name = stepNWithSnapshots)]
+    pub fn step_n_with_snapshots(&mut self, n: u32) -> Result<JsValue, JsValue> {
+        let mut snapshots = Vec::new();
+        let (steps, error) = self
+            .inner
+            .step_n(n, |_, state| snapshots.push(state.clone()));
+        if let Some(error) = &error {
+            wasm_log!(Warn, "step", "stopped after {} steps: {}", steps, error);
+        }
+        let result = SnapshotSteps {
+            snapshots,
+            error: error.map(|error| error.to_string()),
+        };
+        serde_wasm_bindgen::to_value(&result).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Run one 16 ms animation frame's worth of instructions at `target_khz`
+    ///
+    /// The original 1130 ran roughly 500-2000 kHz. Returns the number of
+    /// instructions executed, fewer if the program stopped.
+    #[wasm_bindgen(js_name = runTimed)]
+    pub fn run_timed(&mut self, target_khz: f64) -> u32 {
+        let (executed, _) = self.inner.run(steps_per_frame(target_khz) as u64);
+        executed as u32
+    }
+
+    /// Cap the speed of `run` at `khz`; zero or less removes the cap
+    #[wasm_bindgen(js_name = setSpeedLimit)]
+    pub fn set_speed_limit(&mut self, khz: f64) {
+        self.speed_limit_khz = (khz > 0.0).then_some(khz);
+    }
+
+    /// Measure this host's emulation speed in MIPS
+    ///
+    /// Times 10 000 instructions on a scratch CPU, leaving this one
+    /// untouched. The instructions are `SLA 0` no-ops rather than WAIT,
+    /// which would stop the run after one instruction.
+    #[wasm_bindgen(js_name = calibrateSpeed)]
+    pub fn calibrate_speed(&self) -> f64 {
+        let mut scratch =
+            Cpu::with_memory_size(CALIBRATION_STEPS as usize).expect("valid memory size");
+        let noops = vec![0x2000; CALIBRATION_STEPS as usize];
+        scratch
+            .write_memory_range(0, &noops)
+            .expect("program fits scratch memory");
+
+        let start = js_sys::Date::now();
+        let (executed, _) = scratch.run(CALIBRATION_STEPS as u64);
+        // Date::now has millisecond resolution
+        let elapsed_ms = (js_sys::Date::now() - start).max(1.0);
+        executed as f64 / (elapsed_ms * 1000.0)
+    }
+
+    /// Log every executed instruction, memory access and XIO to the console
+    ///
+    /// Very verbose: meant for single-stepping, not long runs. Works in
+    /// any build, unlike the `debug-logging` records.
+    #[wasm_bindgen(js_name = enableConsoleLogging)]
+    pub fn enable_console_logging(&mut self) {
+        self.inner.set_event_hook(Box::new(logging::ConsoleLogHook));
+    }
+
+    /// Stop logging CPU events to the console
+    #[wasm_bindgen(js_name = disableConsoleLogging)]
+    pub fn disable_console_logging(&mut self) {
+        self.inner.clear_event_hook();
+    }
+
+    /// Drop log records below `level` (`debug`, `info`, `warn`, `error`)
+    ///
+    /// Returns false for an unknown level name. Logging only happens in
+    /// builds with the `debug-logging` feature.
+    #[wasm_bindgen(js_name = setLogLevel)]
+    pub fn set_log_level(level: &str) -> bool {
+        match logging::Level::from_name(level) {
+            Some(level) => {
+                logging::set_min_level(level);
+                true
             }
+            None => false,
         }
-        let state = self.inner.get_state();
-        Ok(serde_wasm_bindgen::to_value(&state).unwrap())
+    }
+
+    /// Get everything printed on the 1132 line printer, lines joined by newlines
+    ///
+    /// The text is already decoded with the printer's encoding (see
+    /// `printerEncoding`); codes it cannot map show as `?`.
+    #[wasm_bindgen(js_name = getPrinter1132Output)]
+    pub fn get_printer_1132_output(&self) -> String {
+        self.inner
+            .get_device(StandardDevice::LinePrinter1132.code())
+            .and_then(|device| device.as_any().downcast_ref::<Device1132>())
+            .map(Device1132::print_to_string)
+            .unwrap_or_default()
+    }
+
+    /// Encoding the 1132 decodes print lines with: `ascii` or `ebcdic`
+    ///
+    /// `None` if no 1132 is attached.
+    #[wasm_bindgen(js_name = printerEncoding)]
+    pub fn printer_encoding(&self) -> Option<String> {
+        self.inner
+            .get_device(StandardDevice::LinePrinter1132.code())
+            .and_then(|device| device.as_any().downcast_ref::<Device1132>())
+            .map(|printer| printer.encoding().name().to_string())
+    }
+
+    /// Set the 1132 encoding by name (`ascii` or `ebcdic`, any case)
+    ///
+    /// Returns false if the name is unknown or no 1132 is attached. Lines
+    /// already printed keep their text.
+    #[wasm_bindgen(js_name = setPrinterEncoding)]
+    pub fn set_printer_encoding(&mut self, name: &str) -> bool {
+        let Some(encoding) = PrinterEncoding::from_name(name) else {
+            return false;
+        };
+        let printer = self
+            .inner
+            .get_device_mut_ref(StandardDevice::LinePrinter1132.code())
+            .and_then(|device| device.as_any_mut().downcast_mut::<Device1132>());
+        match printer {
+            Some(printer) => {
+                printer.set_encoding(encoding);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Put a fresh 2501 card reader at code 9, its hopper holding `new_cards`
+    ///
+    /// `new_cards` is the deck as consecutive 80-word cards. Any reader
+    /// already attached is discarded with its cards and pending read; this
+    /// works mid-run. Returns false, leaving the old reader in place, if
+    /// the length is not a multiple of 80.
+    #[wasm_bindgen(js_name = replaceCardReader)]
+    pub fn replace_card_reader(&mut self, new_cards: Vec<u16>) -> bool {
+        if !new_cards.len().is_multiple_of(CARD_COLUMNS) {
+            return false;
+        }
+
+        let mut reader = Device2501::new();
+        reader.load_cards(
+            new_cards
+                .chunks(CARD_COLUMNS)
+                .map(Card::from_data)
+                .collect(),
+        );
+        let code = StandardDevice::CardReader2501.code();
+        let replaced = match self.inner.replace_device(code, Box::new(reader)) {
+            Ok(old) => old.is_some(),
+            Err(error) => {
+                wasm_log!(Warn, "devices", "card reader not replaced: {}", error);
+                return false;
+            }
+        };
+        wasm_log!(
+            Info,
+            "devices",
+            "card reader {} with {} cards",
+            if replaced { "replaced" } else { "attached" },
+            new_cards.len() / CARD_COLUMNS
+        );
+        true
+    }
+
+    /// Put a fresh 2501 card reader at code 9 holding the deck in `text`
+    ///
+    /// One card per line, as `Device2501::load_from_text` reads it.
+    /// Returns the number of cards, or 0 without touching the attached
+    /// reader if the text has a character that cannot be punched.
+    #[wasm_bindgen(js_name = loadCardDeck)]
+    pub fn load_card_deck(&mut self, text: &str) -> u32 {
+        let mut reader = Device2501::new();
+        let count = match reader.load_from_text(text) {
+            Ok(count) => count,
+            Err(error) => {
+                wasm_log!(Warn, "devices", "card deck not loaded: {}", error);
+                return 0;
+            }
+        };
+        if let Err(error) = self
+            .inner
+            .replace_device(StandardDevice::CardReader2501.code(), Box::new(reader))
+        {
+            wasm_log!(Warn, "devices", "card deck not loaded: {}", error);
+            return 0;
+        }
+        wasm_log!(Info, "devices", "card deck of {} cards loaded", count);
+        count as u32
+    }
+
+    /// Attached devices with their live status words, in device-code order
+    #[wasm_bindgen(js_name = getDevices)]
+    pub fn get_devices(&self) -> Result<JsValue, JsValue> {
+        let devices: Vec<DeviceStatus> = self
+            .inner
+            .attached_devices()
+            .map(|device| DeviceStatus {
+                info: device.device_info(),
+                status: device.get_status_word(),
+                busy: device.is_busy(),
+            })
+            .collect();
+        // `info` is flattened, so serialize maps as plain objects
+        devices
+            .serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Set an instruction breakpoint
+    #[wasm_bindgen(js_name = setBreakpoint)]
+    pub fn set_breakpoint(&mut self, address: u16) {
+        self.inner.set_breakpoint(address);
+    }
+
+    /// Clear an instruction breakpoint, returning whether one was set
+    #[wasm_bindgen(js_name = clearBreakpoint)]
+    pub fn clear_breakpoint(&mut self, address: u16) -> bool {
+        self.inner.clear_breakpoint(address)
+    }
+
+    /// Run until WAIT, a breakpoint/watchpoint, or `max_steps` instructions
+    ///
+    /// Returns `{ reason, address?, state }` where `reason` is one of
+    /// `halted`, `breakpoint`, `watchpoint` or `stepLimit`.
+    #[wasm_bindgen(js_name = runUntilBreakpoint)]
+    pub fn run_until_breakpoint(&mut self, max_steps: u32) -> Result<JsValue, JsValue> {
+        use s1130_core::cpu::RunResult;
+
+        let result = self
+            .inner
+            .run_until_breakpoint(max_steps as u64)
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+        let (reason, address) = match result {
+            RunResult::Halted => ("halted", None),
+            RunResult::Breakpoint(address) => ("breakpoint", Some(address)),
+            RunResult::Watchpoint(address) => ("watchpoint", Some(address)),
+            RunResult::StepLimitReached => ("stepLimit", None),
+        };
+        let outcome = serde_json::json!({
+            "reason": reason,
+            "address": address,
+            "state": self.inner.get_state(),
+        });
+        Ok(serde_wasm_bindgen::to_value(&outcome).unwrap())
+    }
+
+    /// Run until IAR reaches `address` ("run to here")
+    ///
+    /// Returns `{ reason, steps, state }` where `reason` is `target`,
+    /// `halted` or `stepLimit`. Other errors are thrown.
+    #[wasm_bindgen(js_name = runToAddress)]
+    pub fn run_to_address(&mut self, address: u16, max_steps: u32) -> Result<JsValue, JsValue> {
+        let (reason, steps) = match self.inner.run_until_address(address, max_steps as u64) {
+            Ok(steps) => ("target", steps),
+            Err((steps, CpuError::WaitState)) => ("halted", steps),
+            Err((steps, CpuError::StepLimitReached)) => ("stepLimit", steps),
+            Err((_, error)) => return Err(JsValue::from_str(&error.to_string())),
+        };
+        let outcome = serde_json::json!({
+            "reason": reason,
+            "steps": steps,
+            "state": self.inner.get_state(),
+        });
+        Ok(serde_wasm_bindgen::to_value(&outcome).unwrap())
+    }
+
+    /// Attach a note to an address, shown in the disassembly
+    #[wasm_bindgen(js_name = setAnnotation)]
+    pub fn set_annotation(&mut self, address: u16, text: &str) {
+        self.inner.set_annotation(address, text);
+    }
+
+    /// Get the note attached to an address
+    #[wasm_bindgen(js_name = getAnnotation)]
+    pub fn annotation(&self, address: u16) -> Option<String> {
+        self.inner.annotation(address).map(str::to_string)
+    }
+
+    /// Remove the note at an address, returning whether one was set
+    #[wasm_bindgen(js_name = clearAnnotation)]
+    pub fn clear_annotation(&mut self, address: u16) -> bool {
+        self.inner.clear_annotation(address).is_some()
+    }
+
+    /// Disassemble `count` words from `start`, annotations as comments
+    ///
+    /// Addresses are shown with the live symbol table's names.
+    #[wasm_bindgen]
+    pub fn disassemble(&self, start: u16, count: u16) -> Vec<String> {
+        let symbols = self.symbol_table();
+        Disassembler::new().disassemble_range(&self.inner, start, count, Some(&symbols))
+    }
+
+    /// Address of a symbol in the live symbol table
+    ///
+    /// The live table is the loaded program's symbols plus those added
+    /// with `addSymbol`, which win on a clash.
+    #[wasm_bindgen(js_name = getSymbolAddress)]
+    pub fn get_symbol_address(&self, name: &str) -> Option<u16> {
+        self.debug_symbols.get(name).copied().or_else(|| {
+            self.program
+                .as_ref()
+                .and_then(|program| program.symbols.get(name).copied())
+        })
+    }
+
+    /// The live symbol table as a `{ name: address }` object, sorted by name
+    #[wasm_bindgen(js_name = getAllSymbols)]
+    pub fn get_all_symbols(&self) -> JsValue {
+        let symbols: BTreeMap<String, u16> = self.symbol_table().into_iter().collect();
+        serde_wasm_bindgen::to_value(&symbols).unwrap()
+    }
+
+    /// Define a debug symbol without re-assembling
+    ///
+    /// It stays defined when another program is assembled or loaded.
+    /// Returns false, defining nothing, for an empty name.
+    #[wasm_bindgen(js_name = addSymbol)]
+    pub fn add_symbol(&mut self, name: &str, address: u16) -> bool {
+        let name = name.trim();
+        if name.is_empty() {
+            return false;
+        }
+        self.debug_symbols.insert(name.to_string(), address);
+        true
+    }
+
+    /// Name for an address in the live symbol table, for reverse lookup
+    ///
+    /// When several names share the address the alphabetically first is
+    /// returned.
+    #[wasm_bindgen(js_name = resolveAddress)]
+    pub fn resolve_address(&self, address: u16) -> Option<String> {
+        self.symbol_table()
+            .into_iter()
+            .filter(|&(_, value)| value == address)
+            .map(|(name, _)| name)
+            .min()
+    }
+
+    /// Start recording the last `capacity` executed instructions
+    #[wasm_bindgen(js_name = enableTrace)]
+    pub fn enable_trace(&mut self, capacity: usize) {
+        self.inner.enable_trace(capacity);
+    }
+
+    /// Get the execution trace as a JSON array, oldest entry first
+    #[wasm_bindgen(js_name = getTraceJson)]
+    pub fn get_trace_json(&self) -> JsValue {
+        serde_wasm_bindgen::to_value(self.inner.get_trace()).unwrap()
     }
 
     /// Get CPU registers as formatted strings
@@ -205,10 +1168,531 @@ mod tests {
         assert_eq!(state.acc, 0);
     }
 
+    #[wasm_bindgen_test]
+    fn test_wasm_speed_control() {
+        let mut cpu = WasmCpu::new();
+        let noops = vec![0x2000; 0x1000]; // SLA 0
+        for (address, word) in noops.iter().enumerate() {
+            cpu.write_memory(address as u16, *word).unwrap();
+        }
+
+        // 100 kHz for 16 ms
+        assert_eq!(cpu.run_timed(100.0), 1600);
+
+        cpu.set_speed_limit(50.0);
+        cpu.run(10_000).unwrap();
+        assert_eq!(cpu.inner.get_instruction_count(), 1600 + 800);
+
+        cpu.set_speed_limit(0.0);
+        cpu.run(100).unwrap();
+        assert_eq!(cpu.inner.get_instruction_count(), 2500);
+
+        assert!(cpu.calibrate_speed() > 0.0);
+        assert_eq!(cpu.inner.get_instruction_count(), 2500);
+    }
+
     #[wasm_bindgen_test]
     fn test_wasm_memory_operations() {
         let mut cpu = WasmCpu::new();
         cpu.write_memory(0x100, 0x1234).unwrap();
         assert_eq!(cpu.read_memory(0x100).unwrap(), 0x1234);
     }
+
+    #[wasm_bindgen_test]
+    fn test_wasm_protect_memory_range() {
+        let mut cpu = WasmCpu::new();
+        cpu.protect_memory_range(0x0100, 0x0110).unwrap();
+        assert!(cpu.write_memory(0x0100, 1).is_err());
+        assert!(cpu.write_memory(0x0110, 1).is_ok());
+
+        cpu.unprotect_memory_range(0x0100, 0x0110).unwrap();
+        assert!(cpu.write_memory(0x0100, 1).is_ok());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_wasm_read_memory_range_checked() {
+        let cpu = WasmCpu::new();
+        let size: u16 = 0x8000; // default 32K words
+
+        assert_eq!(cpu.read_memory_range(size - 2, 4).len(), 2);
+        assert!(cpu.read_memory_range_checked(size - 2, 4).is_err());
+        assert_eq!(cpu.read_memory_range_checked(size - 2, 2).unwrap().len(), 2);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_wasm_save_and_load_state() {
+        let mut cpu = WasmCpu::new();
+        cpu.assemble(
+            "        ORG  /0100\n        LD   VAL\n        STO  OUT\n        WAIT\nVAL     DC   42\nOUT     BSS  1\n        END  /0100\n",
+        )
+        .unwrap();
+        cpu.run(10).unwrap();
+        let json = cpu.save_state();
+
+        let mut restored = WasmCpu::new();
+        restored
+            .assemble("START   WAIT\n        END  START\n")
+            .unwrap();
+        restored.load_state(&json).unwrap();
+        assert!(restored.download_program().is_empty());
+        assert_eq!(restored.get_symbol_address("START"), None);
+
+        let saved: CpuState = serde_wasm_bindgen::from_value(cpu.get_state()).unwrap();
+        let loaded: CpuState = serde_wasm_bindgen::from_value(restored.get_state()).unwrap();
+        assert_eq!(loaded, saved);
+        assert_eq!(loaded.acc, 42);
+        assert_eq!(restored.read_memory(0x0106).unwrap(), 42); // OUT
+
+        assert!(restored.load_state("{}").is_err());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_wasm_run_reports_stop_reason() {
+        let mut cpu = WasmCpu::new();
+        cpu.write_memory(0x0000, 0xB000).unwrap(); // WAIT
+
+        let value = cpu.run(10).unwrap();
+        assert!(!value.is_instance_of::<js_sys::Map>());
+        let result: serde_json::Value = serde_wasm_bindgen::from_value(value).unwrap();
+        assert_eq!(result["stopReason"], "wait");
+        assert_eq!(result["iar"], 1);
+        assert!(result.get("fault").is_none());
+
+        cpu.reset();
+        cpu.write_memory(0x0000, 0xFF00).unwrap(); // not an opcode
+        let result: serde_json::Value =
+            serde_wasm_bindgen::from_value(cpu.run(10).unwrap()).unwrap();
+        assert_eq!(result["stopReason"], "invalidInstruction");
+        assert_eq!(result["fault"]["iar"], 0);
+        assert_eq!(result["fault"]["words"][0], 0xFF00);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_wasm_get_devices() {
+        let cpu = WasmCpu::new();
+
+        let value = cpu.get_devices().unwrap();
+        let first = js_sys::Array::from(&value).get(0);
+        assert!(!first.is_instance_of::<js_sys::Map>());
+        let devices: serde_json::Value = serde_wasm_bindgen::from_value(value).unwrap();
+        let printer = devices
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|device| device["code"] == 6)
+            .unwrap();
+        assert_eq!(printer["status"], 1);
+        assert_eq!(printer["busy"], false);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_wasm_step_back() {
+        let mut cpu = WasmCpu::new();
+        cpu.write_memory(0x0000, 0x2001).unwrap(); // SLA 1
+        cpu.write_memory(0x0001, 0x7000).unwrap(); // STO 0x0100
+        cpu.write_memory(0x0002, 0x0100).unwrap();
+        cpu.enable_history(10);
+        cpu.inner.set_acc(1);
+        cpu.step().unwrap();
+        cpu.step().unwrap();
+
+        let outcome: serde_json::Value =
+            serde_wasm_bindgen::from_value(cpu.step_back().unwrap()).unwrap();
+        assert_eq!(outcome["memoryModified"], true);
+        assert_eq!(outcome["state"]["iar"], 1);
+
+        let outcome: serde_json::Value =
+            serde_wasm_bindgen::from_value(cpu.step_back().unwrap()).unwrap();
+        assert_eq!(outcome["memoryModified"], false);
+        assert_eq!(outcome["state"]["acc"], 1);
+        assert!(cpu.step_back().is_err());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_wasm_step_with_info() {
+        let mut cpu = WasmCpu::new();
+        cpu.write_memory(0x0000, 0xB000).unwrap(); // WAIT
+
+        let info: serde_json::Value =
+            serde_wasm_bindgen::from_value(cpu.step_with_info().unwrap()).unwrap();
+        assert_eq!(info["iar_before"], 0);
+        assert_eq!(info["iar_after"], 1);
+        assert_eq!(info["instruction"]["opcode"], "WAIT");
+    }
+
+    #[wasm_bindgen_test]
+    fn test_wasm_console_switches() {
+        let mut cpu = WasmCpu::new();
+        cpu.set_console_switches(0x00FF).unwrap();
+        assert_eq!(cpu.console_switches(), 0x00FF);
+        assert_eq!(cpu.read_memory(0x0000).unwrap(), 0x00FF);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_wasm_program_download_upload() {
+        let mut cpu = WasmCpu::new();
+        assert!(cpu.download_program().is_empty());
+        cpu.assemble("        ORG /0100\n        WAIT\n").unwrap();
+        let binary = cpu.download_program();
+
+        let mut other = WasmCpu::new();
+        let result: serde_json::Value =
+            serde_wasm_bindgen::from_value(other.upload_program(&binary).unwrap()).unwrap();
+        assert_eq!(result["success"], true);
+        assert_eq!(other.read_memory(0x0100).unwrap(), 0xB000);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_wasm_deposit_examine() {
+        let mut cpu = WasmCpu::new();
+        cpu.inner.set_iar(0x0100);
+        cpu.deposit_word(0x1234).unwrap();
+        cpu.deposit_word(0x5678).unwrap();
+
+        cpu.inner.set_iar(0x0100);
+        assert_eq!(cpu.examine_word().unwrap(), 0x1234);
+        assert_eq!(cpu.examine_word().unwrap(), 0x5678);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_wasm_search_memory() {
+        let mut cpu = WasmCpu::new();
+        cpu.write_memory(0x0200, 0xC0DE).unwrap();
+        cpu.write_memory(0x0201, 0xBEEF).unwrap();
+
+        assert_eq!(cpu.search_memory(vec![0xC0DE, 0xBEEF], 0), Some(0x0200));
+        assert_eq!(cpu.search_memory(vec![0xC0DE, 0xBEEF], 0x0201), None);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_wasm_assemble_error_span() {
+        let mut cpu = WasmCpu::new();
+        let source = "        ORG  /0100\n        LD   NOWHERE\n";
+
+        let result: serde_json::Value =
+            serde_wasm_bindgen::from_value(cpu.assemble(source).unwrap()).unwrap();
+        assert_eq!(result["success"], false);
+        assert_eq!(
+            result["spans"][0],
+            serde_json::json!({ "line": 2, "columnStart": 14, "columnEnd": 21 })
+        );
+    }
+
+    #[wasm_bindgen_test]
+    fn test_wasm_assemble_reports_footprint() {
+        let mut cpu = WasmCpu::new();
+        let source = "        ORG  /0100\n        WAIT\nBUF     BSS  4\n";
+
+        let result: serde_json::Value =
+            serde_wasm_bindgen::from_value(cpu.assemble(source).unwrap()).unwrap();
+        assert_eq!(result["codeSize"], 1);
+        assert_eq!(result["totalWords"], 5);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_wasm_current_source_line_follows_iar() {
+        let mut cpu = WasmCpu::new();
+        let source = "        ORG  /0100\n        LD   VAL\n        WAIT\nVAL     DC   3\n";
+        let result: serde_json::Value =
+            serde_wasm_bindgen::from_value(cpu.assemble_with_source_map(source)).unwrap();
+        assert_eq!(result["assembly_result"]["success"], true);
+        assert_eq!(result["source_map"][1], serde_json::json!([0x0101, 2]));
+
+        assert_eq!(cpu.current_source_line(), Some(2));
+        cpu.step().unwrap();
+        assert_eq!(cpu.current_source_line(), Some(3));
+
+        // A program without source has no map
+        cpu.assemble(source).unwrap();
+        assert_eq!(cpu.current_source_line(), None);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_wasm_step_n_with_snapshots() {
+        let mut cpu = WasmCpu::new();
+        cpu.write_memory(0x0000, 0x2001).unwrap(); // SLA 1
+        cpu.write_memory(0x0001, 0xB000).unwrap(); // WAIT
+
+        let result: serde_json::Value =
+            serde_wasm_bindgen::from_value(cpu.step_n_with_snapshots(10).unwrap()).unwrap();
+        let snapshots = result["snapshots"].as_array().unwrap();
+        assert_eq!(snapshots.len(), 2);
+        assert_eq!(snapshots[1]["wait"], true);
+        assert_eq!(snapshots[1]["iar"], 2);
+        assert!(result.get("error").is_none());
+
+        cpu.reset();
+        cpu.write_memory(0x0000, 0x2001).unwrap(); // SLA 1
+        cpu.write_memory(0x0001, 0xFF00).unwrap(); // not an opcode
+        let result: serde_json::Value =
+            serde_wasm_bindgen::from_value(cpu.step_n_with_snapshots(10).unwrap()).unwrap();
+        assert_eq!(result["snapshots"].as_array().unwrap().len(), 1);
+        assert!(result["error"].is_string());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_wasm_run_until_breakpoint() {
+        let mut cpu = WasmCpu::new();
+        cpu.write_memory(0x0000, 0x2001).unwrap(); // SLA 1
+        cpu.write_memory(0x0001, 0x2001).unwrap(); // SLA 1
+        cpu.write_memory(0x0002, 0xB000).unwrap(); // WAIT
+        cpu.set_breakpoint(0x0001);
+
+        let outcome: serde_json::Value =
+            serde_wasm_bindgen::from_value(cpu.run_until_breakpoint(10).unwrap()).unwrap();
+        assert_eq!(outcome["reason"], "breakpoint");
+        assert_eq!(outcome["address"], 1);
+
+        assert!(cpu.clear_breakpoint(0x0001));
+        let outcome: serde_json::Value =
+            serde_wasm_bindgen::from_value(cpu.run_until_breakpoint(10).unwrap()).unwrap();
+        assert_eq!(outcome["reason"], "halted");
+    }
+
+    #[wasm_bindgen_test]
+    fn test_wasm_annotations() {
+        let mut cpu = WasmCpu::new();
+        cpu.write_memory(0x0000, 0x2001).unwrap(); // SLA 1
+        cpu.write_memory(0x0001, 0xB000).unwrap(); // WAIT
+        cpu.set_annotation(0x0001, "done");
+
+        cpu.step().unwrap();
+        assert_eq!(cpu.annotation(0x0001), Some("done".to_string()));
+        assert_eq!(cpu.disassemble(0x0000, 2)[1], "0x0001  WAIT  * done");
+
+        cpu.reset_hard();
+        assert_eq!(cpu.annotation(0x0001), None);
+        assert!(!cpu.clear_annotation(0x0001));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_wasm_run_to_address() {
+        let mut cpu = WasmCpu::new();
+        cpu.write_memory(0x0000, 0x2001).unwrap(); // SLA 1
+        cpu.write_memory(0x0001, 0x2001).unwrap(); // SLA 1
+        cpu.write_memory(0x0002, 0xB000).unwrap(); // WAIT
+
+        let outcome: serde_json::Value =
+            serde_wasm_bindgen::from_value(cpu.run_to_address(0x0002, 10).unwrap()).unwrap();
+        assert_eq!(outcome["reason"], "target");
+        assert_eq!(outcome["steps"], 2);
+
+        let outcome: serde_json::Value =
+            serde_wasm_bindgen::from_value(cpu.run_to_address(0x0100, 10).unwrap()).unwrap();
+        assert_eq!(outcome["reason"], "halted");
+    }
+
+    #[wasm_bindgen_test]
+    fn test_wasm_trace_json() {
+        use s1130_core::cpu::TraceEntry;
+
+        let mut cpu = WasmCpu::new();
+        cpu.enable_trace(8);
+        cpu.write_memory(0x0000, 0xB000).unwrap(); // WAIT
+        cpu.step().unwrap();
+
+        let trace: Vec<TraceEntry> = serde_wasm_bindgen::from_value(cpu.get_trace_json()).unwrap();
+        assert_eq!(trace.len(), 1);
+        assert_eq!(trace[0].instruction_word1, 0xB000);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_wasm_snapshot_round_trip() {
+        let mut cpu = WasmCpu::new();
+        cpu.write_memory(0x100, 0x1234).unwrap();
+        let snapshot = cpu.save_snapshot();
+
+        cpu.write_memory(0x100, 0x0000).unwrap();
+        cpu.restore_snapshot(snapshot).unwrap();
+
+        assert_eq!(cpu.read_memory(0x100).unwrap(), 0x1234);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_wasm_state_delta_after_sto() {
+        let mut cpu = WasmCpu::new();
+        cpu.write_memory(0x0000, 0x7000).unwrap(); // STO 0x0200
+        cpu.write_memory(0x0001, 0x0200).unwrap();
+
+        // First call reports everything
+        let full: serde_json::Value = serde_wasm_bindgen::from_value(cpu.state_delta()).unwrap();
+        assert!(full.get("acc").is_some());
+
+        cpu.step().unwrap();
+        let delta: serde_json::Value = serde_wasm_bindgen::from_value(cpu.state_delta()).unwrap();
+        let mut fields: Vec<&String> = delta.as_object().unwrap().keys().collect();
+        fields.sort();
+        assert_eq!(fields, ["cycle_count", "iar", "instruction_count"]);
+
+        // Nothing changed since
+        let delta: serde_json::Value = serde_wasm_bindgen::from_value(cpu.state_delta()).unwrap();
+        assert!(delta.as_object().unwrap().is_empty());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_wasm_printer_1132_output() {
+        let mut cpu = WasmCpu::new();
+        // XIO 0x0010 -> IOCC at 0x0010: InitWrite of "HI" from 0x0020
+        cpu.write_memory(0x0000, 0x4400).unwrap();
+        cpu.write_memory(0x0001, 0x0010).unwrap();
+        cpu.write_memory(0x0010, 0x0020).unwrap();
+        cpu.write_memory(0x0011, (6 << 11) | (4 << 8)).unwrap();
+        cpu.write_memory(0x0020, 2).unwrap();
+        cpu.write_memory(0x0021, 'H' as u16).unwrap();
+        cpu.write_memory(0x0022, 'I' as u16).unwrap();
+
+        cpu.step().unwrap();
+        assert_eq!(cpu.get_printer_1132_output(), "HI");
+    }
+
+    #[wasm_bindgen_test]
+    fn test_wasm_printer_ebcdic_encoding() {
+        let mut cpu = WasmCpu::new();
+        assert_eq!(cpu.printer_encoding().as_deref(), Some("ascii"));
+        assert!(!cpu.set_printer_encoding("utf8"));
+        assert!(cpu.set_printer_encoding("EBCDIC"));
+        assert_eq!(cpu.printer_encoding().as_deref(), Some("ebcdic"));
+
+        // Same XIO as above, with "HI" in EBCDIC
+        cpu.write_memory(0x0000, 0x4400).unwrap();
+        cpu.write_memory(0x0001, 0x0010).unwrap();
+        cpu.write_memory(0x0010, 0x0020).unwrap();
+        cpu.write_memory(0x0011, (6 << 11) | (4 << 8)).unwrap();
+        cpu.write_memory(0x0020, 2).unwrap();
+        cpu.write_memory(0x0021, 0xC8).unwrap();
+        cpu.write_memory(0x0022, 0xC9).unwrap();
+
+        cpu.step().unwrap();
+        assert_eq!(cpu.get_printer_1132_output(), "HI");
+    }
+
+    #[wasm_bindgen_test]
+    fn test_wasm_console_logging_hook() {
+        let mut cpu = WasmCpu::new();
+        cpu.write_memory(0x0000, 0x6000).unwrap(); // LD /0010
+        cpu.write_memory(0x0001, 0x0010).unwrap();
+        cpu.write_memory(0x0002, 0xB000).unwrap(); // WAIT
+
+        cpu.enable_console_logging();
+        cpu.step().unwrap();
+        cpu.disable_console_logging();
+        cpu.step().unwrap();
+        assert!(cpu.inner.get_wait());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_wasm_load_card_deck() {
+        let mut cpu = WasmCpu::new();
+        assert_eq!(cpu.load_card_deck("A\n~ comment\nB\nC\n"), 3);
+        assert_eq!(cpu.load_card_deck("\u{7}\n"), 0);
+
+        let reader = cpu
+            .inner
+            .get_device(StandardDevice::CardReader2501.code())
+            .and_then(|device| device.as_any().downcast_ref::<Device2501>())
+            .unwrap();
+        assert_eq!(reader.card_count(), 3);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_wasm_symbol_table() {
+        let mut cpu = WasmCpu::new();
+        assert!(cpu.get_symbol_address("START").is_none());
+        assert!(cpu.add_symbol("BUFFER", 0x0200));
+        assert!(!cpu.add_symbol("  ", 0x0300));
+
+        cpu.assemble("        ORG  /0100\nSTART   WAIT\n        END  START\n")
+            .unwrap();
+        assert_eq!(cpu.get_symbol_address("START"), Some(0x0100));
+        // Debug symbols survive assembling
+        assert_eq!(cpu.get_symbol_address("BUFFER"), Some(0x0200));
+        assert_eq!(cpu.resolve_address(0x0200).as_deref(), Some("BUFFER"));
+        assert!(cpu.resolve_address(0x0300).is_none());
+
+        let symbols: BTreeMap<String, u16> =
+            serde_wasm_bindgen::from_value(cpu.get_all_symbols()).unwrap();
+        assert_eq!(symbols.len(), 2);
+
+        // A debug symbol overrides the program's
+        cpu.add_symbol("START", 0x0105);
+        assert_eq!(cpu.get_symbol_address("START"), Some(0x0105));
+        assert_eq!(cpu.resolve_address(0x0105).as_deref(), Some("START"));
+        assert!(cpu.resolve_address(0x0100).is_none());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_wasm_replace_card_reader() {
+        let mut cpu = WasmCpu::new();
+        assert!(!cpu.replace_card_reader(vec![0; 79]));
+        assert!(cpu.inner.get_device(9).is_none());
+
+        let mut deck = vec![0; 160];
+        deck[0] = 0x1111;
+        deck[80] = 0x2222;
+        assert!(cpu.replace_card_reader(deck.clone()));
+        deck[0] = 0x3333;
+        assert!(cpu.replace_card_reader(deck));
+
+        // XIO InitRead of 1 word into 0x0021 reads the new deck's first card
+        cpu.write_memory(0x0000, 0x4400).unwrap();
+        cpu.write_memory(0x0001, 0x0010).unwrap();
+        cpu.write_memory(0x0010, 0x0020).unwrap();
+        cpu.write_memory(0x0011, 0x4A00).unwrap();
+        cpu.write_memory(0x0020, 0xFFFF).unwrap();
+
+        cpu.step().unwrap();
+        assert_eq!(cpu.read_memory(0x0021).unwrap(), 0x3333);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_wasm_assemble_returns_address_labels() {
+        let mut cpu = WasmCpu::new();
+        let result = cpu
+            .assemble("        ORG  /0100\n        LD   COUNT\n        WAIT\nCOUNT   DC   3\n")
+            .unwrap();
+
+        #[derive(serde::Deserialize)]
+        struct Labels {
+            labels: BTreeMap<u16, String>,
+        }
+        let result: Labels = serde_wasm_bindgen::from_value(result).unwrap();
+        assert_eq!(
+            result.labels.get(&0x0103).map(String::as_str),
+            Some("COUNT")
+        );
+    }
+
+    #[wasm_bindgen_test]
+    fn test_wasm_assemble_with_listing() {
+        let mut cpu = WasmCpu::new();
+        let result = cpu.assemble_with_listing("        ORG  /0100\n        WAIT\n");
+
+        #[derive(serde::Deserialize)]
+        struct Success {
+            success: bool,
+        }
+        #[derive(serde::Deserialize)]
+        struct Listing {
+            assembly_result: Success,
+            listing: String,
+        }
+        let result: Listing = serde_wasm_bindgen::from_value(result).unwrap();
+        assert!(result.assembly_result.success);
+        assert!(result.listing.contains("0100  B000"));
+        assert_eq!(cpu.read_memory(0x0100).unwrap(), 0xB000);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_wasm_assemble_with_cross_ref() {
+        let mut cpu = WasmCpu::new();
+        let source = "        ORG  /0100\nLOOP    BSC  LOOP\n";
+        let result: serde_json::Value =
+            serde_wasm_bindgen::from_value(cpu.assemble_with_cross_ref(source)).unwrap();
+
+        assert_eq!(result["assembly_result"]["success"], true);
+        assert!(result["crossref"]
+            .as_str()
+            .unwrap()
+            .contains("LOOP    0100   0100"));
+    }
 }
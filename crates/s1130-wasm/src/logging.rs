@@ -0,0 +1,180 @@
+//! Debug Logging
+//!
+//! `wasm_log!` writes leveled, tagged records to the browser console when
+//! the `debug-logging` feature is enabled:
+//!
+//! ```text
+//! wasm_log!(Debug, "assemble", "loading {} words", program.words.len());
+//! // console.debug: [WASM] DEBUG assemble: loading 12 words
+//! ```
+//!
+//! Without the feature the macro expands to nothing (its arguments are still
+//! type-checked), so release builds stay quiet and carry no formatting code.
+//! With it, records below the level set by `setLogLevel` are dropped.
+//!
+//! `ConsoleLogHook` is separate: once installed with `enableConsoleLogging`
+//! it writes every CPU event with `console.log`, whatever the build.
+
+use s1130_core::cpu::CpuEventHook;
+use s1130_core::devices::DeviceFunction;
+use s1130_core::OpCode;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// Severity of a log record, lowest first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl Level {
+    /// Upper-case name used in formatted records
+    pub fn label(self) -> &'static str {
+        match self {
+            Level::Debug => "DEBUG",
+            Level::Info => "INFO",
+            Level::Warn => "WARN",
+            Level::Error => "ERROR",
+        }
+    }
+
+    /// Parse a level name, ignoring case
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_uppercase().as_str() {
+            "DEBUG" => Some(Level::Debug),
+            "INFO" => Some(Level::Info),
+            "WARN" => Some(Level::Warn),
+            "ERROR" => Some(Level::Error),
+            _ => None,
+        }
+    }
+}
+
+/// Lowest level that is emitted
+static MIN_LEVEL: AtomicU8 = AtomicU8::new(Level::Debug as u8);
+
+/// Drop records below `level`
+pub fn set_min_level(level: Level) {
+    MIN_LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+/// Check whether a record at `level` would be emitted
+///
+/// Always false without the `debug-logging` feature.
+pub fn enabled(level: Level) -> bool {
+    cfg!(feature = "debug-logging") && level as u8 >= MIN_LEVEL.load(Ordering::Relaxed)
+}
+
+/// Format a record as it appears in the console
+pub fn format_record(level: Level, target: &str, message: std::fmt::Arguments) -> String {
+    format!("[WASM] {} {}: {}", level.label(), target, message)
+}
+
+/// Write a record to the console method matching its level
+#[cfg(feature = "debug-logging")]
+pub fn emit(level: Level, target: &str, message: std::fmt::Arguments) {
+    use web_sys::console;
+
+    if !enabled(level) {
+        return;
+    }
+    let record = wasm_bindgen::JsValue::from(format_record(level, target, message));
+    match level {
+        Level::Debug => console::debug_1(&record),
+        Level::Info => console::info_1(&record),
+        Level::Warn => console::warn_1(&record),
+        Level::Error => console::error_1(&record),
+    }
+}
+
+/// CPU event hook writing each event to `console.log`
+///
+/// Records use the `wasm_log!` layout at `DEBUG` level under the `cpu`
+/// target, e.g. `[WASM] DEBUG cpu: exec 0100 LD`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ConsoleLogHook;
+
+impl ConsoleLogHook {
+    fn log(&self, message: std::fmt::Arguments) {
+        let record = format_record(Level::Debug, "cpu", message);
+        web_sys::console::log_1(&wasm_bindgen::JsValue::from(record));
+    }
+}
+
+impl CpuEventHook for ConsoleLogHook {
+    fn on_instruction_execute(&self, iar: u16, opcode: OpCode) {
+        self.log(format_args!("exec {:04X} {}", iar, opcode.mnemonic()));
+    }
+
+    fn on_memory_read(&self, addr: u16, value: u16) {
+        self.log(format_args!("read {:04X} {:04X}", addr, value));
+    }
+
+    fn on_memory_write(&self, addr: u16, value: u16) {
+        self.log(format_args!("write {:04X} {:04X}", addr, value));
+    }
+
+    fn on_device_io(&self, device_code: u8, function: DeviceFunction) {
+        self.log(format_args!("io {:02} {:?}", device_code, function));
+    }
+}
+
+/// Log a record: `wasm_log!(Level, "target", "format", args...)`
+#[cfg(feature = "debug-logging")]
+macro_rules! wasm_log {
+    ($level:ident, $target:expr, $($arg:tt)+) => {
+        $crate::logging::emit(
+            $crate::logging::Level::$level,
+            $target,
+            format_args!($($arg)+),
+        )
+    };
+}
+
+/// Log a record: compiled out without the `debug-logging` feature
+#[cfg(not(feature = "debug-logging"))]
+macro_rules! wasm_log {
+    ($level:ident, $target:expr, $($arg:tt)+) => {{
+        let _ = ($crate::logging::Level::$level, $target);
+        if false {
+            let _ = format_args!($($arg)+);
+        }
+    }};
+}
+
+pub(crate) use wasm_log;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_records_follow_feature() {
+        // Suppressed entirely without the feature, emitted with it
+        assert_eq!(enabled(Level::Error), cfg!(feature = "debug-logging"));
+    }
+
+    #[cfg(feature = "debug-logging")]
+    #[test]
+    fn test_level_filter() {
+        set_min_level(Level::Warn);
+        assert!(!enabled(Level::Info));
+        assert!(enabled(Level::Error));
+        set_min_level(Level::Debug);
+    }
+
+    #[test]
+    fn test_format_record() {
+        let record = format_record(Level::Warn, "step", format_args!("IAR {:04X}", 0x100));
+        assert_eq!(record, "[WASM] WARN step: IAR 0100");
+    }
+
+    #[test]
+    fn test_level_names() {
+        assert_eq!(Level::from_name("info"), Some(Level::Info));
+        assert_eq!(Level::from_name("verbose"), None);
+        assert!(Level::Debug < Level::Error);
+    }
+}